@@ -42,16 +42,23 @@ extern crate crossbeam;
 extern crate futures;
 extern crate num_cpus;
 
+use std::any::Any;
+use std::collections::VecDeque;
 use std::panic::{self, AssertUnwindSafe};
-use std::sync::Arc;
+use std::process;
+use std::sync::{Arc, Mutex};
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::thread;
 
 use crossbeam::sync::MsQueue;
 use futures::{IntoFuture, Future, Poll, Async};
 use futures::future::lazy;
-use futures::sync::oneshot::{channel, Sender, Receiver};
-use futures::executor::{self, Run, Executor};
+use futures::sync::oneshot::{channel, Sender, Receiver, Canceled};
+use futures::executor::{self, Run, Executor, Priority};
+
+mod work_stealing;
+
+pub use work_stealing::WorkStealingPool;
 
 /// A thread pool intended to run CPU intensive work.
 ///
@@ -84,6 +91,30 @@ pub struct Builder {
     name_prefix: Option<String>,
     after_start: Option<Arc<Fn() + Send + Sync>>,
     before_stop: Option<Arc<Fn() + Send + Sync>>,
+    panic_policy: PanicPolicy,
+    on_panic: Option<PanicHook>,
+}
+
+// A hook called with the payload of a panic which escaped from a worker
+type PanicHook = Arc<Fn(&(Any + Send)) + Send + Sync>;
+
+/// What a thread pool does when one of its worker threads panics.
+///
+/// Futures spawned onto a pool have their panics caught and propagated to
+/// the future returned by `spawn`, but a panic can still escape from a unit
+/// of work submitted directly through the `Executor` trait. The policy is
+/// set with `Builder::panic_policy`, and once it's been applied the
+/// `Builder::on_panic` hook is called with the panic's payload (before
+/// aborting, in the case of `Abort`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PanicPolicy {
+    /// Replace the worker with a new thread, so the pool keeps its size. This
+    /// is the default.
+    Respawn,
+    /// Let the worker exit, leaving the pool with one fewer thread.
+    Shrink,
+    /// Abort the process.
+    Abort,
 }
 
 struct MySender<F, T> {
@@ -101,10 +132,36 @@ fn _assert() {
 
 struct Inner {
     queue: MsQueue<Message>,
+    runs: [Mutex<VecDeque<Run>>; 3],
     cnt: AtomicUsize,
-    size: usize,
+    size: Mutex<Size>,
+    name_prefix: Option<String>,
     after_start: Option<Arc<Fn() + Send + Sync>>,
     before_stop: Option<Arc<Fn() + Send + Sync>>,
+    panic_policy: PanicPolicy,
+    on_panic: Option<PanicHook>,
+}
+
+// The number of worker threads in a pool, which can change through
+// `CpuPool::resize`.
+struct Size {
+    // The number of worker threads currently running
+    workers: usize,
+    // The number of worker threads there will be once every `Message::Close`
+    // that's been sent has been received
+    target: usize,
+    // The number used to name the next worker thread
+    next_index: usize,
+    // Notified once `workers` reaches `target`
+    waiters: Vec<Sender<()>>,
+}
+
+/// A future which resolves once a `CpuPool` has been resized.
+///
+/// This is created by the `CpuPool::resize` method.
+#[must_use]
+pub struct Resize {
+    inner: Option<Receiver<()>>,
 }
 
 /// The type of future returned from the `CpuPool::spawn` function, which
@@ -118,8 +175,11 @@ pub struct CpuFuture<T, E> {
     keep_running_flag: Arc<AtomicBool>,
 }
 
+// Messages sent to worker threads. A `Run` message indicates that there's a
+// unit of work waiting in one of the `runs` queues, and the worker which
+// receives it picks up the highest priority unit of work available.
 enum Message {
-    Run(Run),
+    Run,
     Close,
 }
 
@@ -173,6 +233,21 @@ impl CpuPool {
         where F: Future + Send + 'static,
               F::Item: Send + 'static,
               F::Error: Send + 'static,
+    {
+        self.spawn_with_priority(f, Priority::Normal)
+    }
+
+    /// Spawns a future to run on this thread pool with the given priority,
+    /// returning a future representing the produced value.
+    ///
+    /// This function behaves the same as `spawn`, except that whenever the
+    /// spawned future is ready to make progress it will be polled before any
+    /// ready futures of a lower priority.
+    pub fn spawn_with_priority<F>(&self, f: F, priority: Priority)
+                                  -> CpuFuture<F::Item, F::Error>
+        where F: Future + Send + 'static,
+              F::Item: Send + 'static,
+              F::Error: Send + 'static,
     {
         let (tx, rx) = channel();
         let keep_running_flag = Arc::new(AtomicBool::new(false));
@@ -184,7 +259,7 @@ impl CpuPool {
             tx: Some(tx),
             keep_running_flag: keep_running_flag.clone(),
         };
-        executor::spawn(sender).execute(self.inner.clone());
+        executor::spawn_with_priority(sender, priority).execute(self.inner.clone());
         CpuFuture { inner: rx , keep_running_flag: keep_running_flag.clone() }
     }
 
@@ -206,17 +281,101 @@ impl CpuPool {
     {
         self.spawn(lazy(f))
     }
+
+    /// Changes the number of worker threads in this pool to `size`.
+    ///
+    /// If the pool is growing then the new workers are started immediately.
+    /// If it's shrinking then the excess workers are retired as they go
+    /// idle: each of them finishes the work it's running, and exits instead
+    /// of picking up work which was queued after this call. Work is never
+    /// dropped by shrinking a pool.
+    ///
+    /// The returned future resolves once the pool has as many workers as
+    /// were most recently requested, so if the pool is resized again before
+    /// then it waits for the later size instead. It resolves to an error if
+    /// the pool is shut down first, by dropping its last handle. The pool is
+    /// resized whether or not the future is polled.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if `size` is 0.
+    pub fn resize(&self, size: usize) -> Resize {
+        assert!(size > 0, "cannot resize a pool to 0 threads");
+        let mut state = self.inner.size.lock().unwrap();
+        if size > state.target {
+            for _ in state.target..size {
+                let index = state.next_index;
+                state.next_index += 1;
+                state.workers += 1;
+                spawn_worker(&self.inner, index);
+            }
+        } else {
+            for _ in size..state.target {
+                self.inner.queue.push(Message::Close);
+            }
+        }
+        state.target = size;
+        if state.workers == state.target {
+            return Resize { inner: None }
+        }
+        let (tx, rx) = channel();
+        state.waiters.push(tx);
+        Resize { inner: Some(rx) }
+    }
 }
 
-fn work(inner: &Inner) {
+fn spawn_worker(inner: &Arc<Inner>, index: usize) {
+    let mut thread_builder = thread::Builder::new();
+    if let Some(ref name_prefix) = inner.name_prefix {
+        thread_builder = thread_builder.name(format!("{}{}", name_prefix, index));
+    }
+    let inner = inner.clone();
+    thread_builder.spawn(move || work(&inner)).unwrap();
+}
+
+fn work(inner: &Arc<Inner>) {
     inner.after_start.as_ref().map(|fun| fun());
-    loop {
-        match inner.queue.pop() {
-            Message::Run(r) => r.run(),
-            Message::Close => break,
+    let res = panic::catch_unwind(AssertUnwindSafe(|| {
+        while let Message::Run = inner.queue.pop() {
+            inner.next_run().run();
         }
-    }
+    }));
     inner.before_stop.as_ref().map(|fun| fun());
+    match res {
+        Ok(()) => inner.retire(),
+        Err(payload) => {
+            match inner.panic_policy {
+                PanicPolicy::Respawn => {
+                    let mut state = inner.size.lock().unwrap();
+                    let index = state.next_index;
+                    state.next_index += 1;
+                    spawn_worker(inner, index);
+                }
+                PanicPolicy::Shrink => {
+                    {
+                        let mut state = inner.size.lock().unwrap();
+                        state.target = state.target.saturating_sub(1);
+                    }
+                    inner.retire();
+                }
+                PanicPolicy::Abort => {}
+            }
+            on_worker_panic(inner.panic_policy, &inner.on_panic, payload);
+        }
+    }
+}
+
+// Reports the panic of a worker to the pool's hook, once the pool's policy
+// has otherwise been applied, and aborts if that's the policy.
+fn on_worker_panic(policy: PanicPolicy,
+                   hook: &Option<PanicHook>,
+                   payload: Box<Any + Send>) {
+    if let Some(ref hook) = *hook {
+        hook(&*payload);
+    }
+    if policy == PanicPolicy::Abort {
+        process::abort();
+    }
 }
 
 impl Clone for CpuPool {
@@ -229,16 +388,86 @@ impl Clone for CpuPool {
 impl Drop for CpuPool {
     fn drop(&mut self) {
         if self.inner.cnt.fetch_sub(1, Ordering::Relaxed) == 1 {
-            for _ in 0..self.inner.size {
+            let mut state = self.inner.size.lock().unwrap();
+            for _ in 0..state.target {
                 self.inner.queue.push(Message::Close);
             }
+            state.target = 0;
+            // Pending resizes never complete now, so their futures are
+            // canceled rather than resolved by the shutdown.
+            state.waiters.clear();
         }
     }
 }
 
+impl Inner {
+    // Called by a worker thread as it exits.
+    fn retire(&self) {
+        let mut state = self.size.lock().unwrap();
+        state.workers -= 1;
+        if state.workers == state.target {
+            for waiter in state.waiters.drain(..) {
+                waiter.complete(());
+            }
+        }
+    }
+
+    fn next_run(&self) -> Run {
+        // Each `Message::Run` is pushed after its unit of work, so there's
+        // always a `Run` available for each message received. Another worker
+        // may have taken it, but only in exchange for a message pushed later
+        // on, so keep looking until we find one.
+        loop {
+            for runs in self.runs.iter().rev() {
+                if let Some(run) = runs.lock().unwrap().pop_front() {
+                    return run
+                }
+            }
+        }
+    }
+}
+
+/// Units of work submitted directly to a pool are run on its worker threads,
+/// just like the futures spawned onto it.
+impl Executor for CpuPool {
+    fn execute(&self, run: Run) {
+        self.inner.execute(run)
+    }
+
+    fn execute_batch(&self, runs: Vec<Run>) {
+        self.inner.execute_batch(runs)
+    }
+}
+
 impl Executor for Inner {
     fn execute(&self, run: Run) {
-        self.queue.push(Message::Run(run))
+        self.runs[queue_index(run.priority())].lock().unwrap().push_back(run);
+        self.queue.push(Message::Run)
+    }
+
+    fn execute_batch(&self, runs: Vec<Run>) {
+        // Take each priority's lock at most once for the whole batch.
+        let mut by_priority: [Vec<Run>; 3] = [Vec::new(), Vec::new(), Vec::new()];
+        let count = runs.len();
+        for run in runs {
+            by_priority[queue_index(run.priority())].push(run);
+        }
+        for (queue, runs) in self.runs.iter().zip(by_priority.iter_mut()) {
+            if !runs.is_empty() {
+                queue.lock().unwrap().extend(runs.drain(..));
+            }
+        }
+        for _ in 0..count {
+            self.queue.push(Message::Run);
+        }
+    }
+}
+
+fn queue_index(priority: Priority) -> usize {
+    match priority {
+        Priority::Low => 0,
+        Priority::Normal => 1,
+        Priority::High => 2,
     }
 }
 
@@ -267,6 +496,18 @@ impl<T: Send + 'static, E: Send + 'static> Future for CpuFuture<T, E> {
     }
 }
 
+impl Future for Resize {
+    type Item = ();
+    type Error = Canceled;
+
+    fn poll(&mut self) -> Poll<(), Canceled> {
+        match self.inner {
+            Some(ref mut rx) => rx.poll(),
+            None => Ok(Async::Ready(())),
+        }
+    }
+}
+
 impl<F: Future> Future for MySender<F, Result<F::Item, F::Error>> {
     type Item = ();
     type Error = ();
@@ -298,6 +539,8 @@ impl Builder {
             name_prefix: None,
             after_start: None,
             before_stop: None,
+            panic_policy: PanicPolicy::Respawn,
+            on_panic: None,
         }
     }
 
@@ -339,28 +582,60 @@ impl Builder {
         self
     }
 
+    /// Set what the pool does when one of its worker threads panics
+    ///
+    /// See `PanicPolicy` for the options, the default being to replace the
+    /// worker with a new thread.
+    pub fn panic_policy(&mut self, policy: PanicPolicy) -> &mut Self {
+        self.panic_policy = policy;
+        self
+    }
+
+    /// Execute function `f` with the payload of a panic which has escaped
+    /// from a worker thread, after the pool's `PanicPolicy` has been applied
+    ///
+    /// This is initially intended for logging such panics, which would
+    /// otherwise go unnoticed
+    pub fn on_panic<F>(&mut self, f: F) -> &mut Self
+        where F: Fn(&(Any + Send)) + Send + Sync + 'static
+    {
+        self.on_panic = Some(Arc::new(f));
+        self
+    }
+
     /// Create CpuPool with configured parameters
     pub fn create(&mut self) -> CpuPool {
         let pool = CpuPool {
             inner: Arc::new(Inner {
                 queue: MsQueue::new(),
+                runs: [Mutex::new(VecDeque::new()),
+                       Mutex::new(VecDeque::new()),
+                       Mutex::new(VecDeque::new())],
                 cnt: AtomicUsize::new(1),
-                size: self.pool_size,
+                size: Mutex::new(Size {
+                    workers: self.pool_size,
+                    target: self.pool_size,
+                    next_index: self.pool_size,
+                    waiters: Vec::new(),
+                }),
+                name_prefix: self.name_prefix.clone(),
                 after_start: self.after_start.clone(),
                 before_stop: self.before_stop.clone(),
+                panic_policy: self.panic_policy,
+                on_panic: self.on_panic.clone(),
             }),
         };
         assert!(self.pool_size > 0);
 
         for counter in 0..self.pool_size {
-            let inner = pool.inner.clone();
-            let mut thread_builder = thread::Builder::new();
-            if let Some(ref name_prefix) = self.name_prefix {
-                thread_builder = thread_builder.name(format!("{}{}", name_prefix, counter));
-            }
-            thread_builder.spawn(move || work(&inner)).unwrap();
+            spawn_worker(&pool.inner, counter);
         }
 
         return pool
     }
+
+    /// Create a `WorkStealingPool` with configured parameters
+    pub fn create_work_stealing(&mut self) -> WorkStealingPool {
+        work_stealing::create(self)
+    }
 }
@@ -0,0 +1,299 @@
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex, Condvar};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+
+use futures::{IntoFuture, Future};
+use futures::future::lazy;
+use futures::sync::oneshot::channel;
+use futures::executor::{self, Run, Executor};
+
+use {Builder, CpuFuture, MySender, PanicHook, PanicPolicy, on_worker_panic};
+
+/// A thread pool which balances work across its threads by stealing.
+///
+/// Each worker thread has its own queue of ready tasks in addition to a global
+/// queue shared by the whole pool. Work submitted from outside the pool goes
+/// onto the global queue, while tasks which are woken up by a worker (for
+/// example a future spawned onto the pool by another future running on it)
+/// are pushed onto that worker's own queue. A worker runs work from its own
+/// queue first, then from the global queue, and otherwise steals half of the
+/// queue of another worker. This avoids funneling every dispatch through a
+/// single shared queue, so fan-out workloads scale across cores.
+///
+/// Unlike `CpuPool`, this pool does not take the priority of spawned tasks
+/// into account when picking the next task to run.
+///
+/// The worker threads are shut down once all handles to the pool have gone
+/// away and all queued work has been run.
+pub struct WorkStealingPool {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    inject: Mutex<VecDeque<Run>>,
+    locals: Vec<Mutex<VecDeque<Run>>>,
+    sleep: Mutex<Sleep>,
+    wakeup: Condvar,
+    cnt: AtomicUsize,
+    after_start: Option<Arc<Fn() + Send + Sync>>,
+    before_stop: Option<Arc<Fn() + Send + Sync>>,
+    name_prefix: Option<String>,
+    panic_policy: PanicPolicy,
+    on_panic: Option<PanicHook>,
+}
+
+struct Sleep {
+    sleepers: usize,
+    closed: bool,
+}
+
+thread_local! {
+    // The pool (by address of its `Inner`) and index of the worker running on
+    // this thread, if any.
+    static WORKER: Cell<Option<(usize, usize)>> = Cell::new(None)
+}
+
+fn _assert() {
+    fn _assert_send<T: Send>() {}
+    fn _assert_sync<T: Sync>() {}
+    _assert_send::<WorkStealingPool>();
+    _assert_sync::<WorkStealingPool>();
+}
+
+impl WorkStealingPool {
+    /// Creates a new work-stealing pool with `size` worker threads.
+    ///
+    /// This is a shortcut for:
+    /// ```rust
+    /// # use futures_cpupool::Builder;
+    /// # let size = 4;
+    /// Builder::new().pool_size(size).create_work_stealing()
+    /// # ;
+    /// ```
+    pub fn new(size: usize) -> WorkStealingPool {
+        Builder::new().pool_size(size).create_work_stealing()
+    }
+
+    /// Creates a new work-stealing pool with a number of workers equal to the
+    /// number of CPUs on the host.
+    ///
+    /// This is a shortcut for:
+    /// ```rust
+    /// # use futures_cpupool::Builder;
+    /// Builder::new().create_work_stealing()
+    /// # ;
+    /// ```
+    pub fn new_num_cpus() -> WorkStealingPool {
+        Builder::new().create_work_stealing()
+    }
+
+    /// Spawns a future to run on this thread pool, returning a future
+    /// representing the produced value.
+    ///
+    /// This behaves the same as `CpuPool::spawn`: panics are propagated
+    /// through the returned future, and dropping the returned future attempts
+    /// to cancel the computation.
+    pub fn spawn<F>(&self, f: F) -> CpuFuture<F::Item, F::Error>
+        where F: Future + Send + 'static,
+              F::Item: Send + 'static,
+              F::Error: Send + 'static,
+    {
+        let (tx, rx) = channel();
+        let keep_running_flag = Arc::new(AtomicBool::new(false));
+        let sender = MySender {
+            fut: AssertUnwindSafe(f).catch_unwind(),
+            tx: Some(tx),
+            keep_running_flag: keep_running_flag.clone(),
+        };
+        executor::spawn(sender).execute(self.inner.clone());
+        CpuFuture { inner: rx, keep_running_flag: keep_running_flag }
+    }
+
+    /// Spawns a closure on this thread pool.
+    ///
+    /// This is a convenience wrapper around `spawn` for running a closure
+    /// wrapped in `future::lazy`.
+    pub fn spawn_fn<F, R>(&self, f: F) -> CpuFuture<R::Item, R::Error>
+        where F: FnOnce() -> R + Send + 'static,
+              R: IntoFuture + 'static,
+              R::Future: Send + 'static,
+              R::Item: Send + 'static,
+              R::Error: Send + 'static,
+    {
+        self.spawn(lazy(f))
+    }
+}
+
+impl Clone for WorkStealingPool {
+    fn clone(&self) -> WorkStealingPool {
+        self.inner.cnt.fetch_add(1, Ordering::Relaxed);
+        WorkStealingPool { inner: self.inner.clone() }
+    }
+}
+
+impl Drop for WorkStealingPool {
+    fn drop(&mut self) {
+        if self.inner.cnt.fetch_sub(1, Ordering::Relaxed) == 1 {
+            self.inner.sleep.lock().unwrap().closed = true;
+            self.inner.wakeup.notify_all();
+        }
+    }
+}
+
+/// Units of work submitted directly to the pool are placed on its global
+/// queue, or on the current worker's queue if submitted from one of the
+/// pool's own threads.
+impl Executor for WorkStealingPool {
+    fn execute(&self, run: Run) {
+        self.inner.execute(run)
+    }
+
+    fn execute_batch(&self, runs: Vec<Run>) {
+        self.inner.execute_batch(runs)
+    }
+}
+
+impl Executor for Inner {
+    fn execute(&self, run: Run) {
+        self.queue_for_current().lock().unwrap().push_back(run);
+        self.notify(1);
+    }
+
+    fn execute_batch(&self, runs: Vec<Run>) {
+        let count = runs.len();
+        self.queue_for_current().lock().unwrap().extend(runs);
+        self.notify(count);
+    }
+}
+
+impl Inner {
+    fn id(&self) -> usize {
+        self as *const Inner as usize
+    }
+
+    fn queue_for_current(&self) -> &Mutex<VecDeque<Run>> {
+        match WORKER.with(|w| w.get()) {
+            Some((id, index)) if id == self.id() => &self.locals[index],
+            _ => &self.inject,
+        }
+    }
+
+    fn notify(&self, count: usize) {
+        // Workers only go to sleep after checking every queue with the
+        // `sleep` lock held, and work is always queued before this lock is
+        // taken, so a wakeup can't be missed.
+        let sleep = self.sleep.lock().unwrap();
+        if count >= sleep.sleepers {
+            self.wakeup.notify_all();
+        } else {
+            for _ in 0..count {
+                self.wakeup.notify_one();
+            }
+        }
+    }
+
+    fn find_run(&self, index: usize) -> Option<Run> {
+        // Our own queue is used as a stack, running the most recently woken
+        // task first while its data is likely still in cache.
+        if let Some(run) = self.locals[index].lock().unwrap().pop_back() {
+            return Some(run)
+        }
+        if let Some(run) = self.inject.lock().unwrap().pop_front() {
+            return Some(run)
+        }
+        self.steal(index)
+    }
+
+    fn steal(&self, index: usize) -> Option<Run> {
+        let n = self.locals.len();
+        for victim in (1..n).map(|i| (index + i) % n) {
+            let mut stolen = {
+                let mut victim = self.locals[victim].lock().unwrap();
+                let half = victim.len() - victim.len() / 2;
+                victim.drain(..half).collect::<VecDeque<_>>()
+            };
+            if let Some(run) = stolen.pop_front() {
+                if !stolen.is_empty() {
+                    self.locals[index].lock().unwrap().extend(stolen);
+                }
+                return Some(run)
+            }
+        }
+        None
+    }
+}
+
+fn work(inner: &Arc<Inner>, index: usize) {
+    WORKER.with(|w| w.set(Some((inner.id(), index))));
+    if let Some(ref fun) = inner.after_start {
+        fun();
+    }
+    let res = panic::catch_unwind(AssertUnwindSafe(|| {
+        loop {
+            if let Some(run) = inner.find_run(index) {
+                run.run();
+                continue
+            }
+            let mut sleep = inner.sleep.lock().unwrap();
+            if let Some(run) = inner.find_run(index) {
+                drop(sleep);
+                run.run();
+                continue
+            }
+            if sleep.closed {
+                break
+            }
+            sleep.sleepers += 1;
+            sleep = inner.wakeup.wait(sleep).unwrap();
+            sleep.sleepers -= 1;
+        }
+    }));
+    if let Some(ref fun) = inner.before_stop {
+        fun();
+    }
+    WORKER.with(|w| w.set(None));
+    if let Err(payload) = res {
+        // A worker which isn't replaced leaves its queue behind, which the
+        // other workers will steal from.
+        if inner.panic_policy == PanicPolicy::Respawn {
+            spawn_worker(inner, index);
+        }
+        on_worker_panic(inner.panic_policy, &inner.on_panic, payload);
+    }
+}
+
+fn spawn_worker(inner: &Arc<Inner>, index: usize) {
+    let mut thread_builder = thread::Builder::new();
+    if let Some(ref name_prefix) = inner.name_prefix {
+        thread_builder = thread_builder.name(format!("{}{}", name_prefix, index));
+    }
+    let inner = inner.clone();
+    thread_builder.spawn(move || work(&inner, index)).unwrap();
+}
+
+pub fn create(builder: &Builder) -> WorkStealingPool {
+    assert!(builder.pool_size > 0);
+    let pool = WorkStealingPool {
+        inner: Arc::new(Inner {
+            inject: Mutex::new(VecDeque::new()),
+            locals: (0..builder.pool_size).map(|_| Mutex::new(VecDeque::new())).collect(),
+            sleep: Mutex::new(Sleep { sleepers: 0, closed: false }),
+            wakeup: Condvar::new(),
+            cnt: AtomicUsize::new(1),
+            after_start: builder.after_start.clone(),
+            before_stop: builder.before_stop.clone(),
+            name_prefix: builder.name_prefix.clone(),
+            panic_policy: builder.panic_policy,
+            on_panic: builder.on_panic.clone(),
+        }),
+    };
+
+    for index in 0..builder.pool_size {
+        spawn_worker(&pool.inner, index);
+    }
+
+    pool
+}
@@ -1,12 +1,15 @@
 extern crate futures;
 extern crate futures_cpupool;
 
+use std::collections::HashSet;
+use std::sync::{mpsc, Arc, Mutex};
 use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
 use std::thread;
 use std::time::Duration;
 
+use futures::executor::{self, Executor, Priority, Run};
 use futures::future::{Future, BoxFuture};
-use futures_cpupool::{CpuPool, Builder};
+use futures_cpupool::{CpuPool, Builder, PanicPolicy, WorkStealingPool};
 
 fn done<T: Send + 'static>(t: T) -> BoxFuture<T, ()> {
     futures::future::ok(t).boxed()
@@ -97,6 +100,119 @@ fn lifecycle_test() {
     panic!("thread didn't exit");
 }
 
+#[test]
+fn resize() {
+    static NUM_STARTS: AtomicUsize = ATOMIC_USIZE_INIT;
+    static NUM_STOPS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+    fn after_start() {
+        NUM_STARTS.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn before_stop() {
+        NUM_STOPS.fetch_add(1, Ordering::SeqCst);
+    }
+
+    let pool = Builder::new()
+        .pool_size(2)
+        .after_start(after_start)
+        .before_stop(before_stop)
+        .create();
+
+    pool.resize(5).wait().unwrap();
+    assert_eq!(pool.spawn_fn(|| Ok::<_, ()>(1)).wait(), Ok(1));
+
+    // Workers which are busy finish their work before retiring
+    let (tx, rx) = mpsc::channel();
+    let busy = pool.spawn_fn(move || {
+        rx.recv().unwrap();
+        Ok::<_, ()>(2)
+    });
+    let resized = pool.resize(1);
+    thread::sleep(Duration::from_millis(50));
+    tx.send(()).unwrap();
+    resized.wait().unwrap();
+    assert_eq!(busy.wait(), Ok(2));
+    assert_eq!(NUM_STARTS.load(Ordering::SeqCst), 5);
+    assert_eq!(NUM_STOPS.load(Ordering::SeqCst), 4);
+
+    // Resizing to the current size resolves immediately
+    pool.resize(1).wait().unwrap();
+    assert_eq!(pool.spawn_fn(|| Ok::<_, ()>(3)).wait(), Ok(3));
+}
+
+#[test]
+fn resize_canceled_by_shutdown() {
+    let pool = CpuPool::new(2);
+
+    // With both workers busy, neither can retire yet
+    let (tx1, rx1) = mpsc::channel();
+    let (tx2, rx2) = mpsc::channel();
+    let busy1 = pool.spawn_fn(move || {
+        rx1.recv().unwrap();
+        Ok::<_, ()>(1)
+    });
+    let busy2 = pool.spawn_fn(move || {
+        rx2.recv().unwrap();
+        Ok::<_, ()>(2)
+    });
+    let resized = pool.resize(1);
+    thread::sleep(Duration::from_millis(50));
+    drop(pool);
+
+    // Work which was running still finishes, but the resize never completes
+    tx1.send(()).unwrap();
+    tx2.send(()).unwrap();
+    assert_eq!(busy1.wait(), Ok(1));
+    assert_eq!(busy2.wait(), Ok(2));
+    assert!(resized.wait().is_err());
+}
+
+// Runs a unit of work on `exec` which panics with `msg`
+fn execute_panic<E: Executor + 'static>(exec: Arc<E>, msg: &'static str) {
+    let f = futures::future::lazy(move || -> Result<(), ()> { panic!("{}", msg) });
+    executor::spawn(f).execute(exec);
+}
+
+#[test]
+fn panic_respawns_worker() {
+    let (tx, rx) = mpsc::channel();
+    let tx = Mutex::new(tx);
+    let pool = Arc::new(Builder::new()
+        .pool_size(1)
+        .on_panic(move |payload| {
+            let msg = payload.downcast_ref::<String>().unwrap();
+            tx.lock().unwrap().send(msg.clone()).unwrap();
+        })
+        .create());
+
+    execute_panic(pool.clone(), "first");
+    assert_eq!(rx.recv().unwrap(), "first");
+    // the pool's only worker has been replaced, so work still runs
+    assert_eq!(pool.spawn_fn(|| Ok::<_, ()>(1)).wait(), Ok(1));
+
+    let pool = Arc::new(Builder::new().pool_size(1).create_work_stealing());
+    execute_panic(pool.clone(), "second");
+    assert_eq!(pool.spawn_fn(|| Ok::<_, ()>(2)).wait(), Ok(2));
+}
+
+#[test]
+fn panic_shrinks_pool() {
+    let (tx, rx) = mpsc::channel();
+    let tx = Mutex::new(tx);
+    let pool = Arc::new(Builder::new()
+        .pool_size(1)
+        .panic_policy(PanicPolicy::Shrink)
+        .on_panic(move |_| tx.lock().unwrap().send(()).unwrap())
+        .create());
+
+    execute_panic(pool.clone(), "boom");
+    rx.recv().unwrap();
+    // the pool is now empty, and resizing it brings back a worker
+    pool.resize(1).wait().unwrap();
+    assert_eq!(pool.spawn_fn(|| Ok::<_, ()>(1)).wait(), Ok(1));
+}
+
 #[test]
 fn thread_name() {
     let pool = Builder::new()
@@ -108,3 +224,116 @@ fn thread_name() {
     });
     let _ = future.wait();
 }
+
+#[test]
+fn priorities() {
+    let pool = CpuPool::new(1);
+    let order = Arc::new(Mutex::new(Vec::new()));
+
+    // Keep the only worker busy until all the work below has been queued up.
+    let (tx, rx) = mpsc::channel::<()>();
+    let blocker = pool.spawn_fn(move || {
+        rx.recv().unwrap();
+        Ok::<(), ()>(())
+    });
+
+    let mut futures = Vec::new();
+    for &(name, priority) in [("low", Priority::Low),
+                              ("normal", Priority::Normal),
+                              ("high", Priority::High)].iter() {
+        let order = order.clone();
+        let f = futures::future::lazy(move || {
+            order.lock().unwrap().push(name);
+            Ok::<(), ()>(())
+        });
+        futures.push(pool.spawn_with_priority(f, priority));
+    }
+
+    tx.send(()).unwrap();
+    blocker.wait().unwrap();
+    futures::future::join_all(futures).wait().unwrap();
+    assert_eq!(*order.lock().unwrap(), ["high", "normal", "low"]);
+}
+
+#[test]
+fn execute_batch() {
+    struct Collect(Mutex<Vec<Run>>);
+
+    impl Executor for Collect {
+        fn execute(&self, run: Run) {
+            self.0.lock().unwrap().push(run);
+        }
+    }
+
+    let collect = Arc::new(Collect(Mutex::new(Vec::new())));
+    let (tx, rx) = mpsc::channel();
+    for i in 0..10 {
+        let tx = tx.clone();
+        let f = futures::future::lazy(move || {
+            tx.send(i).unwrap();
+            Ok::<(), ()>(())
+        });
+        executor::spawn(f).execute(collect.clone());
+    }
+    drop(tx);
+
+    let pool = CpuPool::new(2);
+    let runs = ::std::mem::replace(&mut *collect.0.lock().unwrap(), Vec::new());
+    pool.execute_batch(runs);
+    let mut seen = rx.iter().collect::<Vec<_>>();
+    seen.sort();
+    assert_eq!(seen, (0..10).collect::<Vec<_>>());
+}
+
+#[test]
+fn work_stealing_fan_out() {
+    let pool = WorkStealingPool::new(4);
+    let inner = pool.clone();
+    let total = pool.spawn_fn(move || {
+        let parts = (0..100u64).map(|i| inner.spawn_fn(move || Ok::<u64, ()>(i * i)))
+                               .collect::<Vec<_>>();
+        futures::future::join_all(parts).map(|parts| parts.iter().fold(0, |sum, n| sum + n))
+    });
+    assert_eq!(total.wait().unwrap(), (0..100u64).map(|i| i * i).fold(0, |sum, n| sum + n));
+}
+
+#[test]
+fn work_stealing_runs_on_every_worker() {
+    let pool = Builder::new()
+        .pool_size(3)
+        .name_prefix("worker-")
+        .create_work_stealing();
+    let (tx, rx) = mpsc::channel();
+    let barrier = Arc::new(::std::sync::Barrier::new(3));
+    let futures = (0..3).map(|_| {
+        let (tx, barrier) = (tx.clone(), barrier.clone());
+        pool.spawn_fn(move || {
+            barrier.wait();
+            tx.send(thread::current().name().unwrap().to_string()).unwrap();
+            Ok::<(), ()>(())
+        })
+    }).collect::<Vec<_>>();
+    futures::future::join_all(futures).wait().unwrap();
+    drop(tx);
+    let names = rx.iter().collect::<HashSet<_>>();
+    assert_eq!(names.len(), 3);
+}
+
+#[test]
+fn work_stealing_threads_shut_down() {
+    static CNT: AtomicUsize = ATOMIC_USIZE_INIT;
+    let pool = Builder::new()
+        .pool_size(2)
+        .before_stop(|| { CNT.fetch_add(1, Ordering::SeqCst); })
+        .create_work_stealing();
+    pool.spawn_fn(|| Ok::<(), ()>(())).wait().unwrap();
+    drop(pool);
+
+    for _ in 0..100 {
+        if CNT.load(Ordering::SeqCst) == 2 {
+            return
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    panic!("worker threads didn't shut down");
+}
@@ -0,0 +1,103 @@
+//! Clocks used by the combinators which wait for time to pass.
+//!
+//! Combinators such as `Stream::timeout_map` don't read the system clock
+//! directly, but go through the `Clock` of the task polling them. By default
+//! this is `SystemClock`, the system's monotonic clock, but a task can
+//! override it with `clock::set` or `clock::scope`, for example to run a
+//! time-dependent pipeline against a mock clock in tests, which only moves
+//! forward when the test advances it.
+
+use std::prelude::v1::*;
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use future::BoxFuture;
+use task_impl;
+use timer;
+
+/// A source of time, along with a way to wait for it to reach a deadline.
+pub trait Clock: Send + Sync {
+    /// Returns the current time according to this clock.
+    fn now(&self) -> Instant;
+
+    /// Returns a future which completes once this clock reaches `deadline`.
+    ///
+    /// The future must unpark the task which last polled it once the
+    /// deadline has passed.
+    fn delay(&self, deadline: Instant) -> BoxFuture<(), ()>;
+}
+
+/// The system's monotonic clock, which is the default clock for all tasks.
+///
+/// Delays created by this clock are all driven by a single background thread,
+/// started the first time a delay is polled.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn delay(&self, deadline: Instant) -> BoxFuture<(), ()> {
+        Box::new(timer::Delay::new(deadline))
+    }
+}
+
+task_local!(static CLOCK: Option<Arc<Clock>> = None);
+
+/// Sets the clock used by the current task, for the rest of its lifetime.
+///
+/// # Panics
+///
+/// This function will panic if a task is not currently being executed.
+pub fn set(clock: Arc<Clock>) {
+    CLOCK.set(Some(clock))
+}
+
+/// Runs `f` with the current task using `clock`, restoring its previous clock
+/// once `f` returns.
+///
+/// This allows a clock to be used for the polls of just one sub-future, as
+/// with `LocalKey::scope`.
+///
+/// # Panics
+///
+/// This function will panic if a task is not currently being executed.
+pub fn scope<F, R>(clock: Arc<Clock>, f: F) -> R
+    where F: FnOnce() -> R
+{
+    CLOCK.scope(Some(clock), f)
+}
+
+/// Returns the current time according to the clock of the current task.
+///
+/// Outside of a task, or if the current task hasn't set a clock, this is the
+/// time according to `SystemClock`.
+pub fn now() -> Instant {
+    with_clock(|clock| clock.now())
+}
+
+/// Returns a future which completes once the clock of the current task
+/// reaches `deadline`.
+///
+/// Outside of a task, or if the current task hasn't set a clock, this waits
+/// for `SystemClock` to reach the deadline.
+pub fn delay(deadline: Instant) -> BoxFuture<(), ()> {
+    with_clock(|clock| clock.delay(deadline))
+}
+
+fn with_clock<F, R>(f: F) -> R
+    where F: FnOnce(&Clock) -> R
+{
+    let clock = if task_impl::in_task() {
+        CLOCK.get()
+    } else {
+        None
+    };
+    match clock {
+        Some(clock) => f(&*clock),
+        None => f(&SystemClock),
+    }
+}
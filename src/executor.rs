@@ -4,4 +4,8 @@
 //! which is needed when building *executors* (places where futures can run).
 // TODO: more dox
 
-pub use task_impl::{Spawn, spawn, Unpark, Executor, Run};
+pub use task_impl::{Spawn, spawn, spawn_inheriting, spawn_with_priority, Priority, Unpark};
+
+#[cfg(feature = "use_std")]
+pub use task_impl::{LocalUnpark, Executor, Run, PollHooks, enter, Enter, block_on, CurrentThread,
+                    Remote, blocking, Blocking};
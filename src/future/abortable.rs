@@ -0,0 +1,143 @@
+use std::prelude::v1::*;
+
+use std::error::Error;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering::SeqCst;
+
+use {Future, Poll, Async};
+use task::{self, Task};
+
+/// Future for the `abortable` function, which can be aborted from outside
+/// of its task with an `AbortHandle`.
+#[must_use = "futures do nothing unless polled"]
+pub struct Abortable<F> {
+    future: F,
+    inner: Arc<Inner>,
+}
+
+/// A handle to an `Abortable` future, used to abort it.
+///
+/// This is created by the `abortable` function, and can be cloned to abort
+/// the future from several places.
+#[derive(Clone)]
+pub struct AbortHandle {
+    inner: Arc<Inner>,
+}
+
+/// Error returned from an `Abortable` future which was aborted.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Aborted;
+
+struct Inner {
+    aborted: AtomicBool,
+    // The task which last polled the future. It's stored before `aborted` is
+    // checked in `poll`, and taken after `aborted` is set in `abort`, so that
+    // either the poll sees the flag or the task is unparked.
+    task: Mutex<Option<Task>>,
+}
+
+/// Creates a future which can be aborted from outside of its task, along with
+/// the handle to abort it.
+///
+/// The returned `Abortable` future behaves like `future`, except that once
+/// `AbortHandle::abort` has been called it fails with `Aborted` the next time
+/// it's polled, without polling `future` again, and the task polling it is
+/// woken up so that this happens promptly. This can be used to cancel work
+/// from outside the task graph running it, for example when a client
+/// disconnects.
+///
+/// Because the wrapped future may itself fail, the returned future resolves
+/// to the `Result` of `future`, like `Future::catch_unwind`, and only fails if
+/// it's aborted. Aborting a future which has already completed has no effect.
+///
+/// # Examples
+///
+/// ```
+/// use futures::future::{self, Aborted, Future};
+///
+/// let (f, handle) = future::abortable(future::empty::<u32, ()>());
+/// handle.abort();
+/// assert_eq!(f.wait(), Err(Aborted));
+///
+/// let (f, _handle) = future::abortable(future::ok::<u32, ()>(3));
+/// assert_eq!(f.wait(), Ok(Ok(3)));
+/// ```
+pub fn abortable<F>(future: F) -> (Abortable<F>, AbortHandle)
+    where F: Future,
+{
+    let inner = Arc::new(Inner {
+        aborted: AtomicBool::new(false),
+        task: Mutex::new(None),
+    });
+    let handle = AbortHandle { inner: inner.clone() };
+    (Abortable { future: future, inner: inner }, handle)
+}
+
+impl<F> Abortable<F> {
+    /// Returns whether this future has been aborted.
+    pub fn is_aborted(&self) -> bool {
+        self.inner.aborted.load(SeqCst)
+    }
+}
+
+impl<F> Future for Abortable<F>
+    where F: Future,
+{
+    type Item = Result<F::Item, F::Error>;
+    type Error = Aborted;
+
+    fn poll(&mut self) -> Poll<Self::Item, Aborted> {
+        *self.inner.task.lock().unwrap() = Some(task::park());
+        if self.inner.aborted.load(SeqCst) {
+            return Err(Aborted)
+        }
+        match self.future.poll() {
+            Ok(Async::NotReady) => Ok(Async::NotReady),
+            Ok(Async::Ready(t)) => Ok(Async::Ready(Ok(t))),
+            Err(e) => Ok(Async::Ready(Err(e))),
+        }
+    }
+}
+
+impl AbortHandle {
+    /// Aborts the `Abortable` future this handle belongs to.
+    ///
+    /// The future fails with `Aborted` the next time it's polled, and the
+    /// task which last polled it is woken up. Calling this more than once has
+    /// no further effect.
+    pub fn abort(&self) {
+        self.inner.aborted.store(true, SeqCst);
+        let task = self.inner.task.lock().unwrap().take();
+        if let Some(task) = task {
+            task.unpark();
+        }
+    }
+
+    /// Returns whether `abort` has been called on this handle or one of its
+    /// clones.
+    pub fn is_aborted(&self) -> bool {
+        self.inner.aborted.load(SeqCst)
+    }
+}
+
+impl fmt::Debug for AbortHandle {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("AbortHandle")
+            .field("aborted", &self.is_aborted())
+            .finish()
+    }
+}
+
+impl fmt::Display for Aborted {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "future was aborted")
+    }
+}
+
+impl Error for Aborted {
+    fn description(&self) -> &str {
+        "future was aborted"
+    }
+}
@@ -0,0 +1,87 @@
+use std::sync::Arc;
+
+use {Future, IntoFuture, Poll};
+use executor::Executor;
+use super::chain::Chain;
+use super::with_executor::{self, WithExecutor};
+
+/// Future for the `and_then_spawn` combinator, running the continuation of a
+/// future which completes successfully on an executor.
+///
+/// This is created by the `Future::and_then_spawn` method.
+#[must_use = "futures do nothing unless polled"]
+pub struct AndThenSpawn<A, B, F>
+    where A: Future,
+          B: IntoFuture + 'static,
+          B::Future: Send + 'static,
+          B::Item: Send + 'static,
+          B::Error: Send + 'static,
+          F: FnOnce(A::Item) -> B + Send + 'static,
+          A::Item: Send + 'static,
+{
+    state: Chain<A, Spawned<A, B, F>, Pending<F>>,
+}
+
+type Spawned<A, B, F> = WithExecutor<Continuation<<A as Future>::Item, B, F>>;
+type Pending<F> = (F, Arc<Executor>);
+
+// The continuation shipped to the executor, which calls the closure there
+// before driving the future it returns.
+struct Continuation<T, B, F> where B: IntoFuture {
+    f: Option<(F, T)>,
+    future: Option<B::Future>,
+}
+
+pub fn new<A, B, F>(future: A, exec: Arc<Executor>, f: F) -> AndThenSpawn<A, B, F>
+    where A: Future,
+          B: IntoFuture + 'static,
+          B::Future: Send + 'static,
+          B::Item: Send + 'static,
+          B::Error: Send + 'static,
+          F: FnOnce(A::Item) -> B + Send + 'static,
+          A::Item: Send + 'static,
+{
+    AndThenSpawn {
+        state: Chain::new(future, (f, exec)),
+    }
+}
+
+impl<A, B, F> Future for AndThenSpawn<A, B, F>
+    where A: Future,
+          B: IntoFuture<Error=A::Error> + 'static,
+          B::Future: Send + 'static,
+          B::Item: Send + 'static,
+          B::Error: Send + 'static,
+          F: FnOnce(A::Item) -> B + Send + 'static,
+          A::Item: Send + 'static,
+{
+    type Item = B::Item;
+    type Error = B::Error;
+
+    fn poll(&mut self) -> Poll<B::Item, B::Error> {
+        self.state.poll(|result, (f, exec)| {
+            result.map(|item| {
+                let continuation = Continuation {
+                    f: Some((f, item)),
+                    future: None,
+                };
+                Err(with_executor::new(continuation, exec))
+            })
+        })
+    }
+}
+
+impl<T, B, F> Future for Continuation<T, B, F>
+    where B: IntoFuture,
+          F: FnOnce(T) -> B,
+{
+    type Item = B::Item;
+    type Error = B::Error;
+
+    fn poll(&mut self) -> Poll<B::Item, B::Error> {
+        if let Some((f, item)) = self.f.take() {
+            self.future = Some(f(item).into_future());
+        }
+        self.future.as_mut().expect("cannot poll Continuation twice").poll()
+    }
+}
@@ -3,6 +3,7 @@
 use core::marker;
 
 use {Future, Poll, Async};
+use future::FusedFuture;
 
 /// A future which is never resolved.
 ///
@@ -28,3 +29,9 @@ impl<T, E> Future for Empty<T, E> {
         Ok(Async::NotReady)
     }
 }
+
+impl<T, E> FusedFuture for Empty<T, E> {
+    fn is_terminated(&self) -> bool {
+        false
+    }
+}
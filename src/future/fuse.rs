@@ -1,4 +1,5 @@
 use {Future, Poll, Async};
+use future::FusedFuture;
 
 /// A future which "fuses" a future once it's been resolved.
 ///
@@ -18,6 +19,17 @@ pub fn new<A: Future>(f: A) -> Fuse<A> {
     }
 }
 
+impl<A: Future> Fuse<A> {
+    /// Returns whether the underlying future has finished or not.
+    ///
+    /// If this method returns `true`, then all future calls to `poll` are
+    /// guaranteed to return `Ok(Async::NotReady)`. If this returns `false`,
+    /// then the underlying future has not been driven to completion.
+    pub fn is_done(&self) -> bool {
+        self.future.is_none()
+    }
+}
+
 impl<A: Future> Future for Fuse<A> {
     type Item = A::Item;
     type Error = A::Error;
@@ -34,3 +46,9 @@ impl<A: Future> Future for Fuse<A> {
         }
     }
 }
+
+impl<A: Future> FusedFuture for Fuse<A> {
+    fn is_terminated(&self) -> bool {
+        self.is_done()
+    }
+}
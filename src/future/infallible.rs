@@ -0,0 +1,36 @@
+use core::marker::PhantomData;
+
+use {Future, Poll, Async};
+use never::Never;
+
+/// Future for the `infallible` combinator, changing the error type of a
+/// future which can't fail.
+///
+/// This is created by the `Future::infallible` method.
+#[must_use = "futures do nothing unless polled"]
+pub struct Infallible<A, E> where A: Future<Error=Never> {
+    future: A,
+    e: PhantomData<E>
+}
+
+pub fn new<A, E>(future: A) -> Infallible<A, E>
+    where A: Future<Error=Never>
+{
+    Infallible {
+        future: future,
+        e: PhantomData
+    }
+}
+
+impl<A:Future<Error=Never>, E> Future for Infallible<A, E> {
+    type Item = A::Item;
+    type Error = E;
+
+    fn poll(&mut self) -> Poll<A::Item, E> {
+        match self.future.poll() {
+            Ok(Async::NotReady) => Ok(Async::NotReady),
+            Ok(Async::Ready(item)) => Ok(Async::Ready(item)),
+            Err(never) => never.never_into(),
+        }
+    }
+}
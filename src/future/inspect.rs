@@ -0,0 +1,39 @@
+use {Future, Poll, Async};
+
+/// Future for the `inspect` combinator, observing the item of a future before
+/// passing it on.
+///
+/// This is created by the `Future::inspect` method.
+#[must_use = "futures do nothing unless polled"]
+pub struct Inspect<A, F> where A: Future {
+    future: A,
+    f: Option<F>,
+}
+
+pub fn new<A, F>(future: A, f: F) -> Inspect<A, F>
+    where A: Future,
+          F: FnOnce(&A::Item),
+{
+    Inspect {
+        future: future,
+        f: Some(f),
+    }
+}
+
+impl<A, F> Future for Inspect<A, F>
+    where A: Future,
+          F: FnOnce(&A::Item),
+{
+    type Item = A::Item;
+    type Error = A::Error;
+
+    fn poll(&mut self) -> Poll<A::Item, A::Error> {
+        match try!(self.future.poll()) {
+            Async::NotReady => Ok(Async::NotReady),
+            Async::Ready(e) => {
+                (self.f.take().expect("cannot poll Inspect twice"))(&e);
+                Ok(Async::Ready(e))
+            }
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use {Future, Poll};
+
+/// Future for the `inspect_err` combinator, observing the error of a future
+/// before passing it on.
+///
+/// This is created by the `Future::inspect_err` method.
+#[must_use = "futures do nothing unless polled"]
+pub struct InspectErr<A, F> where A: Future {
+    future: A,
+    f: Option<F>,
+}
+
+pub fn new<A, F>(future: A, f: F) -> InspectErr<A, F>
+    where A: Future,
+          F: FnOnce(&A::Error),
+{
+    InspectErr {
+        future: future,
+        f: Some(f),
+    }
+}
+
+impl<A, F> Future for InspectErr<A, F>
+    where A: Future,
+          F: FnOnce(&A::Error),
+{
+    type Item = A::Item;
+    type Error = A::Error;
+
+    fn poll(&mut self) -> Poll<A::Item, A::Error> {
+        let res = self.future.poll();
+        if let Err(ref e) = res {
+            (self.f.take().expect("cannot poll InspectErr twice"))(e);
+        }
+        res
+    }
+}
@@ -33,6 +33,12 @@ pub struct JoinAll<I>
 /// returned immediately. If all futures complete successfully, however, then
 /// the returned future will succeed with a `Vec` of all the successful results.
 ///
+/// Cancellation happens as soon as the first error is seen: the remaining
+/// futures, along with any results already collected, are dropped before the
+/// error is returned rather than when the `JoinAll` itself is dropped. To
+/// instead let every future run to completion and collect all of the errors,
+/// see `try_join_all`.
+///
 /// # Examples
 ///
 /// ```
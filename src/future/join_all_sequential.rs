@@ -0,0 +1,94 @@
+//! Definition of the JoinAllSequential combinator, running a list of futures
+//! one at a time.
+
+use std::prelude::v1::*;
+
+use std::mem;
+
+use {Future, IntoFuture, Poll, Async};
+
+/// A future which takes a list of futures and runs them one after another,
+/// resolving with a vector of the completed values.
+///
+/// This future is created with the `join_all_sequential` method.
+#[must_use = "futures do nothing unless polled"]
+pub struct JoinAllSequential<I>
+    where I: IntoIterator,
+          I::Item: IntoFuture,
+{
+    iter: I::IntoIter,
+    current: Option<<I::Item as IntoFuture>::Future>,
+    results: Vec<<I::Item as IntoFuture>::Item>,
+}
+
+/// Creates a future which runs the futures given one at a time, collecting
+/// their results.
+///
+/// Unlike `join_all`, which drives all of its futures concurrently, each
+/// future is run to completion before the next one is taken from the
+/// iterator and converted into a future, so at most one of them is in
+/// progress at any time. This is useful when the futures share a resource
+/// which can only serve one request at a time, such as a single connection
+/// or a rate-limited API.
+///
+/// If any future returns an error then the remaining futures are never
+/// created and the error is returned immediately. If all futures complete
+/// successfully then the returned future succeeds with a `Vec` of the
+/// results, in the same order as the futures were given.
+///
+/// # Examples
+///
+/// ```
+/// use futures::future::*;
+///
+/// let f = join_all_sequential((1..4).map(|i| {
+///     // Created only once the previous future has finished
+///     lazy(move || Ok::<u32, ()>(i * 10))
+/// }));
+/// assert_eq!(f.wait(), Ok(vec![10, 20, 30]));
+/// ```
+pub fn join_all_sequential<I>(i: I) -> JoinAllSequential<I>
+    where I: IntoIterator,
+          I::Item: IntoFuture,
+{
+    JoinAllSequential {
+        iter: i.into_iter(),
+        current: None,
+        results: Vec::new(),
+    }
+}
+
+impl<I> Future for JoinAllSequential<I>
+    where I: IntoIterator,
+          I::Item: IntoFuture,
+{
+    type Item = Vec<<I::Item as IntoFuture>::Item>;
+    type Error = <I::Item as IntoFuture>::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        loop {
+            if self.current.is_none() {
+                match self.iter.next() {
+                    Some(f) => self.current = Some(f.into_future()),
+                    None => {
+                        let results = mem::replace(&mut self.results, Vec::new());
+                        return Ok(Async::Ready(results))
+                    }
+                }
+            }
+
+            let item = match self.current.as_mut().unwrap().poll() {
+                Ok(Async::Ready(item)) => item,
+                Ok(Async::NotReady) => return Ok(Async::NotReady),
+                Err(e) => {
+                    // On completion drop all our associated resources ASAP.
+                    self.current = None;
+                    self.results = Vec::new();
+                    return Err(e)
+                }
+            };
+            self.current = None;
+            self.results.push(item);
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use core::marker::PhantomData;
+
+use {Future, Poll, Async};
+
+/// Future for the `map_into` combinator, changing the item type of a future.
+///
+/// This is created by the `Future::map_into` method.
+#[must_use = "futures do nothing unless polled"]
+pub struct MapInto<A, U> where A: Future {
+    future: A,
+    f: PhantomData<U>
+}
+
+pub fn new<A, U>(future: A) -> MapInto<A, U>
+    where A: Future
+{
+    MapInto {
+        future: future,
+        f: PhantomData
+    }
+}
+
+impl<A:Future, U:From<A::Item>> Future for MapInto<A, U> {
+    type Item = U;
+    type Error = A::Error;
+
+    fn poll(&mut self) -> Poll<U, A::Error> {
+        match try!(self.future.poll()) {
+            Async::NotReady => Ok(Async::NotReady),
+            Async::Ready(item) => Ok(Async::Ready(From::from(item))),
+        }
+    }
+}
@@ -8,11 +8,13 @@ use core::result;
 // Primitive futures
 mod empty;
 mod lazy;
+mod loop_fn;
 mod poll_fn;
 #[path = "result.rs"]
 mod result_;
 pub use self::empty::{empty, Empty};
 pub use self::lazy::{lazy, Lazy};
+pub use self::loop_fn::{loop_fn, Loop, LoopFn};
 pub use self::poll_fn::{poll_fn, PollFn};
 pub use self::result_::{result, ok, err, FutureResult};
 
@@ -42,13 +44,22 @@ mod and_then;
 mod flatten;
 mod flatten_stream;
 mod fuse;
+mod inspect;
+mod inspect_err;
+mod infallible;
 mod into_stream;
 mod join;
 mod map;
 mod map_err;
+mod map_into;
+mod never_error;
 mod from_err;
+mod on_cancel;
+mod or;
 mod or_else;
+mod race;
 mod select;
+mod select2;
 mod then;
 mod either;
 
@@ -59,27 +70,56 @@ pub use self::and_then::AndThen;
 pub use self::flatten::Flatten;
 pub use self::flatten_stream::FlattenStream;
 pub use self::fuse::Fuse;
+pub use self::inspect::Inspect;
+pub use self::inspect_err::InspectErr;
+pub use self::infallible::Infallible;
 pub use self::into_stream::IntoStream;
 pub use self::join::{Join, Join3, Join4, Join5};
 pub use self::map::Map;
 pub use self::map_err::MapErr;
+pub use self::map_into::MapInto;
+pub use self::never_error::NeverError;
 pub use self::from_err::FromErr;
+pub use self::on_cancel::OnCancel;
+pub use self::or::Or;
 pub use self::or_else::OrElse;
+pub use self::race::Race;
 pub use self::select::{Select, SelectNext};
+pub use self::select2::Select2;
 pub use self::then::Then;
 pub use self::either::Either;
 
 if_std! {
+    mod abortable;
+    mod and_then_spawn;
     mod catch_unwind;
     mod join_all;
+    mod join_all_sequential;
+    mod pending_profile;
+    mod remote_handle;
+    mod retry;
     mod select_all;
     mod select_ok;
     mod shared;
+    mod try_join_all;
+    mod with_executor;
+    mod with_locals;
+    mod yield_every;
+    pub use self::abortable::{abortable, Abortable, AbortHandle, Aborted};
+    pub use self::and_then_spawn::AndThenSpawn;
     pub use self::catch_unwind::CatchUnwind;
     pub use self::join_all::{join_all, JoinAll};
-    pub use self::select_all::{SelectAll, SelectAllNext, select_all};
+    pub use self::join_all_sequential::{join_all_sequential, JoinAllSequential};
+    pub use self::pending_profile::{PendingProfile, Profile};
+    pub use self::remote_handle::{Remote, RemoteHandle};
+    pub use self::retry::{retry, Retry};
+    pub use self::select_all::{SelectAll, SelectAllNext, select_all, select_biased_all};
     pub use self::select_ok::{SelectOk, select_ok};
-    pub use self::shared::Shared;
+    pub use self::shared::{Shared, WeakShared};
+    pub use self::try_join_all::{try_join_all, TryJoinAll, JoinErrors};
+    pub use self::with_executor::WithExecutor;
+    pub use self::with_locals::WithLocals;
+    pub use self::yield_every::{yield_every, YieldEvery};
 
     #[doc(hidden)]
     #[deprecated(since = "0.1.4", note = "use join_all instead")]
@@ -93,6 +133,9 @@ if_std! {
     /// A type alias for `Box<Future + Send>`
     pub type BoxFuture<T, E> = ::std::boxed::Box<Future<Item = T, Error = E> + Send>;
 
+    /// A type alias for `Box<Future>`, for futures which aren't `Send`
+    pub type LocalBoxFuture<T, E> = ::std::boxed::Box<Future<Item = T, Error = E>>;
+
     impl<F: ?Sized + Future> Future for ::std::boxed::Box<F> {
         type Item = F::Item;
         type Error = F::Error;
@@ -101,6 +144,12 @@ if_std! {
             (**self).poll()
         }
     }
+
+    impl<F: ?Sized + FusedFuture> FusedFuture for ::std::boxed::Box<F> {
+        fn is_terminated(&self) -> bool {
+            (**self).is_terminated()
+        }
+    }
 }
 
 use {Poll, stream};
@@ -254,13 +303,41 @@ pub trait Future {
         ::executor::spawn(self).wait_future()
     }
 
+    /// Polls this future once, returning its result if it's already complete.
+    ///
+    /// This is useful for futures which are expected to be ready straight
+    /// away, such as a cached value or a channel which has been closed, as it
+    /// extracts the result without blocking. The future is polled within a
+    /// fresh task whose notifications are ignored, and if it isn't ready then
+    /// it's dropped and `None` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::future::*;
+    ///
+    /// assert_eq!(ok::<u32, ()>(1).now_or_never(), Some(Ok(1)));
+    /// assert_eq!(err::<(), u32>(2).now_or_never(), Some(Err(2)));
+    /// assert_eq!(empty::<(), ()>().now_or_never(), None);
+    /// ```
+    #[cfg(feature = "use_std")]
+    fn now_or_never(self) -> Option<result::Result<Self::Item, Self::Error>>
+        where Self: Sized
+    {
+        match ::executor::spawn(self).poll_future(::task_impl::noop_unpark()) {
+            Ok(::Async::Ready(item)) => Some(Ok(item)),
+            Ok(::Async::NotReady) => None,
+            Err(e) => Some(Err(e)),
+        }
+    }
+
     /// Convenience function for turning this future into a trait object.
     ///
     /// This simply avoids the need to write `Box::new` and can often help with
     /// type inference as well by always returning a trait object. Note that
     /// this method requires the `Send` bound and returns a `BoxFuture`, which
     /// also encodes this. If you'd like to create a `Box<Future>` without the
-    /// `Send` bound, then the `Box::new` function can be used instead.
+    /// `Send` bound, then the `boxed_local` method can be used instead.
     ///
     /// # Examples
     ///
@@ -276,6 +353,31 @@ pub trait Future {
         ::std::boxed::Box::new(self)
     }
 
+    /// Convenience function for turning this future into a trait object
+    /// which isn't `Send`.
+    ///
+    /// This is the same as `boxed`, except that it doesn't require the future
+    /// to be `Send`, so it can be used to type-erase futures which hold
+    /// thread-local state such as an `Rc`. The returned `LocalBoxFuture` can
+    /// then only be polled on the thread that created it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    /// use futures::future::*;
+    ///
+    /// let shared = Rc::new(1);
+    /// let a: LocalBoxFuture<i32, i32> = ok(shared).map(|s| *s + 1).boxed_local();
+    /// assert_eq!(a.wait(), Ok(2));
+    /// ```
+    #[cfg(feature = "use_std")]
+    fn boxed_local(self) -> LocalBoxFuture<Self::Item, Self::Error>
+        where Self: Sized + 'static
+    {
+        ::std::boxed::Box::new(self)
+    }
+
     /// Map this future's result to a different type, returning a new future of
     /// the resulting type.
     ///
@@ -335,6 +437,85 @@ pub trait Future {
         assert_future::<Self::Item, E, _>(map_err::new(self, f))
     }
 
+    /// Do something with the item of a future, passing it on.
+    ///
+    /// When using futures, you'll often chain several of them together.
+    /// While working on such code, you might want to check out what's
+    /// happening to the data at various parts in the pipeline. To do that,
+    /// insert a call to `inspect`, which calls `f` with a reference to the
+    /// item once this future resolves, and then resolves to the item itself,
+    /// untouched.
+    ///
+    /// The closure provided will only be called if this future is resolved
+    /// successfully.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::future::*;
+    ///
+    /// let future = ok::<u32, u32>(1);
+    /// let new_future = future.inspect(|&x| println!("about to resolve: {}", x));
+    /// assert_eq!(new_future.wait(), Ok(1));
+    /// ```
+    fn inspect<F>(self, f: F) -> Inspect<Self, F>
+        where F: FnOnce(&Self::Item),
+              Self: Sized,
+    {
+        assert_future::<Self::Item, Self::Error, _>(inspect::new(self, f))
+    }
+
+    /// Do something with the error of a future, passing it on.
+    ///
+    /// This is the counterpart of `inspect` for errors: `f` is called with a
+    /// reference to the error if this future fails, and the returned future
+    /// then fails with the error itself, untouched.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::future::*;
+    ///
+    /// let future = err::<u32, u32>(1);
+    /// let new_future = future.inspect_err(|&e| println!("about to fail: {}", e));
+    /// assert_eq!(new_future.wait(), Err(1));
+    /// ```
+    fn inspect_err<F>(self, f: F) -> InspectErr<Self, F>
+        where F: FnOnce(&Self::Error),
+              Self: Sized,
+    {
+        assert_future::<Self::Item, Self::Error, _>(inspect_err::new(self, f))
+    }
+
+    /// Run a closure if this future is dropped before it completes.
+    ///
+    /// The returned future resolves exactly as this one does. If it is
+    /// dropped while still pending, for example because it lost a `select`,
+    /// `f` is called once as it is dropped. Once this future has resolved,
+    /// either successfully or with an error, `f` is dropped without being
+    /// called.
+    ///
+    /// This is useful to release resources tied to an operation, such as a
+    /// reservation, when the operation is abandoned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::future::*;
+    ///
+    /// let future = empty::<u32, u32>().on_cancel(|| println!("canceled"));
+    /// drop(future); // prints "canceled"
+    ///
+    /// let future = ok::<u32, u32>(1).on_cancel(|| unreachable!());
+    /// assert_eq!(future.wait(), Ok(1));
+    /// ```
+    fn on_cancel<F>(self, f: F) -> OnCancel<Self, F>
+        where F: FnOnce(),
+              Self: Sized,
+    {
+        assert_future::<Self::Item, Self::Error, _>(on_cancel::new(self, f))
+    }
+
 
 
     /// Map this future's error to any error implementing `From` for
@@ -349,6 +530,10 @@ pub trait Future {
     /// Note that this function consumes the receiving future and returns a
     /// wrapped version of it.
     ///
+    /// The first type parameter, `F`, is unused and only kept for
+    /// compatibility. When the error type needs to be named, `err_into`
+    /// takes it as its only type parameter.
+    ///
     /// # Examples
     ///
     /// ```
@@ -363,6 +548,99 @@ pub trait Future {
         assert_future::<Self::Item, E, _>(from_err::new(self))
     }
 
+    /// Map this future's error to any error implementing `From` for this
+    /// future's `Error`, returning a new future.
+    ///
+    /// This is the same as `from_err`, named to pair with `map_into`, except
+    /// that the error type is its only type parameter, so it can be written
+    /// as `err_into::<E>()` rather than `from_err::<_, E>()`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::future::*;
+    ///
+    /// let future_of_err_1 = err::<u32, u32>(1).err_into::<u64>();
+    /// assert_eq!(future_of_err_1.wait(), Err(1u64));
+    /// ```
+    fn err_into<E:From<Self::Error>>(self) -> FromErr<Self, E>
+        where Self: Sized,
+    {
+        assert_future::<Self::Item, E, _>(from_err::new(self))
+    }
+
+    /// Map this future's item to any type implementing `From` for this
+    /// future's `Item`, returning a new future.
+    ///
+    /// This is equivalent to `map(Into::into)`, but names the conversion,
+    /// which lets the target type be given with a turbofish rather than
+    /// leaving it to inference.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::future::*;
+    ///
+    /// let future_of_1 = ok::<u32, u32>(1).map_into::<u64>();
+    /// assert_eq!(future_of_1.wait(), Ok(1u64));
+    /// ```
+    fn map_into<U:From<Self::Item>>(self) -> MapInto<Self, U>
+        where Self: Sized,
+    {
+        assert_future::<U, Self::Error, _>(map_into::new(self))
+    }
+
+    /// Changes the error type of a future which can't fail to any other
+    /// error type.
+    ///
+    /// As a future with an error type of `Never` can't actually fail, its
+    /// error can be treated as being of any type. This allows infallible
+    /// futures to be combined with fallible ones, for example with `join` or
+    /// `select`, without `map_err` calls producing dummy errors.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::Never;
+    /// use futures::future::*;
+    ///
+    /// let cached = ok::<u32, Never>(1);
+    /// let fetched = ok::<u32, String>(2);
+    /// let both = cached.infallible().join(fetched);
+    /// assert_eq!(both.wait(), Ok((1, 2)));
+    /// ```
+    fn infallible<E>(self) -> Infallible<Self, E>
+        where Self: Future<Error = ::never::Never> + Sized,
+    {
+        assert_future::<Self::Item, E, _>(infallible::new(self))
+    }
+
+    /// Turns this future into one which can't fail, resolving with the
+    /// `Result` of this future instead.
+    ///
+    /// The returned future has an error type of `Never`, so it can then be
+    /// combined with futures of any error type through `infallible`. This is
+    /// useful when a failure should be handled as a value further on rather
+    /// than short-circuiting, such as when collecting the outcome of every
+    /// future in a `join`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::future::*;
+    ///
+    /// let f = err::<u32, &str>("failed").never_error();
+    /// let g = ok::<u32, ()>(2);
+    /// let both = f.infallible().join(g);
+    /// assert_eq!(both.wait(), Ok((Err("failed"), 2)));
+    /// ```
+    fn never_error(self) -> NeverError<Self>
+        where Self: Sized,
+    {
+        assert_future::<result::Result<Self::Item, Self::Error>, ::never::Never, _>(
+            never_error::new(self))
+    }
+
     /// Chain on a computation for when a future finished, passing the result of
     /// the future to the provided closure `f`.
     ///
@@ -488,6 +766,35 @@ pub trait Future {
         assert_future::<Self::Item, B::Error, _>(or_else::new(self, f))
     }
 
+    /// Falls back to another future if this one resolves with an error.
+    ///
+    /// Return a future that passes along this future's value if it succeeds,
+    /// and otherwise discards the error and waits for `fallback` instead. The
+    /// `fallback` future isn't polled at all unless this future fails, so no
+    /// work is started for it ahead of time; if this future succeeds then
+    /// `fallback` is simply dropped. Use `or_else` instead if the fallback
+    /// depends on the error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::future::*;
+    ///
+    /// let primary = err::<u32, &str>("unavailable");
+    /// let fallback = ok::<u32, &str>(2);
+    /// assert_eq!(primary.or(fallback).wait(), Ok(2));
+    ///
+    /// let primary = ok::<u32, &str>(1);
+    /// let fallback = lazy(|| -> Result<u32, &str> { panic!("not polled") });
+    /// assert_eq!(primary.or(fallback).wait(), Ok(1));
+    /// ```
+    fn or<B>(self, fallback: B) -> Or<Self, B::Future>
+        where B: IntoFuture<Item = Self::Item>,
+              Self: Sized,
+    {
+        assert_future::<Self::Item, B::Error, _>(or::new(self, fallback.into_future()))
+    }
+
     /// Waits for either one of two futures to complete.
     ///
     /// This function will return a new future which awaits for either this or
@@ -525,6 +832,98 @@ pub trait Future {
                         (Self::Error, SelectNext<Self, B::Future>), _>(f)
     }
 
+    /// Waits for either one of two futures to complete, canceling the other.
+    ///
+    /// The returned future resolves with the result of whichever of this or
+    /// the `other` future completes first, whether it succeeded or failed.
+    /// The other future is dropped as soon as there's a winner, which cancels
+    /// any work it represents. This makes it easy to, for example, hedge a
+    /// request by sending it to two servers and using whichever reply arrives
+    /// first. Use `select` instead if the losing future is still needed.
+    ///
+    /// If both futures are ready at the same time then this future's result
+    /// is the one returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::future::*;
+    ///
+    /// let fast = ok::<u32, u32>(1);
+    /// let slow = empty::<u32, u32>();
+    /// assert_eq!(fast.race(slow).wait(), Ok(1));
+    /// ```
+    fn race<B>(self, other: B) -> Race<Self, B::Future>
+        where B: IntoFuture<Item=Self::Item, Error=Self::Error>,
+              Self: Sized,
+    {
+        let f = race::new(self, other.into_future());
+        assert_future::<Self::Item, Self::Error, _>(f)
+    }
+
+    /// Waits for either one of two futures to complete, giving priority to
+    /// this future.
+    ///
+    /// This behaves exactly like `select`, but additionally guarantees that
+    /// this future is polled before the `other` future on every wakeup, so if
+    /// both are ready at the same time then this future's result is the one
+    /// returned. This is useful when one side must take precedence, such as
+    /// a shutdown signal over the work it cancels.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::future::*;
+    ///
+    /// let shutdown = ok::<&str, ()>("shutdown");
+    /// let work = ok::<&str, ()>("work");
+    /// match shutdown.select_biased(work).wait() {
+    ///     Ok((winner, _)) => assert_eq!(winner, "shutdown"),
+    ///     Err(_) => panic!(),
+    /// }
+    /// ```
+    fn select_biased<B>(self, other: B) -> Select<Self, B::Future>
+        where B: IntoFuture<Item=Self::Item, Error=Self::Error>,
+              Self: Sized,
+    {
+        // `Select` already polls its first future first; this method commits
+        // to that order as part of its contract.
+        self.select(other)
+    }
+
+    /// Waits for either one of two differently-typed futures to complete.
+    ///
+    /// This function will return a new future which awaits for either this or
+    /// the `other` future to complete. Unlike `select`, the two futures may
+    /// have different item and error types, so the returned future resolves
+    /// to an `Either`: `Either::A` with the result of this future along with
+    /// the `other` future if this one finished first, or `Either::B` with the
+    /// result of `other` along with this future. Errors are reported the same
+    /// way.
+    ///
+    /// Note that this function consumes the receiving futures and returns a
+    /// wrapped version of them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::future::*;
+    ///
+    /// let number = ok::<u32, ()>(1);
+    /// let name = empty::<&str, String>();
+    ///
+    /// match number.select2(name).wait() {
+    ///     Ok(Either::A((n, _name))) => assert_eq!(n, 1),
+    ///     _ => panic!(),
+    /// }
+    /// ```
+    fn select2<B>(self, other: B) -> Select2<Self, B::Future>
+        where B: IntoFuture,
+              Self: Sized,
+    {
+        select2::new(self, other.into_future())
+    }
+
     /// Joins the result of two futures, waiting for them both to complete.
     ///
     /// This function will return a new future which awaits both this and the
@@ -807,6 +1206,222 @@ pub trait Future {
     {
         Shared::new(self)
     }
+
+    /// Splits this future into a `Remote` future which drives it, and a
+    /// `RemoteHandle` which resolves to its result.
+    ///
+    /// The `Remote` half has an item and error type of `()`, so it can be
+    /// spawned onto an executor, for example with `Spawn::execute`, while the
+    /// `RemoteHandle` is kept to receive the result back without wiring up a
+    /// oneshot channel by hand. If this future panics then the panic is
+    /// caught by the `Remote` and resumed when the `RemoteHandle` is polled.
+    ///
+    /// Dropping the `RemoteHandle` cancels this future the next time the
+    /// `Remote` is polled, unless `RemoteHandle::forget` is used instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    /// use std::thread;
+    /// use futures::future::*;
+    /// use futures::executor::{self, Executor, Run};
+    ///
+    /// struct ThreadPerRun;
+    ///
+    /// impl Executor for ThreadPerRun {
+    ///     fn execute(&self, r: Run) {
+    ///         thread::spawn(move || r.run());
+    ///     }
+    /// }
+    ///
+    /// let (remote, handle) = lazy(|| Ok::<u32, ()>(1 + 2)).remote_handle();
+    /// executor::spawn(remote).execute(Arc::new(ThreadPerRun));
+    /// assert_eq!(handle.wait(), Ok(3));
+    /// ```
+    #[cfg(feature = "use_std")]
+    fn remote_handle(self) -> (Remote<Self>, RemoteHandle<Self::Item, Self::Error>)
+        where Self: Sized
+    {
+        remote_handle::new(self)
+    }
+
+    /// Moves this future onto the executor `exec` when it's first polled,
+    /// resolving to the same value once it completes there.
+    ///
+    /// The returned future doesn't do any of the work of the original future
+    /// itself; instead it submits it to `exec` and waits for the result to be
+    /// sent back. This allows a latency-sensitive task to offload an expensive
+    /// part of its computation to another executor, such as a thread pool,
+    /// without otherwise changing the shape of the pipeline.
+    ///
+    /// If the original future panics then the panic is propagated to the
+    /// returned future, and if the returned future is dropped the original
+    /// future is dropped the next time the executor polls it.
+    ///
+    /// # Panics
+    ///
+    /// The returned future will panic if the executor drops the original
+    /// future without running it to completion.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    /// use std::thread;
+    /// use futures::future::*;
+    /// use futures::executor::{Executor, Run};
+    ///
+    /// struct ThreadPerRun;
+    ///
+    /// impl Executor for ThreadPerRun {
+    ///     fn execute(&self, r: Run) {
+    ///         thread::Builder::new()
+    ///             .name("offloaded".to_string())
+    ///             .spawn(move || r.run())
+    ///             .unwrap();
+    ///     }
+    /// }
+    ///
+    /// let offloaded = lazy(|| ok::<_, ()>(thread::current().name() == Some("offloaded")))
+    ///     .with_executor(Arc::new(ThreadPerRun));
+    /// assert!(offloaded.wait().unwrap());
+    /// ```
+    #[cfg(feature = "use_std")]
+    fn with_executor(self, exec: ::std::sync::Arc<::executor::Executor>) -> WithExecutor<Self>
+        where Self: Sized + Send + 'static,
+              Self::Item: Send + 'static,
+              Self::Error: Send + 'static,
+    {
+        with_executor::new(self, exec)
+    }
+
+    /// Attaches a set of task-local values to this future, regardless of
+    /// which task ends up polling it.
+    ///
+    /// Every time the returned future is polled the values in `map` are
+    /// installed into the current task, shadowing any values the task has for
+    /// the same keys, and once the poll returns they're taken back out and the
+    /// task's own values restored. Changes made to the values during a poll
+    /// are kept for the next one. This allows contextual data such as a trace
+    /// ID or a deadline to follow a particular sub-future, rather than the
+    /// task it happens to run in.
+    ///
+    /// The values are dropped along with the returned future.
+    ///
+    /// # Panics
+    ///
+    /// Polling the returned future panics if any of the keys in `map` are
+    /// currently borrowed through `LocalKey::with` or `LocalKey::with_mut`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// #[macro_use]
+    /// extern crate futures;
+    ///
+    /// use futures::future::*;
+    /// use futures::task::LocalMap;
+    ///
+    /// task_local!(static TRACE_ID: u64 = 0);
+    ///
+    /// # fn main() {
+    /// let mut locals = LocalMap::new();
+    /// locals.insert(&TRACE_ID, 7);
+    ///
+    /// let traced = lazy(|| Ok::<_, ()>(TRACE_ID.with(|id| *id))).with_locals(locals);
+    /// let f = traced.map(|inner| (inner, TRACE_ID.with(|id| *id)));
+    /// assert_eq!(f.wait(), Ok((7, 0)));
+    /// # }
+    /// ```
+    #[cfg(feature = "use_std")]
+    fn with_locals(self, map: ::task::LocalMap) -> WithLocals<Self>
+        where Self: Sized
+    {
+        with_locals::new(self, map)
+    }
+
+    /// Execute another future after this one has resolved successfully,
+    /// running it on the executor `exec`.
+    ///
+    /// This is like `and_then`, except that once this future resolves the
+    /// closure `f` is shipped to `exec` along with the item, and both the call
+    /// to `f` and the future it returns run there. The returned future
+    /// resolves with that future's result once it's sent back. This keeps
+    /// CPU-heavy continuations of an I/O future, such as parsing a response,
+    /// from blocking the event loop which drives the I/O.
+    ///
+    /// Panics in the continuation are propagated to the returned future, as
+    /// with `with_executor`. If this future fails then `f` is never called.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    /// use std::thread;
+    /// use futures::future::*;
+    /// use futures::executor::{Executor, Run};
+    ///
+    /// struct ThreadPerRun;
+    ///
+    /// impl Executor for ThreadPerRun {
+    ///     fn execute(&self, r: Run) {
+    ///         thread::Builder::new()
+    ///             .name("offloaded".to_string())
+    ///             .spawn(move || r.run())
+    ///             .unwrap();
+    ///     }
+    /// }
+    ///
+    /// let f = ok::<_, ()>("1 2 3").and_then_spawn(Arc::new(ThreadPerRun), |s| {
+    ///     assert_eq!(thread::current().name(), Some("offloaded"));
+    ///     Ok(s.split(' ').map(|n| n.parse::<u32>().unwrap()).fold(0, |a, b| a + b))
+    /// });
+    /// assert_eq!(f.wait(), Ok(6));
+    /// ```
+    #[cfg(feature = "use_std")]
+    fn and_then_spawn<F, B>(self, exec: ::std::sync::Arc<::executor::Executor>, f: F)
+                            -> AndThenSpawn<Self, B, F>
+        where F: FnOnce(Self::Item) -> B + Send + 'static,
+              B: IntoFuture<Error = Self::Error> + 'static,
+              B::Future: Send + 'static,
+              B::Item: Send + 'static,
+              B::Error: Send + 'static,
+              Self::Item: Send + 'static,
+              Self: Sized,
+    {
+        assert_future::<B::Item, Self::Error, _>(and_then_spawn::new(self, exec, f))
+    }
+
+    /// Records how often this future is woken up and polled without making
+    /// progress, printing a summary to standard error once it completes.
+    ///
+    /// This is a diagnostic tool for tracking down storms of spurious
+    /// wakeups. Each poll of the returned future records whether the task was
+    /// unparked on this future's behalf since the previous poll, and whether
+    /// the poll made progress. Wakeups are also attributed to the sources
+    /// this future waits on, by the identifiers of the unpark events and
+    /// routes it registers (see `Profile::wakeup_sources`). The statistics
+    /// are printed, prefixed by `name`, when the future resolves or fails,
+    /// and can also be inspected at any time through `PendingProfile::profile`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::future::*;
+    ///
+    /// // Prints "config load: 1 polls (0 without progress, 0 without a
+    /// // wakeup), 0 wakeups"
+    /// let f = ok::<u32, ()>(1).pending_profile("config load");
+    /// assert_eq!(f.wait(), Ok(1));
+    /// ```
+    #[cfg(feature = "use_std")]
+    fn pending_profile<S>(self, name: S) -> PendingProfile<Self>
+        where S: Into<::std::string::String>,
+              Self: Sized
+    {
+        pending_profile::new(self, name.into())
+    }
 }
 
 impl<'a, F: ?Sized + Future> Future for &'a mut F {
@@ -818,6 +1433,29 @@ impl<'a, F: ?Sized + Future> Future for &'a mut F {
     }
 }
 
+/// A future which knows whether it has already completed.
+///
+/// Most futures will panic or behave unpredictably if they're polled again
+/// after they've resolved, which is easy to get wrong when a set of futures is
+/// polled in a loop, only some of which complete on each pass. A future
+/// implementing this trait can instead be asked with `is_terminated` whether
+/// it's finished, so that code which polls many futures can skip the ones
+/// which are done. Requiring `FusedFuture` rather than `Future` in such code
+/// turns polling after completion into a compile time error for futures which
+/// can't track it, and any future can be made to implement it with
+/// `Future::fuse`.
+pub trait FusedFuture: Future {
+    /// Returns `true` if this future has already completed, in which case it
+    /// should no longer be polled.
+    fn is_terminated(&self) -> bool;
+}
+
+impl<'a, F: ?Sized + FusedFuture> FusedFuture for &'a mut F {
+    fn is_terminated(&self) -> bool {
+        (**self).is_terminated()
+    }
+}
+
 // Just a helper function to ensure the futures we're returning all have the
 // right implementations.
 fn assert_future<A, B, F>(t: F) -> F
@@ -863,6 +1501,36 @@ impl<T, E> IntoFuture for result::Result<T, E> {
     }
 }
 
+/// An `Option` converts into a future which resolves to the contained value,
+/// or fails with `()` if there is none.
+///
+/// This allows closures passed to combinators such as `and_then` to return an
+/// `Option` directly when the error type of the chain is `()`. For other error
+/// types the option can be converted with `ok_or` first, as a `Result` also
+/// implements `IntoFuture`.
+///
+/// # Examples
+///
+/// ```
+/// use futures::future::{self, Future};
+///
+/// let words = vec!["one", "two"];
+/// let second = future::ok::<usize, ()>(1).and_then(|i| words.get(i).cloned());
+/// assert_eq!(second.wait(), Ok("two"));
+///
+/// let third = future::ok::<usize, ()>(2).and_then(|i| words.get(i).cloned());
+/// assert_eq!(third.wait(), Err(()));
+/// ```
+impl<T> IntoFuture for Option<T> {
+    type Future = FutureResult<T, ()>;
+    type Item = T;
+    type Error = ();
+
+    fn into_future(self) -> FutureResult<T, ()> {
+        result(self.ok_or(()))
+    }
+}
+
 /// Asynchronous conversion from a type `T`.
 ///
 /// This trait is analogous to `std::convert::From`, adapted to asynchronous
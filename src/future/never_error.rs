@@ -0,0 +1,32 @@
+use {Future, Poll, Async};
+use never::Never;
+
+/// Future for the `never_error` combinator, turning a future into one which
+/// can't fail by resolving with its `Result`.
+///
+/// This is created by the `Future::never_error` method.
+#[must_use = "futures do nothing unless polled"]
+pub struct NeverError<A> where A: Future {
+    future: A,
+}
+
+pub fn new<A>(future: A) -> NeverError<A>
+    where A: Future
+{
+    NeverError {
+        future: future,
+    }
+}
+
+impl<A: Future> Future for NeverError<A> {
+    type Item = Result<A::Item, A::Error>;
+    type Error = Never;
+
+    fn poll(&mut self) -> Poll<Result<A::Item, A::Error>, Never> {
+        match self.future.poll() {
+            Ok(Async::NotReady) => Ok(Async::NotReady),
+            Ok(Async::Ready(item)) => Ok(Async::Ready(Ok(item))),
+            Err(e) => Ok(Async::Ready(Err(e))),
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use {Future, Poll, Async};
+
+/// Future for the `on_cancel` combinator, running a closure if a future is
+/// dropped before it completes.
+///
+/// This is created by the `Future::on_cancel` method.
+#[must_use = "futures do nothing unless polled"]
+pub struct OnCancel<A, F> where A: Future, F: FnOnce() {
+    future: A,
+    f: Option<F>,
+}
+
+pub fn new<A, F>(future: A, f: F) -> OnCancel<A, F>
+    where A: Future,
+          F: FnOnce(),
+{
+    OnCancel {
+        future: future,
+        f: Some(f),
+    }
+}
+
+impl<A, F> Future for OnCancel<A, F>
+    where A: Future,
+          F: FnOnce(),
+{
+    type Item = A::Item;
+    type Error = A::Error;
+
+    fn poll(&mut self) -> Poll<A::Item, A::Error> {
+        let res = self.future.poll();
+        match res {
+            Ok(Async::NotReady) => {}
+            Ok(Async::Ready(_)) | Err(_) => self.f = None,
+        }
+        res
+    }
+}
+
+impl<A, F> Drop for OnCancel<A, F>
+    where A: Future,
+          F: FnOnce(),
+{
+    fn drop(&mut self) {
+        if let Some(f) = self.f.take() {
+            f();
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use {Future, Poll};
+use super::chain::Chain;
+
+/// Future for the `or` combinator, falling back to a second future if the
+/// first one fails.
+///
+/// This is created by the `Future::or` method.
+#[must_use = "futures do nothing unless polled"]
+pub struct Or<A, B> where A: Future, B: Future {
+    state: Chain<A, B, B>,
+}
+
+pub fn new<A, B>(future: A, fallback: B) -> Or<A, B>
+    where A: Future,
+          B: Future<Item=A::Item>,
+{
+    Or {
+        state: Chain::new(future, fallback),
+    }
+}
+
+impl<A, B> Future for Or<A, B>
+    where A: Future,
+          B: Future<Item=A::Item>,
+{
+    type Item = B::Item;
+    type Error = B::Error;
+
+    fn poll(&mut self) -> Poll<B::Item, B::Error> {
+        self.state.poll(|a, fallback| {
+            match a {
+                Ok(item) => Ok(Ok(item)),
+                Err(_) => Ok(Err(fallback)),
+            }
+        })
+    }
+}
@@ -0,0 +1,174 @@
+use std::prelude::v1::*;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, Write};
+use std::mem;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use {Future, Poll, Async};
+use task::{self, EventSet, UnparkEvent};
+use task_impl;
+
+/// A diagnostic future which records how often the underlying future is woken
+/// up and polled without making progress.
+///
+/// This is created by the `Future::pending_profile` method.
+#[must_use = "futures do nothing unless polled"]
+pub struct PendingProfile<F> {
+    future: F,
+    name: String,
+    wakeups: Arc<Wakeups>,
+    profile: Profile,
+}
+
+/// The statistics recorded by a `PendingProfile` future.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Profile {
+    polls: usize,
+    wakeups: usize,
+    unproductive: usize,
+    unprompted: usize,
+    sources: BTreeMap<usize, usize>,
+}
+
+// Counts the unparks of the task made through handles created while polling
+// the underlying future, along with the identifiers of the unpark events and
+// routes which those handles were registered with by the future.
+struct Wakeups {
+    count: AtomicUsize,
+    sources: Mutex<BTreeMap<usize, usize>>,
+}
+
+impl EventSet<()> for Wakeups {
+    fn insert(&self, _id: ()) {
+        self.count.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+impl EventSet<usize> for Wakeups {
+    fn insert(&self, id: usize) {
+        *self.sources.lock().unwrap().entry(id).or_insert(0) += 1;
+    }
+}
+
+pub fn new<F: Future>(future: F, name: String) -> PendingProfile<F> {
+    PendingProfile {
+        future: future,
+        name: name,
+        wakeups: Arc::new(Wakeups {
+            count: AtomicUsize::new(0),
+            sources: Mutex::new(BTreeMap::new()),
+        }),
+        profile: Profile::default(),
+    }
+}
+
+impl<F> PendingProfile<F> {
+    /// Returns the statistics recorded so far.
+    pub fn profile(&self) -> &Profile {
+        &self.profile
+    }
+
+    /// Consumes this combinator, returning the underlying future.
+    pub fn into_inner(self) -> F {
+        self.future
+    }
+}
+
+impl<F: Future> Future for PendingProfile<F> {
+    type Item = F::Item;
+    type Error = F::Error;
+
+    fn poll(&mut self) -> Poll<F::Item, F::Error> {
+        let wakeups = self.wakeups.count.swap(0, Ordering::SeqCst);
+        let sources = mem::replace(&mut *self.wakeups.sources.lock().unwrap(),
+                                   BTreeMap::new());
+        for (id, n) in sources {
+            *self.profile.sources.entry(id).or_insert(0) += n;
+        }
+        self.profile.polls += 1;
+        self.profile.wakeups += wakeups;
+        // The first poll isn't prompted by a wakeup, but it isn't a symptom
+        // of anything either.
+        if wakeups == 0 && self.profile.polls > 1 {
+            self.profile.unprompted += 1;
+        }
+
+        let event = UnparkEvent::new(self.wakeups.clone() as Arc<EventSet<()>>, ());
+        let tap = self.wakeups.clone();
+        let future = &mut self.future;
+        let res = task::with_unpark_event(event, || {
+            task_impl::with_unpark_tap(tap, || future.poll())
+        });
+        match res {
+            Ok(Async::NotReady) => {
+                self.profile.unproductive += 1;
+                return Ok(Async::NotReady)
+            }
+            Ok(Async::Ready(_)) | Err(_) => {}
+        }
+        let _ = writeln!(io::stderr(), "{}: {}", self.name, self.profile);
+        res
+    }
+}
+
+impl Profile {
+    /// Returns the number of times the future has been polled.
+    pub fn polls(&self) -> usize {
+        self.polls
+    }
+
+    /// Returns the number of times the future's task has been unparked
+    /// through a handle which the future registered while it was polled.
+    ///
+    /// Several wakeups between two polls indicate that the future registered
+    /// interest in more sources than it needed to, or that one of them is
+    /// notifying it far more often than it has anything to offer.
+    pub fn wakeups(&self) -> usize {
+        self.wakeups
+    }
+
+    /// Returns the number of polls which returned `NotReady`.
+    ///
+    /// A high number compared to the number of wakeups points to spurious
+    /// wakeups: sources waking the future up without anything being ready.
+    pub fn unproductive_polls(&self) -> usize {
+        self.unproductive
+    }
+
+    /// Returns the number of polls, after the first, which weren't preceded
+    /// by a wakeup of this future.
+    ///
+    /// These polls are caused by something else sharing the task, such as a
+    /// sibling future in a `join` or `select`, waking the task up.
+    pub fn unprompted_polls(&self) -> usize {
+        self.unprompted
+    }
+
+    /// Returns how many times each source the future was waiting on woke it
+    /// up, keyed by the identifier of the source.
+    ///
+    /// Sources are identified by the `usize` identifiers of the unpark events
+    /// (see `task::with_unpark_event`) and unpark routes (see
+    /// `task::with_unpark_id`) which the future registered while it was
+    /// polled, such as the indices which `FuturesUnordered` gives to the
+    /// futures it runs. A wakeup through a handle registered with several of
+    /// them counts towards each one, and identifiers used by different
+    /// combinators within the future are counted together.
+    pub fn wakeup_sources(&self) -> &BTreeMap<usize, usize> {
+        &self.sources
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(write!(f, "{} polls ({} without progress, {} without a wakeup), {} wakeups",
+                    self.polls, self.unproductive, self.unprompted, self.wakeups));
+        if !self.sources.is_empty() {
+            try!(write!(f, " (by source id: {:?})", self.sources));
+        }
+        Ok(())
+    }
+}
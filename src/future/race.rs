@@ -0,0 +1,48 @@
+use {Future, Poll, Async};
+
+/// Future for the `race` combinator, resolving with the result of whichever
+/// of two futures completes first.
+///
+/// This is created by the `Future::race` method.
+#[must_use = "futures do nothing unless polled"]
+pub struct Race<A, B> where A: Future, B: Future<Item=A::Item, Error=A::Error> {
+    inner: Option<(A, B)>,
+}
+
+pub fn new<A, B>(a: A, b: B) -> Race<A, B>
+    where A: Future,
+          B: Future<Item=A::Item, Error=A::Error>
+{
+    Race {
+        inner: Some((a, b)),
+    }
+}
+
+impl<A, B> Future for Race<A, B>
+    where A: Future,
+          B: Future<Item=A::Item, Error=A::Error>,
+{
+    type Item = A::Item;
+    type Error = A::Error;
+
+    fn poll(&mut self) -> Poll<A::Item, A::Error> {
+        let ret = match self.inner {
+            Some((ref mut a, ref mut b)) => {
+                match a.poll() {
+                    Ok(Async::NotReady) => {
+                        match b.poll() {
+                            Ok(Async::NotReady) => return Ok(Async::NotReady),
+                            ret => ret,
+                        }
+                    }
+                    ret => ret,
+                }
+            }
+            None => panic!("cannot poll race twice"),
+        };
+
+        // Drop the loser right away, canceling it.
+        self.inner = None;
+        ret
+    }
+}
@@ -0,0 +1,102 @@
+use std::prelude::v1::*;
+
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering::SeqCst;
+
+use {Future, Poll, Async};
+use future::CatchUnwind;
+use sync::oneshot::{self, Sender, Receiver};
+
+type Output<T, E> = Result<Result<T, E>, Box<Any + Send>>;
+
+/// The driving half of a future split by `Future::remote_handle`.
+///
+/// This future runs the original future to completion and sends its result
+/// to the paired `RemoteHandle`. It's meant to be spawned onto an executor,
+/// for example with `Spawn::execute`.
+#[must_use = "futures do nothing unless polled"]
+pub struct Remote<F> where F: Future {
+    future: CatchUnwind<AssertUnwindSafe<F>>,
+    tx: Option<Sender<Output<F::Item, F::Error>>>,
+    keep_running: Arc<AtomicBool>,
+}
+
+/// The result half of a future split by `Future::remote_handle`.
+///
+/// This future resolves with the result of the original future once the
+/// paired `Remote` has run it to completion. If the original future panicked
+/// then polling this future resumes the panic. Dropping it cancels the
+/// original future, unless `forget` is called first.
+#[must_use = "futures do nothing unless polled"]
+pub struct RemoteHandle<T, E> {
+    rx: Receiver<Output<T, E>>,
+    keep_running: Arc<AtomicBool>,
+}
+
+pub fn new<F>(future: F) -> (Remote<F>, RemoteHandle<F::Item, F::Error>)
+    where F: Future,
+{
+    let (tx, rx) = oneshot::channel();
+    let keep_running = Arc::new(AtomicBool::new(false));
+    let remote = Remote {
+        future: AssertUnwindSafe(future).catch_unwind(),
+        tx: Some(tx),
+        keep_running: keep_running.clone(),
+    };
+    let handle = RemoteHandle {
+        rx: rx,
+        keep_running: keep_running,
+    };
+    (remote, handle)
+}
+
+impl<F> Future for Remote<F>
+    where F: Future,
+{
+    type Item = ();
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<(), ()> {
+        if let Ok(Async::Ready(())) = self.tx.as_mut().unwrap().poll_cancel() {
+            if !self.keep_running.load(SeqCst) {
+                // The handle is gone, so there's nobody to give the result
+                return Ok(().into())
+            }
+        }
+
+        let output = match self.future.poll() {
+            Ok(Async::NotReady) => return Ok(Async::NotReady),
+            Ok(Async::Ready(res)) => Ok(res),
+            Err(e) => Err(e),
+        };
+        self.tx.take().unwrap().complete(output);
+        Ok(Async::Ready(()))
+    }
+}
+
+impl<T, E> RemoteHandle<T, E> {
+    /// Drops this handle without canceling the original future.
+    ///
+    /// The paired `Remote` keeps running the original future to completion,
+    /// and its result is discarded.
+    pub fn forget(self) {
+        self.keep_running.store(true, SeqCst);
+    }
+}
+
+impl<T, E> Future for RemoteHandle<T, E> {
+    type Item = T;
+    type Error = E;
+
+    fn poll(&mut self) -> Poll<T, E> {
+        match self.rx.poll().expect("the remote future was dropped before completing") {
+            Async::Ready(Ok(Ok(t))) => Ok(t.into()),
+            Async::Ready(Ok(Err(e))) => Err(e),
+            Async::Ready(Err(e)) => panic::resume_unwind(e),
+            Async::NotReady => Ok(Async::NotReady),
+        }
+    }
+}
@@ -3,6 +3,7 @@
 use core::result;
 
 use {Future, Poll, Async};
+use future::FusedFuture;
 
 /// A future representing a value that is immediately ready.
 ///
@@ -72,3 +73,9 @@ impl<T, E> Future for FutureResult<T, E> {
         self.inner.take().expect("cannot poll Result twice").map(Async::Ready)
     }
 }
+
+impl<T, E> FusedFuture for FutureResult<T, E> {
+    fn is_terminated(&self) -> bool {
+        self.inner.is_none()
+    }
+}
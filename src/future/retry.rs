@@ -0,0 +1,136 @@
+//! Definition of the Retry combinator, rerunning a failed future according
+//! to a `ReconnectPolicy`.
+
+use std::prelude::v1::*;
+
+use {Async, Future, IntoFuture, Poll};
+use clock;
+use future::BoxFuture;
+use reconnect::ReconnectPolicy;
+
+/// Future which reruns an operation until it succeeds, backing off between
+/// attempts.
+///
+/// This future is created with the `retry` function.
+#[must_use = "futures do nothing unless polled"]
+pub struct Retry<F, R> where R: IntoFuture {
+    f: F,
+    policy: ReconnectPolicy,
+    retries: usize,
+    state: State<R::Future>,
+}
+
+enum State<A> {
+    // An attempt, along with its idle timeout once it's been polled
+    Running(A, Option<BoxFuture<(), ()>>),
+    // The backoff delay before the next attempt
+    Waiting(BoxFuture<(), ()>),
+}
+
+enum Step {
+    Backoff,
+    Restart,
+}
+
+/// Creates a future which runs the operation created by `f`, creating and
+/// running it again whenever it fails, as configured by `policy`.
+///
+/// After each failure the returned future waits for the backoff delay of the
+/// policy before starting another attempt, and once the policy allows no
+/// more retries it fails with the error of the last attempt. If the policy
+/// has an idle timeout, an attempt which hasn't completed within it is
+/// dropped and counted as a failure, except for the last attempt allowed,
+/// which is always left to finish. Time is measured by the clock of the
+/// current task (see the `clock` module).
+///
+/// # Examples
+///
+/// ```
+/// use std::cell::Cell;
+/// use std::time::Duration;
+/// use futures::Future;
+/// use futures::future;
+/// use futures::reconnect::ReconnectPolicy;
+///
+/// let mut policy = ReconnectPolicy::new();
+/// policy.backoff(Duration::from_millis(1), Duration::from_millis(10))
+///       .max_retries(5);
+///
+/// let attempts = Cell::new(0);
+/// let f = future::retry(policy, || {
+///     attempts.set(attempts.get() + 1);
+///     if attempts.get() < 3 { Err("try again") } else { Ok(attempts.get()) }
+/// });
+/// assert_eq!(f.wait(), Ok(3));
+/// ```
+pub fn retry<F, R>(policy: ReconnectPolicy, mut f: F) -> Retry<F, R>
+    where F: FnMut() -> R,
+          R: IntoFuture,
+{
+    let future = f().into_future();
+    Retry {
+        f: f,
+        policy: policy,
+        retries: 0,
+        state: State::Running(future, None),
+    }
+}
+
+impl<F, R> Future for Retry<F, R>
+    where F: FnMut() -> R,
+          R: IntoFuture,
+{
+    type Item = R::Item;
+    type Error = R::Error;
+
+    fn poll(&mut self) -> Poll<R::Item, R::Error> {
+        loop {
+            let step = match self.state {
+                State::Running(ref mut future, ref mut idle) => {
+                    let failed = match future.poll() {
+                        Ok(Async::NotReady) => false,
+                        Ok(Async::Ready(item)) => return Ok(Async::Ready(item)),
+                        Err(e) => {
+                            if !self.policy.allows_retry(self.retries) {
+                                return Err(e)
+                            }
+                            true
+                        }
+                    };
+                    if failed {
+                        Step::Backoff
+                    } else {
+                        // The idle timeout only applies to attempts which can
+                        // be retried.
+                        match self.policy.get_idle_timeout() {
+                            Some(timeout) if self.policy.allows_retry(self.retries) => {
+                                if idle.is_none() {
+                                    *idle = Some(clock::delay(clock::now() + timeout));
+                                }
+                                match idle.as_mut().unwrap().poll() {
+                                    Ok(Async::NotReady) => return Ok(Async::NotReady),
+                                    _ => Step::Backoff,
+                                }
+                            }
+                            _ => return Ok(Async::NotReady),
+                        }
+                    }
+                }
+                State::Waiting(ref mut delay) => {
+                    match delay.poll() {
+                        Ok(Async::NotReady) => return Ok(Async::NotReady),
+                        _ => Step::Restart,
+                    }
+                }
+            };
+            self.state = match step {
+                Step::Backoff => {
+                    let delay = self.policy.delay_for(self.retries).unwrap();
+                    self.retries += 1;
+                    State::Waiting(clock::delay(clock::now() + delay))
+                }
+                Step::Restart => State::Running((self.f)().into_future(), None),
+            };
+        }
+    }
+}
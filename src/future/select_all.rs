@@ -41,6 +41,41 @@ pub fn select_all<I>(iter: I) -> SelectAll<<I::Item as IntoFuture>::Future>
     ret
 }
 
+/// Creates a new future which will select over a list of futures, breaking
+/// ties by their position in the list.
+///
+/// This is the same as `select_all`, except that the order in which the
+/// futures are polled is a documented guarantee: each time the returned future
+/// is polled, the futures are polled in the order in which `iter` produced
+/// them, stopping at the first one which is ready. So if several futures are
+/// ready at the same time, the earliest one in the list always wins, and the
+/// futures after it aren't polled at all. This makes the outcome of a race
+/// reproducible, which simulation and replay-based testing rely on.
+///
+/// The index returned along with the result is the position of the future in
+/// the list, and the remaining futures keep their relative order, so this
+/// guarantee also holds when the remaining futures are selected over again.
+///
+/// # Panics
+///
+/// This function will panic if the iterator specified contains no items.
+///
+/// # Examples
+///
+/// ```
+/// use futures::future::*;
+///
+/// let futures = vec![empty().boxed(), ok::<_, ()>(1).boxed(), ok(2).boxed()];
+/// let (item, index, rest) = select_biased_all(futures).wait().ok().unwrap();
+/// assert_eq!((item, index, rest.len()), (1, 1, 2));
+/// ```
+pub fn select_biased_all<I>(iter: I) -> SelectAll<<I::Item as IntoFuture>::Future>
+    where I: IntoIterator,
+          I::Item: IntoFuture,
+{
+    select_all(iter)
+}
+
 impl<A> Future for SelectAll<A>
     where A: Future,
 {
@@ -15,7 +15,7 @@
 
 use std::mem;
 use std::vec::Vec;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, RwLock, Weak};
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering::SeqCst;
 use std::ops::Deref;
@@ -33,6 +33,16 @@ pub struct Shared<F>
     inner: Arc<Inner<F>>,
 }
 
+/// A weak handle to a `Shared` future, which doesn't keep the original
+/// future alive.
+///
+/// This is created by the `Shared::downgrade` method.
+pub struct WeakShared<F>
+    where F: Future
+{
+    inner: Weak<Inner<F>>,
+}
+
 struct Inner<F>
     where F: Future
 {
@@ -52,6 +62,8 @@ enum State<T, E> {
     Done(Result<Arc<T>, Arc<E>>),
 }
 
+type SharedResult<T, E> = Result<SharedItem<T>, SharedError<E>>;
+
 impl<F> Shared<F>
     where F: Future
 {
@@ -66,6 +78,34 @@ impl<F> Shared<F>
         }
     }
 
+    /// Returns the result of the original future if it has already
+    /// completed, or `None` otherwise.
+    ///
+    /// This doesn't poll the original future, and so can be called from
+    /// outside of a task, for example to synchronously check whether a cached
+    /// computation has finished.
+    pub fn peek(&self) -> Option<SharedResult<F::Item, F::Error>> {
+        if !self.inner.result_ready.load(SeqCst) {
+            return None
+        }
+        match *self.inner.state.read().unwrap() {
+            State::Done(Ok(ref e)) => Some(Ok(SharedItem { item: e.clone() })),
+            State::Done(Err(ref e)) => Some(Err(SharedError { error: e.clone() })),
+            State::Waiting(_) => None,
+        }
+    }
+
+    /// Creates a weak handle to this shared future.
+    ///
+    /// The weak handle doesn't keep the original future, or its result, alive.
+    /// Once every `Shared` handle has been dropped the computation is dropped
+    /// too, and `WeakShared::upgrade` returns `None`. This allows a cache to
+    /// hand out in-flight computations without prolonging work which nobody
+    /// is waiting for anymore.
+    pub fn downgrade(&self) -> WeakShared<F> {
+        WeakShared { inner: Arc::downgrade(&self.inner) }
+    }
+
     fn park(&self) -> Poll<SharedItem<F::Item>, SharedError<F::Error>> {
         let me = task::park();
         match *self.inner.state.write().unwrap() {
@@ -170,6 +210,24 @@ impl<F> Clone for Shared<F>
     }
 }
 
+impl<F> WeakShared<F>
+    where F: Future
+{
+    /// Attempts to get a `Shared` handle to the future, returning `None` if
+    /// every `Shared` handle has already been dropped.
+    pub fn upgrade(&self) -> Option<Shared<F>> {
+        self.inner.upgrade().map(|inner| Shared { inner: inner })
+    }
+}
+
+impl<F> Clone for WeakShared<F>
+    where F: Future
+{
+    fn clone(&self) -> Self {
+        WeakShared { inner: self.inner.clone() }
+    }
+}
+
 impl<F: Future> Drop for Shared<F> {
     fn drop(&mut self) {
         // A `Shared` represents a bunch of handles to one original future
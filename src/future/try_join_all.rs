@@ -0,0 +1,205 @@
+//! Definition of the TryJoinAll combinator, waiting for all of a list of
+//! futures to finish and collecting every error.
+
+use std::prelude::v1::*;
+
+use std::error::Error;
+use std::fmt;
+use std::mem;
+
+use {Future, IntoFuture, Poll, Async};
+
+enum ElemState<T> where T: Future {
+    Pending(T),
+    Done(T::Item),
+    Failed(T::Error),
+}
+
+/// A future which takes a list of futures and resolves with a vector of the
+/// completed values, or with every error if any of them failed.
+///
+/// This future is created with the `try_join_all` method.
+#[must_use = "futures do nothing unless polled"]
+pub struct TryJoinAll<I>
+    where I: IntoIterator,
+          I::Item: IntoFuture,
+{
+    elems: Vec<ElemState<<I::Item as IntoFuture>::Future>>,
+    fail_fast: bool,
+}
+
+/// The errors from a `TryJoinAll` future in which some of the futures failed.
+///
+/// Each error is paired with the index of the future which produced it, in
+/// the order that the futures were given.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JoinErrors<E> {
+    errors: Vec<(usize, E)>,
+}
+
+/// Creates a future which waits for all of the futures given, collecting
+/// every error if any of them fail.
+///
+/// Unlike `join_all`, a failing future doesn't cause the others to be
+/// canceled. Instead every future is driven to completion, and if any of them
+/// failed the returned future fails with a `JoinErrors` holding all of the
+/// errors along with the indices of the futures which produced them. This is
+/// useful when, for example, validating many independent resources where
+/// reporting only the first problem would hide the full picture.
+///
+/// If all futures complete successfully then the returned future succeeds
+/// with a `Vec` of the results, in the same order as the futures were given.
+///
+/// To instead give up as soon as any of the futures fails, see
+/// `TryJoinAll::fail_fast`.
+///
+/// # Examples
+///
+/// ```
+/// use futures::future::*;
+///
+/// let f = try_join_all(vec![
+///     ok::<u32, &str>(1),
+///     err::<u32, &str>("bad"),
+///     ok::<u32, &str>(3),
+///     err::<u32, &str>("worse"),
+/// ]);
+/// let errors = f.wait().unwrap_err();
+/// assert_eq!(errors.errors(), &[(1, "bad"), (3, "worse")]);
+/// ```
+pub fn try_join_all<I>(i: I) -> TryJoinAll<I>
+    where I: IntoIterator,
+          I::Item: IntoFuture,
+{
+    let elems = i.into_iter().map(|f| {
+        ElemState::Pending(f.into_future())
+    }).collect();
+    TryJoinAll {
+        elems: elems,
+        fail_fast: false,
+    }
+}
+
+impl<I> TryJoinAll<I>
+    where I: IntoIterator,
+          I::Item: IntoFuture,
+{
+    /// Makes this future fail as soon as any of its futures fails.
+    ///
+    /// When the first error is seen, every other future, including those
+    /// which are still running and the results of those which have already
+    /// completed, is dropped straight away, canceling them, and the returned
+    /// future fails with a `JoinErrors` holding just that error and the
+    /// index of the future which produced it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::future::*;
+    ///
+    /// let f = try_join_all(vec![
+    ///     ok::<u32, &str>(1).boxed(),
+    ///     err::<u32, &str>("bad").boxed(),
+    ///     empty::<u32, &str>().boxed(),
+    /// ]).fail_fast();
+    /// let errors = f.wait().unwrap_err();
+    /// assert_eq!(errors.errors(), &[(1, "bad")]);
+    /// ```
+    pub fn fail_fast(mut self) -> TryJoinAll<I> {
+        self.fail_fast = true;
+        self
+    }
+}
+
+impl<I> Future for TryJoinAll<I>
+    where I: IntoIterator,
+          I::Item: IntoFuture,
+{
+    type Item = Vec<<I::Item as IntoFuture>::Item>;
+    type Error = JoinErrors<<I::Item as IntoFuture>::Error>;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        let mut all_done = true;
+        let mut first_error = None;
+
+        for (i, elem) in self.elems.iter_mut().enumerate() {
+            let done = match *elem {
+                ElemState::Pending(ref mut t) => {
+                    match t.poll() {
+                        Ok(Async::Ready(v)) => ElemState::Done(v),
+                        Ok(Async::NotReady) => {
+                            all_done = false;
+                            continue
+                        }
+                        Err(e) => {
+                            if self.fail_fast {
+                                first_error = Some((i, e));
+                                break
+                            }
+                            ElemState::Failed(e)
+                        }
+                    }
+                }
+                _ => continue,
+            };
+            *elem = done;
+        }
+
+        if let Some(error) = first_error {
+            // Cancel everything else straight away rather than when this
+            // future is dropped.
+            self.elems = Vec::new();
+            return Err(JoinErrors { errors: vec![error] })
+        }
+
+        if !all_done {
+            return Ok(Async::NotReady)
+        }
+
+        let elems = mem::replace(&mut self.elems, Vec::new());
+        let mut items = Vec::with_capacity(elems.len());
+        let mut errors = Vec::new();
+        for (i, elem) in elems.into_iter().enumerate() {
+            match elem {
+                ElemState::Done(v) => items.push(v),
+                ElemState::Failed(e) => errors.push((i, e)),
+                ElemState::Pending(_) => unreachable!(),
+            }
+        }
+        if errors.is_empty() {
+            Ok(Async::Ready(items))
+        } else {
+            Err(JoinErrors { errors: errors })
+        }
+    }
+}
+
+impl<E> JoinErrors<E> {
+    /// Returns the errors, each paired with the index of the future which
+    /// produced it.
+    pub fn errors(&self) -> &[(usize, E)] {
+        &self.errors
+    }
+
+    /// Consumes this value, returning the errors, each paired with the index
+    /// of the future which produced it.
+    pub fn into_errors(self) -> Vec<(usize, E)> {
+        self.errors
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for JoinErrors<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(write!(f, "{} futures failed", self.errors.len()));
+        for &(i, ref e) in &self.errors {
+            try!(write!(f, "; {}: {}", i, e));
+        }
+        Ok(())
+    }
+}
+
+impl<E: Error> Error for JoinErrors<E> {
+    fn description(&self) -> &str {
+        "futures failed"
+    }
+}
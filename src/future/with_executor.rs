@@ -0,0 +1,107 @@
+use std::prelude::v1::*;
+use std::mem;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+use std::thread;
+
+use {Future, Poll, Async};
+use executor::{self, Executor};
+use sync::oneshot::{channel, Sender, Receiver};
+use future::CatchUnwind;
+
+/// Future for the `with_executor` combinator, running the underlying future
+/// on an executor and proxying its result back.
+///
+/// This is created by the `Future::with_executor` method.
+#[must_use = "futures do nothing unless polled"]
+pub struct WithExecutor<F: Future> {
+    state: State<F>,
+}
+
+enum State<F: Future> {
+    Start(F, Arc<Executor>),
+    Running(Receiver<Outcome<F>>),
+    Done,
+}
+
+// The task spawned onto the executor, driving the original future and sending
+// its result (or the panic it raised) back to the `WithExecutor` future.
+struct Forward<F: Future> {
+    future: CatchUnwind<AssertUnwindSafe<F>>,
+    tx: Option<Sender<Outcome<F>>>,
+}
+
+type Outcome<F> = thread::Result<Result<<F as Future>::Item, <F as Future>::Error>>;
+
+pub fn new<F>(future: F, exec: Arc<Executor>) -> WithExecutor<F>
+    where F: Future + Send + 'static,
+          F::Item: Send + 'static,
+          F::Error: Send + 'static,
+{
+    WithExecutor {
+        state: State::Start(future, exec),
+    }
+}
+
+impl<F> Future for WithExecutor<F>
+    where F: Future + Send + 'static,
+          F::Item: Send + 'static,
+          F::Error: Send + 'static,
+{
+    type Item = F::Item;
+    type Error = F::Error;
+
+    fn poll(&mut self) -> Poll<F::Item, F::Error> {
+        if let State::Start(..) = self.state {
+            let (future, exec) = match mem::replace(&mut self.state, State::Done) {
+                State::Start(future, exec) => (future, exec),
+                _ => unreachable!(),
+            };
+            let (tx, rx) = channel();
+            let forward = Forward {
+                future: AssertUnwindSafe(future).catch_unwind(),
+                tx: Some(tx),
+            };
+            self.state = State::Running(rx);
+            executor::spawn(forward).execute(exec);
+        }
+
+        let res = match self.state {
+            State::Running(ref mut rx) => {
+                match rx.poll().expect("executor dropped the future before completion") {
+                    Async::Ready(res) => res,
+                    Async::NotReady => return Ok(Async::NotReady),
+                }
+            }
+            State::Start(..) => unreachable!(),
+            State::Done => panic!("cannot poll WithExecutor twice"),
+        };
+        self.state = State::Done;
+        match res {
+            Ok(Ok(e)) => Ok(Async::Ready(e)),
+            Ok(Err(e)) => Err(e),
+            Err(e) => panic::resume_unwind(e),
+        }
+    }
+}
+
+impl<F: Future> Future for Forward<F> {
+    type Item = ();
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<(), ()> {
+        if let Ok(Async::Ready(())) = self.tx.as_mut().unwrap().poll_cancel() {
+            // The `WithExecutor` future has gone away, so nobody is
+            // interested in the result any more.
+            return Ok(Async::Ready(()))
+        }
+
+        let res = match self.future.poll() {
+            Ok(Async::Ready(e)) => Ok(e),
+            Ok(Async::NotReady) => return Ok(Async::NotReady),
+            Err(e) => Err(e),
+        };
+        self.tx.take().unwrap().complete(res);
+        Ok(Async::Ready(()))
+    }
+}
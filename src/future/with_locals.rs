@@ -0,0 +1,55 @@
+use {Future, Poll};
+use task::LocalMap;
+use task_impl::LocalScope;
+
+/// Future for the `with_locals` combinator, installing a set of task-local
+/// values around every poll of the underlying future.
+///
+/// This is created by the `Future::with_locals` method.
+#[must_use = "futures do nothing unless polled"]
+pub struct WithLocals<F> {
+    future: F,
+    scope: LocalScope,
+}
+
+pub fn new<F>(future: F, map: LocalMap) -> WithLocals<F>
+    where F: Future,
+{
+    WithLocals {
+        future: future,
+        scope: LocalScope::new(map),
+    }
+}
+
+impl<F> WithLocals<F> {
+    /// Acquires a reference to the underlying future.
+    pub fn get_ref(&self) -> &F {
+        &self.future
+    }
+
+    /// Acquires a mutable reference to the underlying future.
+    pub fn get_mut(&mut self) -> &mut F {
+        &mut self.future
+    }
+}
+
+impl<F> Future for WithLocals<F>
+    where F: Future,
+{
+    type Item = F::Item;
+    type Error = F::Error;
+
+    fn poll(&mut self) -> Poll<F::Item, F::Error> {
+        struct Restore<'a>(&'a mut LocalScope);
+
+        impl<'a> Drop for Restore<'a> {
+            fn drop(&mut self) {
+                self.0.swap();
+            }
+        }
+
+        self.scope.swap();
+        let _restore = Restore(&mut self.scope);
+        self.future.poll()
+    }
+}
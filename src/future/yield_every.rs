@@ -0,0 +1,97 @@
+use {Future, Poll, Async};
+use stream::Stream;
+use task_impl::yield_task;
+
+/// A future or stream which periodically yields to other tasks on its
+/// executor.
+///
+/// This is created by the `future::yield_every` function.
+#[must_use = "futures do nothing unless polled"]
+pub struct YieldEvery<T> {
+    inner: T,
+    n: usize,
+    count: usize,
+}
+
+/// Wraps a future or stream so that it periodically yields its task to other
+/// work on its executor, as if it had waited on `task::yield_now`.
+///
+/// When wrapping a stream, the returned stream yields after every `n` elements
+/// produced by the underlying stream. When wrapping a future, it yields before
+/// every `n`th poll of the underlying future. This is a coarse but effective
+/// way of keeping CPU-heavy computations, whose authors haven't inserted
+/// yields of their own, from starving other tasks.
+///
+/// # Panics
+///
+/// This function will panic if `n` is 0.
+///
+/// # Examples
+///
+/// ```
+/// use futures::Stream;
+/// use futures::future::{self, Future};
+/// use futures::stream;
+///
+/// let numbers = stream::iter((0..1000).map(Ok::<u32, ()>));
+/// let sum = future::yield_every(100, numbers).fold(0, |a, b| Ok::<_, ()>(a + b));
+/// assert_eq!(sum.wait(), Ok(499500));
+/// ```
+pub fn yield_every<T>(n: usize, inner: T) -> YieldEvery<T> {
+    assert!(n > 0, "cannot yield every 0 completions");
+    YieldEvery {
+        inner: inner,
+        n: n,
+        count: 0,
+    }
+}
+
+impl<T> YieldEvery<T> {
+    /// Acquires a reference to the underlying future or stream.
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Acquires a mutable reference to the underlying future or stream.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Consumes this wrapper, returning the underlying future or stream.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<F: Future> Future for YieldEvery<F> {
+    type Item = F::Item;
+    type Error = F::Error;
+
+    fn poll(&mut self) -> Poll<F::Item, F::Error> {
+        if self.count == self.n {
+            self.count = 0;
+            yield_task();
+            return Ok(Async::NotReady)
+        }
+        self.count += 1;
+        self.inner.poll()
+    }
+}
+
+impl<S: Stream> Stream for YieldEvery<S> {
+    type Item = S::Item;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Poll<Option<S::Item>, S::Error> {
+        if self.count == self.n {
+            self.count = 0;
+            yield_task();
+            return Ok(Async::NotReady)
+        }
+        let res = self.inner.poll();
+        if let Ok(Async::Ready(Some(_))) = res {
+            self.count += 1;
+        }
+        res
+    }
+}
@@ -22,6 +22,12 @@
 //! futures = "0.1"
 //! ```
 //!
+//! The crate can also be used without the standard library by disabling the
+//! default `use_std` feature. The `use_alloc` feature then additionally
+//! enables the parts which only need an allocator, such as the task system.
+//! That configuration builds on the `alloc` crate, so it requires Rust 1.36
+//! or newer, unlike the default configuration.
+//!
 //! ## Examples
 //!
 //! Let's take a look at a few examples of how futures might be used:
@@ -156,6 +162,11 @@
 #[cfg(feature = "use_std")]
 extern crate std;
 
+// The `alloc` crate is stable since Rust 1.36, so it's only used without
+// `std` to keep the default configuration building on older compilers.
+#[cfg(all(feature = "use_alloc", not(feature = "use_std")))]
+extern crate alloc;
+
 #[macro_use]
 extern crate log;
 
@@ -166,10 +177,43 @@ macro_rules! if_std {
     )*)
 }
 
+macro_rules! if_alloc {
+    ($($i:item)*) => ($(
+        #[cfg(feature = "use_alloc")]
+        $i
+    )*)
+}
+
+// Without `std`, the modules which only need an allocator still import
+// through `std::...` paths, which this resolves to `core` and `alloc`.
+#[cfg(all(feature = "use_alloc", not(feature = "use_std")))]
+mod std {
+    pub use core::{any, cell, fmt, marker, mem, ptr};
+    pub use alloc::collections;
+
+    pub mod sync {
+        pub use alloc::sync::Arc;
+        pub use core::sync::atomic;
+    }
+
+    pub mod prelude {
+        pub mod v1 {
+            pub use core::prelude::v1::*;
+            pub use alloc::boxed::Box;
+            pub use alloc::vec::Vec;
+        }
+    }
+}
+
 #[macro_use]
 mod poll;
 pub use poll::{Poll, Async, AsyncSink, StartSend};
 
+pub mod poll_helpers;
+
+pub mod never;
+pub use never::Never;
+
 pub mod future;
 pub use future::{Future, IntoFuture};
 
@@ -179,6 +223,8 @@ pub use stream::Stream;
 pub mod sink;
 pub use sink::Sink;
 
+pub mod service;
+
 #[deprecated(since = "0.1.4", note = "import through the future module instead")]
 #[cfg(feature = "with-deprecated")]
 pub use future::{done, empty, failed, finished, lazy};
@@ -192,13 +238,21 @@ pub use future::{
     SelectNext, Then
 };
 
-if_std! {
-    mod lock;
+if_alloc! {
+    #[macro_use]
     mod task_impl;
     mod stack;
 
     pub mod task;
     pub mod executor;
+}
+
+if_std! {
+    mod lock;
+    mod timer;
+
+    pub mod clock;
+    pub mod reconnect;
     pub mod sync;
 
     #[doc(hidden)]
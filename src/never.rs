@@ -0,0 +1,39 @@
+//! The `Never` type, for futures and streams which can't fail.
+
+use core::fmt;
+
+/// A type with no values, used as the error type of futures and streams
+/// which can't fail.
+///
+/// As no value of this type can be constructed, a `Future<Error = Never>`
+/// is statically known to never resolve with an error. Such futures can be
+/// composed with fallible ones through `Future::infallible`, which converts
+/// the error type into any other without a dummy value, and any future can
+/// be made infallible with `Future::never_error`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Never {}
+
+impl Never {
+    /// Converts this value into a value of any type.
+    ///
+    /// This can never actually be called, as there are no values of `Never`,
+    /// but it lets an impossible branch, such as a `Never` error, type check
+    /// as whatever is needed.
+    pub fn never_into<T>(self) -> T {
+        match self {}
+    }
+}
+
+impl fmt::Display for Never {
+    fn fmt(&self, _: &mut fmt::Formatter) -> fmt::Result {
+        match *self {}
+    }
+}
+
+if_std! {
+    impl ::std::error::Error for Never {
+        fn description(&self) -> &str {
+            match *self {}
+        }
+    }
+}
@@ -0,0 +1,94 @@
+//! Functions for working with `Poll` values.
+//!
+//! `Poll<T, E>` is an alias for `Result<Async<T>, E>`, so it can't have
+//! methods of its own beyond those of `Result`. The functions in this module
+//! fill in the operations which hand-written `poll` implementations commonly
+//! need, transforming the value of a ready result while passing `NotReady`
+//! and errors through unchanged.
+//!
+//! # Examples
+//!
+//! ```
+//! use futures::{Async, Future, Poll};
+//! use futures::poll_helpers;
+//!
+//! struct Len<F>(F);
+//!
+//! impl<F: Future<Item = String>> Future for Len<F> {
+//!     type Item = usize;
+//!     type Error = F::Error;
+//!
+//!     fn poll(&mut self) -> Poll<usize, F::Error> {
+//!         poll_helpers::map(self.0.poll(), |s| s.len())
+//!     }
+//! }
+//!
+//! let len = Len(futures::future::ok::<_, ()>("hello".to_string()));
+//! assert_eq!(len.wait(), Ok(5));
+//! ```
+
+use {Async, Poll};
+
+/// Changes the value of a ready `poll` with the closure provided.
+pub fn map<T, U, E, F>(poll: Poll<T, E>, f: F) -> Poll<U, E>
+    where F: FnOnce(T) -> U
+{
+    poll.map(|async| async.map(f))
+}
+
+/// Changes the error of a failed `poll` with the closure provided.
+pub fn map_err<T, E, G, F>(poll: Poll<T, E>, f: F) -> Poll<T, G>
+    where F: FnOnce(E) -> G
+{
+    poll.map_err(f)
+}
+
+/// Chains a computation onto the value of a ready `poll`.
+///
+/// If `poll` is ready then `f` is called with its value, and its result is
+/// returned. Otherwise `NotReady` or the error is passed through unchanged.
+pub fn and_then<T, U, E, F>(poll: Poll<T, E>, f: F) -> Poll<U, E>
+    where F: FnOnce(T) -> Poll<U, E>
+{
+    match poll {
+        Ok(Async::Ready(t)) => f(t),
+        Ok(Async::NotReady) => Ok(Async::NotReady),
+        Err(e) => Err(e),
+    }
+}
+
+/// Returns whether `poll` is `Ok(Async::Ready(_))`.
+pub fn is_ready<T, E>(poll: &Poll<T, E>) -> bool {
+    match *poll {
+        Ok(ref async) => async.is_ready(),
+        Err(_) => false,
+    }
+}
+
+/// Returns whether `poll` is `Ok(Async::NotReady)`.
+pub fn is_not_ready<T, E>(poll: &Poll<T, E>) -> bool {
+    match *poll {
+        Ok(ref async) => async.is_not_ready(),
+        Err(_) => false,
+    }
+}
+
+/// Converts `poll` into a `Result` containing an `Option`, which is `None`
+/// if it wasn't ready.
+pub fn into_option<T, E>(poll: Poll<T, E>) -> Result<Option<T>, E> {
+    poll.map(|async| match async {
+        Async::Ready(t) => Some(t),
+        Async::NotReady => None,
+    })
+}
+
+/// Converts a `Result` containing an `Option` into a `Poll`, which isn't
+/// ready if the option is `None`.
+///
+/// This is the inverse of `into_option`.
+pub fn from_option<T, E>(res: Result<Option<T>, E>) -> Poll<T, E> {
+    res.map(|opt| match opt {
+        Some(t) => Async::Ready(t),
+        None => Async::NotReady,
+    })
+}
@@ -0,0 +1,161 @@
+//! Policies for retrying and reconnecting after failures.
+//!
+//! A `ReconnectPolicy` gathers in one place the settings which decide how
+//! persistently an operation is retried: when an attempt is considered to
+//! have gone idle, how long to back off before the next attempt, and how many
+//! attempts are made before giving up. The same policy can be handed to each
+//! combinator which retries work, currently `future::retry` and
+//! `Stream::timeout_backoff_resubscribe`, so an application configures its
+//! resilience once.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+/// Settings for retrying failed or idle operations with exponential backoff.
+///
+/// The delay before retry `n` (counting from zero) is the initial backoff
+/// multiplied `n` times by the multiplier, capped at the maximum backoff.
+/// With jitter, a random part of up to the configured fraction of that delay
+/// is then taken off, so that many clients which failed together don't all
+/// retry at the same moment.
+///
+/// By default there is no idle timeout, backoff starts at 100 milliseconds
+/// and doubles up to 30 seconds, there is no jitter, and operations are
+/// retried indefinitely.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use futures::reconnect::ReconnectPolicy;
+///
+/// let mut policy = ReconnectPolicy::new();
+/// policy.backoff(Duration::from_millis(10), Duration::from_millis(50))
+///       .max_retries(4);
+///
+/// assert_eq!(policy.delay_for(0), Some(Duration::from_millis(10)));
+/// assert_eq!(policy.delay_for(2), Some(Duration::from_millis(40)));
+/// assert_eq!(policy.delay_for(3), Some(Duration::from_millis(50)));
+/// assert_eq!(policy.delay_for(4), None);
+/// ```
+#[derive(Clone, Debug)]
+pub struct ReconnectPolicy {
+    idle_timeout: Option<Duration>,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    multiplier: u32,
+    jitter: f64,
+    max_retries: Option<usize>,
+}
+
+impl ReconnectPolicy {
+    /// Creates a policy with the default settings.
+    pub fn new() -> ReconnectPolicy {
+        ReconnectPolicy {
+            idle_timeout: None,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(30),
+            multiplier: 2,
+            jitter: 0.0,
+            max_retries: None,
+        }
+    }
+
+    /// Sets how long an attempt may go without making progress before it's
+    /// abandoned in favor of a new one.
+    ///
+    /// What counts as progress depends on the combinator: a future makes
+    /// progress by completing, and a stream by yielding an element.
+    pub fn idle_timeout(&mut self, dur: Duration) -> &mut Self {
+        self.idle_timeout = Some(dur);
+        self
+    }
+
+    /// Sets the delay before the first retry, and the most that the delay
+    /// may grow to.
+    pub fn backoff(&mut self, initial: Duration, max: Duration) -> &mut Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    /// Sets the factor by which the delay grows with each retry.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if `multiplier` is zero.
+    pub fn multiplier(&mut self, multiplier: u32) -> &mut Self {
+        assert!(multiplier > 0, "backoff multiplier must be positive");
+        self.multiplier = multiplier;
+        self
+    }
+
+    /// Sets the largest fraction of each delay which may be randomly taken
+    /// off of it.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if `fraction` isn't between 0 and 1.
+    pub fn jitter(&mut self, fraction: f64) -> &mut Self {
+        assert!(fraction >= 0.0, "jitter must be a fraction between 0 and 1");
+        assert!(fraction <= 1.0, "jitter must be a fraction between 0 and 1");
+        self.jitter = fraction;
+        self
+    }
+
+    /// Sets how many times an operation is retried before giving up.
+    pub fn max_retries(&mut self, max: usize) -> &mut Self {
+        self.max_retries = Some(max);
+        self
+    }
+
+    /// Returns the idle timeout of this policy, if any.
+    pub fn get_idle_timeout(&self) -> Option<Duration> {
+        self.idle_timeout
+    }
+
+    /// Returns whether this policy allows retry `retry`, counting from zero.
+    pub fn allows_retry(&self, retry: usize) -> bool {
+        self.max_retries.map(|max| retry < max).unwrap_or(true)
+    }
+
+    /// Returns how long to wait before retry `retry`, counting from zero, or
+    /// `None` if the operation has been retried as many times as allowed.
+    pub fn delay_for(&self, retry: usize) -> Option<Duration> {
+        if !self.allows_retry(retry) {
+            return None
+        }
+        let mut delay = self.initial_backoff;
+        for _ in 0..retry {
+            if delay >= self.max_backoff / self.multiplier {
+                delay = self.max_backoff;
+                break
+            }
+            delay *= self.multiplier;
+        }
+        if delay > self.max_backoff {
+            delay = self.max_backoff;
+        }
+        Some(self.apply_jitter(delay))
+    }
+
+    fn apply_jitter(&self, delay: Duration) -> Duration {
+        if self.jitter == 0.0 {
+            return delay
+        }
+        // `RandomState` is randomly seeded, which is all the randomness
+        // needed here.
+        let bits = RandomState::new().build_hasher().finish();
+        let random = (bits >> 11) as f64 / (1u64 << 53) as f64;
+        let nanos = delay.as_secs() as f64 * 1e9 + delay.subsec_nanos() as f64;
+        let nanos = (nanos * (1.0 - self.jitter * random)) as u64;
+        Duration::new(nanos / 1_000_000_000, (nanos % 1_000_000_000) as u32)
+    }
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> ReconnectPolicy {
+        ReconnectPolicy::new()
+    }
+}
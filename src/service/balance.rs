@@ -0,0 +1,241 @@
+use std::prelude::v1::*;
+
+use std::fmt;
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use {Async, Future, Poll};
+use service::Service;
+
+/// Middleware which distributes requests across a number of services.
+///
+/// Each request is sent to one of the inner services, picked using the "power
+/// of two choices" strategy: two services are chosen at random and the one
+/// with fewer requests in flight receives the request. This spreads load
+/// nearly as well as always picking the least loaded service, without having
+/// to inspect every one of them.
+///
+/// A service which fails a number of requests in a row is considered dead and
+/// is removed from the balancer. If every service has been removed then
+/// requests fail with `BalanceError::Unavailable`.
+///
+/// The balancer's own lock is released before a request is issued, so the
+/// inner services are free to call back into the balancer, e.g. to check how
+/// many services are left.
+pub struct Balance<S> {
+    inner: Mutex<Endpoints<S>>,
+    max_failures: usize,
+}
+
+/// Future for responses from the `Balance` middleware.
+#[must_use = "futures do nothing unless polled"]
+pub struct BalanceFuture<S: Service> {
+    state: State<S::Future>,
+}
+
+enum State<F> {
+    Running(F, InFlight),
+    Unavailable,
+    Done,
+}
+
+/// Errors produced by the `Balance` middleware.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BalanceError<E> {
+    /// The service the request was sent to failed with an error.
+    Service(E),
+
+    /// There were no services left to send the request to.
+    Unavailable,
+}
+
+struct Endpoints<S> {
+    list: Vec<Arc<Endpoint<S>>>,
+    rng: u32,
+}
+
+struct Endpoint<S> {
+    service: S,
+    load: Arc<Load>,
+}
+
+struct Load {
+    in_flight: AtomicUsize,
+    failures: AtomicUsize,
+}
+
+// Tracks a request in flight on an endpoint, for as long as its response
+// future is alive.
+struct InFlight {
+    load: Arc<Load>,
+}
+
+impl<S: Service> Balance<S> {
+    /// Creates a balancer over `services`, removing any service which fails
+    /// `max_failures` requests in a row.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if `max_failures` is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::Future;
+    /// use futures::service::{service_fn, Balance, Service};
+    ///
+    /// let replica = || service_fn(|x: u32| Ok::<u32, ()>(x + 1));
+    /// let service = Balance::new(vec![replica(), replica()], 3);
+    /// assert_eq!(service.call(1).wait(), Ok(2));
+    /// ```
+    pub fn new<I>(services: I, max_failures: usize) -> Balance<S>
+        where I: IntoIterator<Item = S>,
+    {
+        assert!(max_failures > 0, "max_failures must be at least 1");
+        let balance = Balance {
+            inner: Mutex::new(Endpoints {
+                list: Vec::new(),
+                rng: 0x9e37_79b9,
+            }),
+            max_failures: max_failures,
+        };
+        for service in services {
+            balance.push(service);
+        }
+        balance
+    }
+
+    /// Adds another service for requests to be distributed to.
+    pub fn push(&self, service: S) {
+        self.inner.lock().unwrap().list.push(Arc::new(Endpoint {
+            service: service,
+            load: Arc::new(Load {
+                in_flight: AtomicUsize::new(0),
+                failures: AtomicUsize::new(0),
+            }),
+        }));
+    }
+
+    /// Returns the number of services which haven't been removed for failing.
+    pub fn len(&self) -> usize {
+        let mut inner = self.inner.lock().unwrap();
+        self.remove_dead(&mut inner);
+        inner.list.len()
+    }
+
+    /// Returns whether every service has been removed for failing.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn remove_dead(&self, inner: &mut Endpoints<S>) {
+        let max = self.max_failures;
+        inner.list.retain(|e| e.load.failures.load(Ordering::SeqCst) < max);
+    }
+}
+
+impl<S> Endpoints<S> {
+    // xorshift, which is plenty for picking between endpoints.
+    fn next_index(&mut self) -> usize {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        self.rng as usize % self.list.len()
+    }
+
+    fn pick(&mut self) -> Option<&Arc<Endpoint<S>>> {
+        let idx = match self.list.len() {
+            0 => return None,
+            1 => 0,
+            n => {
+                let a = self.next_index();
+                let b = (a + 1 + self.next_index() % (n - 1)) % n;
+                let load = |i: usize| self.list[i].load.in_flight.load(Ordering::SeqCst);
+                if load(b) < load(a) { b } else { a }
+            }
+        };
+        Some(&self.list[idx])
+    }
+}
+
+impl<S: Service> Service for Balance<S> {
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = BalanceError<S::Error>;
+    type Future = BalanceFuture<S>;
+
+    fn call(&self, req: S::Request) -> BalanceFuture<S> {
+        let endpoint = {
+            let mut inner = self.inner.lock().unwrap();
+            self.remove_dead(&mut inner);
+            match inner.pick() {
+                Some(endpoint) => endpoint.clone(),
+                None => return BalanceFuture { state: State::Unavailable },
+            }
+        };
+        endpoint.load.in_flight.fetch_add(1, Ordering::SeqCst);
+        let in_flight = InFlight { load: endpoint.load.clone() };
+        BalanceFuture {
+            state: State::Running(endpoint.service.call(req), in_flight),
+        }
+    }
+}
+
+impl<S: Service> Future for BalanceFuture<S> {
+    type Item = S::Response;
+    type Error = BalanceError<S::Error>;
+
+    fn poll(&mut self) -> Poll<S::Response, BalanceError<S::Error>> {
+        let res = match self.state {
+            State::Running(ref mut future, ref in_flight) => {
+                match future.poll() {
+                    Ok(Async::NotReady) => return Ok(Async::NotReady),
+                    Ok(Async::Ready(resp)) => {
+                        in_flight.load.failures.store(0, Ordering::SeqCst);
+                        Ok(Async::Ready(resp))
+                    }
+                    Err(e) => {
+                        in_flight.load.failures.fetch_add(1, Ordering::SeqCst);
+                        Err(BalanceError::Service(e))
+                    }
+                }
+            }
+            State::Unavailable => Err(BalanceError::Unavailable),
+            State::Done => panic!("cannot poll BalanceFuture twice"),
+        };
+        self.state = State::Done;
+        res
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.load.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for BalanceError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BalanceError::Service(ref e) => e.fmt(f),
+            BalanceError::Unavailable => write!(f, "no services available"),
+        }
+    }
+}
+
+impl<E: Error> Error for BalanceError<E> {
+    fn description(&self) -> &str {
+        match *self {
+            BalanceError::Service(_) => "service failed",
+            BalanceError::Unavailable => "no services available",
+        }
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        match *self {
+            BalanceError::Service(ref e) => Some(e),
+            BalanceError::Unavailable => None,
+        }
+    }
+}
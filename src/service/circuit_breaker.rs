@@ -0,0 +1,417 @@
+use std::prelude::v1::*;
+
+use std::collections::{HashMap, VecDeque};
+use std::error::Error;
+use std::fmt;
+use std::mem;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use {Async, Future, Poll, Stream};
+use clock;
+use future::BoxFuture;
+use service::Service;
+use task::{self, Task};
+
+/// Middleware which stops sending requests to a failing service for a while.
+///
+/// The breaker keeps track of the outcomes of the most recent requests. While
+/// it's *closed*, requests pass through as usual. Once too many of the recent
+/// requests have failed it *opens*, and requests are rejected immediately
+/// with `CircuitBreakerError::Open` rather than adding to the load on a
+/// service which is struggling.
+///
+/// After a cool-down period the breaker becomes *half-open*, letting a single
+/// probe request through. If the probe succeeds the breaker closes again,
+/// otherwise it reopens for another cool-down period.
+///
+/// Time is measured by the clock of the current task (see the `clock`
+/// module). The cool-down is checked whenever a request is issued, and the
+/// streams returned by `state_changes` also wait for it on a timer, so that
+/// they report the breaker becoming half-open on time even while no requests
+/// are being issued.
+pub struct CircuitBreaker<S> {
+    inner: S,
+    shared: Arc<Mutex<Breaker>>,
+}
+
+/// Future for responses from the `CircuitBreaker` middleware.
+#[must_use = "futures do nothing unless polled"]
+pub struct CircuitBreakerFuture<S: Service> {
+    inner: Option<Pending<S>>,
+}
+
+struct Pending<S: Service> {
+    future: S::Future,
+    shared: Arc<Mutex<Breaker>>,
+    probe: bool,
+}
+
+/// Stream of the states a `CircuitBreaker` moves into, returned by
+/// `CircuitBreaker::state_changes`.
+///
+/// Like a watch channel, this only keeps track of the latest state: if the
+/// breaker changes state several times between two polls of the stream, only
+/// the state it's in at the time of the second poll is yielded. The stream
+/// ends once the `CircuitBreaker` is dropped.
+#[must_use = "streams do nothing unless polled"]
+pub struct CircuitStates {
+    shared: Arc<Mutex<Breaker>>,
+    id: usize,
+    seen: u64,
+    // The timer for the end of the cool-down of the open period with the
+    // given version.
+    cool_down: Option<(u64, BoxFuture<(), ()>)>,
+}
+
+/// The states that a `CircuitBreaker` moves between.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CircuitState {
+    /// Requests are passed through to the inner service.
+    Closed,
+
+    /// Requests are rejected without reaching the inner service.
+    Open,
+
+    /// A single probe request is allowed through to test whether the inner
+    /// service has recovered.
+    HalfOpen,
+}
+
+/// Errors produced by the `CircuitBreaker` middleware.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CircuitBreakerError<E> {
+    /// The inner service failed with an error.
+    Service(E),
+
+    /// The request was rejected because the circuit is open.
+    Open,
+}
+
+struct Breaker {
+    state: CircuitState,
+    opened_at: Instant,
+    probing: bool,
+
+    // Outcomes of the most recent requests, `true` for failures.
+    recent: VecDeque<bool>,
+    failures: usize,
+
+    window: usize,
+    max_failures: usize,
+    cool_down: Duration,
+
+    // Incremented on every state change, so that watchers can tell whether
+    // they've seen the current state.
+    version: u64,
+    dropped: bool,
+    watchers: HashMap<usize, Task>,
+    next_watcher: usize,
+    // Watchers to wake up once the lock on the breaker is released.
+    woken: Vec<Task>,
+}
+
+impl<S> CircuitBreaker<S> {
+    /// Wraps the service `inner` in a circuit breaker which opens once
+    /// `max_failures` of the last `window` requests have failed, and stays
+    /// open for `cool_down` before probing the service again.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if `max_failures` is 0 or greater than
+    /// `window`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use futures::Future;
+    /// use futures::service::{service_fn, CircuitBreaker, CircuitBreakerError, Service};
+    ///
+    /// let failing = service_fn(|_: u32| Err::<u32, _>("down"));
+    /// let service = CircuitBreaker::new(&failing, 2, 10, Duration::from_secs(30));
+    ///
+    /// assert_eq!(service.call(1).wait(), Err(CircuitBreakerError::Service("down")));
+    /// assert_eq!(service.call(2).wait(), Err(CircuitBreakerError::Service("down")));
+    /// assert_eq!(service.call(3).wait(), Err(CircuitBreakerError::Open));
+    /// ```
+    pub fn new(inner: S,
+               max_failures: usize,
+               window: usize,
+               cool_down: Duration) -> CircuitBreaker<S> {
+        assert!(max_failures > 0, "max_failures must be at least 1");
+        assert!(max_failures <= window, "max_failures cannot exceed the window");
+        CircuitBreaker {
+            inner: inner,
+            shared: Arc::new(Mutex::new(Breaker {
+                state: CircuitState::Closed,
+                opened_at: clock::now(),
+                probing: false,
+                recent: VecDeque::with_capacity(window),
+                failures: 0,
+                window: window,
+                max_failures: max_failures,
+                cool_down: cool_down,
+                version: 0,
+                dropped: false,
+                watchers: HashMap::new(),
+                next_watcher: 0,
+                woken: Vec::new(),
+            })),
+        }
+    }
+
+    /// Returns the current state of the breaker.
+    pub fn state(&self) -> CircuitState {
+        with_breaker(&self.shared, |breaker| {
+            breaker.check_cool_down();
+            breaker.state
+        })
+    }
+
+    /// Returns a stream of the states the breaker moves into from now on.
+    ///
+    /// This is intended for logging and alerting on outages. Each call
+    /// returns a new independent stream, which only yields the latest state
+    /// of the breaker, skipping states which were left again before the
+    /// stream was polled.
+    pub fn state_changes(&self) -> CircuitStates {
+        let mut breaker = self.shared.lock().unwrap();
+        let id = breaker.next_watcher;
+        breaker.next_watcher += 1;
+        CircuitStates {
+            shared: self.shared.clone(),
+            id: id,
+            seen: breaker.version,
+            cool_down: None,
+        }
+    }
+
+    /// Get a shared reference to the inner service.
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+}
+
+impl<S: Service> Service for CircuitBreaker<S> {
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = CircuitBreakerError<S::Error>;
+    type Future = CircuitBreakerFuture<S>;
+
+    fn call(&self, req: S::Request) -> CircuitBreakerFuture<S> {
+        let probe = with_breaker(&self.shared, |breaker| {
+            breaker.check_cool_down();
+            match breaker.state {
+                CircuitState::Closed => Some(false),
+                CircuitState::HalfOpen if !breaker.probing => {
+                    breaker.probing = true;
+                    Some(true)
+                }
+                CircuitState::HalfOpen |
+                CircuitState::Open => None,
+            }
+        });
+        let probe = match probe {
+            Some(probe) => probe,
+            None => return CircuitBreakerFuture { inner: None },
+        };
+        CircuitBreakerFuture {
+            inner: Some(Pending {
+                future: self.inner.call(req),
+                shared: self.shared.clone(),
+                probe: probe,
+            }),
+        }
+    }
+}
+
+impl<S: Service> Future for CircuitBreakerFuture<S> {
+    type Item = S::Response;
+    type Error = CircuitBreakerError<S::Error>;
+
+    fn poll(&mut self) -> Poll<S::Response, CircuitBreakerError<S::Error>> {
+        let res = match self.inner {
+            Some(ref mut pending) => {
+                match pending.future.poll() {
+                    Ok(Async::NotReady) => return Ok(Async::NotReady),
+                    res => res,
+                }
+            }
+            None => return Err(CircuitBreakerError::Open),
+        };
+        let mut pending = self.inner.take().unwrap();
+        let failed = res.is_err();
+        let probe = pending.probe;
+        with_breaker(&pending.shared, |breaker| breaker.record(failed, probe));
+        pending.probe = false;
+        res.map_err(CircuitBreakerError::Service)
+    }
+}
+
+impl<S: Service> Drop for Pending<S> {
+    fn drop(&mut self) {
+        // A probe which is abandoned before completing says nothing about the
+        // health of the service, so let another request probe instead.
+        if self.probe {
+            self.shared.lock().unwrap().probing = false;
+        }
+    }
+}
+
+impl<S> Drop for CircuitBreaker<S> {
+    fn drop(&mut self) {
+        with_breaker(&self.shared, |breaker| {
+            breaker.dropped = true;
+            breaker.wake_watchers();
+        });
+    }
+}
+
+impl Stream for CircuitStates {
+    type Item = CircuitState;
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<Option<CircuitState>, ()> {
+        // While the breaker is open, wait for the end of its cool-down so
+        // that it becomes half-open on time.
+        let open = with_breaker(&self.shared, |breaker| {
+            breaker.check_cool_down();
+            if breaker.state == CircuitState::Open {
+                Some((breaker.version, breaker.opened_at + breaker.cool_down))
+            } else {
+                None
+            }
+        });
+        match open {
+            Some((version, deadline)) => {
+                if self.cool_down.as_ref().map(|c| c.0) != Some(version) {
+                    self.cool_down = Some((version, clock::delay(deadline)));
+                }
+                match self.cool_down.as_mut().unwrap().1.poll() {
+                    Ok(Async::NotReady) => {}
+                    _ => with_breaker(&self.shared, |breaker| breaker.check_cool_down()),
+                }
+            }
+            None => self.cool_down = None,
+        }
+
+        let mut breaker = self.shared.lock().unwrap();
+        if breaker.version != self.seen {
+            self.seen = breaker.version;
+            Ok(Async::Ready(Some(breaker.state)))
+        } else if breaker.dropped {
+            Ok(Async::Ready(None))
+        } else {
+            breaker.watchers.insert(self.id, task::park());
+            Ok(Async::NotReady)
+        }
+    }
+}
+
+impl Drop for CircuitStates {
+    fn drop(&mut self) {
+        self.shared.lock().unwrap().watchers.remove(&self.id);
+    }
+}
+
+// Runs `f` with the breaker locked, and then wakes up the watchers of any
+// state changes it made once the lock has been released, as waking them up
+// may poll them right away.
+fn with_breaker<F, R>(shared: &Mutex<Breaker>, f: F) -> R
+    where F: FnOnce(&mut Breaker) -> R
+{
+    let (ret, woken) = {
+        let mut breaker = shared.lock().unwrap();
+        let ret = f(&mut breaker);
+        (ret, mem::replace(&mut breaker.woken, Vec::new()))
+    };
+    for task in woken {
+        task.unpark();
+    }
+    ret
+}
+
+impl Breaker {
+    fn check_cool_down(&mut self) {
+        if self.state == CircuitState::Open &&
+           clock::now() >= self.opened_at + self.cool_down {
+            self.transition(CircuitState::HalfOpen);
+        }
+    }
+
+    fn record(&mut self, failed: bool, probe: bool) {
+        if probe {
+            self.probing = false;
+            if failed {
+                self.open();
+            } else {
+                self.recent.clear();
+                self.failures = 0;
+                self.transition(CircuitState::Closed);
+            }
+            return
+        }
+
+        // Results from requests issued before the breaker opened don't count
+        // towards the next window.
+        if self.state != CircuitState::Closed {
+            return
+        }
+        if self.recent.len() == self.window && self.recent.pop_front() == Some(true) {
+            self.failures -= 1;
+        }
+        self.recent.push_back(failed);
+        if failed {
+            self.failures += 1;
+            if self.failures >= self.max_failures {
+                self.open();
+            }
+        }
+    }
+
+    fn open(&mut self) {
+        self.opened_at = clock::now();
+        self.transition(CircuitState::Open);
+    }
+
+    fn transition(&mut self, state: CircuitState) {
+        if self.state == state {
+            return
+        }
+        self.state = state;
+        self.version += 1;
+        self.wake_watchers();
+    }
+
+    fn wake_watchers(&mut self) {
+        for (_, task) in self.watchers.drain() {
+            self.woken.push(task);
+        }
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for CircuitBreakerError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CircuitBreakerError::Service(ref e) => e.fmt(f),
+            CircuitBreakerError::Open => write!(f, "circuit breaker is open"),
+        }
+    }
+}
+
+impl<E: Error> Error for CircuitBreakerError<E> {
+    fn description(&self) -> &str {
+        match *self {
+            CircuitBreakerError::Service(_) => "service failed",
+            CircuitBreakerError::Open => "circuit breaker is open",
+        }
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        match *self {
+            CircuitBreakerError::Service(ref e) => Some(e),
+            CircuitBreakerError::Open => None,
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use {Async, Future, Poll};
+use service::Service;
+use sync::{Acquire, Permit, Semaphore};
+
+/// Middleware which limits the number of requests in flight at once.
+///
+/// Each request must acquire a permit from a `Semaphore` before it is passed
+/// to the inner service, and the permit is held until the response future
+/// resolves or is dropped. Requests issued while all permits are taken wait,
+/// in the order they were issued, for a permit to be released.
+///
+/// Clones of a `ConcurrencyLimit` share the same semaphore, so the limit
+/// applies across all of them. A semaphore can also be shared explicitly
+/// through `ConcurrencyLimit::with_semaphore`, to cap the requests in flight
+/// across several different services.
+pub struct ConcurrencyLimit<S> {
+    inner: S,
+    semaphore: Semaphore,
+}
+
+/// Future for responses from the `ConcurrencyLimit` middleware.
+#[must_use = "futures do nothing unless polled"]
+pub struct ConcurrencyLimitFuture<S: Service> {
+    state: State<S>,
+}
+
+enum State<S: Service> {
+    Acquiring(S, Option<S::Request>, Acquire),
+    Calling { future: S::Future, _permit: Permit },
+    Done,
+}
+
+impl<S> ConcurrencyLimit<S> {
+    /// Wraps the service `inner`, allowing at most `max` requests to be in
+    /// flight at any one time.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if `max` is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::Future;
+    /// use futures::service::{service_fn, ConcurrencyLimit, Service};
+    ///
+    /// let echo = service_fn(|x: u32| Ok::<u32, ()>(x));
+    /// let service = ConcurrencyLimit::new(&echo, 2);
+    /// assert_eq!(service.call(1).wait(), Ok(1));
+    /// ```
+    pub fn new(inner: S, max: usize) -> ConcurrencyLimit<S> {
+        assert!(max > 0, "concurrency limit must be at least 1");
+        ConcurrencyLimit::with_semaphore(inner, Semaphore::new(max))
+    }
+
+    /// Wraps the service `inner`, acquiring a permit from `semaphore` for each
+    /// request.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::Future;
+    /// use futures::service::{service_fn, ConcurrencyLimit, Service};
+    /// use futures::sync::Semaphore;
+    ///
+    /// // At most 8 requests in flight between both services.
+    /// let semaphore = Semaphore::new(8);
+    /// let double = service_fn(|x: u32| Ok::<u32, ()>(x * 2));
+    /// let square = service_fn(|x: u32| Ok::<u32, ()>(x * x));
+    /// let double = ConcurrencyLimit::with_semaphore(&double, semaphore.clone());
+    /// let square = ConcurrencyLimit::with_semaphore(&square, semaphore.clone());
+    ///
+    /// assert_eq!(double.call(3).wait(), Ok(6));
+    /// assert_eq!(square.call(3).wait(), Ok(9));
+    /// ```
+    pub fn with_semaphore(inner: S, semaphore: Semaphore) -> ConcurrencyLimit<S> {
+        ConcurrencyLimit {
+            inner: inner,
+            semaphore: semaphore,
+        }
+    }
+
+    /// Get a reference to the semaphore limiting requests to this service.
+    pub fn semaphore(&self) -> &Semaphore {
+        &self.semaphore
+    }
+
+    /// Get a shared reference to the inner service.
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    /// Consume the middleware, returning the inner service.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: Clone> Clone for ConcurrencyLimit<S> {
+    fn clone(&self) -> ConcurrencyLimit<S> {
+        ConcurrencyLimit {
+            inner: self.inner.clone(),
+            semaphore: self.semaphore.clone(),
+        }
+    }
+}
+
+impl<S> Service for ConcurrencyLimit<S>
+    where S: Service + Clone,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ConcurrencyLimitFuture<S>;
+
+    fn call(&self, req: S::Request) -> ConcurrencyLimitFuture<S> {
+        ConcurrencyLimitFuture {
+            state: State::Acquiring(self.inner.clone(), Some(req), self.semaphore.acquire()),
+        }
+    }
+}
+
+impl<S: Service> Future for ConcurrencyLimitFuture<S> {
+    type Item = S::Response;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Poll<S::Response, S::Error> {
+        let next = match self.state {
+            State::Acquiring(ref service, ref mut req, ref mut acquire) => {
+                let permit = match acquire.poll() {
+                    Ok(Async::Ready(permit)) => permit,
+                    Ok(Async::NotReady) => return Ok(Async::NotReady),
+                    Err(()) => unreachable!(),
+                };
+                let req = req.take().expect("cannot poll twice");
+                State::Calling { future: service.call(req), _permit: permit }
+            }
+            State::Calling { ref mut future, .. } => {
+                match future.poll() {
+                    Ok(Async::NotReady) => return Ok(Async::NotReady),
+                    res => {
+                        // Release the permit as soon as the response is
+                        // available rather than whenever this is dropped.
+                        self.state = State::Done;
+                        return res
+                    }
+                }
+            }
+            State::Done => panic!("cannot poll ConcurrencyLimitFuture twice"),
+        };
+        self.state = next;
+        self.poll()
+    }
+}
@@ -0,0 +1,118 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use {Async, Future, Poll};
+use clock;
+use service::Service;
+
+/// Middleware which reports the outcome and latency of each request.
+///
+/// The provided callback is invoked once for every response, successful or
+/// not, with the result and the time elapsed since the request was issued,
+/// as measured by the clock of the current task (see the `clock` module).
+/// This is intended as a hook for logging and metrics collection; the
+/// response itself is passed through unchanged.
+pub struct Instrument<S, F> {
+    inner: S,
+    f: Arc<F>,
+}
+
+/// Future for responses from the `Instrument` middleware.
+#[must_use = "futures do nothing unless polled"]
+pub struct InstrumentFuture<S: Service, F> {
+    future: S::Future,
+    f: Arc<F>,
+    start: Instant,
+}
+
+impl<S, F> Instrument<S, F>
+    where S: Service,
+          F: Fn(&Result<S::Response, S::Error>, Duration),
+{
+    /// Wraps the service `inner`, calling `f` with each response and how long
+    /// it took to resolve.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::cell::Cell;
+    /// use futures::Future;
+    /// use futures::service::{service_fn, Instrument, Service};
+    ///
+    /// let echo = service_fn(|x: u32| if x > 0 { Ok(x) } else { Err(()) });
+    /// let errors = Cell::new(0);
+    /// let service = Instrument::new(&echo, |res: &Result<u32, ()>, _elapsed| {
+    ///     if res.is_err() {
+    ///         errors.set(errors.get() + 1);
+    ///     }
+    /// });
+    ///
+    /// assert_eq!(service.call(1).wait(), Ok(1));
+    /// assert_eq!(service.call(0).wait(), Err(()));
+    /// assert_eq!(errors.get(), 1);
+    /// ```
+    pub fn new(inner: S, f: F) -> Instrument<S, F> {
+        Instrument {
+            inner: inner,
+            f: Arc::new(f),
+        }
+    }
+}
+
+impl<S, F> Instrument<S, F> {
+    /// Get a shared reference to the inner service.
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+}
+
+impl<S: Clone, F> Clone for Instrument<S, F> {
+    fn clone(&self) -> Instrument<S, F> {
+        Instrument {
+            inner: self.inner.clone(),
+            f: self.f.clone(),
+        }
+    }
+}
+
+impl<S, F> Service for Instrument<S, F>
+    where S: Service,
+          F: Fn(&Result<S::Response, S::Error>, Duration),
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = InstrumentFuture<S, F>;
+
+    fn call(&self, req: S::Request) -> InstrumentFuture<S, F> {
+        InstrumentFuture {
+            start: clock::now(),
+            future: self.inner.call(req),
+            f: self.f.clone(),
+        }
+    }
+}
+
+impl<S, F> Future for InstrumentFuture<S, F>
+    where S: Service,
+          F: Fn(&Result<S::Response, S::Error>, Duration),
+{
+    type Item = S::Response;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Poll<S::Response, S::Error> {
+        let res = match self.future.poll() {
+            Ok(Async::NotReady) => return Ok(Async::NotReady),
+            Ok(Async::Ready(resp)) => Ok(resp),
+            Err(e) => Err(e),
+        };
+        let now = clock::now();
+        let elapsed = if now > self.start {
+            now - self.start
+        } else {
+            Duration::new(0, 0)
+        };
+        (self.f)(&res, elapsed);
+        res.map(Async::Ready)
+    }
+}
@@ -0,0 +1,90 @@
+//! Asynchronous services
+//!
+//! This module contains the `Service` trait, which represents an asynchronous
+//! function from a request to a response, along with a number of middleware
+//! types which wrap a service to layer on additional behavior such as retries,
+//! timeouts or limits on concurrency.
+//!
+//! Middleware are services themselves, so they can be freely composed with one
+//! another and with any other implementation of `Service`.
+
+use Future;
+
+mod retry;
+mod service_fn;
+pub use self::retry::{Retry, RetryFuture};
+pub use self::service_fn::{service_fn, ServiceFn};
+
+if_std! {
+    mod balance;
+    mod circuit_breaker;
+    mod concurrency_limit;
+    mod instrument;
+    mod timeout;
+    pub use self::balance::{Balance, BalanceError, BalanceFuture};
+    pub use self::circuit_breaker::{CircuitBreaker, CircuitBreakerError, CircuitBreakerFuture};
+    pub use self::circuit_breaker::{CircuitState, CircuitStates};
+    pub use self::concurrency_limit::{ConcurrencyLimit, ConcurrencyLimitFuture};
+    pub use self::instrument::{Instrument, InstrumentFuture};
+    pub use self::timeout::{Timeout, TimeoutError, TimeoutFuture};
+
+    impl<S: ?Sized + Service> Service for ::std::boxed::Box<S> {
+        type Request = S::Request;
+        type Response = S::Response;
+        type Error = S::Error;
+        type Future = S::Future;
+
+        fn call(&self, req: S::Request) -> S::Future {
+            (**self).call(req)
+        }
+    }
+
+    impl<S: ?Sized + Service> Service for ::std::sync::Arc<S> {
+        type Request = S::Request;
+        type Response = S::Response;
+        type Error = S::Error;
+        type Future = S::Future;
+
+        fn call(&self, req: S::Request) -> S::Future {
+            (**self).call(req)
+        }
+    }
+}
+
+/// An asynchronous function from a `Request` to a `Response`.
+///
+/// A `Service` is the basic unit of composition for request/response style
+/// protocols. Each call to `call` processes a single request, returning a
+/// future of its response. Services are frequently wrapped in middleware which
+/// add behavior to every request, without either side needing to know about
+/// the other.
+///
+/// Note that `call` takes `&self`, so a service may be invoked any number of
+/// times concurrently, with each returned future resolving independently.
+pub trait Service {
+    /// Requests handled by the service.
+    type Request;
+
+    /// Responses given by the service.
+    type Response;
+
+    /// Errors produced by the service.
+    type Error;
+
+    /// The future response value.
+    type Future: Future<Item = Self::Response, Error = Self::Error>;
+
+    /// Process the request and return the response asynchronously.
+    fn call(&self, req: Self::Request) -> Self::Future;
+}
+
+impl<'a, S: ?Sized + Service> Service for &'a S {
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn call(&self, req: S::Request) -> S::Future {
+        (**self).call(req)
+    }
+}
@@ -0,0 +1,108 @@
+use {Future, Poll};
+use service::Service;
+
+/// Middleware which retries failed requests.
+///
+/// Each request is first issued to the inner service as usual. If the
+/// response resolves to an error then the request is issued again, up to a
+/// configured number of times, before giving up and returning the last error
+/// seen.
+///
+/// Requests must be `Clone` so they can be reissued, and the inner service
+/// must be `Clone` so the returned futures can issue requests without
+/// borrowing from this middleware.
+#[derive(Clone)]
+pub struct Retry<S> {
+    inner: S,
+    max_retries: usize,
+}
+
+/// Future for responses from the `Retry` middleware.
+#[must_use = "futures do nothing unless polled"]
+pub struct RetryFuture<S: Service> {
+    service: S,
+    req: S::Request,
+    future: S::Future,
+    remaining: usize,
+}
+
+impl<S> Retry<S> {
+    /// Wraps the service `inner`, retrying each failed request up to
+    /// `max_retries` more times.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::cell::Cell;
+    /// use futures::Future;
+    /// use futures::service::{service_fn, Retry, Service};
+    ///
+    /// let attempts = Cell::new(0);
+    /// let flaky = service_fn(|x: u32| {
+    ///     attempts.set(attempts.get() + 1);
+    ///     if attempts.get() < 3 { Err("try again") } else { Ok(x) }
+    /// });
+    ///
+    /// let service = Retry::new(&flaky, 5);
+    /// assert_eq!(service.call(7).wait(), Ok(7));
+    /// assert_eq!(attempts.get(), 3);
+    /// ```
+    pub fn new(inner: S, max_retries: usize) -> Retry<S> {
+        Retry {
+            inner: inner,
+            max_retries: max_retries,
+        }
+    }
+
+    /// Get a shared reference to the inner service.
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    /// Consume the middleware, returning the inner service.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S> Service for Retry<S>
+    where S: Service + Clone,
+          S::Request: Clone,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = RetryFuture<S>;
+
+    fn call(&self, req: S::Request) -> RetryFuture<S> {
+        RetryFuture {
+            future: self.inner.call(req.clone()),
+            service: self.inner.clone(),
+            req: req,
+            remaining: self.max_retries,
+        }
+    }
+}
+
+impl<S> Future for RetryFuture<S>
+    where S: Service,
+          S::Request: Clone,
+{
+    type Item = S::Response;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Poll<S::Response, S::Error> {
+        loop {
+            match self.future.poll() {
+                Err(e) => {
+                    if self.remaining == 0 {
+                        return Err(e)
+                    }
+                    self.remaining -= 1;
+                    self.future = self.service.call(self.req.clone());
+                }
+                res => return res,
+            }
+        }
+    }
+}
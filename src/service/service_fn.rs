@@ -0,0 +1,59 @@
+use core::marker::PhantomData;
+
+use IntoFuture;
+use service::Service;
+
+/// A service implemented by a closure.
+///
+/// This is created by the `service_fn` function.
+pub struct ServiceFn<F, Req> {
+    f: F,
+    _req: PhantomData<fn(Req)>,
+}
+
+/// Creates a new service which processes requests by calling `f`.
+///
+/// The closure provided may return anything which can be converted into a
+/// future, including a plain `Result`.
+///
+/// # Examples
+///
+/// ```
+/// use futures::Future;
+/// use futures::service::{service_fn, Service};
+///
+/// let double = service_fn(|x: u32| Ok::<u32, ()>(x * 2));
+/// assert_eq!(double.call(21).wait(), Ok(42));
+/// ```
+pub fn service_fn<F, Req, R>(f: F) -> ServiceFn<F, Req>
+    where F: Fn(Req) -> R,
+          R: IntoFuture,
+{
+    ServiceFn {
+        f: f,
+        _req: PhantomData,
+    }
+}
+
+impl<F, Req, R> Service for ServiceFn<F, Req>
+    where F: Fn(Req) -> R,
+          R: IntoFuture,
+{
+    type Request = Req;
+    type Response = R::Item;
+    type Error = R::Error;
+    type Future = R::Future;
+
+    fn call(&self, req: Req) -> R::Future {
+        (self.f)(req).into_future()
+    }
+}
+
+impl<F: Clone, Req> Clone for ServiceFn<F, Req> {
+    fn clone(&self) -> ServiceFn<F, Req> {
+        ServiceFn {
+            f: self.f.clone(),
+            _req: PhantomData,
+        }
+    }
+}
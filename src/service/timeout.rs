@@ -0,0 +1,124 @@
+use std::prelude::v1::*;
+
+use std::error::Error;
+use std::fmt;
+use std::time::Duration;
+
+use {Async, Future, Poll};
+use clock;
+use future::BoxFuture;
+use service::Service;
+
+/// Middleware which fails requests that take too long to resolve.
+///
+/// The deadline of each request starts when it is issued, and is measured and
+/// waited for with the clock of the current task (see the `clock` module).
+/// Once a request runs past its deadline its response future is dropped and
+/// the request fails with `TimeoutError::Elapsed`.
+#[derive(Clone)]
+pub struct Timeout<S> {
+    inner: S,
+    dur: Duration,
+}
+
+/// Future for responses from the `Timeout` middleware.
+#[must_use = "futures do nothing unless polled"]
+pub struct TimeoutFuture<S: Service> {
+    future: S::Future,
+    delay: BoxFuture<(), ()>,
+}
+
+/// Errors produced by the `Timeout` middleware.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TimeoutError<E> {
+    /// The inner service failed with an error.
+    Service(E),
+
+    /// The request didn't resolve before its deadline.
+    Elapsed,
+}
+
+impl<S> Timeout<S> {
+    /// Wraps the service `inner`, failing requests which take longer than
+    /// `dur` to resolve.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use futures::{future, Future};
+    /// use futures::service::{service_fn, Service, Timeout, TimeoutError};
+    ///
+    /// let slow = service_fn(|()| future::empty::<(), ()>());
+    /// let service = Timeout::new(&slow, Duration::from_millis(10));
+    ///
+    /// assert_eq!(service.call(()).wait(), Err(TimeoutError::Elapsed));
+    /// ```
+    pub fn new(inner: S, dur: Duration) -> Timeout<S> {
+        Timeout {
+            inner: inner,
+            dur: dur,
+        }
+    }
+
+    /// Get a shared reference to the inner service.
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+}
+
+impl<S: Service> Service for Timeout<S> {
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = TimeoutError<S::Error>;
+    type Future = TimeoutFuture<S>;
+
+    fn call(&self, req: S::Request) -> TimeoutFuture<S> {
+        TimeoutFuture {
+            delay: clock::delay(clock::now() + self.dur),
+            future: self.inner.call(req),
+        }
+    }
+}
+
+impl<S: Service> Future for TimeoutFuture<S> {
+    type Item = S::Response;
+    type Error = TimeoutError<S::Error>;
+
+    fn poll(&mut self) -> Poll<S::Response, TimeoutError<S::Error>> {
+        match self.future.poll() {
+            Ok(Async::NotReady) => {}
+            Ok(Async::Ready(resp)) => return Ok(Async::Ready(resp)),
+            Err(e) => return Err(TimeoutError::Service(e)),
+        }
+        match self.delay.poll() {
+            Ok(Async::NotReady) => Ok(Async::NotReady),
+            _ => Err(TimeoutError::Elapsed),
+        }
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for TimeoutError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TimeoutError::Service(ref e) => e.fmt(f),
+            TimeoutError::Elapsed => write!(f, "request timed out"),
+        }
+    }
+}
+
+impl<E: Error> Error for TimeoutError<E> {
+    fn description(&self) -> &str {
+        match *self {
+            TimeoutError::Service(_) => "service failed",
+            TimeoutError::Elapsed => "request timed out",
+        }
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        match *self {
+            TimeoutError::Service(ref e) => Some(e),
+            TimeoutError::Elapsed => None,
+        }
+    }
+}
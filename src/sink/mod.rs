@@ -13,6 +13,7 @@ mod with;
 mod flush;
 mod send;
 mod send_all;
+mod send_iter;
 
 if_std! {
     mod buffer;
@@ -58,6 +59,7 @@ if_std! {
 pub use self::with::With;
 pub use self::flush::Flush;
 pub use self::send::Send;
+pub use self::send_iter::SendIter;
 pub use self::send_all::SendAll;
 
 /// A `Sink` is a value into which other values can be sent, asynchronously.
@@ -262,6 +264,31 @@ pub trait Sink {
     {
         send_all::new(self, stream)
     }
+
+    /// A future that completes after the items of the given iterator have
+    /// been fully processed into the sink, including flushing.
+    ///
+    /// This is like `send_all`, except that it sends items which are already
+    /// available in memory, avoiding the need to wrap them up in a stream
+    /// first. Items are only taken from the iterator as the sink is ready to
+    /// accept them, and the sink is flushed once the iterator is exhausted.
+    ///
+    /// On completion, the sink is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::{Future, Sink};
+    ///
+    /// let sink = Vec::new().send_iter(vec![1, 2, 3]).wait().unwrap();
+    /// assert_eq!(sink, [1, 2, 3]);
+    /// ```
+    fn send_iter<I>(self, iter: I) -> SendIter<Self, I::IntoIter>
+        where I: IntoIterator<Item = Self::SinkItem>,
+              Self: Sized
+    {
+        send_iter::new(self, iter.into_iter())
+    }
 }
 
 impl<'a, S: ?Sized + Sink> Sink for &'a mut S {
@@ -0,0 +1,65 @@
+use core::iter::Fuse;
+
+use {Poll, Async, Future, AsyncSink};
+use sink::Sink;
+
+/// Future for the `Sink::send_iter` combinator, which sends the items of an
+/// iterator to a sink and then waits until the sink has fully flushed them.
+#[must_use = "futures do nothing unless polled"]
+pub struct SendIter<T: Sink, I> {
+    sink: Option<T>,
+    iter: Fuse<I>,
+    buffered: Option<T::SinkItem>,
+}
+
+pub fn new<T, I>(sink: T, iter: I) -> SendIter<T, I>
+    where T: Sink,
+          I: Iterator<Item = T::SinkItem>,
+{
+    SendIter {
+        sink: Some(sink),
+        iter: iter.fuse(),
+        buffered: None,
+    }
+}
+
+impl<T, I> SendIter<T, I>
+    where T: Sink,
+          I: Iterator<Item = T::SinkItem>,
+{
+    fn sink_mut(&mut self) -> &mut T {
+        self.sink.as_mut().expect("Attempted to poll SendIter after completion")
+    }
+
+    fn try_start_send(&mut self, item: T::SinkItem) -> Poll<(), T::SinkError> {
+        debug_assert!(self.buffered.is_none());
+        if let AsyncSink::NotReady(item) = try!(self.sink_mut().start_send(item)) {
+            self.buffered = Some(item);
+            return Ok(Async::NotReady)
+        }
+        Ok(Async::Ready(()))
+    }
+}
+
+impl<T, I> Future for SendIter<T, I>
+    where T: Sink,
+          I: Iterator<Item = T::SinkItem>,
+{
+    type Item = T;
+    type Error = T::SinkError;
+
+    fn poll(&mut self) -> Poll<T, T::SinkError> {
+        // If we've got an item buffered already, we need to write it to the
+        // sink before we can do anything else
+        if let Some(item) = self.buffered.take() {
+            try_ready!(self.try_start_send(item))
+        }
+
+        while let Some(item) = self.iter.next() {
+            try_ready!(self.try_start_send(item))
+        }
+
+        try_ready!(self.sink_mut().poll_complete());
+        Ok(Async::Ready(self.sink.take().unwrap()))
+    }
+}
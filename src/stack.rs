@@ -8,7 +8,7 @@ use std::mem;
 use std::marker;
 use std::sync::atomic::Ordering::SeqCst;
 
-use task::EventSet;
+use task::{EventSet, UnparkId};
 
 pub struct Stack<T> {
     head: AtomicUsize,
@@ -136,3 +136,9 @@ impl EventSet for Stack<usize> {
         self.push(id);
     }
 }
+
+impl UnparkId for Stack<usize> {
+    fn unpark_id(&self, id: usize) {
+        self.push(id);
+    }
+}
@@ -2,7 +2,7 @@ use std::prelude::v1::*;
 use std::mem;
 use std::sync::Arc;
 
-use task::{self, UnparkEvent};
+use task;
 
 use {Async, IntoFuture, Poll, Future};
 use stream::{Stream, Fuse};
@@ -71,8 +71,8 @@ impl<S> BufferUnordered<S>
         while let Some(idx) = self.pending.next() {
             let result = match self.futures[idx] {
                 Slot::Data(ref mut f) => {
-                    let event = UnparkEvent::new(self.stack.clone(), idx);
-                    match task::with_unpark_event(event, || f.poll()) {
+                    let stack = self.stack.clone();
+                    match task::with_unpark_id(stack, idx, || f.poll()) {
                         Ok(Async::NotReady) => continue,
                         Ok(Async::Ready(e)) => Ok(Async::Ready(Some(e))),
                         Err(e) => Err(e),
@@ -0,0 +1,100 @@
+use std::mem;
+use std::prelude::v1::*;
+
+use {Async, Poll};
+use stream::{Stream, Fuse};
+
+/// An adaptor that chunks up elements in a vector, starting a new chunk
+/// wherever a predicate says there's a boundary.
+///
+/// This is created by the `Stream::chunked_by` method.
+#[must_use = "streams do nothing unless polled"]
+pub struct ChunkedBy<S, F>
+    where S: Stream
+{
+    items: Vec<S::Item>,
+    err: Option<S::Error>,
+    stream: Fuse<S>,
+    f: F,
+}
+
+pub fn new<S, F>(s: S, f: F) -> ChunkedBy<S, F>
+    where S: Stream,
+          F: FnMut(&[S::Item], &S::Item) -> bool,
+{
+    ChunkedBy {
+        items: Vec::new(),
+        err: None,
+        stream: super::fuse::new(s),
+        f: f,
+    }
+}
+
+// Forwarding impl of Sink from the underlying stream
+impl<S, F> ::sink::Sink for ChunkedBy<S, F>
+    where S: ::sink::Sink + Stream
+{
+    type SinkItem = S::SinkItem;
+    type SinkError = S::SinkError;
+
+    fn start_send(&mut self, item: S::SinkItem) -> ::StartSend<S::SinkItem, S::SinkError> {
+        self.stream.start_send(item)
+    }
+
+    fn poll_complete(&mut self) -> Poll<(), S::SinkError> {
+        self.stream.poll_complete()
+    }
+}
+
+impl<S, F> Stream for ChunkedBy<S, F>
+    where S: Stream,
+          F: FnMut(&[S::Item], &S::Item) -> bool,
+{
+    type Item = Vec<S::Item>;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        if let Some(err) = self.err.take() {
+            return Err(err)
+        }
+
+        loop {
+            match self.stream.poll() {
+                Ok(Async::NotReady) => return Ok(Async::NotReady),
+
+                // If this item starts a new chunk then hand back the current
+                // one, and the item becomes the start of the next.
+                Ok(Async::Ready(Some(item))) => {
+                    if !self.items.is_empty() && (self.f)(&self.items, &item) {
+                        let full_buf = mem::replace(&mut self.items, vec![item]);
+                        return Ok(Some(full_buf).into())
+                    }
+                    self.items.push(item);
+                }
+
+                // Since the underlying stream ran out of values, return what we
+                // have buffered, if we have anything.
+                Ok(Async::Ready(None)) => {
+                    return if self.items.is_empty() {
+                        Ok(Async::Ready(None))
+                    } else {
+                        let full_buf = mem::replace(&mut self.items, Vec::new());
+                        Ok(Some(full_buf).into())
+                    }
+                }
+
+                // If we've got buffered items be sure to return them first,
+                // we'll defer our error for later.
+                Err(e) => {
+                    if self.items.is_empty() {
+                        return Err(e)
+                    } else {
+                        self.err = Some(e);
+                        let full_buf = mem::replace(&mut self.items, Vec::new());
+                        return Ok(Some(full_buf).into())
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,224 @@
+use std::prelude::v1::*;
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::mem;
+use std::time::{Duration, Instant};
+
+use {Async, AsyncSink, Future, IntoFuture, Poll};
+use clock;
+use future::BoxFuture;
+use sink::Sink;
+use stream::{Fuse, Stream};
+
+/// Future for the `Stream::collect_into_sink_groups` combinator, which routes
+/// each item of a stream to a sink picked by its key, creating the sinks on
+/// demand.
+#[must_use = "futures do nothing unless polled"]
+pub struct CollectIntoSinkGroups<S, K, F, G, R>
+    where S: Stream,
+          R: IntoFuture,
+{
+    stream: Fuse<S>,
+    key: F,
+    factory: G,
+    groups: HashMap<K, Group<R>>,
+    buffered: Option<(K, S::Item)>,
+    idle: Option<Duration>,
+    // The timer for the next time a sink goes idle
+    idle_delay: Option<(Instant, BoxFuture<(), ()>)>,
+}
+
+enum Group<R: IntoFuture> {
+    Creating(R::Future),
+    // A sink, along with when it was last sent an item if idle sinks are
+    // being closed
+    Ready(R::Item, Option<Instant>),
+}
+
+pub fn new<S, K, F, G, R>(stream: S, key: F, factory: G)
+                          -> CollectIntoSinkGroups<S, K, F, G, R>
+    where S: Stream,
+          K: Eq + Hash + Clone,
+          F: FnMut(&S::Item) -> K,
+          G: FnMut(&K) -> R,
+          R: IntoFuture,
+          R::Item: Sink<SinkItem = S::Item>,
+          S::Error: From<R::Error> + From<<R::Item as Sink>::SinkError>,
+{
+    CollectIntoSinkGroups {
+        stream: stream.fuse(),
+        key: key,
+        factory: factory,
+        groups: HashMap::new(),
+        buffered: None,
+        idle: None,
+        idle_delay: None,
+    }
+}
+
+impl<S, K, F, G, R> CollectIntoSinkGroups<S, K, F, G, R>
+    where S: Stream,
+          R: IntoFuture,
+{
+    /// Closes sinks which haven't been sent an item for `dur`.
+    ///
+    /// Once a sink has gone idle and has been flushed, it's dropped, and it
+    /// won't be part of the map this future resolves to. If an item with its
+    /// key arrives later on, a new sink is created for it by the factory.
+    /// Time is measured by the clock of the task polling this future, see the
+    /// `clock` module.
+    pub fn close_idle_after(mut self, dur: Duration) -> Self {
+        self.idle = Some(dur);
+        self
+    }
+}
+
+impl<S, K, F, G, R> CollectIntoSinkGroups<S, K, F, G, R>
+    where S: Stream,
+          K: Eq + Hash + Clone,
+          F: FnMut(&S::Item) -> K,
+          G: FnMut(&K) -> R,
+          R: IntoFuture,
+          R::Item: Sink<SinkItem = S::Item>,
+          S::Error: From<R::Error> + From<<R::Item as Sink>::SinkError>,
+{
+    // Attempts to hand the buffered item off to its group's sink, creating
+    // the sink first if need be.
+    fn try_send_buffered(&mut self) -> Poll<(), S::Error> {
+        let (key, item) = match self.buffered.take() {
+            Some(pair) => pair,
+            None => return Ok(Async::Ready(())),
+        };
+        if !self.groups.contains_key(&key) {
+            let future = (self.factory)(&key).into_future();
+            self.groups.insert(key.clone(), Group::Creating(future));
+        }
+
+        let group = self.groups.get_mut(&key).unwrap();
+        let (sink, last_used) = match *group {
+            Group::Ready(ref mut sink, ref mut last_used) => (sink, last_used),
+            Group::Creating(ref mut future) => {
+                match try!(future.poll()) {
+                    Async::Ready(sink) => {
+                        *group = Group::Ready(sink, None);
+                        match *group {
+                            Group::Ready(ref mut sink, ref mut last_used) => {
+                                (sink, last_used)
+                            }
+                            Group::Creating(_) => unreachable!(),
+                        }
+                    }
+                    Async::NotReady => {
+                        self.buffered = Some((key, item));
+                        return Ok(Async::NotReady)
+                    }
+                }
+            }
+        };
+        if let AsyncSink::NotReady(item) = try!(sink.start_send(item)) {
+            self.buffered = Some((key, item));
+            return Ok(Async::NotReady)
+        }
+        if self.idle.is_some() {
+            *last_used = Some(clock::now());
+        }
+        Ok(Async::Ready(()))
+    }
+
+    // Flushes every sink created so far, returning whether they're all done,
+    // and closes the sinks which have gone idle.
+    fn poll_complete_all(&mut self) -> Poll<(), S::Error> {
+        loop {
+            let idle = self.idle;
+            let now = idle.map(|_| clock::now());
+            let mut done = true;
+            let mut closed = Vec::new();
+            let mut next_deadline: Option<Instant> = None;
+            for (key, group) in self.groups.iter_mut() {
+                if let Group::Ready(ref mut sink, last_used) = *group {
+                    let flushed = try!(sink.poll_complete()).is_ready();
+                    if !flushed {
+                        done = false;
+                    }
+                    if let (Some(dur), Some(now), Some(last_used)) = (idle, now, last_used) {
+                        let deadline = last_used + dur;
+                        if deadline > now {
+                            if next_deadline.map(|next| deadline < next).unwrap_or(true) {
+                                next_deadline = Some(deadline);
+                            }
+                        } else if flushed {
+                            closed.push(key.clone());
+                        }
+                    }
+                }
+            }
+            for key in closed {
+                self.groups.remove(&key);
+            }
+
+            // Wait for the next sink to go idle, so that it's closed on time.
+            match next_deadline {
+                Some(deadline) => {
+                    if self.idle_delay.as_ref().map(|d| d.0) != Some(deadline) {
+                        self.idle_delay = Some((deadline, clock::delay(deadline)));
+                    }
+                    if let Ok(Async::Ready(())) = self.idle_delay.as_mut().unwrap().1.poll() {
+                        continue
+                    }
+                }
+                None => self.idle_delay = None,
+            }
+            return Ok(if done { Async::Ready(()) } else { Async::NotReady })
+        }
+    }
+
+    fn take_sinks(&mut self) -> HashMap<K, R::Item> {
+        mem::replace(&mut self.groups, HashMap::new())
+            .into_iter()
+            .map(|(key, group)| {
+                match group {
+                    Group::Ready(sink, _) => (key, sink),
+                    Group::Creating(_) => unreachable!(),
+                }
+            })
+            .collect()
+    }
+}
+
+impl<S, K, F, G, R> Future for CollectIntoSinkGroups<S, K, F, G, R>
+    where S: Stream,
+          K: Eq + Hash + Clone,
+          F: FnMut(&S::Item) -> K,
+          G: FnMut(&K) -> R,
+          R: IntoFuture,
+          R::Item: Sink<SinkItem = S::Item>,
+          S::Error: From<R::Error> + From<<R::Item as Sink>::SinkError>,
+{
+    type Item = HashMap<K, R::Item>;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Poll<HashMap<K, R::Item>, S::Error> {
+        loop {
+            if try!(self.try_send_buffered()).is_not_ready() {
+                try!(self.poll_complete_all());
+                return Ok(Async::NotReady)
+            }
+
+            match try!(self.stream.poll()) {
+                Async::Ready(Some(item)) => {
+                    let key = (self.key)(&item);
+                    self.buffered = Some((key, item));
+                }
+                Async::Ready(None) => {
+                    try_ready!(self.poll_complete_all());
+                    return Ok(Async::Ready(self.take_sinks()))
+                }
+                Async::NotReady => {
+                    try!(self.poll_complete_all());
+                    return Ok(Async::NotReady)
+                }
+            }
+        }
+    }
+}
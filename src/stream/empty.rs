@@ -1,6 +1,6 @@
 use core::marker;
 
-use stream::Stream;
+use stream::{Stream, FusedStream};
 use {Poll, Async};
 
 /// A stream which contains no elements.
@@ -26,3 +26,9 @@ impl<T, E> Stream for Empty<T, E> {
         Ok(Async::Ready(None))
     }
 }
+
+impl<T, E> FusedStream for Empty<T, E> {
+    fn is_terminated(&self) -> bool {
+        true
+    }
+}
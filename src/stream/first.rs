@@ -0,0 +1,32 @@
+use {Future, Poll, Async};
+use stream::Stream;
+
+/// A future which resolves to the first element of a stream.
+///
+/// This future is returned by the `Stream::first` method.
+#[must_use = "futures do nothing unless polled"]
+pub struct First<S> {
+    stream: Option<S>,
+}
+
+pub fn new<S: Stream>(s: S) -> First<S> {
+    First {
+        stream: Some(s),
+    }
+}
+
+impl<S: Stream> Future for First<S> {
+    type Item = Option<S::Item>;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Poll<Option<S::Item>, S::Error> {
+        let item = {
+            let s = self.stream.as_mut().expect("cannot poll First twice");
+            try_ready!(s.poll())
+        };
+        // The rest of the stream isn't needed, so it's dropped right away to
+        // release any resources it holds.
+        self.stream = None;
+        Ok(Async::Ready(item))
+    }
+}
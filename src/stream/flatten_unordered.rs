@@ -0,0 +1,167 @@
+use std::prelude::v1::*;
+use std::mem;
+use std::sync::Arc;
+
+use task;
+
+use {Async, Poll};
+use stream::{Stream, Fuse};
+use stack::{Stack, Drain};
+
+/// A combinator used to flatten a stream-of-streams, driving several of the
+/// inner streams concurrently.
+///
+/// This combinator is created by the `Stream::flatten_unordered` method.
+#[must_use = "streams do nothing unless polled"]
+pub struct FlattenUnordered<S>
+    where S: Stream,
+          S::Item: Stream,
+{
+    stream: Fuse<S>,
+
+    // A slab of the inner streams being driven, in the same form as the slab
+    // of futures in `BufferUnordered`.
+    streams: Vec<Slot<S::Item>>,
+    next_stream: usize,
+
+    // The IDs of the inner streams to poll, pushed onto by their tasks
+    // through `with_unpark_id`, and the last batch drained from it.
+    stack: Arc<Stack<usize>>,
+    pending: Drain<usize>,
+
+    // Number of active streams in the `streams` slab
+    active: usize,
+}
+
+enum Slot<T> {
+    Next(usize),
+    Data(T),
+}
+
+type InnerPoll<S> = Poll<Option<<S as Stream>::Item>, <S as Stream>::Error>;
+
+pub fn new<S>(s: S, limit: usize) -> FlattenUnordered<S>
+    where S: Stream,
+          S::Item: Stream,
+          <S::Item as Stream>::Error: From<S::Error>,
+{
+    assert!(limit > 0, "cannot flatten with a limit of 0");
+    FlattenUnordered {
+        stream: super::fuse::new(s),
+        streams: (0..limit).map(|i| Slot::Next(i + 1)).collect(),
+        next_stream: 0,
+        pending: Stack::new().drain(),
+        stack: Arc::new(Stack::new()),
+        active: 0,
+    }
+}
+
+impl<S> FlattenUnordered<S>
+    where S: Stream,
+          S::Item: Stream,
+          <S::Item as Stream>::Error: From<S::Error>,
+{
+    // Polls the inner streams in `pending`, returning the first item or error
+    // produced by one of them, along with whether any of them finished.
+    fn poll_pending(&mut self) -> (Option<InnerPoll<S::Item>>, bool) {
+        let mut finished = false;
+        for idx in self.pending.by_ref() {
+            let result = match self.streams[idx] {
+                Slot::Data(ref mut s) => {
+                    let stack = self.stack.clone();
+                    match task::with_unpark_id(stack, idx, || s.poll()) {
+                        Ok(Async::NotReady) => continue,
+                        Ok(Async::Ready(Some(e))) => Ok(Async::Ready(Some(e))),
+                        Ok(Async::Ready(None)) => {
+                            finished = true;
+                            self.active -= 1;
+                            self.streams[idx] = Slot::Next(self.next_stream);
+                            self.next_stream = idx;
+                            continue
+                        }
+                        Err(e) => Err(e),
+                    }
+                },
+                Slot::Next(_) => continue,
+            };
+            // The stream may have more elements ready, so it's polled again
+            // the next time around.
+            self.stack.push(idx);
+            return (Some(result), finished)
+        }
+        (None, finished)
+    }
+}
+
+impl<S> Stream for FlattenUnordered<S>
+    where S: Stream,
+          S::Item: Stream,
+          <S::Item as Stream>::Error: From<S::Error>,
+{
+    type Item = <S::Item as Stream>::Item;
+    type Error = <S::Item as Stream>::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        loop {
+            // Take as many inner streams as there are free slots for.
+            while self.next_stream < self.streams.len() {
+                let stream = match try!(self.stream.poll()) {
+                    Async::Ready(Some(s)) => s,
+                    Async::Ready(None) |
+                    Async::NotReady => break,
+                };
+                self.active += 1;
+                self.stack.push(self.next_stream);
+                match mem::replace(&mut self.streams[self.next_stream],
+                                   Slot::Data(stream)) {
+                    Slot::Next(next) => self.next_stream = next,
+                    Slot::Data(_) => panic!(),
+                }
+            }
+
+            // Then poll the inner streams we've been notified about, first
+            // those left over from last time and then any new ones.
+            let (ret, mut finished) = self.poll_pending();
+            if let Some(ret) = ret {
+                return ret
+            }
+            assert!(self.pending.next().is_none());
+            self.pending = self.stack.drain();
+            let (ret, also_finished) = self.poll_pending();
+            if let Some(ret) = ret {
+                return ret
+            }
+            finished = finished || also_finished;
+
+            // If an inner stream finished then its slot can be filled with the
+            // next one from the underlying stream, otherwise there's nothing
+            // left to do for now.
+            if !finished || self.stream.is_done() {
+                break
+            }
+        }
+
+        Ok(if self.active > 0 || !self.stream.is_done() {
+            Async::NotReady
+        } else {
+            Async::Ready(None)
+        })
+    }
+}
+
+// Forwarding impl of Sink from the underlying stream
+impl<S> ::sink::Sink for FlattenUnordered<S>
+    where S: ::sink::Sink + Stream,
+          S::Item: Stream,
+{
+    type SinkItem = S::SinkItem;
+    type SinkError = S::SinkError;
+
+    fn start_send(&mut self, item: S::SinkItem) -> ::StartSend<S::SinkItem, S::SinkError> {
+        self.stream.start_send(item)
+    }
+
+    fn poll_complete(&mut self) -> Poll<(), S::SinkError> {
+        self.stream.poll_complete()
+    }
+}
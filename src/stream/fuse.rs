@@ -1,5 +1,5 @@
 use {Poll, Async};
-use stream::Stream;
+use stream::{Stream, FusedStream};
 
 /// A stream which "fuse"s a stream once it's terminated.
 ///
@@ -49,6 +49,12 @@ impl<S: Stream> Stream for Fuse<S> {
     }
 }
 
+impl<S: Stream> FusedStream for Fuse<S> {
+    fn is_terminated(&self) -> bool {
+        self.done
+    }
+}
+
 impl<S> Fuse<S> {
     /// Returns whether the underlying stream has finished or not.
     ///
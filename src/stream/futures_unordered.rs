@@ -4,7 +4,7 @@ use poll::Poll;
 use Async;
 use stack::{Stack, Drain};
 use std::sync::Arc;
-use task::{self, UnparkEvent};
+use task;
 
 use std::prelude::v1::*;
 
@@ -61,8 +61,8 @@ impl<F> FuturesUnordered<F>
             if self.futures[id].is_none() {
                 continue
             }
-            let event = UnparkEvent::new(self.stack.clone(), id);
-            let ret = match task::with_unpark_event(event, || {
+            let stack = self.stack.clone();
+            let ret = match task::with_unpark_id(stack, id, || {
                 self.futures[id]
                     .as_mut()
                     .unwrap()
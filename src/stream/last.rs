@@ -0,0 +1,32 @@
+use {Future, Poll, Async};
+use stream::Stream;
+
+/// A future which resolves to the last element of a stream.
+///
+/// This future is returned by the `Stream::last` method.
+#[must_use = "futures do nothing unless polled"]
+pub struct Last<S> where S: Stream {
+    stream: S,
+    last: Option<S::Item>,
+}
+
+pub fn new<S: Stream>(s: S) -> Last<S> {
+    Last {
+        stream: s,
+        last: None,
+    }
+}
+
+impl<S: Stream> Future for Last<S> {
+    type Item = Option<S::Item>;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Poll<Option<S::Item>, S::Error> {
+        loop {
+            match try_ready!(self.stream.poll()) {
+                Some(e) => self.last = Some(e),
+                None => return Ok(Async::Ready(self.last.take())),
+            }
+        }
+    }
+}
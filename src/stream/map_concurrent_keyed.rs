@@ -0,0 +1,192 @@
+use std::prelude::v1::*;
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+use std::mem;
+use std::sync::Arc;
+
+use task;
+
+use {Async, IntoFuture, Poll, Future};
+use stream::{Stream, Fuse};
+use stack::{Stack, Drain};
+
+/// A stream combinator which maps elements to futures and runs them
+/// concurrently, except for elements with the same key, which are run one
+/// after the other.
+///
+/// This is created by the `Stream::map_concurrent_keyed` method.
+#[must_use = "streams do nothing unless polled"]
+pub struct MapConcurrentKeyed<S, K, G, F, U>
+    where S: Stream,
+          U: IntoFuture,
+{
+    stream: Fuse<S>,
+    key: G,
+    f: F,
+
+    // A slab of the futures being run, at most one for each key, in the same
+    // form as the slab of futures in `BufferUnordered`.
+    futures: Vec<Slot<(K, U::Future)>>,
+    next_future: usize,
+
+    // The IDs of the futures to poll, pushed onto by their tasks through
+    // `with_unpark_id`, and the last batch drained from it.
+    stack: Arc<Stack<usize>>,
+    pending: Drain<usize>,
+
+    // The elements waiting for the future of an earlier element with the
+    // same key to finish. A key has an entry here exactly when it has a
+    // future running.
+    queues: HashMap<K, VecDeque<S::Item>>,
+
+    // Number of elements taken from the stream which haven't finished, both
+    // running and queued
+    held: usize,
+}
+
+enum Slot<T> {
+    Next(usize),
+    Data(T),
+}
+
+pub fn new<S, K, G, F, U>(s: S, key: G, limit: usize, f: F)
+                          -> MapConcurrentKeyed<S, K, G, F, U>
+    where S: Stream,
+          K: Hash + Eq + Clone,
+          G: FnMut(&S::Item) -> K,
+          F: FnMut(S::Item) -> U,
+          U: IntoFuture<Error = S::Error>,
+{
+    assert!(limit > 0, "cannot map with a limit of 0");
+    MapConcurrentKeyed {
+        stream: super::fuse::new(s),
+        key: key,
+        f: f,
+        futures: (0..limit).map(|i| Slot::Next(i + 1)).collect(),
+        next_future: 0,
+        pending: Stack::new().drain(),
+        stack: Arc::new(Stack::new()),
+        queues: HashMap::new(),
+        held: 0,
+    }
+}
+
+impl<S, K, G, F, U> MapConcurrentKeyed<S, K, G, F, U>
+    where S: Stream,
+          K: Hash + Eq + Clone,
+          G: FnMut(&S::Item) -> K,
+          F: FnMut(S::Item) -> U,
+          U: IntoFuture<Error = S::Error>,
+{
+    fn start(&mut self, key: K, item: S::Item) {
+        let future = (self.f)(item).into_future();
+        self.stack.push(self.next_future);
+        match mem::replace(&mut self.futures[self.next_future],
+                           Slot::Data((key, future))) {
+            Slot::Next(next) => self.next_future = next,
+            Slot::Data(_) => panic!(),
+        }
+    }
+
+    fn poll_pending(&mut self) -> Option<Poll<Option<U::Item>, S::Error>> {
+        let mut done = None;
+        for idx in self.pending.by_ref() {
+            let result = match self.futures[idx] {
+                Slot::Data((_, ref mut f)) => {
+                    let stack = self.stack.clone();
+                    match task::with_unpark_id(stack, idx, || f.poll()) {
+                        Ok(Async::NotReady) => continue,
+                        Ok(Async::Ready(e)) => Ok(Async::Ready(Some(e))),
+                        Err(e) => Err(e),
+                    }
+                }
+                Slot::Next(_) => continue,
+            };
+            done = Some((idx, result));
+            break
+        }
+        done.map(|(idx, result)| {
+            self.held -= 1;
+            let key = match mem::replace(&mut self.futures[idx], Slot::Next(self.next_future)) {
+                Slot::Data((key, _)) => key,
+                Slot::Next(_) => panic!(),
+            };
+            self.next_future = idx;
+
+            // Start on the next element with the same key, if there is one.
+            let next = self.queues.get_mut(&key).and_then(|queue| queue.pop_front());
+            match next {
+                Some(item) => self.start(key, item),
+                None => {
+                    self.queues.remove(&key);
+                }
+            }
+            result
+        })
+    }
+}
+
+impl<S, K, G, F, U> Stream for MapConcurrentKeyed<S, K, G, F, U>
+    where S: Stream,
+          K: Hash + Eq + Clone,
+          G: FnMut(&S::Item) -> K,
+          F: FnMut(S::Item) -> U,
+          U: IntoFuture<Error = S::Error>,
+{
+    type Item = U::Item;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Poll<Option<U::Item>, S::Error> {
+        // Take as many elements as the limit allows. Running futures never
+        // outnumber the elements held, so there's always a free slot for an
+        // element whose key isn't running yet.
+        while self.held < self.futures.len() {
+            let item = match try!(self.stream.poll()) {
+                Async::Ready(Some(item)) => item,
+                Async::Ready(None) |
+                Async::NotReady => break,
+            };
+            self.held += 1;
+            let key = (self.key)(&item);
+            if let Some(queue) = self.queues.get_mut(&key) {
+                queue.push_back(item);
+                continue
+            }
+            self.queues.insert(key.clone(), VecDeque::new());
+            self.start(key, item);
+        }
+
+        // Then poll the futures we've been notified about, first those left
+        // over from last time and then any new ones.
+        if let Some(ret) = self.poll_pending() {
+            return ret
+        }
+        self.pending = self.stack.drain();
+        if let Some(ret) = self.poll_pending() {
+            return ret
+        }
+
+        Ok(if self.held > 0 || !self.stream.is_done() {
+            Async::NotReady
+        } else {
+            Async::Ready(None)
+        })
+    }
+}
+
+// Forwarding impl of Sink from the underlying stream
+impl<S, K, G, F, U> ::sink::Sink for MapConcurrentKeyed<S, K, G, F, U>
+    where S: ::sink::Sink + Stream,
+          U: IntoFuture,
+{
+    type SinkItem = S::SinkItem;
+    type SinkError = S::SinkError;
+
+    fn start_send(&mut self, item: S::SinkItem) -> ::StartSend<S::SinkItem, S::SinkError> {
+        self.stream.start_send(item)
+    }
+
+    fn poll_complete(&mut self) -> Poll<(), S::SinkError> {
+        self.stream.poll_complete()
+    }
+}
@@ -24,11 +24,13 @@ mod chain;
 mod empty;
 mod filter;
 mod filter_map;
+mod first;
 mod flatten;
 mod fold;
 mod for_each;
 mod fuse;
 mod future;
+mod last;
 mod map;
 mod map_err;
 mod merge;
@@ -36,6 +38,7 @@ mod once;
 mod or_else;
 mod peek;
 mod select;
+mod select_biased;
 mod skip;
 mod skip_while;
 mod take;
@@ -49,11 +52,13 @@ pub use self::chain::Chain;
 pub use self::empty::{Empty, empty};
 pub use self::filter::Filter;
 pub use self::filter_map::FilterMap;
+pub use self::first::First;
 pub use self::flatten::Flatten;
 pub use self::fold::Fold;
 pub use self::for_each::ForEach;
 pub use self::fuse::Fuse;
 pub use self::future::StreamFuture;
+pub use self::last::Last;
 pub use self::map::Map;
 pub use self::map_err::MapErr;
 pub use self::merge::{Merge, MergedItem};
@@ -61,6 +66,7 @@ pub use self::once::{Once, once};
 pub use self::or_else::OrElse;
 pub use self::peek::Peekable;
 pub use self::select::Select;
+pub use self::select_biased::SelectBiased;
 pub use self::skip::Skip;
 pub use self::skip_while::SkipWhile;
 pub use self::take::Take;
@@ -77,19 +83,39 @@ if_std! {
     mod buffered;
     mod buffer_unordered;
     mod catch_unwind;
+    mod chunked_by;
     mod chunks;
     mod collect;
+    mod collect_into_sink_groups;
+    mod flatten_unordered;
+    mod map_concurrent_keyed;
+    mod prefetch;
     mod wait;
     mod channel;
+    mod pipe;
     mod split;
+    mod spawned;
+    mod terminate_after_idle;
+    mod timeout_backoff_resubscribe;
+    mod timeout_map;
     mod futures_unordered;
     pub use self::buffered::Buffered;
     pub use self::buffer_unordered::BufferUnordered;
     pub use self::catch_unwind::CatchUnwind;
+    pub use self::chunked_by::ChunkedBy;
     pub use self::chunks::Chunks;
     pub use self::collect::Collect;
+    pub use self::collect_into_sink_groups::CollectIntoSinkGroups;
+    pub use self::flatten_unordered::FlattenUnordered;
+    pub use self::map_concurrent_keyed::MapConcurrentKeyed;
+    pub use self::pipe::{pipe, PipeSender, PipeReceiver};
+    pub use self::prefetch::Prefetch;
     pub use self::wait::Wait;
     pub use self::split::{SplitStream, SplitSink};
+    pub use self::spawned::Spawned;
+    pub use self::terminate_after_idle::TerminateAfterIdle;
+    pub use self::timeout_backoff_resubscribe::TimeoutBackoffResubscribe;
+    pub use self::timeout_map::TimeoutMap;
     pub use self::futures_unordered::{futures_unordered, FuturesUnordered};
 
     #[doc(hidden)]
@@ -108,6 +134,12 @@ if_std! {
             (**self).poll()
         }
     }
+
+    impl<S: ?Sized + FusedStream> FusedStream for ::std::boxed::Box<S> {
+        fn is_terminated(&self) -> bool {
+            (**self).is_terminated()
+        }
+    }
 }
 
 /// A stream of values, not all of which have been produced yet.
@@ -533,6 +565,52 @@ pub trait Stream {
         fold::new(self, f, init)
     }
 
+    /// Returns a future which resolves to the first element of this stream.
+    ///
+    /// The future resolves to `None` if the stream ends without producing any
+    /// elements, and to the stream's error if one happens first. As soon as
+    /// the first element arrives the rest of the stream is dropped, releasing
+    /// any resources it holds, rather than being kept around as it is by
+    /// `into_future`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::stream::{self, Stream};
+    /// use futures::future::Future;
+    ///
+    /// let stream = stream::iter::<_, _, ()>((1..4).map(Ok));
+    /// assert_eq!(stream.first().wait(), Ok(Some(1)));
+    /// ```
+    fn first(self) -> First<Self>
+        where Self: Sized
+    {
+        first::new(self)
+    }
+
+    /// Returns a future which resolves to the last element of this stream,
+    /// once it has ended.
+    ///
+    /// The future resolves to `None` if the stream ends without producing any
+    /// elements. If the stream produces an error then the future resolves to
+    /// it immediately. Only the most recent element is kept while waiting for
+    /// the stream to end.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::stream::{self, Stream};
+    /// use futures::future::Future;
+    ///
+    /// let stream = stream::iter::<_, _, ()>((1..4).map(Ok));
+    /// assert_eq!(stream.last().wait(), Ok(Some(3)));
+    /// ```
+    fn last(self) -> Last<Self>
+        where Self: Sized
+    {
+        last::new(self)
+    }
+
     /// Flattens a stream of streams into just one continuous stream.
     ///
     /// If this stream's elements are themselves streams then this combinator
@@ -743,6 +821,303 @@ pub trait Stream {
         buffer_unordered::new(self, amt)
     }
 
+    /// Reads ahead of the consumer of this stream, keeping up to `n` elements
+    /// buffered.
+    ///
+    /// Each time the returned stream is polled, it first polls this stream
+    /// for as long as it has elements ready, until `n` elements are buffered
+    /// on top of the one about to be yielded. A consumer which polls at a
+    /// steady rate, such as an encoder writing at line rate, then sees
+    /// elements which were produced during a burst straight from the buffer,
+    /// smoothing over latency spikes of this stream. Errors are buffered
+    /// along with the elements and yielded in order.
+    ///
+    /// The read-ahead happens in the task polling the returned stream, so
+    /// this stream is only polled when the consumer is, rather than being
+    /// driven independently on an executor.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::Stream;
+    /// use futures::stream;
+    /// use futures::executor;
+    ///
+    /// let mut s = executor::spawn(stream::iter((0..10).map(Ok::<_, ()>)).prefetch(3));
+    /// assert_eq!(s.wait_stream(), Some(Ok(0)));
+    /// assert_eq!(s.get_ref().buffered(), 3);
+    /// ```
+    #[cfg(feature = "use_std")]
+    fn prefetch(self, n: usize) -> Prefetch<Self>
+        where Self: Sized
+    {
+        prefetch::new(self, n)
+    }
+
+    /// Flattens a stream of streams, driving up to `limit` of the inner
+    /// streams concurrently.
+    ///
+    /// Unlike `flatten`, which exhausts each inner stream before moving on to
+    /// the next, this combinator takes up to `limit` inner streams from this
+    /// stream at a time and yields their elements in the order in which they
+    /// become available. Whenever one of the inner streams finishes, the next
+    /// one is taken from this stream in its place. Errors from this stream
+    /// and from the inner streams are passed through, and don't end the
+    /// stream they came from.
+    ///
+    /// This is useful for merging a dynamic set of sources, such as
+    /// subscriptions which arrive over time, while bounding how many are
+    /// active at once.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `limit` is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::{Future, Stream};
+    /// use futures::stream;
+    ///
+    /// let streams = stream::iter(vec![
+    ///     Ok::<_, ()>(stream::iter(vec![Ok::<_, ()>(1), Ok(2)])),
+    ///     Ok(stream::iter(vec![Ok(3)])),
+    /// ]);
+    /// let mut items = streams.flatten_unordered(2).collect().wait().unwrap();
+    /// items.sort();
+    /// assert_eq!(items, [1, 2, 3]);
+    /// ```
+    #[cfg(feature = "use_std")]
+    fn flatten_unordered(self, limit: usize) -> FlattenUnordered<Self>
+        where Self::Item: Stream,
+              <Self::Item as Stream>::Error: From<Self::Error>,
+              Self: Sized
+    {
+        flatten_unordered::new(self, limit)
+    }
+
+    /// Maps each element of this stream to a future, running the futures for
+    /// elements with different keys concurrently and those for elements with
+    /// the same key one after the other.
+    ///
+    /// The key of each element is computed with `key`, and `f` is called to
+    /// create the element's future once all earlier elements with the same
+    /// key have finished, so the futures for each key run in the order of
+    /// their elements. Up to `limit` elements are taken from this stream at
+    /// a time, including those waiting behind an earlier element with the
+    /// same key, which bounds both the number of futures running at once and
+    /// the number of elements buffered.
+    ///
+    /// The returned stream yields the futures' results in the order in which
+    /// they complete, which for any one key is the order of its elements.
+    /// Errors from this stream and from the futures are passed through.
+    ///
+    /// This is the usual way of processing partitioned events, such as
+    /// updates to many accounts which must each be applied in order.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `limit` is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::{Future, Stream};
+    /// use futures::future::ok;
+    /// use futures::stream;
+    ///
+    /// let events = stream::iter(vec![Ok::<_, ()>(("a", 1)), Ok(("b", 1)), Ok(("a", 2))]);
+    /// let handled = events.map_concurrent_keyed(|e| e.0, 4, |e| ok(e))
+    ///                     .collect()
+    ///                     .wait()
+    ///                     .unwrap();
+    /// let a: Vec<_> = handled.iter().filter(|e| e.0 == "a").collect();
+    /// assert_eq!(a, [&("a", 1), &("a", 2)]);
+    /// ```
+    #[cfg(feature = "use_std")]
+    fn map_concurrent_keyed<K, G, F, U>(self, key: G, limit: usize, f: F)
+                                        -> MapConcurrentKeyed<Self, K, G, F, U>
+        where K: ::std::hash::Hash + Eq + Clone,
+              G: FnMut(&Self::Item) -> K,
+              F: FnMut(Self::Item) -> U,
+              U: IntoFuture<Error = Self::Error>,
+              Self: Sized
+    {
+        map_concurrent_keyed::new(self, key, limit, f)
+    }
+
+    /// Yields a fallback value whenever the next element of this stream takes
+    /// longer than `dur` to arrive.
+    ///
+    /// Each time an element is waited on for `dur`, measured from the first
+    /// time it's polled for, the closure `f` is called and its result is
+    /// yielded in place of the element, for example a cached value or a
+    /// placeholder. The stream keeps waiting for the real element, which is
+    /// yielded as usual once it arrives, without any further fallbacks for
+    /// it. This suits progressive interfaces and partial responses, which
+    /// would rather show something than nothing.
+    ///
+    /// Errors and the end of the stream are passed through. Time is measured
+    /// by the clock of the task polling the stream, see the `clock` module.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::thread;
+    /// use std::time::Duration;
+    ///
+    /// use futures::{Future, Stream};
+    /// use futures::sync::oneshot;
+    ///
+    /// let (tx, rx) = oneshot::channel();
+    /// thread::spawn(move || {
+    ///     thread::sleep(Duration::from_millis(100));
+    ///     tx.complete("done");
+    /// });
+    ///
+    /// let items = rx.into_stream()
+    ///               .timeout_map(Duration::from_millis(10), || "loading")
+    ///               .collect();
+    /// assert_eq!(items.wait(), Ok(vec!["loading", "done"]));
+    /// ```
+    #[cfg(feature = "use_std")]
+    fn timeout_map<F>(self, dur: std::time::Duration, f: F) -> TimeoutMap<Self, F>
+        where F: FnMut() -> Self::Item,
+              Self: Sized
+    {
+        timeout_map::new(self, dur, f)
+    }
+
+    /// Ends this stream once no element has arrived for `dur`.
+    ///
+    /// The idle period is measured from the first time the stream is polled
+    /// for each element, and starts over whenever an element or error is
+    /// yielded. If it elapses before the next element arrives then the
+    /// returned stream ends cleanly, yielding `None` rather than an error,
+    /// and the underlying stream isn't polled again. This is useful for
+    /// closing idle keep-alive connections, or for bounding how long a test
+    /// consumes a live feed.
+    ///
+    /// Time is measured by the clock of the task polling the stream, see the
+    /// `clock` module.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// use futures::{Future, Stream};
+    /// use futures::sync::mpsc;
+    ///
+    /// let (mut tx, rx) = mpsc::unbounded::<i32>();
+    /// mpsc::UnboundedSender::send(&mut tx, 1).unwrap();
+    /// mpsc::UnboundedSender::send(&mut tx, 2).unwrap();
+    ///
+    /// // The sender is still alive, but nothing more arrives.
+    /// let items = rx.terminate_after_idle(Duration::from_millis(10)).collect();
+    /// assert_eq!(items.wait(), Ok(vec![1, 2]));
+    /// drop(tx);
+    /// ```
+    #[cfg(feature = "use_std")]
+    fn terminate_after_idle(self, dur: std::time::Duration) -> TerminateAfterIdle<Self>
+        where Self: Sized
+    {
+        terminate_after_idle::new(self, dur)
+    }
+
+    /// Keeps this stream going across failures by resubscribing, as
+    /// configured by `policy`.
+    ///
+    /// Whenever the stream fails, or goes without yielding an element for the
+    /// idle timeout of the policy, it's dropped, and after the backoff delay
+    /// of the policy `f` is called for a new subscription which takes its
+    /// place. The errors of replaced subscriptions aren't yielded. Once the
+    /// policy allows no more retries, the error of the last subscription is
+    /// yielded, and the last subscription is never dropped for being idle.
+    /// The retry count starts over whenever an element is yielded, and the
+    /// returned stream ends when the current subscription ends.
+    ///
+    /// Time is measured by the clock of the task polling the stream, see the
+    /// `clock` module.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::cell::Cell;
+    /// use std::time::Duration;
+    /// use futures::Future;
+    /// use futures::stream::{self, Stream};
+    /// use futures::reconnect::ReconnectPolicy;
+    ///
+    /// let mut policy = ReconnectPolicy::new();
+    /// policy.backoff(Duration::from_millis(1), Duration::from_millis(10))
+    ///       .max_retries(3);
+    ///
+    /// // Every subscription fails after its first element, until the third.
+    /// let subscriptions = Cell::new(0);
+    /// let subscribe = || {
+    ///     subscriptions.set(subscriptions.get() + 1);
+    ///     let last = if subscriptions.get() < 3 { Err("lost") } else { Ok(0) };
+    ///     stream::iter(vec![Ok(subscriptions.get()), last])
+    /// };
+    ///
+    /// let items = subscribe().timeout_backoff_resubscribe(policy, &subscribe);
+    /// assert_eq!(items.collect().wait(), Ok(vec![1, 2, 3, 0]));
+    /// ```
+    #[cfg(feature = "use_std")]
+    fn timeout_backoff_resubscribe<F>(self, policy: ::reconnect::ReconnectPolicy, f: F)
+                                      -> TimeoutBackoffResubscribe<Self, F>
+        where F: FnMut() -> Self,
+              Self: Sized
+    {
+        timeout_backoff_resubscribe::new(self, policy, f)
+    }
+
+    /// Moves this stream onto the executor `exec` when it's first polled,
+    /// receiving its elements back through a channel.
+    ///
+    /// The returned stream doesn't poll the original stream itself; instead
+    /// the original stream is spawned onto `exec`, which sends its elements
+    /// and errors through a bounded channel with room for `buffer` elements.
+    /// This allows an expensive source, such as one decompressing or parsing
+    /// data, to run on a thread pool while the consumer stays on its event
+    /// loop, with the source running ahead of the consumer by at most the
+    /// size of the buffer.
+    ///
+    /// If the returned stream is dropped then the original stream is dropped
+    /// once it next produces an element.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    /// use std::thread;
+    /// use futures::Future;
+    /// use futures::stream::{self, Stream};
+    /// use futures::executor::{Executor, Run};
+    ///
+    /// struct ThreadPerRun;
+    ///
+    /// impl Executor for ThreadPerRun {
+    ///     fn execute(&self, r: Run) {
+    ///         thread::spawn(move || r.run());
+    ///     }
+    /// }
+    ///
+    /// let numbers = stream::iter((0..10).map(Ok::<u32, ()>));
+    /// let squares = numbers.map(|n| n * n).spawned(Arc::new(ThreadPerRun), 2);
+    /// assert_eq!(squares.collect().wait(), Ok(vec![0, 1, 4, 9, 16, 25, 36, 49, 64, 81]));
+    /// ```
+    #[cfg(feature = "use_std")]
+    fn spawned(self, exec: ::std::sync::Arc<::executor::Executor>, buffer: usize)
+               -> Spawned<Self>
+        where Self: Sized + Send + 'static,
+              Self::Item: Send + 'static,
+              Self::Error: Send + 'static,
+    {
+        spawned::new(self, exec, buffer)
+    }
+
     /// An adapter for merging the output of two streams.
     ///
     /// The merged stream produces items from one or both of the underlying
@@ -826,6 +1201,51 @@ pub trait Stream {
         chunks::new(self, capacity)
     }
 
+    /// An adaptor for chunking up items of the stream inside a vector, where
+    /// the boundaries between chunks are decided by a predicate.
+    ///
+    /// For each item of the stream, `f` is called with the chunk built up so
+    /// far and the item. If it returns `true` then the chunk is complete and is
+    /// yielded by the returned stream, and the item starts the next chunk.
+    /// Otherwise the item is added to the current chunk. The predicate is never
+    /// called with an empty chunk.
+    ///
+    /// This allows batching by properties of the items themselves, for
+    /// example starting a new batch whenever a trace ID changes, or by
+    /// properties of the batch, such as its total size in bytes.
+    ///
+    /// Chunks are yielded when they're complete, or when the underlying
+    /// stream ends. If an error happens from the underlying stream then the
+    /// currently buffered items will be yielded first, and errors are
+    /// otherwise passed through unbuffered.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::{Future, Stream};
+    /// use futures::stream;
+    ///
+    /// let spans = stream::iter(vec![
+    ///     Ok::<_, ()>((1, "connect")),
+    ///     Ok((1, "query")),
+    ///     Ok((2, "connect")),
+    ///     Ok((1, "close")),
+    /// ]);
+    /// let traces = spans.chunked_by(|chunk, span| chunk[0].0 != span.0);
+    /// assert_eq!(traces.collect().wait(), Ok(vec![
+    ///     vec![(1, "connect"), (1, "query")],
+    ///     vec![(2, "connect")],
+    ///     vec![(1, "close")],
+    /// ]));
+    /// ```
+    #[cfg(feature = "use_std")]
+    fn chunked_by<F>(self, f: F) -> ChunkedBy<Self, F>
+        where F: FnMut(&[Self::Item], &Self::Item) -> bool,
+              Self: Sized
+    {
+        chunked_by::new(self, f)
+    }
+
     /// Creates a stream that selects the next element from either this stream
     /// or the provided one, whichever is ready first.
     ///
@@ -844,6 +1264,36 @@ pub trait Stream {
         select::new(self, other)
     }
 
+    /// Creates a stream that selects the next element from either this stream
+    /// or the provided one, always preferring this stream.
+    ///
+    /// This is like `select`, except that rather than polling the two streams
+    /// in a round-robin fashion, this stream is always polled first, and the
+    /// `other` stream is only polled when this one isn't ready to yield an
+    /// element. This guarantees a priority between the two, such as handling
+    /// a shutdown signal before any further work, at the cost of `other`
+    /// being starved for as long as this stream keeps yielding elements.
+    ///
+    /// Errors are passed through from either stream.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::{Future, Stream};
+    /// use futures::stream;
+    ///
+    /// let urgent = stream::iter(vec![Ok::<_, ()>(1), Ok(2)]);
+    /// let normal = stream::iter(vec![Ok::<_, ()>(10), Ok(20)]);
+    /// let merged = urgent.select_biased(normal).collect();
+    /// assert_eq!(merged.wait(), Ok(vec![1, 2, 10, 20]));
+    /// ```
+    fn select_biased<S>(self, other: S) -> SelectBiased<Self, S>
+        where S: Stream<Item = Self::Item, Error = Self::Error>,
+              Self: Sized,
+    {
+        select_biased::new(self, other)
+    }
+
     /// A future that completes after the given stream has been fully processed
     /// into the sink, including flushing.
     ///
@@ -861,6 +1311,54 @@ pub trait Stream {
         forward::new(self, sink)
     }
 
+    /// Routes each item of this stream to a sink determined by the item's key,
+    /// creating sinks lazily as new keys are seen.
+    ///
+    /// The `key` closure is called with each item to compute its key. The
+    /// first time a key is seen, `factory` is called with it to create the sink
+    /// for that key, which can be done asynchronously, and every item with that
+    /// key is then sent to the same sink. This is the core of routing output
+    /// per connection or per topic.
+    ///
+    /// Items are routed in order, so while a sink is being created or is not
+    /// ready to accept an item, no further items are pulled from this stream.
+    ///
+    /// The returned future completes once this stream is exhausted and all of
+    /// the sinks have been flushed, yielding the sinks keyed by their keys.
+    /// Errors from this stream, from creating sinks, and from the sinks
+    /// themselves all fail the future. Sinks can also be closed once they go
+    /// idle, see `CollectIntoSinkGroups::close_idle_after`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::{Future, Stream};
+    /// use futures::stream;
+    ///
+    /// let words = stream::iter(vec![Ok::<_, ()>("apple"), Ok("bean"), Ok("avocado")]);
+    /// let mut groups = words.collect_into_sink_groups(
+    ///     |word| word.chars().next().unwrap(),
+    ///     |_| Ok(Vec::new()),
+    /// ).wait().unwrap();
+    ///
+    /// assert_eq!(groups.remove(&'a'), Some(vec!["apple", "avocado"]));
+    /// assert_eq!(groups.remove(&'b'), Some(vec!["bean"]));
+    /// ```
+    #[cfg(feature = "use_std")]
+    fn collect_into_sink_groups<K, F, G, R>(self, key: F, factory: G)
+                                            -> CollectIntoSinkGroups<Self, K, F, G, R>
+        where K: ::std::hash::Hash + Eq + Clone,
+              F: FnMut(&Self::Item) -> K,
+              G: FnMut(&K) -> R,
+              R: IntoFuture,
+              R::Item: super::sink::Sink<SinkItem = Self::Item>,
+              Self::Error: From<R::Error> +
+                           From<<R::Item as super::sink::Sink>::SinkError>,
+              Self: Sized
+    {
+        collect_into_sink_groups::new(self, key, factory)
+    }
+
     /// Splits this `Stream + Sink` object into separate `Stream` and `Sink`
     /// objects, which can be useful when you want to split ownership between
     /// tasks, or allow direct interaction between the two objects (e.g. via
@@ -881,3 +1379,22 @@ impl<'a, S: ?Sized + Stream> Stream for &'a mut S {
         (**self).poll()
     }
 }
+
+/// A stream which knows whether it has already finished.
+///
+/// This is the counterpart of `FusedFuture` for streams: `is_terminated`
+/// reports whether the stream has finished, so that code polling many streams
+/// can skip the ones which are done rather than relying on each of them to
+/// behave once exhausted. Any stream can be made to implement it with
+/// `Stream::fuse`.
+pub trait FusedStream: Stream {
+    /// Returns `true` if this stream has already finished, in which case it
+    /// should no longer be polled.
+    fn is_terminated(&self) -> bool;
+}
+
+impl<'a, S: ?Sized + FusedStream> FusedStream for &'a mut S {
+    fn is_terminated(&self) -> bool {
+        (**self).is_terminated()
+    }
+}
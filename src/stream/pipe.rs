@@ -0,0 +1,100 @@
+use {Poll, Async, Stream, Sink, StartSend};
+use sync::mpsc;
+
+/// Creates a bounded channel whose sending half is a `Sink` and whose
+/// receiving half is a `Stream` of the same item and error types.
+///
+/// Each `Result` sent into the returned `PipeSender` is yielded by the
+/// `PipeReceiver` as an item or an error respectively, so a stream of
+/// `Result<T, E>` values can be forwarded across tasks and carry on as a
+/// `Stream<Item = T, Error = E>` on the other end. The channel is backed by
+/// `sync::mpsc::channel`, with the same back pressure and `buffer` semantics,
+/// and the receiver ends once every sender has been dropped.
+///
+/// # Examples
+///
+/// ```
+/// use std::thread;
+///
+/// use futures::{Future, Sink, Stream};
+/// use futures::stream;
+///
+/// let (tx, rx) = stream::pipe::<i32, String>(1);
+/// thread::spawn(move || {
+///     tx.send(Ok(1))
+///       .and_then(|tx| tx.send(Ok(2)))
+///       .and_then(|tx| tx.send(Err("oops".to_string())))
+///       .wait()
+///       .unwrap();
+/// });
+///
+/// let mut rx = rx.wait();
+/// assert_eq!(rx.next(), Some(Ok(1)));
+/// assert_eq!(rx.next(), Some(Ok(2)));
+/// assert_eq!(rx.next(), Some(Err("oops".to_string())));
+/// assert_eq!(rx.next(), None);
+/// ```
+pub fn pipe<T, E>(buffer: usize) -> (PipeSender<T, E>, PipeReceiver<T, E>) {
+    let (tx, rx) = mpsc::channel(buffer);
+    (PipeSender { inner: tx }, PipeReceiver { inner: rx })
+}
+
+/// The sending half of a pipe, which is a `Sink` of `Result` values.
+///
+/// This is created by the `stream::pipe` function.
+pub struct PipeSender<T, E> {
+    inner: mpsc::Sender<Result<T, E>>,
+}
+
+/// The receiving half of a pipe, which is a `Stream` of the values sent into
+/// it.
+///
+/// This is created by the `stream::pipe` function.
+#[must_use = "streams do nothing unless polled"]
+pub struct PipeReceiver<T, E> {
+    inner: mpsc::Receiver<Result<T, E>>,
+}
+
+impl<T, E> Clone for PipeSender<T, E> {
+    fn clone(&self) -> PipeSender<T, E> {
+        PipeSender { inner: self.inner.clone() }
+    }
+}
+
+impl<T, E> Sink for PipeSender<T, E> {
+    type SinkItem = Result<T, E>;
+    type SinkError = mpsc::SendError<Result<T, E>>;
+
+    fn start_send(&mut self, msg: Result<T, E>)
+                  -> StartSend<Result<T, E>, mpsc::SendError<Result<T, E>>> {
+        self.inner.start_send(msg)
+    }
+
+    fn poll_complete(&mut self) -> Poll<(), mpsc::SendError<Result<T, E>>> {
+        self.inner.poll_complete()
+    }
+}
+
+impl<T, E> PipeReceiver<T, E> {
+    /// Closes the receiving half, preventing any further values from being
+    /// sent.
+    ///
+    /// Values which were already sent can still be received.
+    pub fn close(&mut self) {
+        self.inner.close()
+    }
+}
+
+impl<T, E> Stream for PipeReceiver<T, E> {
+    type Item = T;
+    type Error = E;
+
+    fn poll(&mut self) -> Poll<Option<T>, E> {
+        match self.inner.poll().expect("cannot fail") {
+            Async::Ready(Some(Ok(e))) => Ok(Async::Ready(Some(e))),
+            Async::Ready(Some(Err(e))) => Err(e),
+            Async::Ready(None) => Ok(Async::Ready(None)),
+            Async::NotReady => Ok(Async::NotReady),
+        }
+    }
+}
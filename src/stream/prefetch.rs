@@ -0,0 +1,80 @@
+use std::prelude::v1::*;
+
+use std::collections::VecDeque;
+
+use {Async, Poll};
+use stream::{Stream, Fuse};
+
+/// A stream combinator which reads ahead of its consumer, keeping elements of
+/// the underlying stream buffered.
+///
+/// This is created by the `Stream::prefetch` method.
+#[must_use = "streams do nothing unless polled"]
+pub struct Prefetch<S>
+    where S: Stream,
+{
+    stream: Fuse<S>,
+    // Elements and errors taken from the stream but not yet yielded, in the
+    // order they were produced
+    buffer: VecDeque<Result<S::Item, S::Error>>,
+    n: usize,
+}
+
+pub fn new<S>(s: S, n: usize) -> Prefetch<S>
+    where S: Stream,
+{
+    Prefetch {
+        stream: super::fuse::new(s),
+        buffer: VecDeque::with_capacity(n + 1),
+        n: n,
+    }
+}
+
+impl<S: Stream> Prefetch<S> {
+    /// Returns the number of elements (and errors) which have been read ahead
+    /// of the consumer.
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+}
+
+// Forwarding impl of Sink from the underlying stream
+impl<S> ::sink::Sink for Prefetch<S>
+    where S: ::sink::Sink + Stream,
+{
+    type SinkItem = S::SinkItem;
+    type SinkError = S::SinkError;
+
+    fn start_send(&mut self, item: S::SinkItem) -> ::StartSend<S::SinkItem, S::SinkError> {
+        self.stream.start_send(item)
+    }
+
+    fn poll_complete(&mut self) -> Poll<(), S::SinkError> {
+        self.stream.poll_complete()
+    }
+}
+
+impl<S: Stream> Stream for Prefetch<S> {
+    type Item = S::Item;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Poll<Option<S::Item>, S::Error> {
+        // Top up the buffer, including the element about to be yielded, for
+        // as long as the stream has elements ready.
+        while self.buffer.len() <= self.n {
+            match self.stream.poll() {
+                Ok(Async::Ready(Some(e))) => self.buffer.push_back(Ok(e)),
+                Ok(Async::Ready(None)) |
+                Ok(Async::NotReady) => break,
+                Err(e) => self.buffer.push_back(Err(e)),
+            }
+        }
+
+        match self.buffer.pop_front() {
+            Some(Ok(e)) => Ok(Async::Ready(Some(e))),
+            Some(Err(e)) => Err(e),
+            None if self.stream.is_done() => Ok(Async::Ready(None)),
+            None => Ok(Async::NotReady),
+        }
+    }
+}
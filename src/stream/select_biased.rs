@@ -0,0 +1,46 @@
+use {Poll, Async};
+use stream::{Stream, Fuse};
+
+/// An adapter for merging the output of two streams, giving priority to the
+/// first one.
+///
+/// This is like `Select`, except that the first stream is always polled
+/// before the second one, rather than the two taking turns.
+#[must_use = "streams do nothing unless polled"]
+pub struct SelectBiased<S1, S2> {
+    stream1: Fuse<S1>,
+    stream2: Fuse<S2>,
+}
+
+pub fn new<S1, S2>(stream1: S1, stream2: S2) -> SelectBiased<S1, S2>
+    where S1: Stream,
+          S2: Stream<Item = S1::Item, Error = S1::Error>
+{
+    SelectBiased {
+        stream1: stream1.fuse(),
+        stream2: stream2.fuse(),
+    }
+}
+
+impl<S1, S2> Stream for SelectBiased<S1, S2>
+    where S1: Stream,
+          S2: Stream<Item = S1::Item, Error = S1::Error>
+{
+    type Item = S1::Item;
+    type Error = S1::Error;
+
+    fn poll(&mut self) -> Poll<Option<S1::Item>, S1::Error> {
+        let a_done = match try!(self.stream1.poll()) {
+            Async::Ready(Some(item)) => return Ok(Some(item).into()),
+            Async::Ready(None) => true,
+            Async::NotReady => false,
+        };
+
+        match try!(self.stream2.poll()) {
+            Async::Ready(Some(item)) => Ok(Some(item).into()),
+            Async::Ready(None) if a_done => Ok(None.into()),
+            Async::Ready(None) => Ok(Async::NotReady),
+            Async::NotReady => Ok(Async::NotReady),
+        }
+    }
+}
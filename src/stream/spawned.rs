@@ -0,0 +1,78 @@
+use std::prelude::v1::*;
+use std::mem;
+use std::sync::Arc;
+
+use {Async, Future, Poll};
+use executor::{self, Executor};
+use stream::Stream;
+use sync::mpsc::{self, SendError};
+
+/// A stream which runs the underlying stream on an executor, receiving its
+/// elements through a bounded channel.
+///
+/// This is created by the `Stream::spawned` method.
+#[must_use = "streams do nothing unless polled"]
+pub struct Spawned<S: Stream> {
+    state: State<S>,
+}
+
+enum State<S: Stream> {
+    Start(S, Arc<Executor>, usize),
+    Running(mpsc::Receiver<Result<S::Item, S::Error>>),
+    Done,
+}
+
+pub fn new<S>(s: S, exec: Arc<Executor>, buffer: usize) -> Spawned<S>
+    where S: Stream + Send + 'static,
+          S::Item: Send + 'static,
+          S::Error: Send + 'static,
+{
+    Spawned {
+        state: State::Start(s, exec, buffer),
+    }
+}
+
+impl<S> Stream for Spawned<S>
+    where S: Stream + Send + 'static,
+          S::Item: Send + 'static,
+          S::Error: Send + 'static,
+{
+    type Item = S::Item;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Poll<Option<S::Item>, S::Error> {
+        if let State::Start(..) = self.state {
+            let (stream, exec, buffer) = match mem::replace(&mut self.state, State::Done) {
+                State::Start(stream, exec, buffer) => (stream, exec, buffer),
+                _ => unreachable!(),
+            };
+            let (tx, rx) = mpsc::channel(buffer);
+            // Errors are sent along as elements, and forwarding stops early
+            // if the receiving half goes away.
+            let forward = stream.then(Ok::<_, SendError<_>>)
+                                .forward(tx)
+                                .then(|_| Ok::<(), ()>(()));
+            self.state = State::Running(rx);
+            executor::spawn(forward).execute(exec);
+        }
+
+        let item = match self.state {
+            State::Running(ref mut rx) => {
+                match rx.poll().expect("receivers never fail") {
+                    Async::Ready(item) => item,
+                    Async::NotReady => return Ok(Async::NotReady),
+                }
+            }
+            State::Start(..) => unreachable!(),
+            State::Done => return Ok(Async::Ready(None)),
+        };
+        match item {
+            Some(Ok(item)) => Ok(Async::Ready(Some(item))),
+            Some(Err(e)) => Err(e),
+            None => {
+                self.state = State::Done;
+                Ok(Async::Ready(None))
+            }
+        }
+    }
+}
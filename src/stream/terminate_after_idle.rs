@@ -0,0 +1,105 @@
+use std::time::Duration;
+
+use {Future, Poll, Async};
+use clock;
+use future::BoxFuture;
+use stream::Stream;
+
+/// A stream combinator which ends the stream once no element has arrived for
+/// a given amount of time.
+///
+/// This structure is produced by the `Stream::terminate_after_idle` method.
+#[must_use = "streams do nothing unless polled"]
+pub struct TerminateAfterIdle<S> {
+    stream: S,
+    dur: Duration,
+    delay: Option<BoxFuture<(), ()>>,
+    done: bool,
+}
+
+pub fn new<S>(s: S, dur: Duration) -> TerminateAfterIdle<S>
+    where S: Stream,
+{
+    TerminateAfterIdle {
+        stream: s,
+        dur: dur,
+        delay: None,
+        done: false,
+    }
+}
+
+impl<S> TerminateAfterIdle<S> {
+    /// Acquires a reference to the underlying stream that this combinator is
+    /// pulling from.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Acquires a mutable reference to the underlying stream that this
+    /// combinator is pulling from.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    /// Consumes this combinator, returning the underlying stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<S> Stream for TerminateAfterIdle<S>
+    where S: Stream,
+{
+    type Item = S::Item;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Poll<Option<S::Item>, S::Error> {
+        if self.done {
+            return Ok(Async::Ready(None))
+        }
+
+        let res = self.stream.poll();
+        match res {
+            Ok(Async::NotReady) => {}
+            Ok(Async::Ready(None)) => {
+                self.delay = None;
+                self.done = true;
+                return res
+            }
+            _ => {
+                // The idle period starts over after every element or error.
+                self.delay = None;
+                return res
+            }
+        }
+
+        if self.delay.is_none() {
+            self.delay = Some(clock::delay(clock::now() + self.dur));
+        }
+        let res = self.delay.as_mut().unwrap().poll();
+        match res {
+            Ok(Async::NotReady) => Ok(Async::NotReady),
+            _ => {
+                self.delay = None;
+                self.done = true;
+                Ok(Async::Ready(None))
+            }
+        }
+    }
+}
+
+// Forwarding impl of Sink from the underlying stream
+impl<S> ::sink::Sink for TerminateAfterIdle<S>
+    where S: ::sink::Sink
+{
+    type SinkItem = S::SinkItem;
+    type SinkError = S::SinkError;
+
+    fn start_send(&mut self, item: S::SinkItem) -> ::StartSend<S::SinkItem, S::SinkError> {
+        self.stream.start_send(item)
+    }
+
+    fn poll_complete(&mut self) -> Poll<(), S::SinkError> {
+        self.stream.poll_complete()
+    }
+}
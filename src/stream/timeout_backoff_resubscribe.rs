@@ -0,0 +1,107 @@
+use {Future, Poll, Async};
+use clock;
+use future::BoxFuture;
+use reconnect::ReconnectPolicy;
+use stream::Stream;
+
+/// A stream combinator which replaces a failed or idle stream with a new
+/// subscription, backing off between subscriptions.
+///
+/// This structure is produced by the `Stream::timeout_backoff_resubscribe`
+/// method.
+#[must_use = "streams do nothing unless polled"]
+pub struct TimeoutBackoffResubscribe<S, F> {
+    f: F,
+    policy: ReconnectPolicy,
+    // The number of retries since the last element was yielded
+    retries: usize,
+    state: State<S>,
+}
+
+enum State<S> {
+    // A subscription, along with its idle timeout once it's been polled
+    Running(S, Option<BoxFuture<(), ()>>),
+    // The backoff delay before the next subscription
+    Waiting(BoxFuture<(), ()>),
+}
+
+enum Step {
+    Backoff,
+    Restart,
+}
+
+pub fn new<S, F>(s: S, policy: ReconnectPolicy, f: F) -> TimeoutBackoffResubscribe<S, F>
+    where S: Stream,
+          F: FnMut() -> S,
+{
+    TimeoutBackoffResubscribe {
+        f: f,
+        policy: policy,
+        retries: 0,
+        state: State::Running(s, None),
+    }
+}
+
+impl<S, F> Stream for TimeoutBackoffResubscribe<S, F>
+    where S: Stream,
+          F: FnMut() -> S,
+{
+    type Item = S::Item;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Poll<Option<S::Item>, S::Error> {
+        loop {
+            let step = match self.state {
+                State::Running(ref mut stream, ref mut idle) => {
+                    let failed = match stream.poll() {
+                        Ok(Async::NotReady) => false,
+                        Ok(Async::Ready(Some(item))) => {
+                            self.retries = 0;
+                            *idle = None;
+                            return Ok(Async::Ready(Some(item)))
+                        }
+                        Ok(Async::Ready(None)) => return Ok(Async::Ready(None)),
+                        Err(e) => {
+                            if !self.policy.allows_retry(self.retries) {
+                                return Err(e)
+                            }
+                            true
+                        }
+                    };
+                    if failed {
+                        Step::Backoff
+                    } else {
+                        // The idle timeout only applies to subscriptions which
+                        // can be replaced.
+                        match self.policy.get_idle_timeout() {
+                            Some(timeout) if self.policy.allows_retry(self.retries) => {
+                                if idle.is_none() {
+                                    *idle = Some(clock::delay(clock::now() + timeout));
+                                }
+                                match idle.as_mut().unwrap().poll() {
+                                    Ok(Async::NotReady) => return Ok(Async::NotReady),
+                                    _ => Step::Backoff,
+                                }
+                            }
+                            _ => return Ok(Async::NotReady),
+                        }
+                    }
+                }
+                State::Waiting(ref mut delay) => {
+                    match delay.poll() {
+                        Ok(Async::NotReady) => return Ok(Async::NotReady),
+                        _ => Step::Restart,
+                    }
+                }
+            };
+            self.state = match step {
+                Step::Backoff => {
+                    let delay = self.policy.delay_for(self.retries).unwrap();
+                    self.retries += 1;
+                    State::Waiting(clock::delay(clock::now() + delay))
+                }
+                Step::Restart => State::Running((self.f)(), None),
+            };
+        }
+    }
+}
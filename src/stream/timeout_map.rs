@@ -0,0 +1,107 @@
+use std::time::Duration;
+
+use {Future, Poll, Async};
+use clock;
+use future::BoxFuture;
+use stream::Stream;
+
+/// A stream combinator which yields a fallback value whenever an element
+/// takes too long to arrive.
+///
+/// This structure is produced by the `Stream::timeout_map` method.
+#[must_use = "streams do nothing unless polled"]
+pub struct TimeoutMap<S, F> {
+    stream: S,
+    dur: Duration,
+    f: F,
+    delay: Option<BoxFuture<(), ()>>,
+    // Whether the fallback has already been yielded for the element being
+    // waited on
+    fired: bool,
+}
+
+pub fn new<S, F>(s: S, dur: Duration, f: F) -> TimeoutMap<S, F>
+    where S: Stream,
+          F: FnMut() -> S::Item,
+{
+    TimeoutMap {
+        stream: s,
+        dur: dur,
+        f: f,
+        delay: None,
+        fired: false,
+    }
+}
+
+impl<S, F> TimeoutMap<S, F> {
+    /// Acquires a reference to the underlying stream that this combinator is
+    /// pulling from.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Acquires a mutable reference to the underlying stream that this
+    /// combinator is pulling from.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    /// Consumes this combinator, returning the underlying stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<S, F> Stream for TimeoutMap<S, F>
+    where S: Stream,
+          F: FnMut() -> S::Item,
+{
+    type Item = S::Item;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Poll<Option<S::Item>, S::Error> {
+        let res = self.stream.poll();
+        match res {
+            Ok(Async::NotReady) => {}
+            _ => {
+                // Whatever the stream produced, the next element gets a fresh
+                // deadline.
+                self.delay = None;
+                self.fired = false;
+                return res
+            }
+        }
+        if self.fired {
+            return Ok(Async::NotReady)
+        }
+
+        if self.delay.is_none() {
+            self.delay = Some(clock::delay(clock::now() + self.dur));
+        }
+        let res = self.delay.as_mut().unwrap().poll();
+        match res {
+            Ok(Async::NotReady) => Ok(Async::NotReady),
+            _ => {
+                self.delay = None;
+                self.fired = true;
+                Ok(Async::Ready(Some((self.f)())))
+            }
+        }
+    }
+}
+
+// Forwarding impl of Sink from the underlying stream
+impl<S, F> ::sink::Sink for TimeoutMap<S, F>
+    where S: ::sink::Sink
+{
+    type SinkItem = S::SinkItem;
+    type SinkError = S::SinkError;
+
+    fn start_send(&mut self, item: S::SinkItem) -> ::StartSend<S::SinkItem, S::SinkError> {
+        self.stream.start_send(item)
+    }
+
+    fn poll_complete(&mut self) -> Poll<(), S::SinkError> {
+        self.stream.poll_complete()
+    }
+}
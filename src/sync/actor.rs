@@ -0,0 +1,192 @@
+//! A lightweight actor layer built on `mpsc` and `Spawn::execute`
+//!
+//! An actor is a piece of state which is only ever accessed by the task
+//! processing its mailbox. Messages are sent to the actor through an
+//! `Address`, and are handled one at a time, in order, by an implementation of
+//! the `Handle` trait. The future returned from handling one message is run to
+//! completion before the next message is taken out of the mailbox.
+
+use std::sync::Arc;
+
+use {Future, IntoFuture, Poll, Async, Stream};
+use executor::{self, Executor};
+use sync::mpsc::{self, SendError};
+
+/// The behavior of an actor, invoked for each message it receives.
+///
+/// Implementations receive exclusive access to their own state for each
+/// message, and return a future representing the work to be done in response
+/// to it. The actor will not handle another message until this future has
+/// completed.
+pub trait Handle<Msg> {
+    /// The work performed in response to a message.
+    ///
+    /// If this resolves to an error then the actor stops, and no further
+    /// messages will be handled.
+    type Result: IntoFuture<Item = (), Error = ()>;
+
+    /// Handles a single message sent to this actor.
+    fn handle(&mut self, msg: Msg) -> Self::Result;
+}
+
+/// A handle used to send messages to a running actor.
+///
+/// Addresses can be cloned and sent to other tasks and threads. Once all
+/// addresses for an actor have been dropped, the actor finishes handling the
+/// messages remaining in its mailbox and then stops.
+///
+/// This is created by the `actor::spawn` function.
+pub struct Address<Msg> {
+    tx: mpsc::UnboundedSender<Msg>,
+}
+
+/// The future driving an actor, processing messages from its mailbox.
+///
+/// This is created by the `actor::new` function, and is typically run through
+/// `actor::spawn` rather than used directly.
+#[must_use = "futures do nothing unless polled"]
+pub struct Actor<H, Msg>
+    where H: Handle<Msg>,
+{
+    handler: H,
+    mailbox: mpsc::UnboundedReceiver<Msg>,
+    current: Option<<H::Result as IntoFuture>::Future>,
+}
+
+/// Creates a new actor from the handler `h`, returning its address along with
+/// the future which processes its mailbox.
+///
+/// The returned `Actor` must be polled, on whichever executor is appropriate,
+/// for any messages sent to the `Address` to be handled. See `actor::spawn`
+/// for a version of this function which takes care of that.
+pub fn new<H, Msg>(h: H) -> (Address<Msg>, Actor<H, Msg>)
+    where H: Handle<Msg>,
+{
+    let (sender, rx) = mpsc::unbounded();
+    let actor = Actor {
+        handler: h,
+        mailbox: rx,
+        current: None,
+    };
+    (Address { tx: sender }, actor)
+}
+
+/// Spawns an actor onto the executor `exec`, returning its address.
+///
+/// Each message sent to the returned `Address` is passed to `handler` in the
+/// order it was sent, and the future returned from `Handle::handle` is driven
+/// to completion on `exec` before the next message is handled.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use std::sync::mpsc;
+/// use futures::executor::{Executor, Run};
+/// use futures::sync::actor::{self, Handle};
+///
+/// struct Inline;
+///
+/// impl Executor for Inline {
+///     fn execute(&self, r: Run) {
+///         r.run()
+///     }
+/// }
+///
+/// struct Counter {
+///     total: u32,
+///     report: mpsc::Sender<u32>,
+/// }
+///
+/// impl Handle<u32> for Counter {
+///     type Result = Result<(), ()>;
+///
+///     fn handle(&mut self, amt: u32) -> Result<(), ()> {
+///         self.total += amt;
+///         self.report.send(self.total).map_err(|_| ())
+///     }
+/// }
+///
+/// let (tx, rx) = mpsc::channel();
+/// let mut addr = actor::spawn(Counter { total: 0, report: tx }, Arc::new(Inline));
+///
+/// addr.send(1).unwrap();
+/// addr.send(2).unwrap();
+/// assert_eq!(rx.recv().unwrap(), 1);
+/// assert_eq!(rx.recv().unwrap(), 3);
+/// ```
+pub fn spawn<H, Msg>(handler: H, exec: Arc<Executor>) -> Address<Msg>
+    where H: Handle<Msg> + Send + 'static,
+          <H::Result as IntoFuture>::Future: Send + 'static,
+          Msg: Send + 'static,
+{
+    let (addr, actor) = new(handler);
+    executor::spawn(actor).execute(exec);
+    addr
+}
+
+impl<Msg> Address<Msg> {
+    /// Sends a message to the actor.
+    ///
+    /// The actor's mailbox is unbounded, so this succeeds immediately so long
+    /// as the actor is still running. If the actor has stopped then the
+    /// message is handed back in the returned error.
+    pub fn send(&mut self, msg: Msg) -> Result<(), SendError<Msg>> {
+        self.tx.send(msg)
+    }
+}
+
+impl<Msg> Clone for Address<Msg> {
+    fn clone(&self) -> Address<Msg> {
+        Address { tx: self.tx.clone() }
+    }
+}
+
+impl<H, Msg> Actor<H, Msg>
+    where H: Handle<Msg>,
+{
+    /// Acquires a reference to the underlying handler.
+    pub fn get_ref(&self) -> &H {
+        &self.handler
+    }
+
+    /// Acquires a mutable reference to the underlying handler.
+    ///
+    /// Note that care must be taken to avoid tampering with the state of the
+    /// handler while a message is being processed.
+    pub fn get_mut(&mut self) -> &mut H {
+        &mut self.handler
+    }
+}
+
+impl<H, Msg> Future for Actor<H, Msg>
+    where H: Handle<Msg>,
+{
+    type Item = ();
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<(), ()> {
+        loop {
+            if let Some(mut fut) = self.current.take() {
+                match fut.poll() {
+                    Ok(Async::Ready(())) => {}
+                    Ok(Async::NotReady) => {
+                        self.current = Some(fut);
+                        return Ok(Async::NotReady)
+                    }
+                    Err(()) => {
+                        self.mailbox.close();
+                        return Err(())
+                    }
+                }
+            }
+
+            match try_ready!(self.mailbox.poll()) {
+                Some(msg) => {
+                    self.current = Some(self.handler.handle(msg).into_future());
+                }
+                None => return Ok(Async::Ready(())),
+            }
+        }
+    }
+}
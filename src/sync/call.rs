@@ -0,0 +1,178 @@
+//! A futures-aware request/response channel
+//!
+//! Many actor-like designs end up pairing an `mpsc` channel of requests with a
+//! `oneshot` per request to carry the reply back. This module packages that
+//! pattern up: a `Caller` issues requests and receives a future of the
+//! response for each one, while the serving side reads a stream of requests
+//! paired with a `Responder` used to send the reply.
+
+use {Future, Poll, Async, Sink, Stream};
+use sink::Send;
+use sync::mpsc;
+use sync::oneshot;
+
+pub use sync::oneshot::Canceled;
+
+/// The calling half of a request/response channel.
+///
+/// Each call made through a `Caller` is matched with exactly one response
+/// which is delivered through the `Call` future returned from `call`.
+///
+/// This is created by the `call::channel` function, and can be cloned to issue
+/// requests from any number of tasks.
+pub struct Caller<Req, Resp> {
+    tx: mpsc::Sender<(Req, Responder<Resp>)>,
+}
+
+/// The serving half of a request/response channel.
+///
+/// This is a `Stream` of requests, each paired with the `Responder` through
+/// which its response should be sent.
+///
+/// This is created by the `call::channel` function.
+#[must_use = "streams do nothing unless polled"]
+pub struct Requests<Req, Resp> {
+    rx: mpsc::Receiver<(Req, Responder<Resp>)>,
+}
+
+/// Handle used to send the response to a single request.
+///
+/// If a `Responder` is dropped without responding then the corresponding
+/// `Call` future will resolve to `Canceled`.
+pub struct Responder<Resp> {
+    tx: oneshot::Sender<Resp>,
+}
+
+/// A future representing the response to a request issued through `Caller`.
+///
+/// This is created by the `Caller::call` method.
+#[must_use = "futures do nothing unless polled"]
+pub struct Call<Req, Resp> {
+    state: State<Req, Resp>,
+}
+
+enum State<Req, Resp> {
+    Sending(Send<mpsc::Sender<(Req, Responder<Resp>)>>,
+            oneshot::Receiver<Resp>),
+    Waiting(oneshot::Receiver<Resp>),
+    Empty,
+}
+
+/// Creates a new request/response channel with bounded request capacity.
+///
+/// The capacity of the underlying request queue is managed in the same way as
+/// `mpsc::channel`, so callers will experience back pressure once `buffer`
+/// requests are outstanding and haven't been picked up by the `Requests`
+/// stream.
+///
+/// # Examples
+///
+/// ```
+/// use std::thread;
+/// use futures::{Future, Stream};
+/// use futures::sync::call;
+///
+/// let (caller, requests) = call::channel::<u32, u32>(4);
+///
+/// thread::spawn(move || {
+///     requests.for_each(|(req, responder)| {
+///         responder.respond(req * 2);
+///         Ok(())
+///     }).wait().unwrap();
+/// });
+///
+/// assert_eq!(caller.call(21).wait(), Ok(42));
+/// ```
+pub fn channel<Req, Resp>(buffer: usize) -> (Caller<Req, Resp>, Requests<Req, Resp>) {
+    let (sender, receiver) = mpsc::channel(buffer);
+    (Caller { tx: sender }, Requests { rx: receiver })
+}
+
+impl<Req, Resp> Caller<Req, Resp> {
+    /// Issues a request, returning a future of its response.
+    ///
+    /// The returned future will first wait for capacity in the request queue
+    /// and then for the serving side to respond. If the `Requests` stream is
+    /// dropped, or if the request's `Responder` is dropped without responding,
+    /// the future resolves to `Canceled`.
+    pub fn call(&self, req: Req) -> Call<Req, Resp> {
+        let (complete, rx) = oneshot::channel();
+        let msg = (req, Responder { tx: complete });
+        Call {
+            state: State::Sending(self.tx.clone().send(msg), rx),
+        }
+    }
+}
+
+impl<Req, Resp> Clone for Caller<Req, Resp> {
+    fn clone(&self) -> Caller<Req, Resp> {
+        Caller { tx: self.tx.clone() }
+    }
+}
+
+impl<Req, Resp> Future for Call<Req, Resp> {
+    type Item = Resp;
+    type Error = Canceled;
+
+    fn poll(&mut self) -> Poll<Resp, Canceled> {
+        loop {
+            match ::std::mem::replace(&mut self.state, State::Empty) {
+                State::Sending(mut send, rx) => {
+                    match send.poll() {
+                        Ok(Async::Ready(_)) => self.state = State::Waiting(rx),
+                        Ok(Async::NotReady) => {
+                            self.state = State::Sending(send, rx);
+                            return Ok(Async::NotReady)
+                        }
+                        // The request, and with it its `Responder`, was
+                        // dropped, so `rx` resolves to `Canceled`.
+                        Err(_) => self.state = State::Waiting(rx),
+                    }
+                }
+                State::Waiting(mut rx) => {
+                    let res = rx.poll();
+                    if let Ok(Async::NotReady) = res {
+                        self.state = State::Waiting(rx);
+                    }
+                    return res
+                }
+                State::Empty => panic!("cannot poll Call twice"),
+            }
+        }
+    }
+}
+
+impl<Req, Resp> Requests<Req, Resp> {
+    /// Closes the serving half.
+    ///
+    /// This prevents any further requests from being issued while still
+    /// allowing buffered requests to be drained and responded to.
+    pub fn close(&mut self) {
+        self.rx.close();
+    }
+}
+
+impl<Req, Resp> Stream for Requests<Req, Resp> {
+    type Item = (Req, Responder<Resp>);
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<Option<(Req, Responder<Resp>)>, ()> {
+        self.rx.poll()
+    }
+}
+
+impl<Resp> Responder<Resp> {
+    /// Completes the associated `Call` with the provided response.
+    pub fn respond(self, resp: Resp) {
+        self.tx.complete(resp)
+    }
+
+    /// Polls whether the associated `Call` has gone away.
+    ///
+    /// This behaves the same as `oneshot::Sender::poll_cancel`, and can be
+    /// used to abandon work on a request whose caller is no longer interested
+    /// in the response.
+    pub fn poll_cancel(&mut self) -> Poll<(), ()> {
+        self.tx.poll_cancel()
+    }
+}
@@ -0,0 +1,167 @@
+use std::collections::VecDeque;
+use std::mem;
+use std::sync::{Arc, Mutex};
+
+use {Async, Poll, Stream};
+use task::{self, Task};
+
+/// A stream of completion tokens pushed from arbitrary threads.
+///
+/// This is the consuming half of a completion queue, created by the
+/// `completion_queue` function. Tokens are yielded in the order that they were
+/// pushed through the associated `Completer` handles.
+///
+/// The stream finishes once all `Completer` handles have been dropped and all
+/// pending tokens have been yielded.
+#[must_use = "streams do nothing unless polled"]
+pub struct CompletionQueue<T> {
+    inner: Arc<Inner<T>>,
+}
+
+/// A handle used to push completion tokens onto a `CompletionQueue`.
+///
+/// Unlike most of the handles in this module, pushing a token does not
+/// require a task context, and can be done from any thread. This makes
+/// `Completer` suitable for use in callbacks invoked by foreign code, such as
+/// the completion routines of C libraries.
+///
+/// This is created by the `completion_queue` function, and may be cloned to
+/// push completions from multiple places.
+pub struct Completer<T> {
+    inner: Arc<Inner<T>>,
+}
+
+struct Inner<T> {
+    state: Mutex<State<T>>,
+}
+
+struct State<T> {
+    // Tokens which have been pushed but not yet yielded by the stream.
+    tokens: VecDeque<T>,
+
+    // Number of `Completer` handles in existence.
+    completers: usize,
+
+    // Whether the `CompletionQueue` has been dropped, in which case pushed
+    // tokens are simply discarded.
+    closed: bool,
+
+    // The task blocked on the `CompletionQueue`, if any.
+    task: Option<Task>,
+}
+
+/// Creates a new completion queue, returning the handle used to push
+/// completion tokens along with the stream which receives them.
+///
+/// # Examples
+///
+/// ```
+/// use std::thread;
+/// use futures::{Future, Stream};
+/// use futures::sync::completion_queue;
+///
+/// let (completer, queue) = completion_queue();
+///
+/// thread::spawn(move || {
+///     // e.g. invoked from a C library's completion callback
+///     completer.complete(1);
+///     completer.complete(2);
+/// });
+///
+/// assert_eq!(queue.collect().wait(), Ok(vec![1, 2]));
+/// ```
+pub fn completion_queue<T>() -> (Completer<T>, CompletionQueue<T>) {
+    let shared = Arc::new(Inner {
+        state: Mutex::new(State {
+            tokens: VecDeque::new(),
+            completers: 1,
+            closed: false,
+            task: None,
+        }),
+    });
+    let completer = Completer {
+        inner: shared.clone(),
+    };
+    let queue = CompletionQueue {
+        inner: shared,
+    };
+    (completer, queue)
+}
+
+impl<T> Completer<T> {
+    /// Pushes a completion token onto the queue, waking up the task consuming
+    /// it if necessary.
+    ///
+    /// This may be called from any thread, and does not need to be called
+    /// from within a task. If the `CompletionQueue` has already been dropped
+    /// then the token is discarded.
+    pub fn complete(&self, token: T) {
+        let task = {
+            let mut state = self.inner.state.lock().unwrap();
+            if state.closed {
+                return
+            }
+            state.tokens.push_back(token);
+            state.task.take()
+        };
+        if let Some(task) = task {
+            task.unpark();
+        }
+    }
+}
+
+impl<T> Clone for Completer<T> {
+    fn clone(&self) -> Completer<T> {
+        self.inner.state.lock().unwrap().completers += 1;
+        Completer {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> Drop for Completer<T> {
+    fn drop(&mut self) {
+        let task = {
+            let mut state = self.inner.state.lock().unwrap();
+            state.completers -= 1;
+            if state.completers > 0 {
+                return
+            }
+            state.task.take()
+        };
+        if let Some(task) = task {
+            task.unpark();
+        }
+    }
+}
+
+impl<T> Stream for CompletionQueue<T> {
+    type Item = T;
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<Option<T>, ()> {
+        let mut state = self.inner.state.lock().unwrap();
+        if let Some(token) = state.tokens.pop_front() {
+            return Ok(Async::Ready(Some(token)))
+        }
+        if state.completers == 0 {
+            return Ok(Async::Ready(None))
+        }
+        state.task = Some(task::park());
+        Ok(Async::NotReady)
+    }
+}
+
+impl<T> Drop for CompletionQueue<T> {
+    fn drop(&mut self) {
+        // Pull the pending tokens out before destroying them, as their
+        // destructors may themselves try to push onto this queue.
+        let tokens = {
+            let mut state = self.inner.state.lock().unwrap();
+            state.closed = true;
+            state.task = None;
+            mem::replace(&mut state.tokens, VecDeque::new())
+        };
+        drop(tokens);
+    }
+}
@@ -7,6 +7,13 @@
 
 pub mod oneshot;
 pub mod mpsc;
+pub mod spsc;
+pub mod call;
+pub mod actor;
 mod bilock;
+mod completion_queue;
+mod semaphore;
 
 pub use self::bilock::{BiLock, BiLockGuard, BiLockAcquire, BiLockAcquired};
+pub use self::completion_queue::{completion_queue, CompletionQueue, Completer};
+pub use self::semaphore::{Semaphore, Permit, Acquire};
@@ -26,6 +26,15 @@
 //! will first call `close`, which will prevent any further messages to be sent
 //! into the channel. Then, the receiver consumes the channel to completion, at
 //! which point the receiver can be dropped.
+//!
+//! # Fairness
+//!
+//! Senders of a bounded channel which are blocked because the channel is at
+//! capacity are unblocked in the order in which they blocked, one for each
+//! message read out of the channel. A sender which is blocked can't send again
+//! until it's unblocked, so under contention every sender gets its turn and
+//! none of them can be starved by the others. `Sender::capacity_hint` can be
+//! used to see how close a channel is to blocking its senders.
 
 // At the core, the channel uses an atomic FIFO queue for message passing. This
 // queue is used as the primary coordination primitive. In order to enforce
@@ -452,6 +461,30 @@ impl<T> Sender<T> {
     }
 }
 
+impl<T> Sender<T> {
+    /// Returns the number of messages which can currently be sent on this
+    /// channel without blocking the senders.
+    ///
+    /// This is the number of free slots in the buffer shared by all senders,
+    /// or 0 if this sender is blocked waiting for its turn, or if the channel
+    /// has been closed. As the other senders and the receiver may be active
+    /// concurrently, the value is only a hint, which may be out of date as
+    /// soon as it's returned.
+    pub fn capacity_hint(&self) -> usize {
+        let state = decode_state(self.inner.state.load(SeqCst));
+        if !state.is_open {
+            return 0
+        }
+        if self.maybe_parked && self.sender_task.lock().unwrap().is_some() {
+            return 0
+        }
+        match self.inner.buffer {
+            Some(buffer) => buffer.saturating_sub(state.num_messages),
+            None => usize::MAX,
+        }
+    }
+}
+
 impl<T> Sink for Sender<T> {
     type SinkItem = T;
     type SinkError = SendError<T>;
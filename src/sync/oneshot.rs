@@ -41,6 +41,11 @@ struct Inner<T> {
     /// can return ready from `poll_cancel`.
     complete: AtomicBool,
 
+    /// Set by `Sender::abandon` before the `Sender` is dropped, so the
+    /// `Receiver` can tell a deliberately abandoned computation apart from
+    /// the `Sender` simply going away.
+    abandoned: AtomicBool,
+
     /// The actual data being transferred as part of this `Receiver`. This is
     /// filled in by `Sender::complete` and read by `Receiver::poll`.
     ///
@@ -93,6 +98,7 @@ struct Inner<T> {
 pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
     let inner = Arc::new(Inner {
         complete: AtomicBool::new(false),
+        abandoned: AtomicBool::new(false),
         data: Lock::new(None),
         rx_task: Lock::new(None),
         tx_task: Lock::new(None),
@@ -123,6 +129,27 @@ impl<T> Sender<T> {
         drop(slot);
     }
 
+    /// Abandons this oneshot without sending a value.
+    ///
+    /// This is the same as dropping this `Sender`, except that
+    /// `Receiver::poll_recv` will fail with `RecvError::Abandoned` rather
+    /// than `RecvError::Dropped`, indicating that the value was deliberately
+    /// not produced rather than lost, for example due to a panic.
+    pub fn abandon(self) {
+        self.inner.abandoned.store(true, SeqCst);
+    }
+
+    /// Tests whether the `Receiver` this is paired with has gone away, either
+    /// because it was dropped or closed.
+    ///
+    /// This is a synchronous version of `poll_cancel`, which can be called
+    /// outside the context of a task but won't arrange for the current task to
+    /// be notified when the `Receiver` goes away. If this returns `true` then
+    /// any value sent through this `Sender` will not be received.
+    pub fn is_canceled(&self) -> bool {
+        self.inner.complete.load(SeqCst)
+    }
+
     /// Polls this `Sender` half to detect whether the `Receiver` this has
     /// paired with has gone away.
     ///
@@ -211,6 +238,9 @@ impl<T> Drop for Sender<T> {
 
 /// Error returned from a `Receiver<T>` whenever the correponding `Sender<T>`
 /// is dropped.
+///
+/// This doesn't say why no value was sent; `Receiver::poll_recv` can be used
+/// to find out whether the `Sender` was abandoned deliberately.
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub struct Canceled;
 
@@ -226,6 +256,44 @@ impl Error for Canceled {
     }
 }
 
+/// Error returned from `Receiver::poll_recv` when the corresponding
+/// `Sender<T>` goes away without sending a value, describing how it did so.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RecvError {
+    /// The `Sender` was dropped without sending a value, for example because
+    /// the computation producing it failed or panicked.
+    Dropped,
+    /// The `Sender` was deliberately abandoned with `Sender::abandon`.
+    Abandoned,
+}
+
+impl RecvError {
+    fn as_str(&self) -> &'static str {
+        match *self {
+            RecvError::Dropped => "oneshot sender dropped",
+            RecvError::Abandoned => "oneshot sender abandoned",
+        }
+    }
+}
+
+impl fmt::Display for RecvError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}", self.as_str())
+    }
+}
+
+impl Error for RecvError {
+    fn description(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl From<RecvError> for Canceled {
+    fn from(_: RecvError) -> Canceled {
+        Canceled
+    }
+}
+
 impl<T> Receiver<T> {
     /// Gracefully close this receiver, preventing sending any future messages.
     ///
@@ -244,13 +312,16 @@ impl<T> Receiver<T> {
             }
         }
     }
-}
 
-impl<T> Future for Receiver<T> {
-    type Item = T;
-    type Error = Canceled;
-
-    fn poll(&mut self) -> Poll<T, Canceled> {
+    /// Like `poll`, except that the error describes whether the `Sender` was
+    /// dropped or deliberately abandoned.
+    ///
+    /// This can be used to decide whether a failed computation is worth
+    /// retrying, for example.
+    ///
+    /// Like `Future::poll`, this function will panic if it's not called from
+    /// within the context of a task.
+    pub fn poll_recv(&mut self) -> Poll<T, RecvError> {
         let mut done = false;
 
         // Check to see if some data has arrived. If it hasn't then we need to
@@ -281,7 +352,10 @@ impl<T> Future for Receiver<T> {
         if done || self.inner.complete.load(SeqCst) {
             match self.inner.data.try_lock().unwrap().take() {
                 Some(data) => Ok(data.into()),
-                None => Err(Canceled),
+                None if self.inner.abandoned.load(SeqCst) => {
+                    Err(RecvError::Abandoned)
+                }
+                None => Err(RecvError::Dropped),
             }
         } else {
             Ok(Async::NotReady)
@@ -289,6 +363,15 @@ impl<T> Future for Receiver<T> {
     }
 }
 
+impl<T> Future for Receiver<T> {
+    type Item = T;
+    type Error = Canceled;
+
+    fn poll(&mut self) -> Poll<T, Canceled> {
+        self.poll_recv().map_err(Canceled::from)
+    }
+}
+
 impl<T> Drop for Receiver<T> {
     fn drop(&mut self) {
         // Indicate to the `Sender` that we're done, so any future calls to
@@ -0,0 +1,190 @@
+use std::collections::VecDeque;
+use std::mem;
+use std::sync::{Arc, Mutex};
+
+use {Async, Future, Poll};
+use task::{self, Task};
+
+/// A future-aware counting semaphore.
+///
+/// A semaphore hands out a fixed number of permits. Acquiring a permit while
+/// none are available will park the current task until one is released,
+/// rather than blocking the thread. Permits are released automatically when
+/// the `Permit` guard is dropped.
+///
+/// Waiting tasks are granted permits in the order they started waiting, and
+/// a released permit is handed directly to the oldest waiter so that tasks
+/// arriving later can't starve it.
+///
+/// Handles to a semaphore are cheaply cloneable and all refer to the same set
+/// of permits.
+#[derive(Clone)]
+pub struct Semaphore {
+    inner: Arc<Inner>,
+}
+
+/// A permit acquired from a `Semaphore`.
+///
+/// The permit is returned to the semaphore when this is dropped.
+pub struct Permit {
+    inner: Arc<Inner>,
+}
+
+/// Future returned by `Semaphore::acquire`, resolving to a `Permit` once one
+/// is available.
+///
+/// Dropping this future before it resolves gives up its place in the queue.
+#[must_use = "futures do nothing unless polled"]
+pub struct Acquire {
+    inner: Arc<Inner>,
+    waiter: Option<Arc<Mutex<Waiter>>>,
+}
+
+struct Inner {
+    state: Mutex<State>,
+}
+
+struct State {
+    available: usize,
+    waiters: VecDeque<Arc<Mutex<Waiter>>>,
+}
+
+enum Waiter {
+    Waiting(Option<Task>),
+    Granted,
+    Canceled,
+}
+
+impl Semaphore {
+    /// Creates a new semaphore with `permits` permits available.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::Future;
+    /// use futures::sync::Semaphore;
+    ///
+    /// let semaphore = Semaphore::new(1);
+    /// let permit = semaphore.acquire().wait().unwrap();
+    /// assert!(semaphore.try_acquire().is_none());
+    /// drop(permit);
+    /// assert!(semaphore.try_acquire().is_some());
+    /// ```
+    pub fn new(permits: usize) -> Semaphore {
+        Semaphore {
+            inner: Arc::new(Inner {
+                state: Mutex::new(State {
+                    available: permits,
+                    waiters: VecDeque::new(),
+                }),
+            }),
+        }
+    }
+
+    /// Returns a future which resolves to a permit once one is available.
+    pub fn acquire(&self) -> Acquire {
+        Acquire {
+            inner: self.inner.clone(),
+            waiter: None,
+        }
+    }
+
+    /// Attempts to acquire a permit without waiting.
+    ///
+    /// This will fail if no permits are available, or if other tasks are
+    /// already waiting for one.
+    pub fn try_acquire(&self) -> Option<Permit> {
+        let mut state = self.inner.state.lock().unwrap();
+        if state.available == 0 {
+            return None
+        }
+        state.available -= 1;
+        Some(Permit { inner: self.inner.clone() })
+    }
+
+    /// Returns the number of permits which are currently available.
+    pub fn available_permits(&self) -> usize {
+        self.inner.state.lock().unwrap().available
+    }
+}
+
+impl Inner {
+    fn release(&self) {
+        // The waiter is only woken up once the locks are released, as it may
+        // be polled right away, for example by an inline executor.
+        if let Some(task) = self.grant() {
+            task.unpark();
+        }
+    }
+
+    // Hands a released permit to the oldest waiter, returning its task if it
+    // needs to be woken up, or makes the permit available if there are no
+    // waiters.
+    fn grant(&self) -> Option<Task> {
+        let mut state = self.state.lock().unwrap();
+        while let Some(waiter) = state.waiters.pop_front() {
+            let mut waiter = waiter.lock().unwrap();
+            match mem::replace(&mut *waiter, Waiter::Granted) {
+                Waiter::Waiting(task) => return task,
+                Waiter::Granted => return None,
+                Waiter::Canceled => *waiter = Waiter::Canceled,
+            }
+        }
+        state.available += 1;
+        None
+    }
+}
+
+impl Future for Acquire {
+    type Item = Permit;
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<Permit, ()> {
+        let waiter = match self.waiter {
+            Some(ref waiter) => waiter.clone(),
+            None => {
+                let mut state = self.inner.state.lock().unwrap();
+                if state.available > 0 {
+                    state.available -= 1;
+                    return Ok(Async::Ready(Permit { inner: self.inner.clone() }))
+                }
+                let waiter = Arc::new(Mutex::new(Waiter::Waiting(Some(task::park()))));
+                state.waiters.push_back(waiter.clone());
+                self.waiter = Some(waiter);
+                return Ok(Async::NotReady)
+            }
+        };
+
+        match *waiter.lock().unwrap() {
+            Waiter::Waiting(ref mut slot) => {
+                *slot = Some(task::park());
+                return Ok(Async::NotReady)
+            }
+            Waiter::Granted => {}
+            Waiter::Canceled => panic!("cannot poll Acquire twice"),
+        }
+        self.waiter = None;
+        Ok(Async::Ready(Permit { inner: self.inner.clone() }))
+    }
+}
+
+impl Drop for Acquire {
+    fn drop(&mut self) {
+        let waiter = match self.waiter.take() {
+            Some(waiter) => waiter,
+            None => return,
+        };
+        // If a permit was handed to us after we last looked then it needs to
+        // be passed on, otherwise we just need to leave the queue.
+        let prev = mem::replace(&mut *waiter.lock().unwrap(), Waiter::Canceled);
+        if let Waiter::Granted = prev {
+            self.inner.release();
+        }
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        self.inner.release();
+    }
+}
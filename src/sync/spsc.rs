@@ -0,0 +1,273 @@
+//! A single-producer, single-consumer, futures-aware, bounded FIFO queue.
+//!
+//! This channel is a specialization of `mpsc` for the common case of a
+//! pipeline stage, where exactly one task produces values and one task
+//! consumes them. Messages are stored in a fixed-size ring buffer which the
+//! two halves access without locking, and the only synchronization needed
+//! when neither side has to wait is an atomic load and store of the ring's
+//! indices. As there's only one `Sender`, it can't be cloned.
+//!
+//! When the `Sender` is dropped the `Receiver` yields the messages still
+//! buffered and then ends, and when the `Receiver` is dropped further sends
+//! fail.
+
+use std::prelude::v1::*;
+
+use std::any::Any;
+use std::cell::UnsafeCell;
+use std::error::Error;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicUsize};
+use std::sync::atomic::Ordering::SeqCst;
+
+use {Async, AsyncSink, Poll, StartSend, Sink, Stream};
+use task::{self, Task};
+
+/// The sending half of a single-producer, single-consumer channel.
+///
+/// This is created by the `spsc::channel` function.
+pub struct Sender<T> {
+    inner: Arc<Inner<T>>,
+}
+
+/// The receiving half of a single-producer, single-consumer channel, which
+/// implements the `Stream` trait.
+///
+/// This is created by the `spsc::channel` function.
+pub struct Receiver<T> {
+    inner: Arc<Inner<T>>,
+}
+
+/// Error type for sending, used when the receiving half of the channel is
+/// dropped.
+pub struct SendError<T>(T);
+
+struct Inner<T> {
+    // The ring buffer. The slots from `head` up to, but not including, `tail`
+    // (modulo the length) are filled, and are only touched by the receiver.
+    // The other slots are only touched by the sender.
+    buffer: Box<[UnsafeCell<Option<T>>]>,
+
+    // Total number of messages received and sent. These only ever grow,
+    // wrapping around on overflow, and are each written by only one side.
+    head: AtomicUsize,
+    tail: AtomicUsize,
+
+    // Set when the sender and receiver are dropped respectively
+    tx_dropped: AtomicBool,
+    rx_dropped: AtomicBool,
+
+    // The receiver waiting for a message, and the sender waiting for space
+    rx_task: Waiter,
+    tx_task: Waiter,
+}
+
+unsafe impl<T: Send> Send for Inner<T> {}
+unsafe impl<T: Send> Sync for Inner<T> {}
+
+// A slot for a task waiting on the other half of the channel.
+//
+// The waiting side stores its task and then sets `parked`, before checking
+// once more whether it still needs to wait. The other side makes progress
+// visible before clearing `parked`, and only unparks the task if it was set,
+// so either the waiting side sees the progress or its task is unparked.
+struct Waiter {
+    parked: AtomicBool,
+    task: Mutex<Option<Task>>,
+}
+
+impl Waiter {
+    fn new() -> Waiter {
+        Waiter {
+            parked: AtomicBool::new(false),
+            task: Mutex::new(None),
+        }
+    }
+
+    fn park(&self) {
+        *self.task.lock().unwrap() = Some(task::park());
+        self.parked.store(true, SeqCst);
+    }
+
+    fn unpark(&self) {
+        if self.parked.swap(false, SeqCst) {
+            let task = self.task.lock().unwrap().take();
+            if let Some(task) = task {
+                task.unpark();
+            }
+        }
+    }
+}
+
+/// Creates a bounded, single-producer, single-consumer channel which can hold
+/// up to `capacity` messages.
+///
+/// The `Sender` implements `Sink` and won't accept a message while the
+/// channel is full, and the `Receiver` implements `Stream`, ending once the
+/// `Sender` has been dropped and every message has been received.
+///
+/// # Panics
+///
+/// This function will panic if `capacity` is 0.
+///
+/// # Examples
+///
+/// ```
+/// use std::thread;
+///
+/// use futures::{Future, Sink, Stream};
+/// use futures::stream;
+/// use futures::sync::spsc;
+///
+/// let (tx, rx) = spsc::channel(16);
+/// thread::spawn(move || {
+///     tx.send_all(stream::iter((0..100).map(Ok))).wait().unwrap();
+/// });
+/// let sum = rx.fold(0, |a, b| Ok(a + b)).wait();
+/// assert_eq!(sum, Ok(4950));
+/// ```
+pub fn channel<T>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    assert!(capacity > 0, "cannot create a channel with a capacity of 0");
+    let inner = Arc::new(Inner {
+        buffer: (0..capacity).map(|_| UnsafeCell::new(None)).collect::<Vec<_>>()
+                             .into_boxed_slice(),
+        head: AtomicUsize::new(0),
+        tail: AtomicUsize::new(0),
+        tx_dropped: AtomicBool::new(false),
+        rx_dropped: AtomicBool::new(false),
+        rx_task: Waiter::new(),
+        tx_task: Waiter::new(),
+    });
+    (Sender { inner: inner.clone() }, Receiver { inner: inner })
+}
+
+impl<T> Inner<T> {
+    fn is_full(&self) -> bool {
+        let tail = self.tail.load(SeqCst);
+        tail.wrapping_sub(self.head.load(SeqCst)) == self.buffer.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.head.load(SeqCst) == self.tail.load(SeqCst)
+    }
+}
+
+impl<T> Sender<T> {
+    /// Returns whether the channel is full, in which case `start_send` won't
+    /// accept a message until the receiver catches up.
+    pub fn is_full(&self) -> bool {
+        self.inner.is_full()
+    }
+}
+
+impl<T> Sink for Sender<T> {
+    type SinkItem = T;
+    type SinkError = SendError<T>;
+
+    fn start_send(&mut self, msg: T) -> StartSend<T, SendError<T>> {
+        if self.inner.rx_dropped.load(SeqCst) {
+            return Err(SendError(msg))
+        }
+        if self.inner.is_full() {
+            self.inner.tx_task.park();
+            if self.inner.is_full() {
+                return Ok(AsyncSink::NotReady(msg))
+            }
+        }
+
+        let tail = self.inner.tail.load(SeqCst);
+        let slot = &self.inner.buffer[tail % self.inner.buffer.len()];
+        // The slot is free, so the receiver won't access it until `tail` is
+        // advanced past it.
+        unsafe {
+            *slot.get() = Some(msg);
+        }
+        self.inner.tail.store(tail.wrapping_add(1), SeqCst);
+        self.inner.rx_task.unpark();
+        Ok(AsyncSink::Ready)
+    }
+
+    fn poll_complete(&mut self) -> Poll<(), SendError<T>> {
+        Ok(Async::Ready(()))
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        self.inner.tx_dropped.store(true, SeqCst);
+        self.inner.rx_task.unpark();
+    }
+}
+
+impl<T> Receiver<T> {
+    fn next_message(&mut self) -> Option<T> {
+        if self.inner.is_empty() {
+            return None
+        }
+        let head = self.inner.head.load(SeqCst);
+        let slot = &self.inner.buffer[head % self.inner.buffer.len()];
+        // The slot is filled, and the sender won't access it until `head` is
+        // advanced past it.
+        let msg = unsafe { (*slot.get()).take() };
+        self.inner.head.store(head.wrapping_add(1), SeqCst);
+        self.inner.tx_task.unpark();
+        msg
+    }
+}
+
+impl<T> Stream for Receiver<T> {
+    type Item = T;
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<Option<T>, ()> {
+        if let Some(msg) = self.next_message() {
+            return Ok(Async::Ready(Some(msg)))
+        }
+        self.inner.rx_task.park();
+        // Messages sent before the sender was dropped are visible once the
+        // drop is, so they're checked for afterwards.
+        let done = self.inner.tx_dropped.load(SeqCst);
+        match self.next_message() {
+            Some(msg) => Ok(Async::Ready(Some(msg))),
+            None if done => Ok(Async::Ready(None)),
+            None => Ok(Async::NotReady),
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.inner.rx_dropped.store(true, SeqCst);
+        self.inner.tx_task.unpark();
+    }
+}
+
+impl<T> fmt::Debug for SendError<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_tuple("SendError")
+            .field(&"...")
+            .finish()
+    }
+}
+
+impl<T> fmt::Display for SendError<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "send failed because receiver is gone")
+    }
+}
+
+impl<T> Error for SendError<T>
+    where T: Any
+{
+    fn description(&self) -> &str {
+        "send failed because receiver is gone"
+    }
+}
+
+impl<T> SendError<T> {
+    /// Returns the message that was attempted to be sent but failed.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
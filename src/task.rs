@@ -21,16 +21,35 @@
 //! There is an important bare function in this module: `park`. The `park`
 //! function is similar to the standard library's `thread::park` method where it
 //! returns a handle to wake up a task at a later date (via an `unpark` method).
+//!
+//! ## `no_std`
+//!
+//! With the `use_alloc` feature instead of `use_std`, the core of the task
+//! system (`park`, `with_unpark_event`, task-local data and polling tasks
+//! through `executor::Spawn`) is available on targets with an allocator but
+//! without `std`. The storage for the task currently being polled must then
+//! be registered with `init` before any task is polled.
 
 #[doc(hidden)]
 #[deprecated(since = "0.1.4", note = "import through the executor module instead")]
-#[cfg(feature = "with-deprecated")]
+#[cfg(all(feature = "use_std", feature = "with-deprecated"))]
 pub use task_impl::{Spawn, spawn, Unpark, Executor, Run};
 
-pub use task_impl::{Task, LocalKey, park, with_unpark_event, UnparkEvent, EventSet};
+pub use task_impl::{Task, LocalKey, LocalMap, Inherit, park, current_id, with_unpark_event,
+                    with_unpark_events, UnparkEvent, with_unpark_id, UnparkId,
+                    set_max_event_depth, EventSet, AtomicBitSet, BitSetDrain, ReadyQueue,
+                    ReadyQueueDrain, TaskContext, with_context, ContextFuture, Implicit,
+                    implicit};
+
+#[cfg(not(feature = "use_std"))]
+pub use task_impl::init;
+
+#[cfg(feature = "use_std")]
+pub use task_impl::{LocalTask, park_local, yield_now, YieldNow, TaskLocalCell, TaskLocalRef,
+                    TaskLocalRefMut, Released, TaskLocalCellError};
 
 #[doc(hidden)]
 #[deprecated(since = "0.1.4", note = "import through the executor module instead")]
-#[cfg(feature = "with-deprecated")]
+#[cfg(all(feature = "use_std", feature = "with-deprecated"))]
 #[allow(deprecated)]
-pub use task_impl::TaskRc;
+pub use task_impl::{TaskRc, TaskRcError};
@@ -0,0 +1,188 @@
+use std::prelude::v1::*;
+
+use std::any::Any;
+use std::collections::VecDeque;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Mutex, Condvar, Once, ONCE_INIT};
+use std::thread;
+use std::time::Duration;
+
+use {Future, Poll, Async};
+use sync::oneshot;
+
+// The most threads the pool will grow to. Operations submitted while this
+// many are busy wait in a queue for one of them to become free.
+const MAX_THREADS: usize = 512;
+
+// How long a thread waits for more work before exiting.
+const KEEP_ALIVE_SECS: u64 = 10;
+
+/// Future for the result of a blocking operation, resolving once it has run
+/// on the blocking pool.
+///
+/// This is created by the `executor::blocking` function.
+#[must_use = "futures do nothing unless polled"]
+pub struct Blocking<R> {
+    rx: oneshot::Receiver<thread::Result<R>>,
+}
+
+/// Runs the blocking operation `f` on a dedicated pool of threads, returning
+/// a future for its result.
+///
+/// Executors poll many futures on a small number of threads, so a future
+/// which blocks, for example on a filesystem call or a DNS lookup, stalls
+/// every other future waiting to run on the same thread. This function
+/// offloads such an operation onto a separate pool of threads which exists
+/// just for this purpose, and the task polling the returned future is
+/// unparked once it completes.
+///
+/// The pool is started lazily, and grows by one thread whenever an operation
+/// is submitted while all of its threads are busy, up to a limit after which
+/// operations are queued. Threads exit after being idle for a while.
+///
+/// The returned future resolves to the value returned by `f`, or to the
+/// payload of the panic if `f` panics. Dropping the future doesn't cancel the
+/// operation, which still runs to completion, but its result is discarded.
+///
+/// # Examples
+///
+/// ```
+/// use futures::Future;
+/// use futures::executor;
+///
+/// let len = executor::blocking(|| {
+///     // e.g. `std::fs::read_to_string(path).map(|s| s.len())`
+///     "hello".len()
+/// });
+/// assert_eq!(len.wait().ok(), Some(5));
+/// ```
+pub fn blocking<F, R>(f: F) -> Blocking<R>
+    where F: FnOnce() -> R + Send + 'static,
+          R: Send + 'static,
+{
+    let (tx, rx) = oneshot::channel();
+    let mut op = Some((f, tx));
+    pool().submit(Box::new(move || {
+        let (f, tx) = op.take().unwrap();
+        // If the receiver has been dropped then nobody is interested in the
+        // result, and it's simply discarded.
+        tx.complete(panic::catch_unwind(AssertUnwindSafe(f)));
+    }));
+    Blocking { rx: rx }
+}
+
+impl<R> Future for Blocking<R> {
+    type Item = R;
+    type Error = Box<Any + Send>;
+
+    fn poll(&mut self) -> Poll<R, Box<Any + Send>> {
+        match self.rx.poll() {
+            Ok(Async::Ready(Ok(r))) => Ok(Async::Ready(r)),
+            Ok(Async::Ready(Err(e))) => Err(e),
+            Ok(Async::NotReady) => Ok(Async::NotReady),
+            // The operation always sends its result, even if it panics, so
+            // this only happens if it's dropped without being run.
+            Err(_) => Err(Box::new("blocking operation was never run")),
+        }
+    }
+}
+
+impl<R> fmt::Debug for Blocking<R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Blocking").finish()
+    }
+}
+
+type Job = Box<FnMut() + Send>;
+
+struct Pool {
+    state: Mutex<State>,
+    condvar: Condvar,
+}
+
+struct State {
+    jobs: VecDeque<Job>,
+    // The number of threads in the pool
+    threads: usize,
+    // The number of threads waiting for a job, and the number of those which
+    // have been woken up to take one
+    idle: usize,
+    notified: usize,
+}
+
+fn pool() -> &'static Pool {
+    static INIT: Once = ONCE_INIT;
+    static mut POOL: *const Pool = 0 as *const Pool;
+
+    unsafe {
+        INIT.call_once(|| {
+            POOL = Box::into_raw(Box::new(Pool {
+                state: Mutex::new(State {
+                    jobs: VecDeque::new(),
+                    threads: 0,
+                    idle: 0,
+                    notified: 0,
+                }),
+                condvar: Condvar::new(),
+            }));
+        });
+        &*POOL
+    }
+}
+
+impl Pool {
+    fn submit(&'static self, job: Job) {
+        let mut state = self.state.lock().unwrap();
+        state.jobs.push_back(job);
+        if state.idle > 0 {
+            state.idle -= 1;
+            state.notified += 1;
+            self.condvar.notify_one();
+        } else if state.threads < MAX_THREADS {
+            let spawned = thread::Builder::new()
+                .name("futures-blocking".to_string())
+                .spawn(move || self.run());
+            // If no thread could be spawned then the job is left queued for
+            // one of the existing threads, if there are any.
+            match spawned {
+                Ok(_) => state.threads += 1,
+                Err(e) => {
+                    if state.threads == 0 {
+                        drop(state);
+                        panic!("failed to spawn a blocking pool thread: {}", e)
+                    }
+                }
+            }
+        }
+    }
+
+    fn run(&self) {
+        let keep_alive = Duration::from_secs(KEEP_ALIVE_SECS);
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(mut job) = state.jobs.pop_front() {
+                drop(state);
+                job();
+                state = self.state.lock().unwrap();
+                continue
+            }
+
+            state.idle += 1;
+            loop {
+                let (s, res) = self.condvar.wait_timeout(state, keep_alive).unwrap();
+                state = s;
+                if state.notified > 0 {
+                    // `submit` has already taken us off of the idle count
+                    state.notified -= 1;
+                    break
+                }
+                if res.timed_out() {
+                    state.idle -= 1;
+                    state.threads -= 1;
+                    return
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,95 @@
+#[cfg(not(feature = "use_std"))]
+pub use self::imp::init;
+pub use self::imp::{get, set};
+
+#[cfg(feature = "use_std")]
+mod imp {
+    use std::cell::Cell;
+    use std::ptr;
+
+    thread_local!(static CURRENT: Cell<*mut u8> = Cell::new(ptr::null_mut()));
+
+    pub fn get() -> *mut u8 {
+        CURRENT.with(|c| c.get())
+    }
+
+    pub fn set(ptr: *mut u8) {
+        CURRENT.with(|c| c.set(ptr))
+    }
+}
+
+#[cfg(not(feature = "use_std"))]
+mod imp {
+    use std::mem;
+    use std::ptr;
+    use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+
+    // The states of the storage registered through `init`.
+    const UNINITIALIZED: usize = 0;
+    const INITIALIZING: usize = 1;
+    const INITIALIZED: usize = 2;
+
+    static STATE: AtomicUsize = ATOMIC_USIZE_INIT;
+    static GET: AtomicUsize = ATOMIC_USIZE_INIT;
+    static SET: AtomicUsize = ATOMIC_USIZE_INIT;
+
+    /// Registers the storage used to keep track of the task currently being
+    /// polled.
+    ///
+    /// While a task is being polled, functions like `task::park` need to find
+    /// it, so the task system stores a pointer to it in a slot which is local
+    /// to the current thread of execution. With the `use_std` feature this is
+    /// a thread local variable, but without `std` there's no such thing, so
+    /// the storage has to be provided by the application: `get` must return
+    /// the pointer most recently passed to `set` on the current thread of
+    /// execution (or null if there's been no such call), and `set` must
+    /// replace it. On a target with just one thread of execution, for
+    /// example, this can be a single `static`.
+    ///
+    /// The storage can only be registered once, and this function returns
+    /// whether `get` and `set` were registered, that is `false` if some
+    /// storage had already been registered. This function is only available
+    /// without the `use_std` feature.
+    ///
+    /// # Panics
+    ///
+    /// Polling a task through `Spawn` will panic if no storage has been
+    /// registered.
+    pub fn init(get: fn() -> *mut u8, set: fn(*mut u8)) -> bool {
+        if STATE.compare_exchange(UNINITIALIZED, INITIALIZING,
+                                  Ordering::SeqCst, Ordering::SeqCst).is_err() {
+            return false
+        }
+        GET.store(get as usize, Ordering::SeqCst);
+        SET.store(set as usize, Ordering::SeqCst);
+        STATE.store(INITIALIZED, Ordering::SeqCst);
+        true
+    }
+
+    type Storage = (fn() -> *mut u8, fn(*mut u8));
+
+    fn storage() -> Option<Storage> {
+        if STATE.load(Ordering::SeqCst) != INITIALIZED {
+            return None
+        }
+        unsafe {
+            Some((mem::transmute::<usize, fn() -> *mut u8>(GET.load(Ordering::SeqCst)),
+                  mem::transmute::<usize, fn(*mut u8)>(SET.load(Ordering::SeqCst))))
+        }
+    }
+
+    pub fn get() -> *mut u8 {
+        match storage() {
+            Some((get, _)) => get(),
+            None => ptr::null_mut(),
+        }
+    }
+
+    pub fn set(ptr: *mut u8) {
+        match storage() {
+            Some((_, set)) => set(ptr),
+            None => panic!("no storage for the current task has been registered \
+                            with `task::init`"),
+        }
+    }
+}
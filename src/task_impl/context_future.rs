@@ -0,0 +1,72 @@
+use {Future, Poll};
+use super::{with_context, TaskContext};
+
+/// A future which is polled with the context of its task passed explicitly.
+///
+/// This is an alternative to `Future` for futures which don't rely on the
+/// implicit current task, instead receiving a `TaskContext` with each call
+/// to `poll_context`. Such futures can be driven with `Spawn::poll_context`,
+/// which doesn't set the current task at all.
+///
+/// Every `Future` is also a `ContextFuture`, polled by making the context's
+/// task the current one, and a `ContextFuture` can be used where a `Future`
+/// is expected by wrapping it with `task::implicit`. This allows code to be
+/// migrated to explicit context passing one future at a time.
+pub trait ContextFuture {
+    /// The type of value that this future will resolve with if it's
+    /// successful.
+    type Item;
+
+    /// The type of error that this future will resolve with if it fails.
+    type Error;
+
+    /// Query this future to see if its value has become available, using
+    /// `cx` to register interest in being woken up if it's not ready yet.
+    ///
+    /// This behaves the same as `Future::poll`, except that the context of
+    /// the task must be obtained from `cx`, as there may not be a current
+    /// task.
+    fn poll_context(&mut self, cx: &mut TaskContext) -> Poll<Self::Item, Self::Error>;
+}
+
+impl<F: Future> ContextFuture for F {
+    type Item = F::Item;
+    type Error = F::Error;
+
+    fn poll_context(&mut self, cx: &mut TaskContext) -> Poll<F::Item, F::Error> {
+        cx.enter(|| self.poll())
+    }
+}
+
+/// Future for the `implicit` function, polling a `ContextFuture` with the
+/// context of the current task.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled"]
+pub struct Implicit<F> {
+    inner: F,
+}
+
+/// Adapts a `ContextFuture` into a `Future`, which polls it with the context
+/// of the current task.
+pub fn implicit<F: ContextFuture>(f: F) -> Implicit<F> {
+    Implicit {
+        inner: f,
+    }
+}
+
+impl<F> Implicit<F> {
+    /// Consumes this adapter, returning the underlying future.
+    pub fn into_inner(self) -> F {
+        self.inner
+    }
+}
+
+impl<F: ContextFuture> Future for Implicit<F> {
+    type Item = F::Item;
+    type Error = F::Error;
+
+    fn poll(&mut self) -> Poll<F::Item, F::Error> {
+        let inner = &mut self.inner;
+        with_context(|cx| inner.poll_context(cx))
+    }
+}
@@ -0,0 +1,237 @@
+use std::prelude::v1::*;
+
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use std::mem;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use {Future, Async};
+use future::BoxFuture;
+use super::{spawn, Spawn, Unpark, enter, assert_not_entered};
+
+// The ID used to notify the future passed to `CurrentThread::run`.
+const MAIN: usize = !0;
+
+/// An executor which runs all of its tasks on the current thread.
+///
+/// Futures are spawned onto the executor with `spawn`, which doesn't require
+/// them to be `Send`, and are run whenever the thread is blocked in `run`.
+/// Other threads can spawn futures onto the executor through the `Remote`
+/// handles returned by `remote`.
+pub struct CurrentThread {
+    tasks: Vec<Option<Entry>>,
+    free: Vec<usize>,
+    shared: Arc<Shared>,
+    // tied to the thread that created it
+    _marker: PhantomData<Rc<()>>,
+}
+
+/// A handle for spawning futures onto a `CurrentThread` executor from any
+/// thread.
+///
+/// This is created by the `CurrentThread::remote` method.
+#[derive(Clone)]
+pub struct Remote {
+    shared: Arc<Shared>,
+}
+
+type Entry = (Spawn<Box<Future<Item = (), Error = ()>>>, Arc<Unpark>);
+
+struct Shared {
+    queue: Mutex<Queue>,
+    thread: thread::Thread,
+}
+
+struct Queue {
+    // IDs of the tasks which have been unparked since they were last polled
+    ready: VecDeque<usize>,
+    // Futures spawned through a `Remote`, which haven't been added yet
+    spawned: Vec<BoxFuture<(), ()>>,
+    // Set once the executor has been dropped
+    closed: bool,
+}
+
+struct TaskUnpark {
+    id: usize,
+    shared: Arc<Shared>,
+}
+
+impl CurrentThread {
+    /// Creates a new executor for the current thread, with no tasks.
+    pub fn new() -> CurrentThread {
+        CurrentThread {
+            tasks: Vec::new(),
+            free: Vec::new(),
+            shared: Arc::new(Shared {
+                queue: Mutex::new(Queue {
+                    ready: VecDeque::new(),
+                    spawned: Vec::new(),
+                    closed: false,
+                }),
+                thread: thread::current(),
+            }),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Spawns a future onto this executor.
+    ///
+    /// The future is first polled the next time `run` is called.
+    pub fn spawn<F>(&mut self, f: F)
+        where F: Future<Item = (), Error = ()> + 'static,
+    {
+        let id = self.insert(Box::new(f));
+        self.shared.queue.lock().unwrap().ready.push_back(id);
+    }
+
+    /// Returns a handle which can be used to spawn futures onto this executor
+    /// from other threads.
+    pub fn remote(&self) -> Remote {
+        Remote { shared: self.shared.clone() }
+    }
+
+    /// Runs the tasks on this executor until the future `f` completes,
+    /// returning its result.
+    ///
+    /// The current thread is blocked whenever neither `f` nor any of the
+    /// tasks on this executor are ready to make progress. Tasks which haven't
+    /// completed by the time `f` does are left on the executor, and will run
+    /// again in the next call to `run`.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if it's called from within an executor, that
+    /// is while an `executor::enter` guard is held on the current thread.
+    pub fn run<F: Future>(&mut self, f: F) -> Result<F::Item, F::Error> {
+        assert_not_entered();
+        let mut main = spawn(f);
+        let main_unpark: Arc<Unpark> = Arc::new(TaskUnpark {
+            id: MAIN,
+            shared: self.shared.clone(),
+        });
+        let mut main_ready = true;
+        loop {
+            if main_ready {
+                main_ready = false;
+                let _enter = enter();
+                if let Async::Ready(e) = try!(main.poll_future(main_unpark.clone())) {
+                    return Ok(e)
+                }
+            }
+
+            let (ready, spawned) = {
+                let mut queue = self.shared.queue.lock().unwrap();
+                (mem::replace(&mut queue.ready, VecDeque::new()),
+                 mem::replace(&mut queue.spawned, Vec::new()))
+            };
+            if ready.is_empty() && spawned.is_empty() {
+                // Notifications unpark this thread after queueing, so any
+                // which arrived since the queue was checked will make this
+                // return right away.
+                thread::park();
+                continue
+            }
+
+            let _enter = enter();
+            for f in spawned {
+                let id = self.insert(f);
+                self.poll(id);
+            }
+            for id in ready {
+                if id == MAIN {
+                    main_ready = true;
+                } else {
+                    self.poll(id);
+                }
+            }
+        }
+    }
+
+    fn insert(&mut self, f: Box<Future<Item = (), Error = ()>>) -> usize {
+        let id = match self.free.pop() {
+            Some(id) => id,
+            None => {
+                self.tasks.push(None);
+                self.tasks.len() - 1
+            }
+        };
+        let unpark = Arc::new(TaskUnpark {
+            id: id,
+            shared: self.shared.clone(),
+        });
+        self.tasks[id] = Some((spawn(f), unpark));
+        id
+    }
+
+    fn poll(&mut self, id: usize) {
+        let done = match self.tasks[id] {
+            // A task may be notified after it's completed, in which case
+            // its slot may since have been reused by another task, which is
+            // harmlessly polled an extra time.
+            Some((ref mut task, ref unpark)) => {
+                match task.poll_future(unpark.clone()) {
+                    Ok(Async::NotReady) => false,
+                    Ok(Async::Ready(())) | Err(()) => true,
+                }
+            }
+            None => false,
+        };
+        if done {
+            self.tasks[id] = None;
+            self.free.push(id);
+        }
+    }
+}
+
+impl Default for CurrentThread {
+    fn default() -> CurrentThread {
+        CurrentThread::new()
+    }
+}
+
+impl Drop for CurrentThread {
+    fn drop(&mut self) {
+        // Futures are dropped outside of the lock, as their destructors may
+        // try to spawn more futures through a `Remote`.
+        let spawned = {
+            let mut queue = self.shared.queue.lock().unwrap();
+            queue.closed = true;
+            mem::replace(&mut queue.spawned, Vec::new())
+        };
+        drop(spawned);
+    }
+}
+
+impl Remote {
+    /// Spawns a future onto the executor this handle belongs to.
+    ///
+    /// The future is run on the executor's thread the next time it's blocked
+    /// in `CurrentThread::run`. If the executor has been dropped then the
+    /// future is dropped without being run.
+    pub fn spawn<F>(&self, f: F)
+        where F: Future<Item = (), Error = ()> + Send + 'static,
+    {
+        let mut queue = self.shared.queue.lock().unwrap();
+        if queue.closed {
+            drop(queue);
+            drop(f);
+            return
+        }
+        queue.spawned.push(Box::new(f));
+        drop(queue);
+        self.shared.thread.unpark();
+    }
+}
+
+impl Unpark for TaskUnpark {
+    fn unpark(&self) {
+        let mut queue = self.shared.queue.lock().unwrap();
+        if !queue.closed {
+            queue.ready.push_back(self.id);
+            drop(queue);
+            self.shared.thread.unpark();
+        }
+    }
+}
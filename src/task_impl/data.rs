@@ -2,8 +2,16 @@ use std::prelude::v1::*;
 
 use std::any::TypeId;
 use std::cell::RefCell;
-use std::hash::{BuildHasherDefault, Hasher};
+use std::mem;
+
+use super::TaskContext;
+
+#[cfg(feature = "use_std")]
 use std::collections::HashMap;
+#[cfg(feature = "use_std")]
+use std::hash::{BuildHasherDefault, Hasher};
+#[cfg(not(feature = "use_std"))]
+use std::collections::BTreeMap;
 
 /// A macro to create a `static` of type `LocalKey`
 ///
@@ -12,6 +20,30 @@ use std::collections::HashMap;
 ///
 /// The data associated with each task local is per-task, so different tasks
 /// will contain different values.
+///
+/// An eviction callback can optionally be provided after the initialization
+/// expression. When the task's data is destroyed, the value of the key is
+/// passed to the callback rather than simply being dropped, which allows
+/// task-scoped resources such as buffered loggers or metrics to flush their
+/// final state deterministically. Values taken out of the task through
+/// `remove`, `replace` or `take_local_data` are handed back to the caller
+/// instead, and the callback isn't run for them.
+///
+/// # Examples
+///
+/// ```
+/// #[macro_use]
+/// extern crate futures;
+///
+/// fn flush(lines: Vec<String>) {
+///     for line in lines {
+///         println!("{}", line);
+///     }
+/// }
+///
+/// task_local!(static LOG: Vec<String> = Vec::new(); evict = flush);
+/// # fn main() {}
+/// ```
 #[macro_export]
 macro_rules! task_local {
     (static $NAME:ident: $t:ty = $e:expr) => (
@@ -24,29 +56,331 @@ macro_rules! task_local {
             $crate::task::LocalKey {
                 __init: __init,
                 __key: __key,
+                __evict: None,
+            }
+        };
+    );
+    (static $NAME:ident: $t:ty = $e:expr; evict = $f:expr) => (
+        static $NAME: $crate::task::LocalKey<$t> = {
+            fn __init() -> $t { $e }
+            fn __key() -> ::std::any::TypeId {
+                struct __A;
+                ::std::any::TypeId::of::<__A>()
+            }
+            fn __evict(value: $t) { ($f)(value) }
+            $crate::task::LocalKey {
+                __init: __init,
+                __key: __key,
+                __evict: Some(__evict),
             }
         };
-    )
+    );
+}
+
+pub type LocalData = RefCell<Slots>;
+
+pub fn local_data() -> LocalData {
+    RefCell::new(Slots::new())
 }
 
-pub type LocalMap = RefCell<HashMap<TypeId,
-                                    Box<Opaque>,
-                                    BuildHasherDefault<IdHasher>>>;
+/// A set of task-local values to install into a task before it's first
+/// polled.
+///
+/// Normally task-local data is initialized lazily, the first time each key is
+/// accessed from within the task. A `LocalMap` instead allows an executor to
+/// provide values up front, such as a logger or request ID for the task, by
+/// passing it to `Spawn::with_local_data`. The data of an existing task can
+/// also be moved out into a `LocalMap` with `Spawn::take_local_data`.
+///
+/// # Examples
+///
+/// ```
+/// #[macro_use]
+/// extern crate futures;
+///
+/// use futures::executor;
+/// use futures::future::lazy;
+/// use futures::task::LocalMap;
+///
+/// task_local!(static REQUEST_ID: u64 = 0);
+///
+/// # fn main() {
+/// let mut locals = LocalMap::new();
+/// locals.insert(&REQUEST_ID, 42);
+///
+/// let f = lazy(|| Ok::<_, ()>(REQUEST_ID.with(|id| *id)));
+/// let id = executor::spawn(f).with_local_data(locals).wait_future();
+/// assert_eq!(id, Ok(42));
+/// # }
+/// ```
+pub struct LocalMap {
+    slots: Slots,
+}
+
+impl LocalMap {
+    /// Creates a new, empty set of task-local values.
+    pub fn new() -> LocalMap {
+        LocalMap { slots: Slots::new() }
+    }
+
+    /// Sets the value of `key`, replacing any value previously set in this
+    /// map.
+    ///
+    /// Values are inserted into the task in the same order as they're
+    /// inserted into this map, which determines the order in which they're
+    /// destroyed.
+    pub fn insert<T: Send + 'static>(&mut self, key: &'static LocalKey<T>, value: T)
+                                     -> &mut LocalMap {
+        self.slots.insert((key.__key)(), Box::new(value), key.evictor());
+        self
+    }
 
-pub fn local_map() -> LocalMap {
-    RefCell::new(HashMap::default())
+    /// Sets the value of `key` to a clone of its value in the current task,
+    /// if it has one.
+    ///
+    /// If `key` hasn't been initialized in the current task then this map is
+    /// left unchanged, and the key will be initialized as usual in the task
+    /// this map is installed into.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if there is not a current task.
+    pub fn inherit<T>(&mut self, key: &'static LocalKey<T>) -> &mut LocalMap
+        where T: Clone + Send + 'static,
+    {
+        let present = super::with(|_, data| {
+            data.borrow().map.contains_key(&(key.__key)())
+        });
+        if present {
+            // The value is cloned through `with` so that the clone runs
+            // outside of any borrow of the current task's data.
+            let value = key.with(|value| value.clone());
+            self.insert(key, value);
+        }
+        self
+    }
+}
+
+impl Default for LocalMap {
+    fn default() -> LocalMap {
+        LocalMap::new()
+    }
+}
+
+/// A task-local key whose value can be inherited by tasks spawned from the
+/// current task.
+///
+/// This is implemented for every `LocalKey` whose data is `Clone`, so that
+/// keys of different types can be passed to `executor::spawn_inheriting`
+/// together.
+pub trait Inherit {
+    /// Copies the current task's value for this key, if it has one, into
+    /// `map`, as `LocalMap::inherit` does.
+    fn inherit_into(&'static self, map: &mut LocalMap);
+}
+
+impl<T: Clone + Send + 'static> Inherit for LocalKey<T> {
+    fn inherit_into(&'static self, map: &mut LocalMap) {
+        map.inherit(self);
+    }
+}
+
+pub fn install(data: &LocalData, map: LocalMap) {
+    // Replaced values are destroyed outside of the borrow of the map as their
+    // destructors may access other task-local data.
+    let replaced = data.borrow_mut().extend(map.slots);
+    drop(replaced);
+}
+
+pub fn take(data: &LocalData) -> LocalMap {
+    let slots = mem::replace(&mut *data.borrow_mut(), Slots::new());
+    LocalMap { slots: slots }
+}
+
+// A set of task-local values which are installed into the current task only
+// temporarily, for example for the duration of a single poll.
+//
+// The values of the keys covered by the scope are held in `slots` whenever
+// they're not installed, and calling `swap` exchanges them with the current
+// task's values for the same keys. Swapping twice therefore installs the
+// scope's values and then restores the task's own, keeping any changes made
+// to either side in the meantime.
+#[cfg(feature = "use_std")]
+pub struct LocalScope {
+    keys: Vec<TypeId>,
+    slots: Slots,
+}
+
+#[cfg(feature = "use_std")]
+impl LocalScope {
+    pub fn new(map: LocalMap) -> LocalScope {
+        LocalScope {
+            keys: map.slots.map.keys().cloned().collect(),
+            slots: map.slots,
+        }
+    }
+
+    // Panics if there is no current task, or if any of the keys is borrowed.
+    pub fn swap(&mut self) {
+        super::with(|_, data| {
+            let mut data = data.borrow_mut();
+            for key in self.keys.iter() {
+                let outer = self.slots.map.remove(key);
+                if let Some(inner) = data.restore(*key, outer) {
+                    self.slots.map.insert(*key, inner);
+                }
+            }
+        })
+    }
+}
+
+// Storage for all of a task's local data.
+//
+// Each value is tagged with a sequence number recording when it was inserted,
+// so that the values can be destroyed in a well defined order (the reverse of
+// insertion) when the task goes away. Values which are currently handed out
+// through `LocalKey::with` and `LocalKey::with_mut` are also tracked, like
+// the borrows of a `RefCell`, so that they can't be mutated or removed while
+// a reference to them is live.
+pub struct Slots {
+    map: Map,
+    next_seq: u64,
+}
+
+#[cfg(feature = "use_std")]
+type Map = HashMap<TypeId, Slot, BuildHasherDefault<IdHasher>>;
+#[cfg(not(feature = "use_std"))]
+type Map = BTreeMap<TypeId, Slot>;
+
+struct Slot {
+    seq: u64,
+    borrows: usize,
+    borrowed_mut: bool,
+    value: Box<Opaque>,
+    evict: Option<&'static Evict>,
+}
+
+impl Slots {
+    fn new() -> Slots {
+        Slots {
+            map: Map::default(),
+            next_seq: 0,
+        }
+    }
+
+    // Moves all of the values in `other` into this map, preserving their
+    // relative order and returning the values they replaced.
+    fn extend(&mut self, mut other: Slots) -> Vec<Box<Opaque>> {
+        let mut slots = mem::replace(&mut other.map, Map::default())
+            .into_iter()
+            .collect::<Vec<_>>();
+        slots.sort_by_key(|pair| pair.1.seq);
+        slots.into_iter()
+             .filter_map(|(key, slot)| self.insert(key, slot.value, slot.evict))
+             .collect()
+    }
+
+    fn insert(&mut self, key: TypeId, data: Box<Opaque>, evict: Option<&'static Evict>)
+              -> Option<Box<Opaque>> {
+        let prev = self.take(key);
+        let slot = Slot {
+            seq: self.next_seq,
+            borrows: 0,
+            borrowed_mut: false,
+            value: data,
+            evict: evict,
+        };
+        self.next_seq += 1;
+        self.map.insert(key, slot);
+        prev.map(|slot| slot.value)
+    }
+
+    fn remove(&mut self, key: TypeId) -> Option<Box<Opaque>> {
+        self.take(key).map(|slot| slot.value)
+    }
+
+    fn take(&mut self, key: TypeId) -> Option<Slot> {
+        if let Some(slot) = self.map.get(&key) {
+            assert!(slot.borrows == 0 && !slot.borrowed_mut,
+                    "cannot remove task-local data while it's borrowed");
+        }
+        self.map.remove(&key)
+    }
+
+    fn restore(&mut self, key: TypeId, slot: Option<Slot>) -> Option<Slot> {
+        let scoped = self.take(key);
+        if let Some(slot) = slot {
+            self.map.insert(key, slot);
+        }
+        scoped
+    }
+}
+
+impl Drop for Slots {
+    fn drop(&mut self) {
+        // Task-local data is destroyed in the reverse order that it was
+        // inserted in, so values can rely on data created before them still
+        // being alive while they're destroyed. Keys with an eviction callback
+        // are handed their value instead of it being dropped directly.
+        let mut slots = mem::replace(&mut self.map, Map::default())
+            .into_iter()
+            .map(|(_, slot)| slot)
+            .collect::<Vec<_>>();
+        slots.sort_by_key(|slot| slot.seq);
+        for slot in slots.into_iter().rev() {
+            match slot.evict {
+                Some(evict) => evict.evict(slot.value),
+                None => drop(slot),
+            }
+        }
+    }
+}
+
+// Releases a borrow of task-local data taken by `LocalKey::borrow`.
+struct Release<'a> {
+    data: &'a LocalData,
+    key: TypeId,
+    mutable: bool,
+}
+
+impl<'a> Drop for Release<'a> {
+    fn drop(&mut self) {
+        if let Some(slot) = self.data.borrow_mut().map.get_mut(&self.key) {
+            if self.mutable {
+                slot.borrowed_mut = false;
+            } else {
+                slot.borrows -= 1;
+            }
+        }
+    }
 }
 
 pub trait Opaque: Send {}
 impl<T: Send> Opaque for T {}
 
+// Type-erased access to a key's eviction callback, so that it can be invoked
+// on the opaque value stored for the key.
+trait Evict: Sync {
+    fn evict(&'static self, value: Box<Opaque>);
+}
+
+impl<T: Send + 'static> Evict for LocalKey<T> {
+    fn evict(&'static self, value: Box<Opaque>) {
+        let value = unsafe { *Box::from_raw(Box::into_raw(value) as *mut T) };
+        (self.__evict.unwrap())(value)
+    }
+}
+
 /// A key for task-local data stored in a future's task.
 ///
 /// This type is generated by the `task_local!` macro and performs very
 /// similarly to the `thread_local!` macro and `std::thread::LocalKey` types.
 /// Data associated with a `LocalKey<T>` is stored inside of a future's task,
 /// and the data is destroyed when the future is completed and the task is
-/// destroyed.
+/// destroyed. Data is destroyed in the reverse order that it was inserted into
+/// the task, and can also be torn down early through the `remove` and
+/// `replace` methods. Keys declared with an eviction callback pass their
+/// value to it when the task is destroyed instead.
 ///
 /// Task-local data can migrate between threads and hence requires a `Send`
 /// bound. Additionally, task-local data also requires the `'static` bound to
@@ -60,18 +394,23 @@ pub struct LocalKey<T> {
     pub __key: fn() -> TypeId,
     #[doc(hidden)]
     pub __init: fn() -> T,
+    #[doc(hidden)]
+    pub __evict: Option<fn(T)>,
 }
 
+#[cfg(feature = "use_std")]
 pub struct IdHasher {
     id: u64,
 }
 
+#[cfg(feature = "use_std")]
 impl Default for IdHasher {
     fn default() -> IdHasher {
         IdHasher { id: 0 }
     }
 }
 
+#[cfg(feature = "use_std")]
 impl Hasher for IdHasher {
     fn write(&mut self, _bytes: &[u8]) {
         // TODO: need to do something sensible
@@ -88,6 +427,14 @@ impl Hasher for IdHasher {
 }
 
 impl<T: Send + 'static> LocalKey<T> {
+    fn evictor(&'static self) -> Option<&'static Evict> {
+        if self.__evict.is_some() {
+            Some(self)
+        } else {
+            None
+        }
+    }
+
     /// Access this task-local key, running the provided closure with a
     /// reference to the value.
     ///
@@ -111,18 +458,201 @@ impl<T: Send + 'static> LocalKey<T> {
     pub fn with<F, R>(&'static self, f: F) -> R
         where F: FnOnce(&T) -> R
     {
-        let key = (self.__key)();
+        super::with(|_, data| self.with_data(data, f))
+    }
+
+    /// Like `with`, except that it accesses the data of the task that `cx`
+    /// belongs to, rather than the current task.
+    ///
+    /// # Panics
+    ///
+    /// This function panics under the same conditions as `with`, except that
+    /// it doesn't require a current task.
+    pub fn with_context<F, R>(&'static self, cx: &TaskContext, f: F) -> R
+        where F: FnOnce(&T) -> R
+    {
+        self.with_data(cx.data, f)
+    }
+
+    fn with_data<F, R>(&'static self, data: &LocalData, f: F) -> R
+        where F: FnOnce(&T) -> R
+    {
+        let (_release, raw_pointer) = self.borrow(data, false);
+        unsafe {
+            f(&*raw_pointer)
+        }
+    }
+
+    /// Access this task-local key, running the provided closure with a
+    /// mutable reference to the value.
+    ///
+    /// This behaves like `with`, initializing the value if necessary, except
+    /// that the value can be modified in place without wrapping it in a
+    /// `RefCell`. Borrows of the value are checked at runtime, in the same way
+    /// as the borrows of a `RefCell`, so this can't be called while the value
+    /// is borrowed through `with` or `with_mut`, for example from within one
+    /// of their closures.
+    ///
+    /// # Panics
+    ///
+    /// This function panics under the same conditions as `with`, and also if
+    /// the value is already borrowed.
+    pub fn with_mut<F, R>(&'static self, f: F) -> R
+        where F: FnOnce(&mut T) -> R
+    {
         super::with(|_, data| {
-            let raw_pointer = {
-                let mut data = data.borrow_mut();
-                let entry = data.entry(key).or_insert_with(|| {
-                    Box::new((self.__init)())
-                });
-                &**entry as *const Opaque as *const T
-            };
+            let (_release, raw_pointer) = self.borrow(data, true);
             unsafe {
-                f(&*raw_pointer)
+                f(&mut *raw_pointer)
+            }
+        })
+    }
+
+    /// Returns a clone of the value associated with this key in the current
+    /// task, initializing it if necessary.
+    ///
+    /// # Panics
+    ///
+    /// This function panics under the same conditions as `with`, and also if
+    /// the value is mutably borrowed through `with_mut`.
+    pub fn get(&'static self) -> T
+        where T: Clone,
+    {
+        self.with(|value| value.clone())
+    }
+
+    /// Sets the value associated with this key in the current task.
+    ///
+    /// Unlike `replace`, this overwrites an existing value in place, so the
+    /// value keeps its original position in the task's destruction order. If
+    /// there's no value yet then `value` is inserted without running the
+    /// initialization expression. The previous value, if any, is dropped.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if there is not a current task, or if the
+    /// value is borrowed through `with` or `with_mut`.
+    pub fn set(&'static self, value: T) {
+        let key = (self.__key)();
+        let present = super::with(|_, data| {
+            data.borrow().map.contains_key(&key)
+        });
+        if present {
+            // The previous value is dropped outside of the borrow, as its
+            // destructor may access this key.
+            let prev = self.with_mut(|slot| mem::replace(slot, value));
+            drop(prev);
+        } else {
+            self.replace(value);
+        }
+    }
+
+    // Borrows the value of this key in `data`, initializing it if necessary,
+    // and returns a pointer to it along with a guard which releases the
+    // borrow when dropped.
+    fn borrow<'a>(&'static self, data: &'a LocalData, mutable: bool)
+                  -> (Release<'a>, *mut T) {
+        let key = (self.__key)();
+        let mut slots = data.borrow_mut();
+        if !slots.map.contains_key(&key) {
+            slots.insert(key, Box::new((self.__init)()), self.evictor());
+        }
+        let slot = slots.map.get_mut(&key).unwrap();
+        if slot.borrowed_mut {
+            panic!("task-local data is already mutably borrowed");
+        }
+        if mutable {
+            if slot.borrows > 0 {
+                panic!("task-local data is already borrowed");
             }
+            slot.borrowed_mut = true;
+        } else {
+            slot.borrows += 1;
+        }
+        let raw_pointer = &mut *slot.value as *mut Opaque as *mut T;
+        (Release { data: data, key: key, mutable: mutable }, raw_pointer)
+    }
+
+    /// Removes the value associated with this key from the current task,
+    /// returning it if it was present.
+    ///
+    /// This can be used to tear down expensive task-local data before the
+    /// task itself completes. If the key is accessed again after being removed
+    /// then it will be initialized again with the initialization expression
+    /// provided to `task_local!`.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if there is not a current task, or if it is
+    /// called from within a `with` closure for this same key.
+    pub fn remove(&'static self) -> Option<T> {
+        let key = (self.__key)();
+        super::with(|_, data| {
+            data.borrow_mut().remove(key).map(|value| unsafe {
+                *Box::from_raw(Box::into_raw(value) as *mut T)
+            })
+        })
+    }
+
+    /// Replaces the value associated with this key in the current task,
+    /// returning the previous value if it was present.
+    ///
+    /// For the purposes of destruction order, the new value is considered to
+    /// have been inserted into the task at the time of this call.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if there is not a current task, or if it is
+    /// called from within a `with` closure for this same key.
+    pub fn replace(&'static self, value: T) -> Option<T> {
+        let key = (self.__key)();
+        super::with(|_, data| {
+            data.borrow_mut().insert(key, Box::new(value), self.evictor()).map(|value| unsafe {
+                *Box::from_raw(Box::into_raw(value) as *mut T)
+            })
+        })
+    }
+
+    /// Overrides the value associated with this key in the current task for
+    /// the duration of the closure `f`.
+    ///
+    /// While `f` runs, accesses to this key from the current task will see
+    /// `value`. Once `f` returns, or if it panics, `value` is destroyed and the
+    /// previous value associated with this key, if any, is restored, keeping
+    /// its original position in the task's destruction order.
+    ///
+    /// This is useful for temporarily shadowing data such as a request ID for
+    /// the polls of a sub-future, similarly to `task::with_unpark_event`.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if there is not a current task, or if it is
+    /// called from within a `with` closure for this same key.
+    pub fn scope<F, R>(&'static self, value: T, f: F) -> R
+        where F: FnOnce() -> R
+    {
+        struct Restore<'a>(&'a LocalData, TypeId, Option<Slot>);
+
+        impl<'a> Drop for Restore<'a> {
+            fn drop(&mut self) {
+                // The scoped value is destroyed outside of the borrow of the
+                // map as its destructor may access other task-local data.
+                let prev = self.2.take();
+                let scoped = self.0.borrow_mut().restore(self.1, prev);
+                drop(scoped);
+            }
+        }
+
+        let key = (self.__key)();
+        super::with(|_, data| {
+            let prev = {
+                let mut slots = data.borrow_mut();
+                let prev = slots.take(key);
+                slots.insert(key, Box::new(value), self.evictor());
+                prev
+            };
+            let _restore = Restore(data, key, prev);
+            f()
         })
     }
 }
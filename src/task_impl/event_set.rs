@@ -0,0 +1,184 @@
+use std::prelude::v1::*;
+
+use std::mem;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use stack::{self, Stack};
+use super::{EventSet, UnparkId};
+
+const BITS: usize = mem::size_of::<usize>() * 8;
+
+/// An `EventSet` recording event identifiers in a fixed-size atomic bitmap.
+///
+/// This is suited to combinators tracking a small, dense range of indices,
+/// such as the branches of a select. Inserting an identifier is a single
+/// atomic operation, and inserting the same identifier several times before
+/// it's drained only records it once.
+///
+/// # Panics
+///
+/// Inserting an identifier which isn't less than the capacity the set was
+/// created with will panic.
+pub struct AtomicBitSet {
+    words: Box<[AtomicUsize]>,
+    capacity: usize,
+}
+
+/// An iterator over the identifiers removed from an `AtomicBitSet` by
+/// `AtomicBitSet::drain`, in ascending order.
+pub struct BitSetDrain<'a> {
+    words: &'a [AtomicUsize],
+    idx: usize,
+    cur: usize,
+}
+
+impl AtomicBitSet {
+    /// Creates a new empty set which can hold identifiers in the range
+    /// `0..capacity`.
+    pub fn new(capacity: usize) -> AtomicBitSet {
+        let len = if capacity == 0 { 0 } else { (capacity - 1) / BITS + 1 };
+        AtomicBitSet {
+            words: (0..len).map(|_| AtomicUsize::new(0)).collect::<Vec<_>>()
+                           .into_boxed_slice(),
+            capacity: capacity,
+        }
+    }
+
+    /// Returns the number of identifiers this set can hold.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns whether `id` is currently in the set.
+    pub fn contains(&self, id: usize) -> bool {
+        let (word, bit) = self.locate(id);
+        self.words[word].load(Ordering::SeqCst) & bit != 0
+    }
+
+    /// Removes `id` from the set, returning whether it was present.
+    pub fn remove(&self, id: usize) -> bool {
+        let (word, bit) = self.locate(id);
+        self.words[word].fetch_and(!bit, Ordering::SeqCst) & bit != 0
+    }
+
+    /// Removes every identifier from the set, returning an iterator over
+    /// them in ascending order.
+    ///
+    /// Each word of the bitmap is taken atomically, so identifiers inserted
+    /// concurrently with a drain are either yielded by it or left in the set
+    /// for the next one, but never lost.
+    pub fn drain<'a>(&'a self) -> BitSetDrain<'a> {
+        BitSetDrain {
+            words: &self.words,
+            idx: 0,
+            cur: 0,
+        }
+    }
+
+    fn locate(&self, id: usize) -> (usize, usize) {
+        assert!(id < self.capacity,
+                "identifier {} out of range for AtomicBitSet of capacity {}",
+                id, self.capacity);
+        (id / BITS, 1 << (id % BITS))
+    }
+}
+
+impl EventSet for AtomicBitSet {
+    fn insert(&self, id: usize) {
+        let (word, bit) = self.locate(id);
+        self.words[word].fetch_or(bit, Ordering::SeqCst);
+    }
+}
+
+impl UnparkId for AtomicBitSet {
+    fn unpark_id(&self, id: usize) {
+        self.insert(id);
+    }
+}
+
+impl<'a> Iterator for BitSetDrain<'a> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        loop {
+            if self.cur != 0 {
+                let bit = self.cur.trailing_zeros() as usize;
+                self.cur &= self.cur - 1;
+                return Some((self.idx - 1) * BITS + bit)
+            }
+            if self.idx == self.words.len() {
+                return None
+            }
+            self.cur = self.words[self.idx].swap(0, Ordering::SeqCst);
+            self.idx += 1;
+        }
+    }
+}
+
+/// An `EventSet` which queues up event identifiers as they're inserted.
+///
+/// This is suited to combinators managing a large or sparse set of
+/// identifiers, where only a few of them are expected to fire between polls.
+/// Insertion is lock-free, and `drain` takes every queued identifier at once
+/// so the cost of a poll is proportional to the number of events which fired.
+///
+/// Unlike `AtomicBitSet`, an identifier inserted several times will be
+/// yielded several times.
+pub struct ReadyQueue<T = usize> {
+    stack: Stack<T>,
+}
+
+/// An iterator over the identifiers removed from a `ReadyQueue` by
+/// `ReadyQueue::drain`.
+pub struct ReadyQueueDrain<T> {
+    drain: stack::Drain<T>,
+}
+
+impl<T> ReadyQueue<T> {
+    /// Creates a new, empty, queue.
+    pub fn new() -> ReadyQueue<T> {
+        ReadyQueue { stack: Stack::new() }
+    }
+
+    /// Adds `id` to the queue.
+    ///
+    /// This is the same as `EventSet::insert`, and is provided so that
+    /// identifiers can be queued up without an `UnparkEvent`.
+    pub fn push(&self, id: T) {
+        self.stack.push(id);
+    }
+
+    /// Removes every identifier from the queue, returning an iterator over
+    /// them.
+    ///
+    /// Identifiers are yielded most recently inserted first.
+    pub fn drain(&self) -> ReadyQueueDrain<T> {
+        ReadyQueueDrain { drain: self.stack.drain() }
+    }
+}
+
+impl<T> Default for ReadyQueue<T> {
+    fn default() -> ReadyQueue<T> {
+        ReadyQueue::new()
+    }
+}
+
+impl<T: Send + Sync + 'static> EventSet<T> for ReadyQueue<T> {
+    fn insert(&self, id: T) {
+        self.push(id);
+    }
+}
+
+impl UnparkId for ReadyQueue<usize> {
+    fn unpark_id(&self, id: usize) {
+        self.push(id);
+    }
+}
+
+impl<T> Iterator for ReadyQueueDrain<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.drain.next()
+    }
+}
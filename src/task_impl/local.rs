@@ -0,0 +1,122 @@
+use std::prelude::v1::*;
+
+use std::cell::Cell;
+use std::fmt;
+use std::ptr;
+use std::rc::Rc;
+
+use super::{with, Events, Task};
+
+thread_local!(static CURRENT_LOCAL: Cell<*const Rc<LocalUnpark>> = {
+    Cell::new(ptr::null())
+});
+
+/// A trait which represents a sink of notifications that a future is ready to
+/// make progress, for executors running all of their tasks on one thread.
+///
+/// This is the single-threaded counterpart of `Unpark`. It's provided to
+/// `Spawn::poll_future_local` and used to wake the task through handles
+/// returned from `task::park_local`, which can't leave the thread the task is
+/// running on. Implementations therefore don't need to be `Send` or `Sync`,
+/// and can use `Rc` and `Cell` rather than atomics to keep track of which
+/// tasks are ready.
+pub trait LocalUnpark {
+    /// Indicates that an associated future and/or task are ready to make
+    /// progress.
+    fn unpark(&self);
+}
+
+/// A handle to a task which can only be used on the thread the task is
+/// running on.
+///
+/// This is obtained by the `task::park_local` function.
+#[derive(Clone)]
+pub struct LocalTask {
+    inner: Inner,
+}
+
+#[derive(Clone)]
+enum Inner {
+    Local(usize, Rc<LocalUnpark>, Events),
+    Shared(Task),
+}
+
+/// Returns a handle to the current task which can only be used on the current
+/// thread.
+///
+/// This is like `task::park`, except that if the task is being polled through
+/// `Spawn::poll_future_local` or `Spawn::poll_stream_local`, unparking the
+/// returned handle notifies the `LocalUnpark` provided there without any
+/// synchronization. This makes it cheaper to use for futures which are always
+/// completed from the thread they're polled on, such as single-threaded
+/// channels. If the task is being polled in some other way, the returned
+/// handle simply wraps the handle returned by `task::park`.
+///
+/// # Panics
+///
+/// This function will panic if a task is not currently being executed. That
+/// is, this method can be dangerous to call outside of an implementation of
+/// `poll`.
+pub fn park_local() -> LocalTask {
+    with(|task, _| {
+        let local = CURRENT_LOCAL.with(|c| c.get());
+        let inner = if local.is_null() {
+            Inner::Shared(task.clone())
+        } else {
+            let local = unsafe { (*local).clone() };
+            Inner::Local(task.id, local, task.events.clone())
+        };
+        LocalTask { inner: inner }
+    })
+}
+
+impl LocalTask {
+    /// Indicate that the task should attempt to poll its future in a timely
+    /// fashion.
+    ///
+    /// This behaves the same as `Task::unpark`.
+    pub fn unpark(&self) {
+        match self.inner {
+            Inner::Local(_, ref unpark, ref events) => {
+                events.trigger();
+                unpark.unpark();
+            }
+            Inner::Shared(ref task) => task.unpark(),
+        }
+    }
+
+    /// Returns the ID of the task this handle refers to.
+    pub fn id(&self) -> usize {
+        match self.inner {
+            Inner::Local(id, _, _) => id,
+            Inner::Shared(ref task) => task.id(),
+        }
+    }
+}
+
+impl fmt::Debug for LocalTask {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LocalTask")
+         .field("id", &self.id())
+         .finish()
+    }
+}
+
+// Sets the `LocalUnpark` used by `park_local` for the duration of `f`, or
+// clears it if `local` is `None`.
+pub fn set<F, R>(local: Option<&Rc<LocalUnpark>>, f: F) -> R
+    where F: FnOnce() -> R
+{
+    struct Reset(*const Rc<LocalUnpark>);
+    impl Drop for Reset {
+        fn drop(&mut self) {
+            CURRENT_LOCAL.with(|c| c.set(self.0));
+        }
+    }
+
+    CURRENT_LOCAL.with(|c| {
+        let _reset = Reset(c.get());
+        c.set(local.map_or(ptr::null(), |local| local as *const _));
+        f()
+    })
+}
@@ -1,28 +1,70 @@
 use std::prelude::v1::*;
 
-use std::cell::Cell;
+use std::any::Any;
 use std::fmt;
 use std::sync::Arc;
-use std::sync::atomic::{Ordering, AtomicBool, AtomicUsize, ATOMIC_USIZE_INIT};
-use std::thread;
+use std::sync::atomic::{Ordering, AtomicUsize, ATOMIC_USIZE_INIT};
 
-use {Poll, Future, Async};
-use future::BoxFuture;
+use {Poll, Future};
 use stream::Stream;
 
-mod unpark_mutex;
-use self::unpark_mutex::UnparkMutex;
-
-mod task_rc;
+mod context;
+mod context_future;
+#[macro_use]
 mod data;
-#[allow(deprecated)]
-#[cfg(feature = "with-deprecated")]
-pub use self::task_rc::TaskRc;
-pub use self::data::LocalKey;
+mod event_set;
+#[cfg(not(feature = "use_std"))]
+pub use self::context::init;
+pub use self::context_future::{ContextFuture, Implicit, implicit};
+pub use self::data::{LocalKey, LocalMap, Inherit};
+#[cfg(feature = "use_std")]
+pub use self::data::LocalScope;
+pub use self::event_set::{AtomicBitSet, BitSetDrain, ReadyQueue, ReadyQueueDrain};
 
-thread_local!(static CURRENT_TASK: Cell<(*const Task, *const data::LocalMap)> = {
-    Cell::new((0 as *const _, 0 as *const _))
-});
+if_std! {
+    use std::cell::Cell;
+    use std::marker::PhantomData;
+    use std::rc::Rc;
+    use std::sync::atomic::AtomicBool;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use Async;
+    use future::BoxFuture;
+
+    mod unpark_mutex;
+    use self::unpark_mutex::UnparkMutex;
+
+    mod task_rc;
+    mod task_local_cell;
+    mod blocking;
+    mod local;
+    mod current_thread;
+    mod yield_now;
+    #[allow(deprecated)]
+    #[cfg(feature = "with-deprecated")]
+    pub use self::task_rc::{TaskRc, TaskRcError};
+    pub use self::task_local_cell::{TaskLocalCell, TaskLocalRef, TaskLocalRefMut, Released,
+                                    TaskLocalCellError};
+    pub use self::blocking::{blocking, Blocking};
+    pub use self::local::{LocalUnpark, LocalTask, park_local};
+    pub use self::current_thread::{CurrentThread, Remote};
+    pub use self::yield_now::{yield_now, yield_task, YieldNow};
+
+    thread_local!(static ENTERED: Cell<bool> = Cell::new(false));
+
+    // Set by `yield_now` to ask `Run::run` to resubmit the task to its
+    // executor rather than polling it again right away.
+    thread_local!(static YIELDED: Cell<bool> = Cell::new(false));
+
+    // The number of times `Run::run` polls a task in a row before
+    // resubmitting it to its executor.
+    const POLL_BUDGET: usize = 32;
+}
+
+// What the current context points to while a task is being polled: the task
+// and its local data.
+type Current = (*const Task, *const data::LocalData);
 
 fn fresh_task_id() -> usize {
     // TODO: this assert is a real bummer, need to figure out how to reuse
@@ -34,32 +76,47 @@ fn fresh_task_id() -> usize {
     id
 }
 
-fn set<F, R>(task: &Task, data: &data::LocalMap, f: F) -> R
+fn set<F, R>(task: &Task, data: &data::LocalData, f: F) -> R
     where F: FnOnce() -> R
 {
-    struct Reset((*const Task, *const data::LocalMap));
+    struct Reset(*mut u8);
     impl Drop for Reset {
         fn drop(&mut self) {
-            CURRENT_TASK.with(|c| c.set(self.0));
+            context::set(self.0);
         }
     }
 
-    CURRENT_TASK.with(|c| {
-        let _reset = Reset(c.get());
-        c.set((task as *const _, data as *const _));
-        f()
-    })
+    let current: Current = (task as *const _, data as *const _);
+    let _reset = Reset(context::get());
+    context::set(&current as *const Current as *mut u8);
+    f()
 }
 
-fn with<F: FnOnce(&Task, &data::LocalMap) -> R, R>(f: F) -> R {
-    let (task, data) = CURRENT_TASK.with(|c| c.get());
-    assert!(!task.is_null(), "no Task is currently running");
-    debug_assert!(!data.is_null());
+fn with<F: FnOnce(&Task, &data::LocalData) -> R, R>(f: F) -> R {
+    let current = context::get() as *const Current;
+    assert!(!current.is_null(), "no Task is currently running");
     unsafe {
+        let (task, data) = *current;
         f(&*task, &*data)
     }
 }
 
+// Whether a task is currently running, for code which can do without one.
+pub fn in_task() -> bool {
+    !context::get().is_null()
+}
+
+// Like `current_id`, but doesn't panic if there's no task running.
+#[cfg(feature = "use_std")]
+fn current_task_id() -> Option<usize> {
+    let current = context::get() as *const Current;
+    if current.is_null() {
+        None
+    } else {
+        Some(unsafe { (*(*current).0).id })
+    }
+}
+
 /// A handle to a "task", which represents a single lightweight "thread" of
 /// execution driving a future to completion.
 ///
@@ -73,6 +130,10 @@ pub struct Task {
     id: usize,
     unpark: Arc<Unpark>,
     events: Events,
+    route: Option<Route>,
+    tap: Option<Arc<Tap>>,
+    notified: Arc<AtomicUsize>,
+    poll: usize,
 }
 
 fn _assert_kinds() {
@@ -110,6 +171,22 @@ pub fn park() -> Task {
     with(|task, _| task.clone())
 }
 
+/// Returns the ID of the task currently being executed.
+///
+/// Task IDs are unique for the lifetime of the process, and are the same IDs
+/// returned by `Task::id` and passed to `PollHooks`. This allows logging,
+/// metrics, and debugging tools to tag the events they record with the task
+/// which produced them.
+///
+/// # Panics
+///
+/// This function will panic if a task is not currently being executed. That
+/// is, this method can be dangerous to call outside of an implementation of
+/// `poll`.
+pub fn current_id() -> usize {
+    with(|task, _| task.id)
+}
+
 impl Task {
     /// Indicate that the task should attempt to poll its future in a timely
     /// fashion. This is typically done when alerting a future that an event of
@@ -120,9 +197,19 @@ impl Task {
     /// If the task is currently polling its future when `notify` is called, it
     /// must poll the future *again* afterwards, ensuring that all relevant
     /// events are eventually observed by the future.
+    ///
+    /// Repeated calls to `unpark` between two polls of the task are coalesced:
+    /// the task's unpark events are still triggered each time, but only the
+    /// first call is forwarded to the underlying `Unpark` handle, as the task
+    /// is already scheduled to be polled.
     pub fn unpark(&self) {
         self.events.trigger();
-        self.unpark.unpark();
+        if let Some(ref route) = self.route {
+            route.trigger();
+        }
+        if self.notified.swap(self.poll, Ordering::SeqCst) != self.poll {
+            self.unpark.unpark();
+        }
     }
 
     /// Returns `true` when called from within the context of the task. In
@@ -131,6 +218,66 @@ impl Task {
     pub fn is_current(&self) -> bool {
         with(|current, _| current.id == self.id)
     }
+
+    /// Returns the ID of the task this handle refers to.
+    ///
+    /// All handles to the same task, including those created within
+    /// `with_unpark_event`, share the same ID. See `current_id` for more
+    /// information.
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    /// Returns a handle to the same task which reports `id` when unparked,
+    /// instead of the identifier it was routed with.
+    ///
+    /// Handles created within `with_unpark_id` report an identifier to an
+    /// `UnparkId` when they're unparked. This allows a combinator which knows
+    /// where a handle came from to redirect it to another of its
+    /// sub-futures, such as one waiting on the same resource, without having
+    /// to poll it. Handles created outside of `with_unpark_id` are returned
+    /// unchanged.
+    pub fn with_id(&self, id: usize) -> Task {
+        let mut task = self.clone();
+        if let Some(ref mut route) = task.route {
+            route.id = id;
+        }
+        task
+    }
+
+    /// Converts this handle into a raw pointer paired with a function which
+    /// unparks the task, for use by foreign callbacks.
+    ///
+    /// The returned pointer owns the `Task` handle. Ownership is released in
+    /// exactly one of two ways:
+    ///
+    /// * Calling the returned function with the pointer, which unparks the
+    ///   task and then destroys the handle. This is intended to be used as,
+    ///   for example, a C library's completion routine.
+    /// * Passing the pointer to `Task::from_raw`, which reclaims the handle
+    ///   without unparking the task, e.g. if the callback is canceled.
+    ///
+    /// Failing to do either leaks the handle, and doing both, or doing either
+    /// more than once, is undefined behavior. The pointer may be sent to, and
+    /// released from, any thread.
+    pub fn into_raw(self) -> (*mut (), unsafe extern "C" fn(*mut ())) {
+        unsafe extern "C" fn unpark_raw(ptr: *mut ()) {
+            Task::from_raw(ptr).unpark();
+        }
+
+        (Box::into_raw(Box::new(self)) as *mut (), unpark_raw)
+    }
+
+    /// Reclaims a `Task` handle previously converted with `Task::into_raw`.
+    ///
+    /// # Safety
+    ///
+    /// The pointer provided must have been returned from `Task::into_raw`, and
+    /// must not have already been released, either through this function or
+    /// by calling the unpark function returned alongside it.
+    pub unsafe fn from_raw(ptr: *mut ()) -> Task {
+        *Box::from_raw(ptr as *mut Task)
+    }
 }
 
 impl fmt::Debug for Task {
@@ -163,19 +310,273 @@ impl fmt::Debug for Task {
 /// This function will panic if a task is not currently being executed. That
 /// is, this method can be dangerous to call outside of an implementation of
 /// `poll`.
-pub fn with_unpark_event<F, R>(event: UnparkEvent, f: F) -> R
+pub fn with_unpark_event<T, F, R>(event: UnparkEvent<T>, f: F) -> R
+    where T: Clone + Send + Sync + 'static,
+          F: FnOnce() -> R
+{
+    with(|task, data| attach_events(task, Some(event), |task| set(task, data, f)))
+}
+
+/// For the duration of the given callback, add a number of "unpark events" to
+/// be triggered when the task handle is used to unpark the task.
+///
+/// This is the same as `with_unpark_event`, except that all of the `events`
+/// provided are attached at once. This avoids nesting calls to
+/// `with_unpark_event` when a future is watching several sources, each of
+/// which would otherwise copy the set of events registered so far.
+///
+/// # Panics
+///
+/// This function will panic if a task is not currently being executed. That
+/// is, this method can be dangerous to call outside of an implementation of
+/// `poll`.
+pub fn with_unpark_events<I, T, F, R>(events: I, f: F) -> R
+    where I: IntoIterator<Item = UnparkEvent<T>>,
+          T: Clone + Send + Sync + 'static,
+          F: FnOnce() -> R
+{
+    with(|task, data| attach_events(task, events, |task| set(task, data, f)))
+}
+
+/// A target for the identifiers reported by task handles created within
+/// `with_unpark_id`.
+pub trait UnparkId: Send + Sync {
+    /// Records that the handle routed with `id` has been unparked.
+    ///
+    /// This is called before the task itself is unparked, so the task will
+    /// see `id` when it's next polled.
+    fn unpark_id(&self, id: usize);
+}
+
+/// For the duration of the given callback, route unparks of the current task
+/// through `target`, reporting `id` to it.
+///
+/// This serves the same purpose as `with_unpark_event`, allowing a
+/// combinator to tell which of its sub-futures should be polled, but is
+/// cheaper: handles created within `f` carry the identifier directly, so
+/// routing a sub-future's wakeups doesn't allocate or copy any events. A
+/// combinator can keep a single `target` for all of its sub-futures,
+/// identifying each of them with a stable index.
+///
+/// A handle only carries one route. If the current task already has one,
+/// for example because combinators using `with_unpark_id` are nested, the
+/// existing route is kept as an unpark event, so that unparking the handle
+/// notifies both targets, and counts towards the limit set with
+/// `set_max_event_depth`.
+///
+/// # Panics
+///
+/// This function will panic if a task is not currently being executed. That
+/// is, this method can be dangerous to call outside of an implementation of
+/// `poll`.
+pub fn with_unpark_id<F, R>(target: Arc<UnparkId>, id: usize, f: F) -> R
+    where F: FnOnce() -> R
+{
+    let route = Route { target: target, id: id };
+    with(|task, data| attach_route(task, route, |task| set(task, data, f)))
+}
+
+// The maximum number of events which can be attached to a task handle, or 0
+// for no limit
+static MAX_EVENT_DEPTH: AtomicUsize = ATOMIC_USIZE_INIT;
+
+/// Limits the number of unpark events which can be attached to a task handle.
+///
+/// Each nested call to `with_unpark_event` attaches one more event to the
+/// handles returned by `task::park` within it, and every one of those events
+/// is triggered when such a handle is unparked. Pathologically deep nesting
+/// of combinators like `FuturesUnordered` therefore makes both creating and
+/// unparking handles increasingly expensive.
+///
+/// Once a handle carries `max` events, further events aren't attached to it.
+/// Each of them is instead collapsed into a coarse event: it's triggered as
+/// soon as the closure passed to `with_unpark_event` returns, so that the set
+/// it belongs to reports it as having occurred whenever the task is next
+/// polled, whatever the task was unparked by. The sub-futures behind those
+/// events are then polled on every wakeup of the task rather than just the
+/// ones meant for them, which is less efficient but still correct.
+///
+/// The limit applies to all tasks, and affects handles created after it's
+/// set. A limit of 0, which is the default, means that there's no limit.
+pub fn set_max_event_depth(max: usize) {
+    MAX_EVENT_DEPTH.store(max, Ordering::SeqCst);
+}
+
+// For the duration of `f`, also reports the `usize` identifiers of the unpark
+// events and routes registered within it to `tap` whenever they're triggered.
+// This is how diagnostics like `Future::pending_profile` tell which of the
+// sources a future is waiting on woke it up.
+#[cfg(feature = "use_std")]
+pub fn with_unpark_tap<F, R>(tap: Arc<EventSet<usize>>, f: F) -> R
     where F: FnOnce() -> R
 {
     with(|task, data| {
-        let new_task = Task {
-            id: task.id,
-            unpark: task.unpark.clone(),
-            events: task.events.with_event(event),
-        };
+        let mut new_task = task.clone();
+        new_task.tap = Some(Arc::new(Tap {
+            set: tap,
+            next: task.tap.clone(),
+        }));
         set(&new_task, data, f)
     })
 }
 
+// Calls `f` with a copy of `task` which also triggers `events`, and then
+// triggers any of the events which didn't fit within the limit set by
+// `set_max_event_depth`.
+fn attach_events<I, T, F, R>(task: &Task, events: I, f: F) -> R
+    where I: IntoIterator<Item = UnparkEvent<T>>,
+          T: Clone + Send + Sync + 'static,
+          F: FnOnce(&Task) -> R
+{
+    let (events, overflow) = match task.tap {
+        Some(ref tap) => task.events.with_events(events.into_iter().map(|e| e.tapped(tap))),
+        None => task.events.with_events(events),
+    };
+    let new_task = Task {
+        id: task.id,
+        unpark: task.unpark.clone(),
+        events: events,
+        route: task.route.clone(),
+        tap: task.tap.clone(),
+        notified: task.notified.clone(),
+        poll: task.poll,
+    };
+    let ret = f(&new_task);
+    for event in overflow {
+        event.trigger();
+    }
+    ret
+}
+
+// Calls `f` with a copy of `task` which is routed by `route`. Any route
+// `task` already had is moved onto its events, or triggered once `f` returns
+// if that would exceed the limit set by `set_max_event_depth`.
+fn attach_route<F, R>(task: &Task, route: Route, f: F) -> R
+    where F: FnOnce(&Task) -> R
+{
+    let (events, overflow) = match task.route {
+        Some(ref prev) => task.events.with_route(prev.clone()),
+        None => (task.events.clone(), None),
+    };
+    let route = match task.tap {
+        Some(ref tap) => route.tapped(tap),
+        None => route,
+    };
+    let new_task = Task {
+        id: task.id,
+        unpark: task.unpark.clone(),
+        events: events,
+        route: Some(route),
+        tap: task.tap.clone(),
+        notified: task.notified.clone(),
+        poll: task.poll,
+    };
+    let ret = f(&new_task);
+    if let Some(prev) = overflow {
+        prev.trigger();
+    }
+    ret
+}
+
+/// The context of a task, passed explicitly to `ContextFuture::poll_context`.
+///
+/// Futures normally find the task they're being polled by through the
+/// current task, which is implicitly set for the duration of each poll. A
+/// `TaskContext` instead provides the same information, the handle used to
+/// unpark the task, its ID and its task-local data, as an argument. This
+/// avoids looking up the current task each time it's needed, and allows
+/// futures to be polled by runtimes which can't provide the implicit current
+/// task.
+///
+/// Explicit and implicit polling can be mixed during a migration: `enter`
+/// polls futures written against the implicit API from within
+/// `poll_context`, and `with_context` obtains the context of the current task
+/// to poll a `ContextFuture` from within `poll`.
+pub struct TaskContext<'a> {
+    task: &'a Task,
+    data: &'a data::LocalData,
+}
+
+impl<'a> TaskContext<'a> {
+    /// Returns a handle to the task this context belongs to, to call
+    /// `unpark` at a later date.
+    ///
+    /// This is the explicit equivalent of `task::park`.
+    pub fn park(&self) -> Task {
+        self.task.clone()
+    }
+
+    /// Returns the ID of the task this context belongs to.
+    ///
+    /// This is the explicit equivalent of `task::current_id`.
+    pub fn id(&self) -> usize {
+        self.task.id
+    }
+
+    /// Calls `f` with a context whose task handles trigger the given unpark
+    /// event, in addition to the events already registered with this
+    /// context.
+    ///
+    /// This is the explicit equivalent of `task::with_unpark_event`.
+    pub fn with_unpark_event<T, F, R>(&mut self, event: UnparkEvent<T>, f: F) -> R
+        where T: Clone + Send + Sync + 'static,
+              F: FnOnce(&mut TaskContext) -> R
+    {
+        let data = self.data;
+        attach_events(self.task, Some(event), |task| {
+            f(&mut TaskContext { task: task, data: data })
+        })
+    }
+
+    /// Calls `f` with a context whose task handles report `id` to `target`
+    /// when unparked.
+    ///
+    /// This is the explicit equivalent of `task::with_unpark_id`.
+    pub fn with_unpark_id<F, R>(&mut self, target: Arc<UnparkId>, id: usize, f: F) -> R
+        where F: FnOnce(&mut TaskContext) -> R
+    {
+        let data = self.data;
+        let route = Route { target: target, id: id };
+        attach_route(self.task, route, |task| {
+            f(&mut TaskContext { task: task, data: data })
+        })
+    }
+
+    /// Makes this context's task the current task for the duration of `f`.
+    ///
+    /// This allows futures and streams which use the implicit API, such as
+    /// `task::park`, to be polled from within `ContextFuture::poll_context`.
+    pub fn enter<F, R>(&mut self, f: F) -> R
+        where F: FnOnce() -> R
+    {
+        set(self.task, self.data, f)
+    }
+}
+
+impl<'a> fmt::Debug for TaskContext<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TaskContext")
+         .field("id", &self.task.id)
+         .finish()
+    }
+}
+
+/// Calls `f` with the context of the current task.
+///
+/// This bridges from the implicit API to explicit context passing, allowing
+/// a `ContextFuture` to be polled from within `Future::poll`.
+///
+/// # Panics
+///
+/// This function will panic if a task is not currently being executed. That
+/// is, this method can be dangerous to call outside of an implementation of
+/// `poll`.
+pub fn with_context<F, R>(f: F) -> R
+    where F: FnOnce(&mut TaskContext) -> R
+{
+    with(|task, data| f(&mut TaskContext { task: task, data: data }))
+}
+
 #[derive(Clone)]
 /// A set insertion to trigger upon `unpark`.
 ///
@@ -183,15 +584,19 @@ pub fn with_unpark_event<F, R>(event: UnparkEvent, f: F) -> R
 /// occured, in particular populating sets with event identifiers so that the
 /// unparked task can avoid extraneous polling. See `with_unpark_event` for
 /// more.
-pub struct UnparkEvent {
-    set: Arc<EventSet>,
-    item: usize,
+///
+/// Identifiers are `usize` by default, but may be any cloneable type. This
+/// allows an event to carry routing information, such as a `u64` generation
+/// or a richer token, directly to its `EventSet`.
+pub struct UnparkEvent<T = usize> {
+    set: Arc<EventSet<T>>,
+    item: T,
 }
 
-impl UnparkEvent {
+impl<T> UnparkEvent<T> {
     /// Construct an unpark event that will insert `id` into `set` when
     /// triggered.
-    pub fn new(set: Arc<EventSet>, id: usize) -> UnparkEvent {
+    pub fn new(set: Arc<EventSet<T>>, id: T) -> UnparkEvent<T> {
         UnparkEvent {
             set: set,
             item: id,
@@ -199,48 +604,257 @@ impl UnparkEvent {
     }
 }
 
-/// A concurrent set which allows for the insertion of `usize` values.
+impl<T: Clone + 'static> UnparkEvent<T> {
+    fn trigger(&self) {
+        self.set.insert(self.item.clone());
+    }
+}
+
+impl<T: Clone + Send + Sync + 'static> UnparkEvent<T> {
+    // Returns this event reporting its identifier to `tap` as well, if it's a
+    // `usize`.
+    fn tapped(self, tap: &Arc<Tap>) -> UnparkEvent<T> {
+        if !(&self.item as &Any).is::<usize>() {
+            return self
+        }
+        UnparkEvent {
+            set: Arc::new(Tapped {
+                set: self.set,
+                tap: tap.clone(),
+            }),
+            item: self.item,
+        }
+    }
+}
+
+/// A concurrent set which allows for the insertion of identifiers, `usize`
+/// values by default.
 ///
 /// `EventSet`s are used to communicate precise information about the event(s)
 /// that trigged a task notification. See `task::with_unpark_event` for details.
-pub trait EventSet: Send + Sync + 'static {
+pub trait EventSet<T = usize>: Send + Sync + 'static {
     /// Insert the given ID into the set
-    fn insert(&self, id: usize);
+    fn insert(&self, id: T);
 }
 
 // A collection of UnparkEvents to trigger on `unpark`
+//
+// This is a persistent singly linked list, with the most recently added event
+// at the head. Combinators nest `with_unpark_event` calls to build up the set
+// of events for a sub-future, so sharing tails means that adding an event is
+// O(1) and cloning a `Task` never copies the events already registered.
+//
+// Events may carry different identifier types, so the nodes of the list are
+// type erased behind the `EventNode` trait. The length of the list is tracked
+// so that it can be capped, see `set_max_event_depth`.
+#[derive(Clone)]
+struct Events {
+    head: Option<Arc<EventNode>>,
+    depth: usize,
+}
+
+trait EventNode: Send + Sync {
+    fn trigger(&self);
+    fn next(&self) -> Option<&Arc<EventNode>>;
+    fn take_next(&mut self) -> Option<Arc<EventNode>>;
+}
+
+struct Node<T> {
+    event: UnparkEvent<T>,
+    next: Option<Arc<EventNode>>,
+}
+
+// The identifier a task handle reports when it's unparked, see
+// `with_unpark_id`.
 #[derive(Clone)]
-enum Events {
-    Zero,
-    One(UnparkEvent),
-    Lots(Vec<UnparkEvent>),
+struct Route {
+    target: Arc<UnparkId>,
+    id: usize,
+}
+
+struct RouteNode {
+    route: Route,
+    next: Option<Arc<EventNode>>,
+}
+
+impl Route {
+    fn trigger(&self) {
+        self.target.unpark_id(self.id);
+    }
+
+    // Returns this route reporting its identifier to `tap` as well.
+    fn tapped(self, tap: &Arc<Tap>) -> Route {
+        Route {
+            target: Arc::new(Tapped {
+                set: self.target,
+                tap: tap.clone(),
+            }),
+            id: self.id,
+        }
+    }
+}
+
+// The sets which the identifiers of the events and routes registered within
+// `with_unpark_tap` are reported to. Each tap also reports to the taps it was
+// created within.
+#[cfg_attr(not(feature = "use_std"), allow(dead_code))]
+struct Tap {
+    set: Arc<EventSet<usize>>,
+    next: Option<Arc<Tap>>,
+}
+
+impl Tap {
+    fn insert(&self, id: usize) {
+        let mut tap = self;
+        loop {
+            tap.set.insert(id);
+            match tap.next {
+                Some(ref next) => tap = next,
+                None => break,
+            }
+        }
+    }
+}
+
+// An event set or route target which also reports identifiers to a tap
+struct Tapped<S: ?Sized> {
+    set: Arc<S>,
+    tap: Arc<Tap>,
+}
+
+impl<T> EventSet<T> for Tapped<EventSet<T>>
+    where T: Clone + Send + Sync + 'static,
+{
+    fn insert(&self, id: T) {
+        if let Some(id) = (&id as &Any).downcast_ref::<usize>() {
+            self.tap.insert(*id);
+        }
+        self.set.insert(id);
+    }
+}
+
+impl UnparkId for Tapped<UnparkId> {
+    fn unpark_id(&self, id: usize) {
+        self.tap.insert(id);
+        self.set.unpark_id(id);
+    }
 }
 
 impl Events {
     fn new() -> Events {
-        Events::Zero
+        Events { head: None, depth: 0 }
     }
 
     fn trigger(&self) {
-        match *self {
-            Events::Zero => {}
-            Events::One(ref event) => event.set.insert(event.item),
-            Events::Lots(ref list) => {
-                for event in list {
-                    event.set.insert(event.item);
-                }
+        let mut cur = self.head.as_ref();
+        while let Some(node) = cur {
+            node.trigger();
+            cur = node.next();
+        }
+    }
+
+    fn with_event<T>(&self, event: UnparkEvent<T>) -> Events
+        where T: Clone + Send + Sync + 'static,
+    {
+        Events {
+            head: Some(Arc::new(Node {
+                event: event,
+                next: self.head.clone(),
+            })),
+            depth: self.depth + 1,
+        }
+    }
+
+    // Returns this list with `events` added, along with the events which
+    // couldn't be added without exceeding the maximum depth.
+    fn with_events<I, T>(&self, events: I) -> (Events, Vec<UnparkEvent<T>>)
+        where I: IntoIterator<Item = UnparkEvent<T>>,
+              T: Clone + Send + Sync + 'static,
+    {
+        let mut list = self.clone();
+        let mut overflow = Vec::new();
+        for event in events {
+            if list.is_full() {
+                overflow.push(event);
+            } else {
+                list = list.with_event(event);
             }
         }
+        (list, overflow)
     }
 
-    fn with_event(&self, event: UnparkEvent) -> Events {
-        let mut list = match *self {
-            Events::Zero => return Events::One(event),
-            Events::One(ref event) => vec![event.clone()],
-            Events::Lots(ref list) => list.clone(),
+    // Returns this list with `route` added, or the route back if adding it
+    // would exceed the maximum depth.
+    fn with_route(&self, route: Route) -> (Events, Option<Route>) {
+        if self.is_full() {
+            return (self.clone(), Some(route))
+        }
+        let list = Events {
+            head: Some(Arc::new(RouteNode {
+                route: route,
+                next: self.head.clone(),
+            })),
+            depth: self.depth + 1,
+        };
+        (list, None)
+    }
+
+    fn is_full(&self) -> bool {
+        let max = MAX_EVENT_DEPTH.load(Ordering::SeqCst);
+        max != 0 && self.depth >= max
+    }
+}
+
+impl<T> EventNode for Node<T>
+    where T: Clone + Send + Sync + 'static,
+{
+    fn trigger(&self) {
+        self.event.trigger();
+    }
+
+    fn next(&self) -> Option<&Arc<EventNode>> {
+        self.next.as_ref()
+    }
+
+    fn take_next(&mut self) -> Option<Arc<EventNode>> {
+        self.next.take()
+    }
+}
+
+impl EventNode for RouteNode {
+    fn trigger(&self) {
+        self.route.trigger();
+    }
+
+    fn next(&self) -> Option<&Arc<EventNode>> {
+        self.next.as_ref()
+    }
+
+    fn take_next(&mut self) -> Option<Arc<EventNode>> {
+        self.next.take()
+    }
+}
+
+impl<T> Drop for Node<T> {
+    fn drop(&mut self) {
+        unlink(self.next.take());
+    }
+}
+
+impl Drop for RouteNode {
+    fn drop(&mut self) {
+        unlink(self.next.take());
+    }
+}
+
+// Unlinks the tail of a list of events iteratively, as dropping a long list
+// recursively could overflow the stack.
+fn unlink(mut next: Option<Arc<EventNode>>) {
+    while let Some(mut node) = next {
+        next = match Arc::get_mut(&mut node) {
+            Some(node) => node.take_next(),
+            None => break,
         };
-        list.push(event);
-        Events::Lots(list)
     }
 }
 
@@ -257,7 +871,39 @@ impl Events {
 pub struct Spawn<T> {
     obj: T,
     id: usize,
-    data: data::LocalMap,
+    priority: Priority,
+    data: data::LocalData,
+    notify: Notify,
+}
+
+// Tracks unparks of a spawned task so that redundant ones can be coalesced.
+//
+// Every poll of the task is numbered, and the `Task` handles created during a
+// poll carry its number. Unparking a handle records its poll's number in
+// `notified`, and the unpark is only forwarded if it wasn't already recorded.
+// Entering a new poll resets `notified`, as the task then needs to be
+// notified afresh. Numbering the polls means that a stale handle, which may
+// have a different `Unpark`, can't suppress unparks through newer handles.
+struct Notify {
+    notified: Arc<AtomicUsize>,
+    polls: usize,
+}
+
+/// The priority with which an executor should schedule a task.
+///
+/// Executors which support priorities will poll ready tasks of a higher
+/// priority before those of a lower priority. Executors are free to ignore
+/// this entirely, however, and tasks are by default spawned with the `Normal`
+/// priority.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    /// Background or batch work which should only run when nothing else is
+    /// ready.
+    Low,
+    /// The default priority for a task.
+    Normal,
+    /// Latency-sensitive work which should run before all other tasks.
+    High,
 }
 
 /// Spawns a new future, returning the fused future and task.
@@ -270,10 +916,44 @@ pub struct Spawn<T> {
 /// attempt to run code in the background. The future will not make progress
 /// until the methods on `Spawn` are called in turn.
 pub fn spawn<T>(obj: T) -> Spawn<T> {
+    spawn_with_priority(obj, Priority::Normal)
+}
+
+/// Spawns a new future whose task inherits some of the current task's
+/// task-local data.
+///
+/// This is the same as `spawn`, except that the new task starts out with a
+/// clone of the current task's value for each of the `keys` provided, as with
+/// `LocalMap::inherit`. This allows data such as request IDs and deadlines to
+/// follow work spawned off from a task onto an executor.
+///
+/// # Panics
+///
+/// This function will panic if there is not a current task.
+pub fn spawn_inheriting<T>(obj: T, keys: &[&'static Inherit]) -> Spawn<T> {
+    let mut map = LocalMap::new();
+    for key in keys {
+        key.inherit_into(&mut map);
+    }
+    spawn(obj).with_local_data(map)
+}
+
+/// Spawns a new future with the given priority, returning the fused future
+/// and task.
+///
+/// This is the same as `spawn`, except that the `priority` provided will be
+/// reported to executors through `Run::priority` when the task is run via
+/// `Spawn::execute`.
+pub fn spawn_with_priority<T>(obj: T, priority: Priority) -> Spawn<T> {
     Spawn {
         obj: obj,
         id: fresh_task_id(),
-        data: data::local_map(),
+        priority: priority,
+        data: data::local_data(),
+        notify: Notify {
+            notified: Arc::new(AtomicUsize::new(0)),
+            polls: 0,
+        },
     }
 }
 
@@ -292,6 +972,34 @@ impl<T> Spawn<T> {
     pub fn into_inner(self) -> T {
         self.obj
     }
+
+    /// Installs the task-local values in `map` into this task.
+    ///
+    /// This allows executors to provide task-local data, such as a logger or
+    /// request ID, before the task is first polled rather than relying on the
+    /// initialization expression of each key. Values in `map` replace any
+    /// values the task already has for the same keys.
+    pub fn with_local_data(self, map: LocalMap) -> Spawn<T> {
+        data::install(&self.data, map);
+        self
+    }
+
+    /// Removes all of the task-local data from this task, returning it.
+    ///
+    /// The returned map can be installed into another task with
+    /// `with_local_data`, allowing a logical operation to continue under a
+    /// fresh task (for example after being checkpointed or migrated to
+    /// another executor) while keeping its task-local data, including the
+    /// order in which the values are destroyed. This task is left with no
+    /// task-local data, as if it had never accessed any keys.
+    pub fn take_local_data(&mut self) -> LocalMap {
+        data::take(&self.data)
+    }
+
+    /// Returns the priority this task was spawned with.
+    pub fn priority(&self) -> Priority {
+        self.priority
+    }
 }
 
 impl<F: Future> Spawn<F> {
@@ -312,14 +1020,35 @@ impl<F: Future> Spawn<F> {
         self.enter(unpark, |f| f.poll())
     }
 
+    /// Like `poll_future`, except that handles to the task obtained with
+    /// `task::park_local` will notify `local` rather than `unpark`.
+    ///
+    /// This is intended for executors which run all of their tasks on a
+    /// single thread, allowing futures which are always completed from that
+    /// thread to wake their task without any synchronization. The `unpark`
+    /// argument is still used for handles obtained with `task::park`, which
+    /// may be sent to and unparked from other threads.
+    #[cfg(feature = "use_std")]
+    pub fn poll_future_local(&mut self, unpark: Arc<Unpark>, local: Rc<LocalUnpark>)
+                             -> Poll<F::Item, F::Error> {
+        self.enter_local(unpark, Some(&local), |f| f.poll())
+    }
+
     /// Waits for the internal future to complete, blocking this thread's
     /// execution until it does.
     ///
     /// This function will call `poll_future` in a loop, waiting for the future
     /// to complete. When a future cannot make progress it will use
     /// `thread::park` to block the current thread.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if it's called from within an executor, that
+    /// is while an `executor::enter` guard is held on the current thread.
+    #[cfg(feature = "use_std")]
     pub fn wait_future(&mut self) -> Result<F::Item, F::Error> {
-        let unpark = Arc::new(ThreadUnpark::new(thread::current()));
+        assert_not_entered();
+        let unpark = ThreadUnpark::current();
         loop {
             match try!(self.poll_future(unpark.clone())) {
                 Async::NotReady => unpark.park(),
@@ -328,6 +1057,39 @@ impl<F: Future> Spawn<F> {
         }
     }
 
+    /// Like `wait_future`, except that it gives up waiting once `timeout` has
+    /// elapsed.
+    ///
+    /// Returns `NotReady` if the future didn't complete in time, in which case
+    /// it's left in this `Spawn` and may be waited on or polled again later.
+    #[cfg(feature = "use_std")]
+    pub fn wait_future_timeout(&mut self, timeout: Duration) -> Poll<F::Item, F::Error> {
+        self.wait_future_deadline(Instant::now() + timeout)
+    }
+
+    /// Like `wait_future`, except that it gives up waiting once `deadline` has
+    /// passed.
+    ///
+    /// The future is always polled at least once, even if `deadline` has
+    /// already passed. Returns `NotReady` if the future didn't complete in
+    /// time, in which case it's left in this `Spawn` and may be waited on or
+    /// polled again later.
+    #[cfg(feature = "use_std")]
+    pub fn wait_future_deadline(&mut self, deadline: Instant) -> Poll<F::Item, F::Error> {
+        assert_not_entered();
+        let unpark = ThreadUnpark::current();
+        loop {
+            match try!(self.poll_future(unpark.clone())) {
+                Async::NotReady => {
+                    if !unpark.park_until(deadline) {
+                        return Ok(Async::NotReady)
+                    }
+                }
+                Async::Ready(e) => return Ok(Async::Ready(e)),
+            }
+        }
+    }
+
     /// A specialized function to request running a future to completion on the
     /// specified executor.
     ///
@@ -343,8 +1105,29 @@ impl<F: Future> Spawn<F> {
     /// This method is not appropriate for all futures, and other kinds of
     /// executors typically provide a similar function with perhaps relaxed
     /// bounds as well.
+    #[cfg(feature = "use_std")]
     pub fn execute(self, exec: Arc<Executor>)
         where F: Future<Item=(), Error=()> + Send + 'static,
+    {
+        self.execute_inner(exec, None)
+    }
+
+    /// Like `execute`, except that `hooks` are invoked around each poll of
+    /// the future.
+    ///
+    /// This allows profilers and other instrumentation to attribute time
+    /// spent polling to individual tasks, identified by the task ID passed to
+    /// the hooks, without having to wrap the future itself.
+    #[cfg(feature = "use_std")]
+    pub fn execute_with_hooks(self, exec: Arc<Executor>, hooks: Arc<PollHooks>)
+        where F: Future<Item=(), Error=()> + Send + 'static,
+    {
+        self.execute_inner(exec, Some(hooks))
+    }
+
+    #[cfg(feature = "use_std")]
+    fn execute_inner(self, exec: Arc<Executor>, hooks: Option<Arc<PollHooks>>)
+        where F: Future<Item=(), Error=()> + Send + 'static,
     {
         exec.clone().execute(Run {
             // Ideally this method would be defined directly on
@@ -353,17 +1136,30 @@ impl<F: Future> Spawn<F> {
             // link error on nightly: rust-lang/rust#36155
             spawn: Spawn {
                 id: self.id,
+                priority: self.priority,
                 data: self.data,
+                notify: self.notify,
                 obj: self.obj.boxed(),
             },
             inner: Arc::new(Inner {
                 exec: exec,
-                mutex: UnparkMutex::new()
+                mutex: UnparkMutex::new(),
+                hooks: hooks,
             }),
         })
     }
 }
 
+impl<F: ContextFuture> Spawn<F> {
+    /// Like `poll_future`, except that the future is polled with its task's
+    /// context passed explicitly, rather than by setting the current task.
+    pub fn poll_context(&mut self, unpark: Arc<Unpark>) -> Poll<F::Item, F::Error> {
+        let task = self.new_task(unpark);
+        let mut cx = TaskContext { task: &task, data: &self.data };
+        self.obj.poll_context(&mut cx)
+    }
+}
+
 impl<S: Stream> Spawn<S> {
     /// Like `poll_future`, except polls the underlying stream.
     pub fn poll_stream(&mut self, unpark: Arc<Unpark>)
@@ -371,10 +1167,19 @@ impl<S: Stream> Spawn<S> {
         self.enter(unpark, |stream| stream.poll())
     }
 
+    /// Like `poll_future_local`, except polls the underlying stream.
+    #[cfg(feature = "use_std")]
+    pub fn poll_stream_local(&mut self, unpark: Arc<Unpark>, local: Rc<LocalUnpark>)
+                             -> Poll<Option<S::Item>, S::Error> {
+        self.enter_local(unpark, Some(&local), |stream| stream.poll())
+    }
+
     /// Like `wait_future`, except only waits for the next element to arrive on
     /// the underlying stream.
+    #[cfg(feature = "use_std")]
     pub fn wait_stream(&mut self) -> Option<Result<S::Item, S::Error>> {
-        let unpark = Arc::new(ThreadUnpark::new(thread::current()));
+        assert_not_entered();
+        let unpark = ThreadUnpark::current();
         loop {
             match self.poll_stream(unpark.clone()) {
                 Ok(Async::NotReady) => unpark.park(),
@@ -384,22 +1189,185 @@ impl<S: Stream> Spawn<S> {
             }
         }
     }
+
+    /// Like `wait_stream`, except that it gives up waiting for the next
+    /// element once `timeout` has elapsed.
+    ///
+    /// Returns `NotReady` if no element arrived in time.
+    #[cfg(feature = "use_std")]
+    pub fn wait_stream_timeout(&mut self, timeout: Duration)
+                               -> Poll<Option<S::Item>, S::Error> {
+        self.wait_stream_deadline(Instant::now() + timeout)
+    }
+
+    /// Like `wait_stream`, except that it gives up waiting for the next
+    /// element once `deadline` has passed.
+    ///
+    /// The stream is always polled at least once, even if `deadline` has
+    /// already passed. Returns `NotReady` if no element arrived in time.
+    #[cfg(feature = "use_std")]
+    pub fn wait_stream_deadline(&mut self, deadline: Instant)
+                                -> Poll<Option<S::Item>, S::Error> {
+        assert_not_entered();
+        let unpark = ThreadUnpark::current();
+        loop {
+            match try!(self.poll_stream(unpark.clone())) {
+                Async::NotReady => {
+                    if !unpark.park_until(deadline) {
+                        return Ok(Async::NotReady)
+                    }
+                }
+                ready => return Ok(ready),
+            }
+        }
+    }
 }
 
 impl<T> Spawn<T> {
+    #[cfg(feature = "use_std")]
     fn enter<F, R>(&mut self, unpark: Arc<Unpark>, f: F) -> R
         where F: FnOnce(&mut T) -> R
     {
-        let task = Task {
+        self.enter_local(unpark, None, f)
+    }
+
+    #[cfg(not(feature = "use_std"))]
+    fn enter<F, R>(&mut self, unpark: Arc<Unpark>, f: F) -> R
+        where F: FnOnce(&mut T) -> R
+    {
+        let task = self.new_task(unpark);
+        let obj = &mut self.obj;
+        set(&task, &self.data, || f(obj))
+    }
+
+    #[cfg(feature = "use_std")]
+    fn enter_local<F, R>(&mut self, unpark: Arc<Unpark>, local: Option<&Rc<LocalUnpark>>, f: F)
+                         -> R
+        where F: FnOnce(&mut T) -> R
+    {
+        let task = self.new_task(unpark);
+        let obj = &mut self.obj;
+        let data = &self.data;
+        local::set(local, || set(&task, data, || f(obj)))
+    }
+
+    // Starts a new poll of this task, returning the handle to it for the
+    // poll.
+    fn new_task(&mut self, unpark: Arc<Unpark>) -> Task {
+        // Poll numbers start at 1 and skip 0 on wrapping around, as 0 marks
+        // that no handle has been unparked since the last poll began.
+        self.notify.polls = self.notify.polls.wrapping_add(1);
+        if self.notify.polls == 0 {
+            self.notify.polls = 1;
+        }
+        self.notify.notified.store(0, Ordering::SeqCst);
+        Task {
             id: self.id,
             unpark: unpark,
             events: Events::new(),
+            route: None,
+            tap: None,
+            notified: self.notify.notified.clone(),
+            poll: self.notify.polls,
+        }
+    }
+}
+
+/// Marks the current thread as running an executor until the returned guard
+/// is dropped.
+///
+/// Blocking the thread an executor runs on while waiting for a future, for
+/// example with `Future::wait`, can deadlock the executor if the future can
+/// only complete once some other work on that executor has run. To catch this
+/// early, `Spawn::wait_future`, `Spawn::wait_stream` and the methods built on
+/// them panic if they're called while the current thread is marked.
+///
+/// `Run::run` marks the current thread while it polls a task, so executors
+/// built on `Spawn::execute` get this check automatically. Other executors,
+/// such as those driving tasks with `Spawn::poll_future`, should hold the
+/// guard returned by this function while polling. Guards may be nested, and
+/// the thread is unmarked once the outermost guard is dropped.
+#[cfg(feature = "use_std")]
+pub fn enter() -> Enter {
+    let prev = ENTERED.with(|e| {
+        let prev = e.get();
+        e.set(true);
+        prev
+    });
+    Enter { prev: prev, _marker: PhantomData }
+}
+
+/// Runs a future to completion on the current thread, returning its result.
+///
+/// This is a convenience for simple programs and tests, equivalent to
+/// spawning the future with `spawn` and blocking the current thread with
+/// `Spawn::wait_future`, except that the current thread is also marked as
+/// running an executor (see `enter`) while the future is polled. Any attempt
+/// to block the thread from within the future, such as a nested call to
+/// `block_on`, therefore panics rather than risking a deadlock.
+///
+/// # Panics
+///
+/// This function will panic if it's called from within an executor, that is
+/// while an `executor::enter` guard is held on the current thread.
+///
+/// # Examples
+///
+/// ```
+/// use futures::executor;
+/// use futures::future;
+///
+/// assert_eq!(executor::block_on(future::ok::<u32, ()>(1)), Ok(1));
+/// ```
+#[cfg(feature = "use_std")]
+pub fn block_on<F: Future>(f: F) -> Result<F::Item, F::Error> {
+    assert_not_entered();
+    let unpark = ThreadUnpark::current();
+    let mut spawn = spawn(f);
+    loop {
+        let res = {
+            let _enter = enter();
+            spawn.poll_future(unpark.clone())
         };
-        let obj = &mut self.obj;
-        set(&task, &self.data, || f(obj))
+        match try!(res) {
+            Async::NotReady => unpark.park(),
+            Async::Ready(e) => return Ok(e),
+        }
+    }
+}
+
+/// A guard marking the current thread as running an executor.
+///
+/// This is created by the `enter` function.
+#[cfg(feature = "use_std")]
+pub struct Enter {
+    prev: bool,
+    // tied to the thread that created it
+    _marker: PhantomData<*const ()>,
+}
+
+#[cfg(feature = "use_std")]
+impl Drop for Enter {
+    fn drop(&mut self) {
+        ENTERED.with(|e| e.set(self.prev));
+    }
+}
+
+#[cfg(feature = "use_std")]
+impl fmt::Debug for Enter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Enter").finish()
     }
 }
 
+#[cfg(feature = "use_std")]
+fn assert_not_entered() {
+    assert!(!ENTERED.with(|e| e.get()),
+            "cannot block the current thread waiting for a future from within \
+             an executor, as it may deadlock the executor; spawn the future \
+             onto the executor or compose it with the current one instead");
+}
+
 /// A trait which represents a sink of notifications that a future is ready to
 /// make progress.
 ///
@@ -421,16 +1389,59 @@ pub trait Unpark: Send + Sync {
 /// This trait is an argument to the `Spawn::execute` which is used to run a
 /// future to completion. An executor will receive requests to run a future and
 /// an executor is responsible for ensuring that happens in a timely fashion.
+#[cfg(feature = "use_std")]
 pub trait Executor: Send + Sync + 'static {
     /// Requests that `Run` is executed soon on the given executor.
     fn execute(&self, r: Run);
+
+    /// Requests that each `Run` in `runs` is executed soon on the given
+    /// executor.
+    ///
+    /// This is equivalent to calling `execute` with each unit of work, which
+    /// is what the default implementation does. Executors whose queues are
+    /// relatively expensive to synchronize on may override this to submit a
+    /// whole batch with a single lock or atomic operation, amortizing the cost
+    /// when a large number of tasks become ready at once.
+    fn execute_batch(&self, runs: Vec<Run>) {
+        for r in runs {
+            self.execute(r);
+        }
+    }
+}
+
+/// Callbacks invoked around each poll of a task run through
+/// `Spawn::execute_with_hooks`.
+///
+/// Both methods do nothing by default, so implementations only need to
+/// provide the ones they're interested in. The hooks are called on whichever
+/// thread is polling the task, so they should be cheap to avoid skewing the
+/// measurements they're taking.
+#[cfg(feature = "use_std")]
+pub trait PollHooks: Send + Sync + 'static {
+    /// Called just before the task with ID `task_id` is polled.
+    fn on_poll_start(&self, task_id: usize) {
+        let _ = task_id;
+    }
+
+    /// Called just after the task with ID `task_id` has been polled, with the
+    /// time the poll took and its result.
+    fn on_poll_end(&self, task_id: usize, elapsed: Duration, result: &Poll<(), ()>) {
+        let _ = (task_id, elapsed, result);
+    }
 }
 
+#[cfg(feature = "use_std")]
 struct ThreadUnpark {
     thread: thread::Thread,
     ready: AtomicBool,
 }
 
+#[cfg(feature = "use_std")]
+thread_local!(static THREAD_UNPARK: Arc<ThreadUnpark> = {
+    Arc::new(ThreadUnpark::new(thread::current()))
+});
+
+#[cfg(feature = "use_std")]
 impl ThreadUnpark {
     fn new(thread: thread::Thread) -> ThreadUnpark {
         ThreadUnpark {
@@ -439,13 +1450,40 @@ impl ThreadUnpark {
         }
     }
 
+    // Returns the handle used for blocking the current thread. It's shared by
+    // all blocking waits on the thread, as handles from a previous wait (for
+    // example of an earlier element of a stream) may be the ones unparked to
+    // signal readiness for the current one.
+    fn current() -> Arc<ThreadUnpark> {
+        THREAD_UNPARK.with(|unpark| unpark.clone())
+    }
+
+    // Blocks until this has been unparked. The notification is consumed
+    // before returning, and `thread::park` can wake up spuriously (or be
+    // woken by an unrelated `Thread::unpark`), so loop until it's seen.
     fn park(&self) {
-        if !self.ready.swap(false, Ordering::SeqCst) {
+        while !self.ready.swap(false, Ordering::SeqCst) {
             thread::park();
         }
     }
+
+    // Like `park`, but gives up once `deadline` has passed, returning whether
+    // a notification was received.
+    fn park_until(&self, deadline: Instant) -> bool {
+        loop {
+            if self.ready.swap(false, Ordering::SeqCst) {
+                return true
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return false
+            }
+            thread::park_timeout(deadline - now);
+        }
+    }
 }
 
+#[cfg(feature = "use_std")]
 impl Unpark for ThreadUnpark {
     fn unpark(&self) {
         self.ready.store(true, Ordering::SeqCst);
@@ -453,45 +1491,104 @@ impl Unpark for ThreadUnpark {
     }
 }
 
+// An unpark handle which ignores notifications, for polling a future once
+// without waiting on it.
+#[cfg(feature = "use_std")]
+struct NoopUnpark;
+
+#[cfg(feature = "use_std")]
+impl Unpark for NoopUnpark {
+    fn unpark(&self) {}
+}
+
+#[cfg(feature = "use_std")]
+pub fn noop_unpark() -> Arc<Unpark> {
+    Arc::new(NoopUnpark)
+}
+
 /// Units of work submitted to an `Executor`, currently only created
 /// internally.
+#[cfg(feature = "use_std")]
 pub struct Run {
     spawn: Spawn<BoxFuture<(), ()>>,
     inner: Arc<Inner>,
 }
 
+#[cfg(feature = "use_std")]
 struct Inner {
     mutex: UnparkMutex<Run>,
     exec: Arc<Executor>,
+    hooks: Option<Arc<PollHooks>>,
 }
 
+#[cfg(feature = "use_std")]
 impl Run {
+    /// Returns the priority of the task this unit of work belongs to.
+    ///
+    /// Executors may use this to decide the order in which pending units of
+    /// work are run.
+    pub fn priority(&self) -> Priority {
+        self.spawn.priority
+    }
+
     /// Actually run the task (invoking `poll` on its future) on the current
     /// thread.
+    ///
+    /// If the task is unparked while it's being polled, it's polled again
+    /// right away. To keep a task which is always ready from monopolizing
+    /// the current thread, this happens at most a fixed number of times (its
+    /// poll budget), after which the task is resubmitted to its executor
+    /// instead so that other work gets a chance to run. The same happens
+    /// immediately if the task yields with `task::yield_now`.
     pub fn run(self) {
         let Run { mut spawn, inner } = self;
+        let _enter = enter();
 
         // SAFETY: the ownership of this `Run` object is evidence that
         // we are in the `POLLING`/`REPOLL` state for the mutex.
         unsafe {
             inner.mutex.start_poll();
 
+            let mut budget = POLL_BUDGET;
             loop {
-                match spawn.poll_future(inner.clone()) {
+                YIELDED.with(|y| y.set(false));
+                let res = match inner.hooks {
+                    Some(ref hooks) => {
+                        hooks.on_poll_start(spawn.id);
+                        let start = Instant::now();
+                        let res = spawn.poll_future(inner.clone());
+                        hooks.on_poll_end(spawn.id, start.elapsed(), &res);
+                        res
+                    }
+                    None => spawn.poll_future(inner.clone()),
+                };
+                match res {
                     Ok(Async::NotReady) => {}
                     Ok(Async::Ready(())) |
                     Err(()) => return inner.mutex.complete(),
                 }
+                let yielded = YIELDED.with(|y| {
+                    let yielded = y.get();
+                    y.set(false);
+                    yielded
+                });
                 let run = Run { spawn: spawn, inner: inner.clone() };
                 match inner.mutex.wait(run) {
                     Ok(()) => return,            // we've waited
-                    Err(r) => spawn = r.spawn,   // someone's notified us
+                    Err(r) => {                  // someone's notified us
+                        budget -= 1;
+                        if yielded || budget == 0 {
+                            return inner.exec.execute(r)
+                        }
+                        spawn = r.spawn;
+                    }
                 }
             }
         }
     }
 }
 
+#[cfg(feature = "use_std")]
 impl Unpark for Inner {
     fn unpark(&self) {
         match self.mutex.notify() {
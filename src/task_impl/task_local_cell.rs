@@ -0,0 +1,321 @@
+use std::prelude::v1::*;
+
+use std::error::Error;
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::cell::UnsafeCell;
+
+use {Future, Poll, Async};
+use super::{park, current_task_id, Task};
+
+// The borrow state of a cell: `UNUSED` when not borrowed, `WRITING` while
+// mutably borrowed, and otherwise the number of shared borrows.
+const UNUSED: usize = 0;
+const WRITING: usize = !0;
+
+/// A mutable memory location shared between the futures of one task, with
+/// dynamically checked borrows.
+///
+/// This is much like a `RefCell` which is bound to the task it was created
+/// on, replacing `TaskRc`. The handle can be cloned and handed to several
+/// futures running on that task, which can then borrow the data without an
+/// `Arc<Mutex<T>>`.
+///
+/// Unlike `RefCell`, a borrow which conflicts with an outstanding one isn't a
+/// panic but is reported as an error through `try_borrow` and
+/// `try_borrow_mut`. The borrow guards own a handle to the data, so they may
+/// be kept across calls to `poll`, and a future which finds the cell borrowed
+/// can wait for it with `released`.
+pub struct TaskLocalCell<T> {
+    task_id: usize,
+    inner: Arc<Inner<T>>,
+}
+
+struct Inner<T> {
+    data: UnsafeCell<T>,
+    borrow: AtomicUsize,
+    waiters: Mutex<Vec<Task>>,
+}
+
+/// A shared borrow of the data in a `TaskLocalCell`.
+///
+/// This is created by `TaskLocalCell::borrow` and `TaskLocalCell::try_borrow`.
+pub struct TaskLocalRef<T> {
+    inner: Arc<Inner<T>>,
+}
+
+/// A mutable borrow of the data in a `TaskLocalCell`.
+///
+/// This is created by `TaskLocalCell::borrow_mut` and
+/// `TaskLocalCell::try_borrow_mut`.
+pub struct TaskLocalRefMut<T> {
+    inner: Arc<Inner<T>>,
+}
+
+/// A future which resolves once a `TaskLocalCell` is no longer borrowed.
+///
+/// This is created by the `TaskLocalCell::released` method.
+#[must_use = "futures do nothing unless polled"]
+pub struct Released<T> {
+    cell: TaskLocalCell<T>,
+}
+
+/// The error returned when the data of a `TaskLocalCell` can't be borrowed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TaskLocalCellError {
+    /// No task is currently running.
+    NoTask,
+    /// The cell belongs to a task other than the one currently running.
+    WrongTask,
+    /// The data is already borrowed in a conflicting way.
+    Borrowed,
+}
+
+// The borrow state makes sure that the data is only shared while there's no
+// mutable borrow, just like `RwLock`, and borrows can only be taken on the
+// task that created the cell.
+unsafe impl<T: Send> Send for TaskLocalCell<T> {}
+unsafe impl<T: Send + Sync> Sync for TaskLocalCell<T> {}
+unsafe impl<T: Send + Sync> Send for TaskLocalRef<T> {}
+unsafe impl<T: Send + Sync> Sync for TaskLocalRef<T> {}
+unsafe impl<T: Send> Send for TaskLocalRefMut<T> {}
+unsafe impl<T: Send + Sync> Sync for TaskLocalRefMut<T> {}
+
+impl<T> TaskLocalCell<T> {
+    /// Creates a new cell holding `value`, bound to the current task.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if a task is not currently running.
+    pub fn new(value: T) -> TaskLocalCell<T> {
+        TaskLocalCell {
+            task_id: super::current_id(),
+            inner: Arc::new(Inner {
+                data: UnsafeCell::new(value),
+                borrow: AtomicUsize::new(UNUSED),
+                waiters: Mutex::new(Vec::new()),
+            }),
+        }
+    }
+
+    /// Immutably borrows the data, returning an error if it's currently
+    /// mutably borrowed.
+    ///
+    /// This method also returns an error if a task is not currently running
+    /// or if the cell belongs to another task.
+    pub fn try_borrow(&self) -> Result<TaskLocalRef<T>, TaskLocalCellError> {
+        try!(self.check_task());
+        let mut cur = self.inner.borrow.load(Ordering::SeqCst);
+        loop {
+            if cur == WRITING {
+                return Err(TaskLocalCellError::Borrowed)
+            }
+            assert!(cur < WRITING - 1, "too many TaskLocalCell borrows");
+            match self.inner.borrow.compare_exchange(cur, cur + 1,
+                                                     Ordering::SeqCst,
+                                                     Ordering::SeqCst) {
+                Ok(_) => return Ok(TaskLocalRef { inner: self.inner.clone() }),
+                Err(actual) => cur = actual,
+            }
+        }
+    }
+
+    /// Mutably borrows the data, returning an error if it's currently
+    /// borrowed at all.
+    ///
+    /// This method also returns an error if a task is not currently running
+    /// or if the cell belongs to another task.
+    pub fn try_borrow_mut(&self)
+                          -> Result<TaskLocalRefMut<T>, TaskLocalCellError> {
+        try!(self.check_task());
+        match self.inner.borrow.compare_exchange(UNUSED, WRITING,
+                                                 Ordering::SeqCst,
+                                                 Ordering::SeqCst) {
+            Ok(_) => Ok(TaskLocalRefMut { inner: self.inner.clone() }),
+            Err(_) => Err(TaskLocalCellError::Borrowed),
+        }
+    }
+
+    /// Immutably borrows the data.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic in the cases where `try_borrow` returns an
+    /// error.
+    pub fn borrow(&self) -> TaskLocalRef<T> {
+        match self.try_borrow() {
+            Ok(r) => r,
+            Err(e) => panic!("TaskLocalCell::borrow failed: {}", e),
+        }
+    }
+
+    /// Mutably borrows the data.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic in the cases where `try_borrow_mut` returns an
+    /// error.
+    pub fn borrow_mut(&self) -> TaskLocalRefMut<T> {
+        match self.try_borrow_mut() {
+            Ok(r) => r,
+            Err(e) => panic!("TaskLocalCell::borrow_mut failed: {}", e),
+        }
+    }
+
+    /// Returns whether the data is currently borrowed, either immutably or
+    /// mutably.
+    pub fn is_borrowed(&self) -> bool {
+        self.inner.borrow.load(Ordering::SeqCst) != UNUSED
+    }
+
+    /// Returns a future which resolves once the data is no longer borrowed.
+    ///
+    /// While the data is borrowed, polling the returned future registers the
+    /// current task to be unparked when the last borrow is released. Once it
+    /// resolves, a call to `try_borrow_mut` made before any other borrow is
+    /// taken will succeed.
+    ///
+    /// The future fails if it's polled outside of the task the cell belongs
+    /// to.
+    pub fn released(&self) -> Released<T> {
+        Released { cell: self.clone() }
+    }
+
+    fn check_task(&self) -> Result<(), TaskLocalCellError> {
+        match current_task_id() {
+            None => Err(TaskLocalCellError::NoTask),
+            Some(id) if id != self.task_id => Err(TaskLocalCellError::WrongTask),
+            Some(_) => Ok(()),
+        }
+    }
+}
+
+impl<T> Clone for TaskLocalCell<T> {
+    fn clone(&self) -> TaskLocalCell<T> {
+        TaskLocalCell {
+            task_id: self.task_id,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for TaskLocalCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TaskLocalCell")
+            .field("task_id", &self.task_id)
+            .field("borrowed", &self.is_borrowed())
+            .finish()
+    }
+}
+
+impl<T> Inner<T> {
+    fn release(&self, state: usize) {
+        self.borrow.store(state, Ordering::SeqCst);
+        if state == UNUSED {
+            let waiters = {
+                let mut waiters = self.waiters.lock().unwrap();
+                ::std::mem::replace(&mut *waiters, Vec::new())
+            };
+            for task in waiters {
+                task.unpark();
+            }
+        }
+    }
+}
+
+impl<T> Deref for TaskLocalRef<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // Safe as no mutable borrow exists while this one does.
+        unsafe { &*self.inner.data.get() }
+    }
+}
+
+impl<T> Drop for TaskLocalRef<T> {
+    fn drop(&mut self) {
+        let prev = self.inner.borrow.fetch_sub(1, Ordering::SeqCst);
+        if prev == 1 {
+            self.inner.release(UNUSED);
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for TaskLocalRef<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+impl<T> Deref for TaskLocalRefMut<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // Safe as no other borrow exists while this one does.
+        unsafe { &*self.inner.data.get() }
+    }
+}
+
+impl<T> DerefMut for TaskLocalRefMut<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // Safe as no other borrow exists while this one does.
+        unsafe { &mut *self.inner.data.get() }
+    }
+}
+
+impl<T> Drop for TaskLocalRefMut<T> {
+    fn drop(&mut self) {
+        self.inner.release(UNUSED);
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for TaskLocalRefMut<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+impl<T> Future for Released<T> {
+    type Item = ();
+    type Error = TaskLocalCellError;
+
+    fn poll(&mut self) -> Poll<(), TaskLocalCellError> {
+        try!(self.cell.check_task());
+        if !self.cell.is_borrowed() {
+            return Ok(Async::Ready(()))
+        }
+        self.cell.inner.waiters.lock().unwrap().push(park());
+        // The last borrow may have been released before we were registered.
+        if self.cell.is_borrowed() {
+            Ok(Async::NotReady)
+        } else {
+            Ok(Async::Ready(()))
+        }
+    }
+}
+
+impl TaskLocalCellError {
+    fn as_str(&self) -> &'static str {
+        match *self {
+            TaskLocalCellError::NoTask => "no task is currently running",
+            TaskLocalCellError::WrongTask => {
+                "TaskLocalCell accessed on task it does not belong to"
+            }
+            TaskLocalCellError::Borrowed => "TaskLocalCell already borrowed",
+        }
+    }
+}
+
+impl fmt::Display for TaskLocalCellError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Error for TaskLocalCellError {
+    fn description(&self) -> &str {
+        self.as_str()
+    }
+}
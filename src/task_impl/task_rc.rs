@@ -5,8 +5,12 @@
                       for removal due to confusion")]
 
 use std::prelude::v1::*;
+use std::error::Error;
+use std::fmt;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::cell::UnsafeCell;
+use std::thread;
 
 // One critical piece of this module's contents are the `TaskRc<A>` handles.
 // The purpose of this is to conceptually be able to store data in a task,
@@ -61,9 +65,42 @@ use std::cell::UnsafeCell;
 /// This data is `Send` even when `A` is not `Sync`, because the data stored
 /// within is accessed in a single-threaded way. The thread accessing it may
 /// change over time, if the task migrates, so `A` must be `Send`.
+///
+/// If a closure passed to `with` or `try_with` panics, the data is considered
+/// *poisoned*, as it may have been left in an inconsistent state. Poisoning
+/// is only reported by `try_with` and `is_poisoned`; `with` continues to
+/// provide access to poisoned data.
 pub struct TaskRc<A> {
     task_id: usize,
-    ptr: Arc<UnsafeCell<A>>,
+    ptr: Arc<Inner<A>>,
+}
+
+struct Inner<A> {
+    data: UnsafeCell<A>,
+    poisoned: AtomicBool,
+}
+
+/// The error returned by `TaskRc::try_with` when the data can't be accessed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TaskRcError {
+    /// No task is currently running.
+    NoTask,
+    /// The data belongs to a task other than the one currently running.
+    WrongTask,
+    /// A previous access to the data panicked.
+    Poisoned,
+}
+
+// Marks the data as poisoned if it's dropped while panicking, i.e. if the
+// closure accessing the data panics.
+struct PoisonGuard<'a>(&'a AtomicBool);
+
+impl<'a> Drop for PoisonGuard<'a> {
+    fn drop(&mut self) {
+        if thread::panicking() {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
 }
 
 // for safety here, see docs at the top of this module
@@ -91,7 +128,10 @@ impl<A> TaskRc<A> {
         super::with(|task, _| {
             TaskRc {
                 task_id: task.id,
-                ptr: Arc::new(UnsafeCell::new(a)),
+                ptr: Arc::new(Inner {
+                    data: UnsafeCell::new(a),
+                    poisoned: AtomicBool::new(false),
+                }),
             }
         })
     }
@@ -111,13 +151,73 @@ impl<A> TaskRc<A> {
     pub fn with<F, R>(&self, f: F) -> R
         where F: FnOnce(&A) -> R
     {
-        // for safety here, see docs at the top of this module
         super::with(|task, _| {
             assert!(self.task_id == task.id,
                     "TaskRc being accessed on task it does not belong to");
-            f(unsafe { &*self.ptr.get() })
+            self.access(f)
         })
     }
+
+    /// Like `with`, except that an error is returned instead of panicking if
+    /// the data can't be accessed.
+    ///
+    /// This method returns an error if a task is not currently running, if
+    /// `self` does not belong to the task that is currently running, or if
+    /// the data has been poisoned by a previous access panicking.
+    pub fn try_with<F, R>(&self, f: F) -> Result<R, TaskRcError>
+        where F: FnOnce(&A) -> R
+    {
+        let task_id = match super::current_task_id() {
+            Some(id) => id,
+            None => return Err(TaskRcError::NoTask),
+        };
+        if self.task_id != task_id {
+            return Err(TaskRcError::WrongTask)
+        }
+        if self.is_poisoned() {
+            return Err(TaskRcError::Poisoned)
+        }
+        Ok(self.access(f))
+    }
+
+    /// Returns whether a closure passed to `with` or `try_with` has panicked
+    /// while accessing the data.
+    pub fn is_poisoned(&self) -> bool {
+        self.ptr.poisoned.load(Ordering::SeqCst)
+    }
+
+    /// Returns a mutable reference to the underlying data if this is the only
+    /// handle to it.
+    ///
+    /// Unlike `with`, this doesn't need to be called on the task the data
+    /// belongs to, as a unique handle can't be used to access the data
+    /// concurrently. Returns `None` if there are other clones of this handle.
+    pub fn get_mut(&mut self) -> Option<&mut A> {
+        Arc::get_mut(&mut self.ptr).map(|inner| inner.data.get_mut())
+    }
+
+    /// Returns the underlying data if `this` is the only handle to it, or
+    /// `this` back otherwise.
+    ///
+    /// This allows data such as buffers to be reclaimed once a task is done
+    /// sharing them between its futures, rather than cloning out of them.
+    /// Like `get_mut`, this doesn't need to be called on the task the data
+    /// belongs to.
+    pub fn try_unwrap(this: TaskRc<A>) -> Result<A, TaskRc<A>> {
+        let task_id = this.task_id;
+        Arc::try_unwrap(this.ptr)
+            .map(|inner| inner.data.into_inner())
+            .map_err(|ptr| TaskRc { task_id: task_id, ptr: ptr })
+    }
+
+    // Callers must have checked that `self` belongs to the current task.
+    fn access<F, R>(&self, f: F) -> R
+        where F: FnOnce(&A) -> R
+    {
+        let _guard = PoisonGuard(&self.ptr.poisoned);
+        // for safety here, see docs at the top of this module
+        f(unsafe { &*self.ptr.data.get() })
+    }
 }
 
 impl<A> Clone for TaskRc<A> {
@@ -128,3 +228,25 @@ impl<A> Clone for TaskRc<A> {
         }
     }
 }
+
+impl TaskRcError {
+    fn as_str(&self) -> &'static str {
+        match *self {
+            TaskRcError::NoTask => "no task is currently running",
+            TaskRcError::WrongTask => "TaskRc accessed on task it does not belong to",
+            TaskRcError::Poisoned => "TaskRc poisoned by a panic during a previous access",
+        }
+    }
+}
+
+impl fmt::Display for TaskRcError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Error for TaskRcError {
+    fn description(&self) -> &str {
+        self.as_str()
+    }
+}
@@ -0,0 +1,54 @@
+use std::marker;
+
+use {Future, Poll, Async};
+use super::{park, YIELDED};
+
+/// A future which yields to other tasks once before completing.
+///
+/// This future is created by the `yield_now` function.
+#[must_use = "futures do nothing unless polled"]
+pub struct YieldNow<E> {
+    yielded: bool,
+    _data: marker::PhantomData<E>,
+}
+
+/// Creates a future which yields the current task to other work on its
+/// executor once, and then completes.
+///
+/// The first time the returned future is polled it unparks the current task
+/// and returns `NotReady`, so it'll be polled again soon. Executors built on
+/// `Spawn::execute` will run other tasks in the meantime rather than polling
+/// the current task again right away. This allows a long running computation
+/// made up of futures which are always ready, such as draining a busy
+/// channel, to share its executor fairly with other tasks.
+///
+/// # Panics
+///
+/// The returned future will panic if it's polled outside of a task.
+pub fn yield_now<E>() -> YieldNow<E> {
+    YieldNow {
+        yielded: false,
+        _data: marker::PhantomData,
+    }
+}
+
+impl<E> Future for YieldNow<E> {
+    type Item = ();
+    type Error = E;
+
+    fn poll(&mut self) -> Poll<(), E> {
+        if self.yielded {
+            return Ok(Async::Ready(()))
+        }
+        self.yielded = true;
+        yield_task();
+        Ok(Async::NotReady)
+    }
+}
+
+// Arranges for the current task to be polled again after other work on its
+// executor has had a chance to run. The caller should return `NotReady`.
+pub fn yield_task() {
+    YIELDED.with(|y| y.set(true));
+    park().unpark();
+}
@@ -0,0 +1,149 @@
+//! A minimal timer for the combinators which need to wait for a deadline.
+//!
+//! Deadlines are tracked by a single background thread, which is started the
+//! first time one is registered and unparks the task waiting on each deadline
+//! once it has passed.
+
+use std::prelude::v1::*;
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::sync::{Arc, Weak, Mutex, Condvar, Once, ONCE_INIT};
+use std::thread;
+use std::time::Instant;
+
+use {Future, Poll, Async};
+use task::{self, Task};
+
+/// A future which completes once a deadline has passed.
+pub struct Delay {
+    deadline: Instant,
+    // The task to unpark at the deadline, shared with the timer thread once
+    // the delay has been registered with it
+    slot: Option<Arc<Mutex<Option<Task>>>>,
+}
+
+impl Delay {
+    pub fn new(deadline: Instant) -> Delay {
+        Delay {
+            deadline: deadline,
+            slot: None,
+        }
+    }
+}
+
+impl Future for Delay {
+    type Item = ();
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<(), ()> {
+        if Instant::now() >= self.deadline {
+            return Ok(Async::Ready(()))
+        }
+        match self.slot {
+            Some(ref slot) => *slot.lock().unwrap() = Some(task::park()),
+            None => {
+                let slot = Arc::new(Mutex::new(Some(task::park())));
+                timer().register(self.deadline, Arc::downgrade(&slot));
+                self.slot = Some(slot);
+            }
+        }
+        Ok(Async::NotReady)
+    }
+}
+
+struct Timer {
+    entries: Mutex<BinaryHeap<Entry>>,
+    condvar: Condvar,
+}
+
+struct Entry {
+    deadline: Instant,
+    // Dropping a `Delay` drops its slot, so entries for delays which are no
+    // longer needed are simply skipped.
+    slot: Weak<Mutex<Option<Task>>>,
+}
+
+fn timer() -> &'static Timer {
+    static INIT: Once = ONCE_INIT;
+    static mut TIMER: *const Timer = 0 as *const Timer;
+
+    unsafe {
+        INIT.call_once(|| {
+            TIMER = Box::into_raw(Box::new(Timer {
+                entries: Mutex::new(BinaryHeap::new()),
+                condvar: Condvar::new(),
+            }));
+            // The thread waits for the initialization to finish when it
+            // looks up the timer.
+            thread::Builder::new()
+                .name("futures-timer".to_string())
+                .spawn(|| timer().run())
+                .expect("failed to spawn the timer thread");
+        });
+        &*TIMER
+    }
+}
+
+impl Timer {
+    fn register(&self, deadline: Instant, slot: Weak<Mutex<Option<Task>>>) {
+        let mut entries = self.entries.lock().unwrap();
+        let earliest = entries.peek().map(|e| deadline < e.deadline).unwrap_or(true);
+        entries.push(Entry { deadline: deadline, slot: slot });
+        // The timer thread only needs to wake up early if it's now got an
+        // earlier deadline to wait for.
+        if earliest {
+            self.condvar.notify_one();
+        }
+    }
+
+    fn run(&self) {
+        let mut entries = self.entries.lock().unwrap();
+        loop {
+            let now = Instant::now();
+            let mut expired = Vec::new();
+            while entries.peek().map(|e| e.deadline <= now).unwrap_or(false) {
+                expired.push(entries.pop().unwrap());
+            }
+            if !expired.is_empty() {
+                // Tasks are unparked outside of the lock, as unparking may
+                // poll them and register more deadlines.
+                drop(entries);
+                for entry in expired {
+                    let task = entry.slot.upgrade().and_then(|slot| slot.lock().unwrap().take());
+                    if let Some(task) = task {
+                        task.unpark();
+                    }
+                }
+                entries = self.entries.lock().unwrap();
+                continue
+            }
+            entries = match entries.peek().map(|e| e.deadline) {
+                Some(deadline) => self.condvar.wait_timeout(entries, deadline - now).unwrap().0,
+                None => self.condvar.wait(entries).unwrap(),
+            };
+        }
+    }
+}
+
+// Entries are ordered so that the earliest deadline is at the top of the
+// heap.
+impl Ord for Entry {
+    fn cmp(&self, other: &Entry) -> Ordering {
+        other.deadline.cmp(&self.deadline)
+    }
+}
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Entry) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Entry {
+    fn eq(&self, other: &Entry) -> bool {
+        self.deadline == other.deadline
+    }
+}
+
+impl Eq for Entry {}
@@ -0,0 +1,126 @@
+extern crate futures;
+
+use std::sync::Arc;
+use std::sync::mpsc;
+use std::thread;
+
+use futures::Future;
+use futures::executor::{Executor, Run};
+use futures::sync::actor::{self, Handle};
+use futures::sync::oneshot;
+
+struct Inline;
+
+impl Executor for Inline {
+    fn execute(&self, r: Run) {
+        r.run()
+    }
+}
+
+struct Recorder {
+    seen: mpsc::Sender<u32>,
+}
+
+impl Handle<u32> for Recorder {
+    type Result = Result<(), ()>;
+
+    fn handle(&mut self, msg: u32) -> Result<(), ()> {
+        self.seen.send(msg).map_err(|_| ())
+    }
+}
+
+#[test]
+fn messages_handled_in_order() {
+    let (tx, rx) = mpsc::channel();
+    let addr = actor::spawn(Recorder { seen: tx }, Arc::new(Inline));
+
+    let mut a = addr.clone();
+    let t = thread::spawn(move || {
+        for i in 0..100 {
+            a.send(i).unwrap();
+        }
+    });
+    t.join().unwrap();
+    let mut addr = addr;
+    addr.send(100).unwrap();
+
+    for i in 0..101 {
+        assert_eq!(rx.recv().unwrap(), i);
+    }
+}
+
+#[test]
+fn stops_when_addresses_dropped() {
+    let (tx, rx) = mpsc::channel();
+    let (addr, actor) = actor::new(Recorder { seen: tx });
+    let mut addr2 = addr.clone();
+    addr2.send(1).unwrap();
+    drop(addr);
+    drop(addr2);
+
+    assert_eq!(actor.wait(), Ok(()));
+    assert_eq!(rx.recv().unwrap(), 1);
+    assert!(rx.recv().is_err());
+}
+
+struct Sequential {
+    pending: mpsc::Receiver<oneshot::Receiver<()>>,
+    log: mpsc::Sender<&'static str>,
+}
+
+impl Handle<&'static str> for Sequential {
+    type Result = Box<Future<Item = (), Error = ()> + Send>;
+
+    fn handle(&mut self, msg: &'static str) -> Self::Result {
+        let log = self.log.clone();
+        log.send(msg).unwrap();
+        let done = self.pending.recv().unwrap();
+        Box::new(done.map_err(|_| ()).map(move |()| {
+            log.send("done").unwrap();
+        }))
+    }
+}
+
+#[test]
+fn waits_for_handler_future() {
+    let (pending_tx, pending_rx) = mpsc::channel();
+    let (log_tx, log_rx) = mpsc::channel();
+    let handler = Sequential { pending: pending_rx, log: log_tx };
+    let mut addr = actor::spawn(handler, Arc::new(Inline));
+
+    let (c1, p1) = oneshot::channel();
+    let (c2, p2) = oneshot::channel();
+    pending_tx.send(p1).unwrap();
+    pending_tx.send(p2).unwrap();
+
+    addr.send("a").unwrap();
+    addr.send("b").unwrap();
+    assert_eq!(log_rx.recv().unwrap(), "a");
+    assert!(log_rx.try_recv().is_err());
+
+    c1.complete(());
+    assert_eq!(log_rx.recv().unwrap(), "done");
+    assert_eq!(log_rx.recv().unwrap(), "b");
+    assert!(log_rx.try_recv().is_err());
+
+    c2.complete(());
+    assert_eq!(log_rx.recv().unwrap(), "done");
+}
+
+#[test]
+fn handler_error_stops_actor() {
+    struct Fail;
+
+    impl Handle<()> for Fail {
+        type Result = Result<(), ()>;
+
+        fn handle(&mut self, _msg: ()) -> Result<(), ()> {
+            Err(())
+        }
+    }
+
+    let (mut addr, actor) = actor::new(Fail);
+    addr.send(()).unwrap();
+    assert_eq!(actor.wait(), Err(()));
+    assert!(addr.send(()).is_err());
+}
@@ -2,6 +2,7 @@ extern crate futures;
 
 use std::sync::mpsc::{channel, TryRecvError};
 
+use futures::Async;
 use futures::future::*;
 use futures::future;
 use futures::executor;
@@ -118,6 +119,11 @@ fn smoke_oneshot() {
         drop(c);
         p
     }, Err(Canceled));
+    assert_done(|| {
+        let (c, p) = oneshot::channel::<i32>();
+        c.abandon();
+        p
+    }, Err(Canceled));
     let mut completes = Vec::new();
     assert_empty(|| {
         let (a, b) = oneshot::channel::<i32>();
@@ -247,8 +253,91 @@ fn collect_collects() {
     assert_done(|| join_all(vec![f_ok(1), f_ok(2)]), Ok(vec![1, 2]));
     assert_done(|| join_all(vec![f_ok(1)]), Ok(vec![1]));
     assert_done(|| join_all(Vec::<Result<i32, u32>>::new()), Ok(vec![]));
+    assert_done(|| join_all(vec![f_ok(1), f_err(2), f_err(3)]), Err(2));
+
+    // Results are in the order of the input, not of completion
+    let (a, b) = oneshot::channel::<i32>();
+    let (c, d) = oneshot::channel::<i32>();
+    let mut f = executor::spawn(join_all(vec![b, d]));
+    assert!(f.poll_future(unpark_noop()).unwrap().is_not_ready());
+    c.complete(2);
+    assert!(f.poll_future(unpark_noop()).unwrap().is_not_ready());
+    a.complete(1);
+    assert_eq!(f.wait_future(), Ok(vec![1, 2]));
 
-    // TODO: needs more tests
+    // The first error resolves the future without waiting for the others,
+    // which are dropped straight away rather than along with the future
+    let (a, b) = oneshot::channel::<i32>();
+    let (c, d) = oneshot::channel::<i32>();
+    let mut f = executor::spawn(join_all(vec![b, d]));
+    assert!(f.poll_future(unpark_noop()).unwrap().is_not_ready());
+    assert!(!a.is_canceled());
+    drop(c);
+    assert!(f.wait_future().is_err());
+    assert!(a.is_canceled());
+    drop(f);
+}
+
+#[test]
+fn join_all_sequential_runs_in_order() {
+    assert_done(|| join_all_sequential(vec![f_ok(1), f_ok(2)]), Ok(vec![1, 2]));
+    assert_done(|| join_all_sequential(Vec::<Result<i32, u32>>::new()), Ok(vec![]));
+    assert_done(|| join_all_sequential(vec![f_ok(1), f_err(2), f_err(3)]), Err(2));
+
+    // Each future is only created once the previous one has completed
+    let (a, b) = oneshot::channel::<i32>();
+    let (c, d) = oneshot::channel::<i32>();
+    let mut rxs = vec![Some(b), Some(d)].into_iter();
+    let (created_tx, created_rx) = channel();
+    let futures = (0..2).map(move |i| {
+        created_tx.send(i).unwrap();
+        rxs.next().unwrap().unwrap()
+    });
+    let mut f = executor::spawn(join_all_sequential(futures));
+    assert!(f.poll_future(unpark_noop()).unwrap().is_not_ready());
+    assert_eq!(created_rx.try_recv(), Ok(0));
+    assert_eq!(created_rx.try_recv(), Err(TryRecvError::Empty));
+    c.complete(2);
+    assert!(f.poll_future(unpark_noop()).unwrap().is_not_ready());
+    assert_eq!(created_rx.try_recv(), Err(TryRecvError::Empty));
+    a.complete(1);
+    assert_eq!(f.wait_future(), Ok(vec![1, 2]));
+    assert_eq!(created_rx.try_recv(), Ok(1));
+}
+
+#[test]
+fn try_join_all_collects_every_error() {
+    assert_done(|| try_join_all(vec![f_ok(1), f_ok(2)]), Ok(vec![1, 2]));
+    assert_done(|| try_join_all(Vec::<Result<i32, u32>>::new()), Ok(vec![]));
+    assert_done(|| try_join_all(vec![f_err(1), f_ok(2), f_err(3)]).map_err(|e| e.into_errors()),
+                Err(vec![(0, 1), (2, 3)]));
+
+    // A failure doesn't cancel the futures still running.
+    let (tx, rx) = oneshot::channel::<i32>();
+    let (tx2, rx2) = channel();
+    let slow = rx.map(move |v| { tx2.send(v).unwrap(); v }).map_err(|_| 0);
+    let mut f = executor::spawn(try_join_all(vec![f_err(1).boxed(), slow.boxed()]));
+    assert!(f.poll_future(unpark_noop()).unwrap().is_not_ready());
+    tx.complete(2);
+    let errors = f.wait_future().unwrap_err();
+    assert_eq!(errors.errors(), &[(0, 1)]);
+    assert_eq!(rx2.try_recv(), Ok(2));
+}
+
+#[test]
+fn try_join_all_fail_fast_cancels_the_rest() {
+    assert_done(|| try_join_all(vec![f_ok(1), f_ok(2)]).fail_fast(), Ok(vec![1, 2]));
+
+    // The first failure drops the futures still running straight away, and
+    // only its error is reported.
+    let (tx, rx) = oneshot::channel::<i32>();
+    let (tx2, rx2) = oneshot::channel::<i32>();
+    let mut f = executor::spawn(try_join_all(vec![rx, rx2]).fail_fast());
+    assert!(f.poll_future(unpark_noop()).unwrap().is_not_ready());
+    drop(tx2);
+    let errors = f.poll_future(unpark_noop()).unwrap_err();
+    assert_eq!(errors.errors(), &[(1, Canceled)]);
+    assert!(tx.is_canceled());
 }
 
 #[test]
@@ -353,3 +442,350 @@ fn select2() {
         assert!(rx.recv().is_err());
     }
 }
+
+#[test]
+fn select2_different_types() {
+    match f_ok(1).select2(empty::<&str, ()>()).wait() {
+        Ok(Either::A((1, _))) => {}
+        _ => panic!(),
+    }
+    match empty::<(), ()>().select2(err::<u8, &str>("boom")).wait() {
+        Err(Either::B(("boom", _))) => {}
+        _ => panic!(),
+    }
+
+    // The future which didn't finish can still be driven to completion
+    let (tx, rx) = oneshot::channel::<&str>();
+    let (tx2, rx2) = oneshot::channel::<u32>();
+    let mut f = executor::spawn(rx.select2(rx2));
+    match f.poll_future(unpark_noop()) {
+        Ok(Async::NotReady) => {}
+        _ => panic!(),
+    }
+    tx2.complete(2);
+    let rest = match f.poll_future(unpark_noop()) {
+        Ok(Async::Ready(Either::B((2, rest)))) => rest,
+        _ => panic!(),
+    };
+    tx.complete("done");
+    assert_eq!(rest.wait(), Ok("done"));
+}
+
+#[test]
+fn pending_profile() {
+    let (tx, rx) = oneshot::channel::<i32>();
+    let mut f = executor::spawn(rx.pending_profile("rx"));
+    assert!(f.poll_future(unpark_noop()).unwrap().is_not_ready());
+    // Polled again without anything having happened
+    assert!(f.poll_future(unpark_noop()).unwrap().is_not_ready());
+    tx.complete(1);
+    assert_eq!(f.poll_future(unpark_noop()).unwrap(), Async::Ready(1));
+
+    let profile = f.get_ref().profile();
+    assert_eq!(profile.polls(), 3);
+    assert_eq!(profile.wakeups(), 1);
+    assert_eq!(profile.unproductive_polls(), 2);
+    assert_eq!(profile.unprompted_polls(), 1);
+}
+
+#[test]
+fn pending_profile_wakeup_sources() {
+    use futures::Stream;
+    use futures::stream::futures_unordered;
+
+    let (tx0, rx0) = oneshot::channel::<i32>();
+    let (tx1, rx1) = oneshot::channel::<i32>();
+    let (_tx2, rx2) = oneshot::channel::<i32>();
+    let all = futures_unordered(vec![rx0, rx1, rx2]).take(2).collect();
+    let mut f = executor::spawn(all.pending_profile("all"));
+    assert!(f.poll_future(unpark_noop()).unwrap().is_not_ready());
+    tx1.complete(1);
+    assert!(f.poll_future(unpark_noop()).unwrap().is_not_ready());
+    tx0.complete(0);
+    assert_eq!(f.poll_future(unpark_noop()).unwrap(), Async::Ready(vec![1, 0]));
+
+    let profile = f.get_ref().profile();
+    assert_eq!(profile.wakeups(), 2);
+    // `FuturesUnordered` identifies its futures by their indices, and the
+    // future which is never completed never wakes the task up.
+    let sources = profile.wakeup_sources();
+    assert_eq!(sources.iter().collect::<Vec<_>>(), [(&0, &1), (&1, &1)]);
+    assert_eq!(profile.to_string(),
+               "3 polls (2 without progress, 0 without a wakeup), 2 wakeups \
+                (by source id: {0: 1, 1: 1})");
+}
+
+#[test]
+fn inspect() {
+    let mut seen = None;
+    assert_eq!(f_ok(1).inspect(|&x| seen = Some(x)).wait(), Ok(1));
+    assert_eq!(seen, Some(1));
+
+    let mut called = false;
+    assert_eq!(f_err(2).inspect(|_| called = true).wait(), Err(2));
+    assert!(!called);
+
+    let mut seen = None;
+    assert_eq!(f_err(2).inspect_err(|&e| seen = Some(e)).wait(), Err(2));
+    assert_eq!(seen, Some(2));
+
+    let mut called = false;
+    assert_eq!(f_ok(1).inspect_err(|_| called = true).wait(), Ok(1));
+    assert!(!called);
+}
+
+#[test]
+fn loop_fn_counts() {
+    let f = loop_fn(0, |n| {
+        if n == 100_000 {
+            Ok::<_, ()>(Loop::Break(n))
+        } else {
+            Ok(Loop::Continue(n + 1))
+        }
+    });
+    assert_eq!(f.wait(), Ok(100_000));
+
+    // Errors end the loop
+    let f = loop_fn(0, |n| {
+        if n == 3 {
+            err(n)
+        } else {
+            ok::<Loop<(), i32>, i32>(Loop::Continue(n + 1))
+        }
+    });
+    assert_eq!(f.wait(), Err(3));
+}
+
+#[test]
+fn loop_fn_waits_between_iterations() {
+    let (tx, rx) = oneshot::channel::<i32>();
+    let mut rx = Some(rx);
+    let f = loop_fn(0, move |n| -> BoxFuture<Loop<i32, i32>, oneshot::Canceled> {
+        match n {
+            0 => rx.take().unwrap().map(Loop::Continue).boxed(),
+            n => ok(Loop::Break(n)).boxed(),
+        }
+    });
+    let mut f = executor::spawn(f);
+    match f.poll_future(unpark_noop()) {
+        Ok(Async::NotReady) => {}
+        _ => panic!(),
+    }
+    tx.complete(7);
+    assert_eq!(f.wait_future(), Ok(7));
+}
+
+#[test]
+fn option_into_future() {
+    assert_done(|| Some(1).into_future(), Ok(1));
+    assert_done(|| None::<i32>.into_future(), Err(()));
+    assert_done(|| ok::<i32, ()>(1).and_then(|a| Some(a + 1)), Ok(2));
+    assert_done(|| ok::<i32, ()>(1).and_then(|a| if a > 1 { Some(a) } else { None }), Err(()));
+    assert_done(|| ok::<i32, u32>(1).and_then(|a| None::<i32>.ok_or(a as u32)), Err(1));
+}
+
+#[test]
+fn from_err() {
+    #[derive(Debug, PartialEq, Eq)]
+    struct Wrapped(u32);
+
+    impl From<u32> for Wrapped {
+        fn from(e: u32) -> Wrapped {
+            Wrapped(e)
+        }
+    }
+
+    assert_done(|| f_err(3).from_err::<(), Wrapped>(), Err(Wrapped(3)));
+    assert_done(|| f_ok(3).from_err::<(), Wrapped>(), Ok(3));
+    assert_done(|| f_err(3).err_into().or_else(|e: Wrapped| Ok::<i32, ()>(e.0 as i32)), Ok(3));
+}
+
+#[test]
+fn boxed_local() {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    let counter = Rc::new(Cell::new(0));
+    let futures: Vec<LocalBoxFuture<(), ()>> = vec![
+        ok::<Rc<Cell<i32>>, ()>(counter.clone()).map(|c| c.set(c.get() + 1)).boxed_local(),
+        lazy({
+            let counter = counter.clone();
+            move || {
+                counter.set(counter.get() + 10);
+                Ok(())
+            }
+        }).boxed_local(),
+    ];
+    assert_done(|| join_all(futures).boxed_local(), Ok(vec![(), ()]));
+    assert_eq!(counter.get(), 11);
+}
+
+#[test]
+fn select_biased() {
+    assert_done(|| f_ok(1).select_biased(f_ok(2)).then(unselect), Ok(1));
+    assert_done(|| f_err(1).select_biased(f_ok(2)).then(unselect), Err(1));
+    assert_done(|| empty().select_biased(f_ok(2)).then(unselect), Ok(2));
+}
+
+#[test]
+fn race() {
+    assert_done(|| f_ok(1).race(f_ok(2)), Ok(1));
+    assert_done(|| f_err(1).race(f_ok(2)), Err(1));
+    assert_done(|| empty().race(f_err(2)), Err(2));
+
+    // The loser is dropped as soon as the winner completes
+    let (a, b) = oneshot::channel::<i32>();
+    let (c, d) = oneshot::channel::<i32>();
+    let mut f = executor::spawn(b.race(d));
+    assert!(f.poll_future(unpark_noop()).unwrap().is_not_ready());
+    c.complete(3);
+    assert_eq!(f.poll_future(unpark_noop()), Ok(Async::Ready(3)));
+    assert!(a.is_canceled());
+}
+
+#[test]
+fn abortable() {
+    assert_done(|| future::abortable(f_ok(1)).0, Ok(Ok(1)));
+    assert_done(|| future::abortable(f_err(1)).0, Ok(Err(1)));
+
+    // Aborting wakes up the task waiting on the future, which is dropped
+    let (tx, rx) = oneshot::channel::<i32>();
+    let (f, handle) = future::abortable(rx);
+    let t = std::thread::spawn(move || f.wait());
+    std::thread::sleep(std::time::Duration::from_millis(50));
+    assert!(!handle.is_aborted());
+    handle.clone().abort();
+    assert!(handle.is_aborted());
+    assert_eq!(t.join().unwrap(), Err(Aborted));
+    assert!(tx.is_canceled());
+
+    // Aborting a future that has completed does nothing
+    let (f, handle) = future::abortable(f_ok(2));
+    let mut f = executor::spawn(f);
+    assert_eq!(f.poll_future(unpark_noop()), Ok(Async::Ready(Ok(2))));
+    handle.abort();
+}
+
+#[test]
+fn remote_handle() {
+    let (remote, handle) = f_ok(1).remote_handle();
+    assert_done(|| remote, Ok(()));
+    assert_eq!(handle.wait(), Ok(1));
+
+    let (remote, handle) = f_err(2).remote_handle();
+    let t = std::thread::spawn(move || remote.wait());
+    assert_eq!(handle.wait(), Err(2));
+    assert_eq!(t.join().unwrap(), Ok(()));
+
+    // Panics are propagated to the handle
+    let (remote, handle) = lazy(|| -> Result<i32, u32> { panic!("boom") }).remote_handle();
+    assert_eq!(remote.wait(), Ok(()));
+    assert!(std::thread::spawn(move || handle.wait()).join().is_err());
+
+    // Dropping the handle cancels the original future
+    let (tx, rx) = oneshot::channel::<i32>();
+    let (remote, handle) = rx.remote_handle();
+    let mut remote = executor::spawn(remote);
+    assert!(remote.poll_future(unpark_noop()).unwrap().is_not_ready());
+    drop(handle);
+    assert_eq!(remote.poll_future(unpark_noop()), Ok(Async::Ready(())));
+    drop(remote);
+    assert!(tx.is_canceled());
+
+    // ... unless it's forgotten
+    let (tx, rx) = oneshot::channel::<i32>();
+    let (remote, handle) = rx.remote_handle();
+    let mut remote = executor::spawn(remote);
+    handle.forget();
+    assert!(remote.poll_future(unpark_noop()).unwrap().is_not_ready());
+    tx.complete(3);
+    assert_eq!(remote.poll_future(unpark_noop()), Ok(Async::Ready(())));
+}
+
+#[test]
+fn now_or_never() {
+    assert_eq!(f_ok(1).now_or_never(), Some(Ok(1)));
+    assert_eq!(f_err(1).now_or_never(), Some(Err(1)));
+
+    let (tx, rx) = oneshot::channel::<i32>();
+    assert_eq!(rx.now_or_never(), None);
+    // The future is dropped if it isn't ready
+    assert!(tx.is_canceled());
+
+    let (tx, rx) = oneshot::channel::<i32>();
+    tx.complete(2);
+    assert_eq!(rx.now_or_never(), Some(Ok(2)));
+}
+
+#[test]
+fn or() {
+    assert_done(|| f_ok(1).or(f_ok(2)), Ok(1));
+    assert_done(|| f_err(1).or(f_ok(2)), Ok(2));
+    assert_done(|| f_err(1).or(f_err(2)), Err(2));
+    assert_done(|| f_ok(1).or(Err::<i32, ()>(())), Ok(1));
+
+    // The fallback isn't polled until the primary future fails
+    let (tx, rx) = oneshot::channel::<i32>();
+    let (polled_tx, polled_rx) = channel();
+    let fallback = lazy(move || {
+        polled_tx.send(()).unwrap();
+        Ok::<i32, oneshot::Canceled>(3)
+    });
+    let mut f = executor::spawn(rx.or(fallback));
+    assert!(f.poll_future(unpark_noop()).unwrap().is_not_ready());
+    assert_eq!(polled_rx.try_recv(), Err(TryRecvError::Empty));
+    drop(tx);
+    assert_eq!(f.wait_future(), Ok(3));
+    assert_eq!(polled_rx.try_recv(), Ok(()));
+}
+
+#[test]
+fn map_into_and_err_into() {
+    assert_done(|| f_ok(1).map_into::<i64>(), Ok(1i64));
+    assert_done(|| f_err(1).map_into::<i64>(), Err(1));
+    assert_done(|| f_err(1).err_into::<u64>(), Err(1u64));
+    assert_done(|| f_ok(1).map_into::<i64>().err_into::<u64>(), Ok(1i64));
+}
+
+#[test]
+fn infallible_and_never_error() {
+    use futures::Never;
+
+    assert_done(|| ok::<i32, Never>(1).infallible::<u32>(), Ok(1));
+    assert_done(|| ok::<i32, Never>(1).infallible().join(f_ok(2)), Ok((1, 2)));
+    assert_done(|| f_ok(1).never_error().infallible::<u32>(), Ok(Ok(1)));
+    assert_done(|| f_err(1).never_error().infallible::<u32>(), Ok(Err(1)));
+    assert_done(|| {
+        f_err(1).never_error().infallible().join(f_ok(2))
+    }, Ok((Err(1), 2)));
+}
+
+#[test]
+fn on_cancel() {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    let canceled = Rc::new(Cell::new(0));
+
+    let c = canceled.clone();
+    drop(empty::<i32, u32>().on_cancel(move || c.set(c.get() + 1)));
+    assert_eq!(canceled.get(), 1);
+
+    let c = canceled.clone();
+    assert_done(move || {
+        let c = c.clone();
+        f_ok(1).on_cancel(move || c.set(c.get() + 1))
+    }, Ok(1));
+    let c = canceled.clone();
+    assert_done(move || {
+        let c = c.clone();
+        f_err(1).on_cancel(move || c.set(c.get() + 1))
+    }, Err(1));
+    assert_eq!(canceled.get(), 1);
+
+    let c = canceled.clone();
+    let loser = empty::<i32, u32>().on_cancel(move || c.set(c.get() + 1));
+    assert_done(|| f_ok(2).select(loser).map(|(v, _)| v).map_err(|(e, _)| e),
+                Ok(2));
+    assert_eq!(canceled.get(), 2);
+}
@@ -0,0 +1,35 @@
+extern crate futures;
+
+use std::sync::{Arc, Barrier};
+use std::thread;
+
+use futures::Future;
+use futures::executor;
+use futures::future;
+
+#[test]
+fn smoke() {
+    let name = executor::blocking(|| thread::current().name().map(|s| s.to_string()));
+    assert_eq!(name.wait().unwrap(), Some("futures-blocking".to_string()));
+}
+
+#[test]
+fn panics_are_caught() {
+    let err = executor::blocking(|| -> i32 { panic!("boom") }).wait().unwrap_err();
+    assert_eq!(err.downcast_ref::<&str>(), Some(&"boom"));
+}
+
+#[test]
+fn runs_concurrently() {
+    // Every operation waits for all of the others, so this only completes if
+    // the pool grows to run them all at once.
+    let barrier = Arc::new(Barrier::new(8));
+    let ops = (0..8).map(|i| {
+        let barrier = barrier.clone();
+        executor::blocking(move || {
+            barrier.wait();
+            i
+        })
+    }).collect::<Vec<_>>();
+    assert_eq!(future::join_all(ops).wait().unwrap(), (0..8).collect::<Vec<_>>());
+}
@@ -0,0 +1,85 @@
+extern crate futures;
+
+use std::thread;
+
+use futures::{Future, Stream};
+use futures::executor;
+use futures::future::lazy;
+use futures::sync::call::*;
+
+mod support;
+use support::*;
+
+#[test]
+fn smoke() {
+    let (caller, requests) = channel::<u32, u32>(1);
+
+    let t = thread::spawn(move || {
+        requests.for_each(|(req, responder)| {
+            responder.respond(req + 1);
+            Ok(())
+        }).wait().unwrap();
+    });
+
+    assert_eq!(caller.call(1).wait(), Ok(2));
+    assert_eq!(caller.clone().call(2).wait(), Ok(3));
+    drop(caller);
+    t.join().unwrap();
+}
+
+#[test]
+fn responses_are_correlated() {
+    let (caller, requests) = channel::<u32, u32>(4);
+
+    let a = caller.call(1);
+    let b = caller.call(2);
+    let c = caller.call(3);
+
+    thread::spawn(move || {
+        let mut pending = requests.take(3).collect().wait().unwrap();
+        while let Some((req, responder)) = pending.pop() {
+            responder.respond(req * 10);
+        }
+    });
+
+    assert_eq!(a.join3(b, c).wait(), Ok((10, 20, 30)));
+}
+
+#[test]
+fn dropped_requests_cancels() {
+    let (caller, requests) = channel::<u32, u32>(1);
+    drop(requests);
+    assert_eq!(caller.call(1).wait(), Err(Canceled));
+}
+
+#[test]
+fn dropped_responder_cancels() {
+    let (caller, requests) = channel::<u32, u32>(1);
+
+    thread::spawn(move || {
+        requests.for_each(|(_req, responder)| {
+            drop(responder);
+            Ok(())
+        }).wait().unwrap();
+    });
+
+    assert_eq!(caller.call(1).wait(), Err(Canceled));
+}
+
+#[test]
+fn responder_poll_cancel() {
+    let (caller, requests) = channel::<u32, u32>(1);
+    let mut call = executor::spawn(caller.call(1));
+    assert!(call.poll_future(unpark_noop()).unwrap().is_not_ready());
+
+    let (_req, mut responder) = match requests.wait().next() {
+        Some(Ok(pair)) => pair,
+        _ => panic!("expected a request"),
+    };
+    lazy(|| {
+        assert!(responder.poll_cancel().unwrap().is_not_ready());
+        drop(call);
+        assert!(responder.poll_cancel().unwrap().is_ready());
+        Ok::<(), ()>(())
+    }).wait().unwrap();
+}
@@ -0,0 +1,52 @@
+extern crate futures;
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use futures::{Future, Stream, Async};
+use futures::clock::{self, Clock};
+use futures::executor;
+use futures::sync::mpsc;
+
+mod support;
+use support::*;
+
+#[test]
+fn system_clock_outside_task() {
+    let before = Instant::now();
+    let now = clock::now();
+    assert!(before <= now && now <= Instant::now());
+}
+
+#[test]
+fn task_clock() {
+    let mock = MockClock::new();
+    let start = mock.now();
+    let f = futures::future::lazy(|| {
+        clock::set(Arc::new(mock.clone()));
+        mock.advance(Duration::from_secs(5));
+        Ok::<_, ()>(clock::now())
+    });
+    assert_eq!(f.wait(), Ok(start + Duration::from_secs(5)));
+}
+
+#[test]
+fn timeout_map_mock_clock() {
+    let mock = MockClock::new();
+    let (mut tx, rx) = mpsc::unbounded::<i32>();
+    let stream = rx.timeout_map(Duration::from_secs(10), || 0);
+    let mut task = executor::spawn(Clocked(Arc::new(mock.clone()), stream));
+
+    assert!(task.poll_stream(unpark_noop()).unwrap().is_not_ready());
+    mock.advance(Duration::from_secs(5));
+    assert!(task.poll_stream(unpark_noop()).unwrap().is_not_ready());
+    mock.advance(Duration::from_secs(5));
+    assert_eq!(task.poll_stream(unpark_noop()), Ok(Async::Ready(Some(0))));
+
+    // Only one fallback is yielded for each item
+    mock.advance(Duration::from_secs(60));
+    assert!(task.poll_stream(unpark_noop()).unwrap().is_not_ready());
+
+    mpsc::UnboundedSender::send(&mut tx, 1).unwrap();
+    assert_eq!(task.poll_stream(unpark_noop()), Ok(Async::Ready(Some(1))));
+}
@@ -0,0 +1,56 @@
+extern crate futures;
+
+use std::thread;
+
+use futures::{Future, Stream};
+use futures::sync::completion_queue;
+
+mod support;
+use support::*;
+
+#[test]
+fn smoke() {
+    let (completer, mut queue) = completion_queue();
+    sassert_empty(&mut queue);
+    completer.complete(1);
+    completer.complete(2);
+    sassert_next(&mut queue, 1);
+    sassert_next(&mut queue, 2);
+    sassert_empty(&mut queue);
+    drop(completer);
+    sassert_done(&mut queue);
+}
+
+#[test]
+fn ends_after_all_completers_dropped() {
+    let (completer, mut queue) = completion_queue();
+    let completer2 = completer.clone();
+    drop(completer);
+    sassert_empty(&mut queue);
+    completer2.complete("a");
+    drop(completer2);
+    sassert_next(&mut queue, "a");
+    sassert_done(&mut queue);
+}
+
+#[test]
+fn ordered_across_threads() {
+    let (completer, queue) = completion_queue();
+
+    let t = thread::spawn(move || {
+        for i in 0..1000 {
+            completer.complete(i);
+        }
+    });
+
+    let tokens = queue.collect().wait().unwrap();
+    t.join().unwrap();
+    assert_eq!(tokens, (0..1000).collect::<Vec<_>>());
+}
+
+#[test]
+fn complete_after_queue_dropped() {
+    let (completer, queue) = completion_queue();
+    drop(queue);
+    completer.complete(1);
+}
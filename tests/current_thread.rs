@@ -0,0 +1,75 @@
+extern crate futures;
+
+use std::cell::Cell;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+use futures::Future;
+use futures::executor::CurrentThread;
+use futures::future::{self, lazy};
+use futures::sync::oneshot;
+
+#[test]
+fn run_local_futures() {
+    let mut exec = CurrentThread::new();
+    let ran = Rc::new(Cell::new(false));
+    let ran2 = ran.clone();
+    let (tx, rx) = oneshot::channel();
+    exec.spawn(lazy(move || {
+        ran2.set(true);
+        tx.complete(1);
+        Ok(())
+    }));
+    assert!(!ran.get());
+    assert_eq!(exec.run(rx).unwrap(), 1);
+    assert!(ran.get());
+}
+
+#[test]
+fn spawn_from_other_threads() {
+    let mut exec = CurrentThread::new();
+    let remote = exec.remote();
+    let (tx, rx) = oneshot::channel();
+    let t = thread::spawn(move || {
+        remote.spawn(lazy(move || {
+            tx.complete(thread::current().name().map(|s| s.to_string()));
+            Ok(())
+        }));
+    });
+    let name = exec.run(rx).unwrap();
+    assert_eq!(name, thread::current().name().map(|s| s.to_string()));
+    t.join().unwrap();
+}
+
+#[test]
+fn tasks_wait_for_notifications() {
+    let mut exec = CurrentThread::new();
+    let (tx1, rx1) = oneshot::channel::<u32>();
+    let (tx2, rx2) = oneshot::channel();
+    exec.spawn(rx1.map(move |n| tx2.complete(n + 1)).map_err(|_| ()));
+    let t = thread::spawn(move || tx1.complete(1));
+    assert_eq!(exec.run(rx2).unwrap(), 2);
+    t.join().unwrap();
+}
+
+#[test]
+fn remote_after_drop() {
+    struct SetOnDrop(Arc<AtomicBool>);
+
+    impl Drop for SetOnDrop {
+        fn drop(&mut self) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    let exec = CurrentThread::new();
+    let remote = exec.remote();
+    drop(exec);
+
+    let dropped = Arc::new(AtomicBool::new(false));
+    let guard = SetOnDrop(dropped.clone());
+    remote.spawn(future::ok(()).map(move |()| drop(guard)));
+    assert!(dropped.load(Ordering::SeqCst));
+}
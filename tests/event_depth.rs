@@ -0,0 +1,84 @@
+extern crate futures;
+
+use std::sync::Arc;
+
+use futures::{Async, Poll, Stream};
+use futures::executor;
+use futures::future;
+use futures::stream;
+use futures::sync::oneshot;
+use futures::task::{self, AtomicBitSet, UnparkEvent};
+
+mod support;
+use support::*;
+
+// The limit on the depth of events is global, so these tests live in their
+// own binary and are run one after another from a single test.
+#[test]
+fn max_event_depth() {
+    collapses_events();
+    nested_futures_unordered();
+    task::set_max_event_depth(0);
+}
+
+fn collapses_events() {
+    task::set_max_event_depth(1);
+
+    let outer = Arc::new(AtomicBitSet::new(1));
+    let inner = Arc::new(AtomicBitSet::new(1));
+    let mut handle = None;
+    {
+        let (outer, inner) = (outer.clone(), inner.clone());
+        let handle = &mut handle;
+        let mut task = executor::spawn(future::poll_fn(move || -> Poll<(), ()> {
+            task::with_unpark_event(UnparkEvent::new(outer.clone(), 0), || {
+                task::with_unpark_event(UnparkEvent::new(inner.clone(), 0), || {
+                    *handle = Some(task::park());
+                    // The inner event doesn't fit, so it's only triggered
+                    // once this closure returns
+                    assert!(!inner.contains(0));
+                });
+                assert!(inner.contains(0));
+            });
+            Ok(Async::NotReady)
+        }));
+        assert!(task.poll_future(unpark_noop()).unwrap().is_not_ready());
+    }
+
+    assert_eq!(inner.drain().collect::<Vec<_>>(), [0]);
+    assert!(!outer.contains(0));
+    handle.unwrap().unpark();
+    assert_eq!(outer.drain().collect::<Vec<_>>(), [0]);
+    assert!(!inner.contains(0));
+}
+
+fn nested_futures_unordered() {
+    task::set_max_event_depth(1);
+
+    // Three levels of `FuturesUnordered` need two events on the handles of
+    // the innermost futures, so one of them is collapsed
+    let (txs, rxs): (Vec<_>, Vec<_>) = (0..8).map(|_| oneshot::channel::<i32>()).unzip();
+    let mut rxs = rxs.into_iter();
+    let inner = (0..4).map(|_| {
+        stream::futures_unordered(rxs.by_ref().take(2)).collect()
+    }).collect::<Vec<_>>();
+    let mut inner = inner.into_iter();
+    let middle = (0..2).map(|_| {
+        stream::futures_unordered(inner.by_ref().take(2)).collect()
+    }).collect::<Vec<_>>();
+    let mut task = executor::spawn(stream::futures_unordered(middle).collect());
+
+    assert!(task.poll_future(unpark_noop()).unwrap().is_not_ready());
+    for (i, tx) in txs.into_iter().enumerate() {
+        tx.complete(i as i32);
+    }
+    let results = match task.poll_future(unpark_noop()).unwrap() {
+        Async::Ready(results) => results,
+        Async::NotReady => panic!("not ready"),
+    };
+    let mut results = results.into_iter().flat_map(|v: Vec<Vec<i32>>| v)
+                             .flat_map(|v| v)
+                             .collect::<Vec<_>>();
+    results.sort();
+    assert_eq!(results, (0..8).collect::<Vec<_>>());
+}
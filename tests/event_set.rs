@@ -0,0 +1,110 @@
+extern crate futures;
+
+use std::sync::Arc;
+use std::thread;
+
+use futures::{Async, Poll};
+use futures::executor;
+use futures::future;
+use futures::task::{self, AtomicBitSet, EventSet, ReadyQueue, UnparkEvent};
+
+mod support;
+use support::*;
+
+#[test]
+fn bit_set_smoke() {
+    let set = AtomicBitSet::new(200);
+    assert_eq!(set.capacity(), 200);
+    set.insert(3);
+    set.insert(150);
+    set.insert(64);
+    set.insert(3);
+    assert!(set.contains(64));
+    assert!(!set.contains(63));
+    assert_eq!(set.drain().collect::<Vec<_>>(), [3, 64, 150]);
+    assert_eq!(set.drain().count(), 0);
+
+    set.insert(199);
+    assert!(set.remove(199));
+    assert!(!set.remove(199));
+}
+
+#[test]
+#[should_panic]
+fn bit_set_out_of_range() {
+    AtomicBitSet::new(10).insert(10);
+}
+
+#[test]
+fn bit_set_concurrent_inserts() {
+    let set = Arc::new(AtomicBitSet::new(1000));
+    let threads = (0..4).map(|i| {
+        let set = set.clone();
+        thread::spawn(move || {
+            for id in (0..1000).filter(|id| id % 4 == i) {
+                set.insert(id);
+            }
+        })
+    }).collect::<Vec<_>>();
+    for t in threads {
+        t.join().unwrap();
+    }
+    assert_eq!(set.drain().collect::<Vec<_>>(), (0..1000).collect::<Vec<_>>());
+}
+
+#[test]
+fn ready_queue_smoke() {
+    let queue = ReadyQueue::new();
+    queue.insert(1);
+    queue.push(2);
+    queue.insert(1);
+    let mut ids = queue.drain().collect::<Vec<_>>();
+    ids.sort();
+    assert_eq!(ids, [1, 1, 2]);
+    assert_eq!(queue.drain().count(), 0);
+}
+
+#[test]
+fn with_unpark_event() {
+    let bits = Arc::new(AtomicBitSet::new(4));
+    let ready = Arc::new(ReadyQueue::new());
+    let (b, r) = (bits.clone(), ready.clone());
+    let mut spawn = executor::spawn(future::poll_fn(move || -> Poll<task::Task, ()> {
+        let task = task::with_unpark_event(UnparkEvent::new(b.clone(), 2), || {
+            task::with_unpark_event(UnparkEvent::new(r.clone(), "ready"), task::park)
+        });
+        Ok(Async::Ready(task))
+    }));
+    let task = match spawn.poll_future(unpark_noop()) {
+        Ok(Async::Ready(task)) => task,
+        _ => panic!("expected a task"),
+    };
+    task.unpark();
+    assert_eq!(bits.drain().collect::<Vec<_>>(), [2]);
+    assert_eq!(ready.drain().collect::<Vec<_>>(), ["ready"]);
+}
+
+#[test]
+fn with_unpark_id() {
+    let outer = Arc::new(AtomicBitSet::new(4));
+    let inner = Arc::new(AtomicBitSet::new(4));
+    let (o, i) = (outer.clone(), inner.clone());
+    let mut spawn = executor::spawn(future::poll_fn(move || -> Poll<task::Task, ()> {
+        let task = task::with_unpark_id(o.clone(), 1, || {
+            task::with_unpark_id(i.clone(), 3, task::park)
+        });
+        Ok(Async::Ready(task))
+    }));
+    let task = match spawn.poll_future(unpark_noop()) {
+        Ok(Async::Ready(task)) => task,
+        _ => panic!("expected a task"),
+    };
+    task.unpark();
+    assert_eq!(outer.drain().collect::<Vec<_>>(), [1]);
+    assert_eq!(inner.drain().collect::<Vec<_>>(), [3]);
+
+    // rerouting a handle only changes the innermost identifier
+    task.with_id(2).unpark();
+    assert_eq!(outer.drain().collect::<Vec<_>>(), [1]);
+    assert_eq!(inner.drain().collect::<Vec<_>>(), [2]);
+}
@@ -9,6 +9,29 @@ use support::*;
 #[test]
 fn fuse() {
     let mut future = executor::spawn(ok::<i32, u32>(2).fuse());
+    assert!(!future.get_ref().is_done());
     assert!(future.poll_future(unpark_panic()).unwrap().is_ready());
+    assert!(future.get_ref().is_done());
     assert!(future.poll_future(unpark_panic()).unwrap().is_not_ready());
+    assert!(future.poll_future(unpark_panic()).unwrap().is_not_ready());
+}
+
+#[test]
+fn is_terminated() {
+    use futures::future::{empty, FusedFuture};
+    use futures::stream::{self, FusedStream, Stream};
+
+    let mut future = executor::spawn(ok::<i32, u32>(2).fuse());
+    assert!(!future.get_ref().is_terminated());
+    assert!(future.poll_future(unpark_panic()).unwrap().is_ready());
+    assert!(future.get_ref().is_terminated());
+
+    assert!(!empty::<i32, u32>().fuse().is_terminated());
+
+    let mut stream = executor::spawn(stream::iter(vec![Ok::<i32, u32>(1)]).fuse());
+    assert!(!stream.get_ref().is_terminated());
+    assert_eq!(stream.wait_stream(), Some(Ok(1)));
+    assert!(!stream.get_ref().is_terminated());
+    assert_eq!(stream.wait_stream(), None);
+    assert!(stream.get_ref().is_terminated());
 }
@@ -0,0 +1,38 @@
+extern crate futures;
+
+use std::panic::AssertUnwindSafe;
+
+use futures::future::{self, Future};
+
+#[test]
+fn panic_becomes_error() {
+    let future = future::lazy(|| -> Result<i32, bool> { panic!("boom") });
+    let err = future.catch_unwind().wait().unwrap_err();
+    assert_eq!(err.downcast_ref::<&str>(), Some(&"boom"));
+}
+
+#[test]
+fn no_panic() {
+    assert_eq!(future::ok::<i32, bool>(1).catch_unwind().wait().unwrap(), Ok(1));
+    assert_eq!(future::err::<i32, bool>(true).catch_unwind().wait().unwrap(), Err(true));
+}
+
+#[test]
+fn restart_after_panic() {
+    // A supervisor can retry work which panicked
+    let mut attempts = 0;
+    let result = loop {
+        attempts += 1;
+        let attempt = attempts;
+        let work = future::lazy(move || {
+            if attempt < 3 {
+                panic!("attempt {} failed", attempt);
+            }
+            Ok::<_, ()>(attempt)
+        });
+        if let Ok(res) = AssertUnwindSafe(work).catch_unwind().wait() {
+            break res
+        }
+    };
+    assert_eq!(result, Ok(3));
+}
@@ -9,7 +9,9 @@ use futures::sync::mpsc;
 use std::time::Duration;
 use std::thread;
 use std::sync::{Arc, Mutex};
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicUsize, AtomicBool, Ordering};
+
+use futures::executor::{self, Unpark};
 
 fn is_send<T: Send>() {}
 
@@ -278,6 +280,61 @@ fn stress_receiver_multi_task_bounded_hard() {
     assert_eq!(AMT, n.load(Ordering::Relaxed));
 }
 
+#[test]
+fn blocked_senders_unblocked_in_order() {
+    struct Flag(AtomicBool);
+
+    impl Unpark for Flag {
+        fn unpark(&self) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    let (tx, mut rx) = mpsc::channel::<i32>(0);
+
+    // Each sender gets its message in, and is then blocked as the channel is
+    // over capacity
+    let mut flags = Vec::new();
+    let mut senders = Vec::new();
+    for i in 0..3 {
+        let flag = Arc::new(Flag(AtomicBool::new(false)));
+        let mut task = executor::spawn(tx.clone().send(i));
+        match task.poll_future(flag.clone()) {
+            Ok(Async::Ready(tx)) => {
+                assert_eq!(tx.capacity_hint(), 0);
+                senders.push(tx);
+            }
+            _ => panic!(),
+        }
+        flags.push(flag);
+    }
+
+    lazy(move || {
+        for i in 0..3 {
+            assert_eq!(rx.poll().unwrap(), Async::Ready(Some(i)));
+            let unparked = flags.iter()
+                                .map(|f| f.0.load(Ordering::SeqCst))
+                                .collect::<Vec<_>>();
+            let expected = (0..3).map(|j| j <= i).collect::<Vec<_>>();
+            assert_eq!(unparked, expected);
+        }
+        Ok::<(), ()>(())
+    }).wait().unwrap();
+}
+
+#[test]
+fn capacity_hint() {
+    let (tx, rx) = mpsc::channel::<i32>(2);
+    assert_eq!(tx.capacity_hint(), 2);
+    let tx = tx.send(1).wait().unwrap();
+    assert_eq!(tx.capacity_hint(), 1);
+    let tx = tx.send(2).wait().unwrap();
+    assert_eq!(tx.capacity_hint(), 0);
+
+    drop(rx);
+    assert_eq!(tx.capacity_hint(), 0);
+}
+
 fn is_ready<T>(res: &AsyncSink<T>) -> bool {
     match *res {
         AsyncSink::Ready => true,
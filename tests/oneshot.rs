@@ -4,7 +4,7 @@ use std::sync::mpsc;
 use std::thread;
 
 use futures::{Future, Poll};
-use futures::future::{lazy, ok};
+use futures::future::{lazy, ok, poll_fn};
 use futures::sync::oneshot::*;
 
 mod support;
@@ -89,3 +89,33 @@ fn close_wakes() {
     tx2.send(()).unwrap();
     t.join().unwrap();
 }
+
+#[test]
+fn is_canceled() {
+    let (tx, rx) = channel::<u32>();
+    assert!(!tx.is_canceled());
+    drop(rx);
+    assert!(tx.is_canceled());
+
+    let (tx, mut rx) = channel::<u32>();
+    rx.close();
+    assert!(tx.is_canceled());
+}
+
+#[test]
+fn poll_recv_tells_dropped_and_abandoned_apart() {
+    let (tx, mut rx) = channel::<u32>();
+    drop(tx);
+    assert_eq!(poll_fn(move || rx.poll_recv()).wait(), Err(RecvError::Dropped));
+
+    let (tx, mut rx) = channel::<u32>();
+    let t = thread::spawn(move || tx.abandon());
+    let err = poll_fn(move || rx.poll_recv()).wait().unwrap_err();
+    assert_eq!(err, RecvError::Abandoned);
+    assert_eq!(err.to_string(), "oneshot sender abandoned");
+    t.join().unwrap();
+
+    let (tx, mut rx) = channel::<u32>();
+    tx.complete(1);
+    assert_eq!(poll_fn(move || rx.poll_recv()).wait(), Ok(1));
+}
@@ -0,0 +1,44 @@
+extern crate futures;
+
+use futures::{Async, Poll};
+use futures::poll_helpers;
+
+#[test]
+fn map_and_then() {
+    let ready: Poll<u32, ()> = Ok(Async::Ready(1));
+    let not_ready: Poll<u32, ()> = Ok(Async::NotReady);
+    let err: Poll<u32, ()> = Err(());
+
+    assert_eq!(poll_helpers::map(ready, |n| n + 1), Ok(Async::Ready(2)));
+    assert_eq!(poll_helpers::map(not_ready, |n| n + 1), Ok(Async::NotReady));
+    assert_eq!(poll_helpers::map(err, |n| n + 1), Err(()));
+    assert_eq!(poll_helpers::map_err(err, |()| 3), Err(3));
+
+    assert_eq!(poll_helpers::and_then(ready, |n| Ok(Async::Ready(n * 2))),
+               Ok(Async::Ready(2)));
+    assert_eq!(poll_helpers::and_then(ready, |_| Ok::<Async<u32>, ()>(Async::NotReady)),
+               Ok(Async::NotReady));
+    assert_eq!(poll_helpers::and_then(not_ready, |_| -> Poll<u32, ()> { panic!() }),
+               Ok(Async::NotReady));
+    assert_eq!(poll_helpers::and_then(err, |_| -> Poll<u32, ()> { panic!() }),
+               Err(()));
+}
+
+#[test]
+fn predicates_and_options() {
+    let ready: Poll<u32, ()> = Ok(Async::Ready(1));
+    let not_ready: Poll<u32, ()> = Ok(Async::NotReady);
+    let err: Poll<u32, ()> = Err(());
+
+    assert!(poll_helpers::is_ready(&ready));
+    assert!(!poll_helpers::is_ready(&not_ready));
+    assert!(!poll_helpers::is_ready(&err));
+    assert!(poll_helpers::is_not_ready(&not_ready));
+    assert!(!poll_helpers::is_not_ready(&err));
+
+    assert_eq!(poll_helpers::into_option(ready), Ok(Some(1)));
+    assert_eq!(poll_helpers::into_option(not_ready), Ok(None));
+    assert_eq!(poll_helpers::into_option(err), Err(()));
+    assert_eq!(poll_helpers::from_option(Ok::<_, ()>(Some(1))), ready);
+    assert_eq!(poll_helpers::from_option(Ok::<Option<u32>, ()>(None)), not_ready);
+}
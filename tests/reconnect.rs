@@ -0,0 +1,144 @@
+extern crate futures;
+
+use std::cell::{Cell, RefCell};
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::{Async, Future, Stream};
+use futures::executor;
+use futures::future;
+use futures::reconnect::ReconnectPolicy;
+use futures::stream;
+use futures::sync::mpsc;
+
+mod support;
+use support::*;
+
+fn ms(n: u64) -> Duration {
+    Duration::from_millis(n)
+}
+
+#[test]
+fn policy_backoff() {
+    let mut policy = ReconnectPolicy::new();
+    assert_eq!(policy.delay_for(0), Some(ms(100)));
+    assert_eq!(policy.delay_for(1), Some(ms(200)));
+    assert_eq!(policy.delay_for(1000), Some(Duration::from_secs(30)));
+    assert!(policy.allows_retry(1000));
+
+    policy.backoff(ms(10), ms(1000)).multiplier(3).max_retries(5);
+    assert_eq!(policy.delay_for(2), Some(ms(90)));
+    assert_eq!(policy.delay_for(4), Some(ms(810)));
+    assert!(!policy.allows_retry(5));
+    assert_eq!(policy.delay_for(5), None);
+
+    policy.jitter(0.5);
+    for _ in 0..100 {
+        let delay = policy.delay_for(4).unwrap();
+        assert!(ms(405) <= delay && delay <= ms(810));
+    }
+}
+
+#[test]
+fn future_retry_backs_off() {
+    let mock = MockClock::new();
+    let attempts = Cell::new(0);
+    let mut policy = ReconnectPolicy::new();
+    policy.backoff(ms(10), ms(15)).max_retries(2);
+    let f = future::retry(policy, || {
+        attempts.set(attempts.get() + 1);
+        Err::<(), _>(attempts.get())
+    });
+    let mut task = executor::spawn(Clocked(Arc::new(mock.clone()), f));
+
+    assert!(task.poll_future(unpark_noop()).unwrap().is_not_ready());
+    assert_eq!(attempts.get(), 1);
+    mock.advance(ms(9));
+    assert!(task.poll_future(unpark_noop()).unwrap().is_not_ready());
+    assert_eq!(attempts.get(), 1);
+    mock.advance(ms(1));
+    assert!(task.poll_future(unpark_noop()).unwrap().is_not_ready());
+    assert_eq!(attempts.get(), 2);
+    mock.advance(ms(15));
+    assert_eq!(task.poll_future(unpark_noop()), Err(3));
+}
+
+#[test]
+fn future_retry_idle_timeout() {
+    let mock = MockClock::new();
+    let attempts = RefCell::new(Vec::new());
+    let mut policy = ReconnectPolicy::new();
+    policy.backoff(ms(10), ms(10)).idle_timeout(ms(50)).max_retries(1);
+    let f = future::retry(policy, || {
+        let (tx, rx) = futures::sync::oneshot::channel::<i32>();
+        attempts.borrow_mut().push(tx);
+        rx
+    });
+    let mut task = executor::spawn(Clocked(Arc::new(mock.clone()), f));
+
+    // The first attempt is abandoned once idle, but the last one isn't.
+    assert!(task.poll_future(unpark_noop()).unwrap().is_not_ready());
+    mock.advance(ms(50));
+    assert!(task.poll_future(unpark_noop()).unwrap().is_not_ready());
+    mock.advance(ms(10));
+    assert!(task.poll_future(unpark_noop()).unwrap().is_not_ready());
+    assert_eq!(attempts.borrow().len(), 2);
+    mock.advance(ms(1000));
+    assert!(task.poll_future(unpark_noop()).unwrap().is_not_ready());
+    assert_eq!(attempts.borrow().len(), 2);
+
+    assert!(attempts.borrow()[0].is_canceled());
+    attempts.borrow_mut().remove(1).complete(2);
+    assert_eq!(task.poll_future(unpark_noop()), Ok(Async::Ready(2)));
+}
+
+#[test]
+fn stream_resubscribes_when_idle() {
+    let mock = MockClock::new();
+    let senders = RefCell::new(Vec::new());
+    let subscribe = || {
+        let (tx, rx) = mpsc::unbounded::<i32>();
+        senders.borrow_mut().push(tx);
+        rx
+    };
+    let mut policy = ReconnectPolicy::new();
+    policy.backoff(ms(10), ms(10)).idle_timeout(ms(50)).max_retries(1);
+    let s = subscribe().timeout_backoff_resubscribe(policy, &subscribe);
+    let mut task = executor::spawn(Clocked(Arc::new(mock.clone()), s));
+
+    assert!(task.poll_stream(unpark_noop()).unwrap().is_not_ready());
+    mock.advance(ms(50));
+    assert!(task.poll_stream(unpark_noop()).unwrap().is_not_ready());
+    mock.advance(ms(10));
+    assert!(task.poll_stream(unpark_noop()).unwrap().is_not_ready());
+    assert_eq!(senders.borrow().len(), 2);
+
+    // An element resets the retry count, so the second subscription can be
+    // replaced for going idle too.
+    mpsc::UnboundedSender::send(&mut senders.borrow_mut()[1], 1).unwrap();
+    assert_eq!(task.poll_stream(unpark_noop()), Ok(Async::Ready(Some(1))));
+    assert!(task.poll_stream(unpark_noop()).unwrap().is_not_ready());
+    mock.advance(ms(50));
+    assert!(task.poll_stream(unpark_noop()).unwrap().is_not_ready());
+    mock.advance(ms(10));
+    assert!(task.poll_stream(unpark_noop()).unwrap().is_not_ready());
+    assert_eq!(senders.borrow().len(), 3);
+
+    // Senders of replaced subscriptions are disconnected.
+    assert!(mpsc::UnboundedSender::send(&mut senders.borrow_mut()[0], 0).is_err());
+    senders.borrow_mut().clear();
+    assert_eq!(task.poll_stream(unpark_noop()), Ok(Async::Ready(None)));
+}
+
+#[test]
+fn stream_gives_up_on_errors() {
+    let mut policy = ReconnectPolicy::new();
+    policy.backoff(ms(1), ms(1)).max_retries(2);
+    let subscriptions = Cell::new(0);
+    let subscribe = || {
+        subscriptions.set(subscriptions.get() + 1);
+        stream::iter(vec![Err::<i32, _>(subscriptions.get())])
+    };
+    let s = subscribe().timeout_backoff_resubscribe(policy, &subscribe);
+    assert_eq!(s.collect().wait(), Err(3));
+}
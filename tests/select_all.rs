@@ -24,3 +24,20 @@ fn smoke() {
 
     assert!(v.len() == 0);
 }
+
+#[test]
+fn biased_ties() {
+    // The first ready future in the list wins, and later ones aren't polled
+    let v = vec![
+        empty().boxed(),
+        ok(1).boxed(),
+        ok(2).boxed(),
+        lazy(|| -> FutureResult<i32, ()> { panic!("polled after the winner") }).boxed(),
+    ];
+    let (i, idx, v) = select_biased_all(v).wait().ok().unwrap();
+    assert_eq!((i, idx), (1, 1));
+
+    let (i, idx, v) = select_biased_all(v).wait().ok().unwrap();
+    assert_eq!((i, idx), (2, 1));
+    assert_eq!(v.len(), 2);
+}
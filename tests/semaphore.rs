@@ -0,0 +1,104 @@
+extern crate futures;
+
+use std::sync::{mpsc, Arc};
+use std::thread;
+
+use futures::{Async, Future};
+use futures::executor::{self, Executor, Run};
+use futures::sync::Semaphore;
+
+mod support;
+use support::*;
+
+#[test]
+fn smoke() {
+    let semaphore = Semaphore::new(2);
+    let a = semaphore.acquire().wait().unwrap();
+    let b = semaphore.try_acquire().unwrap();
+    assert_eq!(semaphore.available_permits(), 0);
+    assert!(semaphore.try_acquire().is_none());
+    assert_empty(|| semaphore.acquire());
+    drop(a);
+    drop(b);
+    assert_eq!(semaphore.available_permits(), 2);
+}
+
+#[test]
+fn waiters_are_fifo() {
+    let semaphore = Semaphore::new(1);
+    let permit = semaphore.try_acquire().unwrap();
+
+    let mut a = executor::spawn(semaphore.acquire());
+    let mut b = executor::spawn(semaphore.acquire());
+    assert!(b.poll_future(unpark_noop()).unwrap().is_not_ready());
+    assert!(a.poll_future(unpark_noop()).unwrap().is_not_ready());
+
+    // The released permit goes straight to the first waiter, even ahead of
+    // a newcomer trying to take it.
+    drop(permit);
+    assert!(semaphore.try_acquire().is_none());
+    assert!(a.poll_future(unpark_noop()).unwrap().is_not_ready());
+    let permit = match b.poll_future(unpark_noop()) {
+        Ok(Async::Ready(permit)) => permit,
+        _ => panic!("expected a permit"),
+    };
+    drop(permit);
+    assert!(a.poll_future(unpark_noop()).unwrap().is_ready());
+}
+
+#[test]
+fn dropped_waiter_passes_permit_on() {
+    let semaphore = Semaphore::new(1);
+    let permit = semaphore.try_acquire().unwrap();
+
+    let mut a = executor::spawn(semaphore.acquire());
+    let mut b = executor::spawn(semaphore.acquire());
+    let mut c = executor::spawn(semaphore.acquire());
+    assert!(a.poll_future(unpark_noop()).unwrap().is_not_ready());
+    assert!(b.poll_future(unpark_noop()).unwrap().is_not_ready());
+    assert!(c.poll_future(unpark_noop()).unwrap().is_not_ready());
+
+    // `a` is granted the permit but goes away before seeing it, and `b` gave
+    // up waiting, so the permit ends up with `c`.
+    drop(b);
+    drop(permit);
+    drop(a);
+    assert!(c.poll_future(unpark_noop()).unwrap().is_ready());
+}
+
+#[test]
+fn across_threads() {
+    let semaphore = Semaphore::new(1);
+    let permit = semaphore.try_acquire().unwrap();
+    let acquire = semaphore.acquire();
+    let t = thread::spawn(move || acquire.wait().is_ok());
+    drop(permit);
+    assert!(t.join().unwrap());
+    assert_eq!(semaphore.available_permits(), 1);
+}
+
+struct Inline;
+
+impl Executor for Inline {
+    fn execute(&self, r: Run) {
+        r.run()
+    }
+}
+
+#[test]
+fn release_to_inline_executor() {
+    let semaphore = Semaphore::new(1);
+    let permit = semaphore.try_acquire().unwrap();
+
+    // Releasing the permit runs the waiting task right away on this thread.
+    let (tx, rx) = mpsc::channel();
+    executor::spawn(semaphore.acquire().map(move |permit| {
+        tx.send(()).unwrap();
+        drop(permit);
+    })).execute(Arc::new(Inline));
+    assert!(rx.try_recv().is_err());
+
+    drop(permit);
+    rx.recv().unwrap();
+    assert_eq!(semaphore.available_permits(), 1);
+}
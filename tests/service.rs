@@ -0,0 +1,411 @@
+extern crate futures;
+
+use std::cell::{Cell, RefCell};
+use std::rc::{Rc, Weak};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use futures::{Async, Future};
+use futures::clock::Clock;
+use futures::executor::{self, Unpark};
+use futures::future;
+use futures::service::*;
+use futures::sync::{oneshot, Semaphore};
+
+mod support;
+use support::*;
+
+#[test]
+fn service_fn_smoke() {
+    let add = service_fn(|(a, b): (i32, i32)| f_ok(a + b));
+    assert_done(|| add.call((1, 2)), Ok(3));
+    assert_done(|| Box::new(&add).call((5, 6)), Ok(11));
+}
+
+#[test]
+fn retry_until_success() {
+    let attempts = Cell::new(0);
+    let flaky = service_fn(|a: i32| {
+        attempts.set(attempts.get() + 1);
+        if attempts.get() < 3 { r_err(attempts.get()) } else { r_ok(a) }
+    });
+    assert_done(|| Retry::new(&flaky, 2).call(1), Ok(1));
+    assert_eq!(attempts.get(), 3);
+}
+
+#[test]
+fn retry_gives_up() {
+    let attempts = Cell::new(0);
+    let failing = service_fn(|_: i32| {
+        attempts.set(attempts.get() + 1);
+        r_err(attempts.get())
+    });
+    assert_done(|| Retry::new(&failing, 2).call(1), Err(3));
+    assert_done(|| Retry::new(&failing, 0).call(1), Err(4));
+}
+
+#[test]
+fn concurrency_limit_queues_requests() {
+    let inner = service_fn(|rx: oneshot::Receiver<i32>| rx);
+    let service = ConcurrencyLimit::new(&inner, 1);
+
+    let (tx1, rx1) = oneshot::channel();
+    let (tx2, rx2) = oneshot::channel();
+    let (tx3, rx3) = oneshot::channel();
+    let mut a = executor::spawn(service.call(rx1));
+    let mut b = executor::spawn(service.call(rx2));
+    let mut c = executor::spawn(service.call(rx3));
+
+    assert!(a.poll_future(unpark_noop()).unwrap().is_not_ready());
+    assert!(b.poll_future(unpark_noop()).unwrap().is_not_ready());
+    assert!(c.poll_future(unpark_noop()).unwrap().is_not_ready());
+
+    // Only the first request has made it to the inner service, so the
+    // others can't complete even though their responses are available.
+    tx3.complete(3);
+    tx2.complete(2);
+    assert!(c.poll_future(unpark_noop()).unwrap().is_not_ready());
+    assert!(b.poll_future(unpark_noop()).unwrap().is_not_ready());
+
+    tx1.complete(1);
+    assert_eq!(a.poll_future(unpark_noop()), Ok(Async::Ready(1)));
+    assert_eq!(b.poll_future(unpark_noop()), Ok(Async::Ready(2)));
+    assert_eq!(c.poll_future(unpark_noop()), Ok(Async::Ready(3)));
+}
+
+#[test]
+fn concurrency_limit_dropped_waiter_passes_permit_on() {
+    let inner = service_fn(|rx: oneshot::Receiver<i32>| rx);
+    let service = ConcurrencyLimit::new(&inner, 1);
+
+    let (tx1, rx1) = oneshot::channel();
+    let (_tx2, rx2) = oneshot::channel();
+    let (tx3, rx3) = oneshot::channel();
+    let mut a = executor::spawn(service.call(rx1));
+    let mut b = executor::spawn(service.call(rx2));
+    let mut c = executor::spawn(service.call(rx3));
+
+    assert!(a.poll_future(unpark_noop()).unwrap().is_not_ready());
+    assert!(b.poll_future(unpark_noop()).unwrap().is_not_ready());
+    assert!(c.poll_future(unpark_noop()).unwrap().is_not_ready());
+
+    // Dropping an in-flight request releases its permit, and the permit then
+    // skips over requests which gave up waiting.
+    drop(a);
+    drop(tx1);
+    drop(b);
+    tx3.complete(3);
+    assert_eq!(c.poll_future(unpark_noop()), Ok(Async::Ready(3)));
+}
+
+#[test]
+fn concurrency_limit_shared_semaphore() {
+    let semaphore = Semaphore::new(1);
+    let inner = service_fn(|rx: oneshot::Receiver<i32>| rx);
+    let a = ConcurrencyLimit::with_semaphore(&inner, semaphore.clone());
+    let b = ConcurrencyLimit::with_semaphore(&inner, semaphore.clone());
+
+    let (tx1, rx1) = oneshot::channel();
+    let (tx2, rx2) = oneshot::channel();
+    let mut first = executor::spawn(a.call(rx1));
+    let mut second = executor::spawn(b.call(rx2));
+    assert!(first.poll_future(unpark_noop()).unwrap().is_not_ready());
+    assert!(second.poll_future(unpark_noop()).unwrap().is_not_ready());
+    assert_eq!(semaphore.available_permits(), 0);
+
+    tx2.complete(2);
+    assert!(second.poll_future(unpark_noop()).unwrap().is_not_ready());
+    tx1.complete(1);
+    assert_eq!(first.poll_future(unpark_noop()), Ok(Async::Ready(1)));
+    assert_eq!(second.poll_future(unpark_noop()), Ok(Async::Ready(2)));
+    assert_eq!(semaphore.available_permits(), 1);
+}
+
+#[test]
+fn instrument_reports_results() {
+    let seen = RefCell::new(Vec::new());
+    let inner = service_fn(|a: i32| if a > 0 { r_ok(a) } else { r_err(0) });
+    let service = Instrument::new(&inner, |res: &Result<i32, u32>, _| {
+        seen.borrow_mut().push(*res);
+    });
+    assert_done(|| service.call(1), Ok(1));
+    assert_done(|| service.call(-1), Err(0));
+    assert_eq!(*seen.borrow(), vec![Ok(1), Err(0)]);
+}
+
+#[test]
+fn middleware_compose() {
+    let attempts = Cell::new(0);
+    let flaky = service_fn(|a: i32| {
+        attempts.set(attempts.get() + 1);
+        if attempts.get() % 2 == 1 { r_err(0) } else { r_ok(a) }
+    });
+    let service = Retry::new(ConcurrencyLimit::new(&flaky, 1), 1);
+    assert_done(|| service.call(1), Ok(1));
+    assert_done(|| service.call(2), Ok(2));
+    assert_eq!(attempts.get(), 4);
+}
+
+#[test]
+fn balance_prefers_less_loaded() {
+    let log = RefCell::new(Vec::new());
+    let tagged = |tag| {
+        let log = &log;
+        service_fn(move |rx: oneshot::Receiver<i32>| { log.borrow_mut().push(tag); rx })
+    };
+    let service = Balance::new(vec![tagged('a'), tagged('b')], 1);
+
+    // With two services, both are always considered, so requests alternate
+    // while earlier ones are still in flight.
+    let (_tx1, rx1) = oneshot::channel();
+    let (_tx2, rx2) = oneshot::channel();
+    let first = service.call(rx1);
+    let second = service.call(rx2);
+    let log2 = log.borrow().clone();
+    assert!(log2 == ['a', 'b'] || log2 == ['b', 'a']);
+    drop((first, second));
+}
+
+#[test]
+fn balance_removes_failing_services() {
+    let healthy = |ok| service_fn(move |a: i32| if ok { r_ok(a) } else { r_err(0) });
+    let service = Balance::new(vec![healthy(true), healthy(false)], 2);
+
+    let mut errors = 0;
+    for _ in 0..20 {
+        if service.call(1).wait().is_err() {
+            errors += 1;
+        }
+    }
+    assert_eq!(errors, 2);
+    assert_eq!(service.len(), 1);
+}
+
+#[test]
+fn balance_unavailable() {
+    let bad = service_fn(|_: i32| r_err(0));
+    let service = Balance::new(vec![bad], 1);
+    assert_done(|| service.call(1), Err(BalanceError::Service(0)));
+    assert_done(|| service.call(1), Err(BalanceError::Unavailable));
+    assert!(service.is_empty());
+}
+
+#[test]
+#[should_panic]
+fn balance_future_polled_after_completion() {
+    let service = Balance::new(vec![service_fn(|a: i32| r_ok(a))], 1);
+    let mut f = executor::spawn(service.call(1));
+    assert_eq!(f.poll_future(unpark_noop()), Ok(Async::Ready(1)));
+    drop(f.poll_future(unpark_noop()));
+}
+
+#[test]
+fn balance_calls_services_without_lock() {
+    // Calls back into the balancer it belongs to, which would deadlock if the
+    // balancer's lock were held.
+    struct Reentrant(Rc<RefCell<Weak<Balance<Reentrant>>>>);
+
+    impl Service for Reentrant {
+        type Request = ();
+        type Response = usize;
+        type Error = ();
+        type Future = future::FutureResult<usize, ()>;
+
+        fn call(&self, _: ()) -> Self::Future {
+            let balance = self.0.borrow().upgrade().unwrap();
+            future::ok(balance.len())
+        }
+    }
+
+    let slot = Rc::new(RefCell::new(Weak::new()));
+    let service = Rc::new(Balance::new(vec![Reentrant(slot.clone())], 1));
+    *slot.borrow_mut() = Rc::downgrade(&service);
+    assert_done(|| service.call(()), Ok(1));
+}
+
+struct Flag(AtomicBool);
+
+impl Flag {
+    fn new() -> Arc<Flag> {
+        Arc::new(Flag(AtomicBool::new(false)))
+    }
+
+    fn take(&self) -> bool {
+        self.0.swap(false, Ordering::SeqCst)
+    }
+}
+
+impl Unpark for Flag {
+    fn unpark(&self) {
+        self.0.store(true, Ordering::SeqCst)
+    }
+}
+
+#[test]
+fn circuit_breaker_opens_and_recovers() {
+    let mock = MockClock::new();
+    let clock: Arc<Clock> = Arc::new(mock.clone());
+    let healthy = Cell::new(false);
+    let inner = service_fn(|a: i32| if healthy.get() { r_ok(a) } else { r_err(0) });
+    let service = CircuitBreaker::new(&inner, 2, 3, Duration::from_secs(30));
+    let flag = Flag::new();
+    let mut changes = executor::spawn(Clocked(clock.clone(), service.state_changes()));
+    {
+        let call = |a| Clocked(clock.clone(), future::lazy(|| service.call(a))).wait();
+
+        assert_eq!(changes.poll_stream(flag.clone()), Ok(Async::NotReady));
+        assert_eq!(call(1), Err(CircuitBreakerError::Service(0)));
+        assert!(!flag.take());
+        assert_eq!(call(1), Err(CircuitBreakerError::Service(0)));
+        assert!(flag.take());
+        assert_eq!(changes.poll_stream(flag.clone()),
+                   Ok(Async::Ready(Some(CircuitState::Open))));
+
+        // The stream waits for the cool-down on a timer, so it sees the
+        // breaker become half-open without any requests being issued.
+        assert_eq!(changes.poll_stream(flag.clone()), Ok(Async::NotReady));
+        mock.advance(Duration::from_secs(30));
+        assert!(flag.take());
+        assert_eq!(changes.poll_stream(flag.clone()),
+                   Ok(Async::Ready(Some(CircuitState::HalfOpen))));
+
+        // A failed probe opens the breaker again, and a successful one
+        // closes it. Only the latest state is reported.
+        assert_eq!(call(1), Err(CircuitBreakerError::Service(0)));
+        mock.advance(Duration::from_secs(30));
+        healthy.set(true);
+        assert_eq!(call(2), Ok(2));
+        assert!(flag.take());
+        assert_eq!(changes.poll_stream(flag.clone()),
+                   Ok(Async::Ready(Some(CircuitState::Closed))));
+        assert_eq!(changes.poll_stream(flag.clone()), Ok(Async::NotReady));
+    }
+
+    drop(service);
+    assert!(flag.take());
+    assert_eq!(changes.poll_stream(flag.clone()), Ok(Async::Ready(None)));
+}
+
+#[test]
+fn circuit_breaker_notifies_without_lock() {
+    type Breaker = CircuitBreaker<ServiceFn<fn(i32) -> Result<i32, u32>, i32>>;
+
+    // Reads the state of the breaker when unparked, which must not deadlock
+    // on the lock held while the state changes.
+    struct Inspect(Arc<Breaker>, Mutex<Option<CircuitState>>);
+
+    impl Unpark for Inspect {
+        fn unpark(&self) {
+            *self.1.lock().unwrap() = Some(self.0.state());
+        }
+    }
+
+    fn fail(a: i32) -> Result<i32, u32> { Err(a as u32) }
+    let service: Arc<Breaker> = Arc::new(CircuitBreaker::new(service_fn(fail),
+                                                             1,
+                                                             1,
+                                                             Duration::from_secs(3600)));
+    let inspect = Arc::new(Inspect(service.clone(), Mutex::new(None)));
+    let mut changes = executor::spawn(service.state_changes());
+    assert_eq!(changes.poll_stream(inspect.clone()), Ok(Async::NotReady));
+    assert_eq!(service.call(1).wait(), Err(CircuitBreakerError::Service(1)));
+    assert_eq!(*inspect.1.lock().unwrap(), Some(CircuitState::Open));
+}
+
+#[test]
+fn circuit_breaker_rejects_while_open() {
+    let calls = Cell::new(0);
+    let inner = service_fn(|a: i32| { calls.set(calls.get() + 1); r_err(a as u32) });
+    let service = CircuitBreaker::new(&inner, 1, 1, Duration::from_secs(3600));
+    assert_done(|| service.call(1), Err(CircuitBreakerError::Service(1)));
+    assert_done(|| service.call(2), Err(CircuitBreakerError::Open));
+    assert_eq!(service.state(), CircuitState::Open);
+    assert_eq!(calls.get(), 1);
+}
+
+#[test]
+fn circuit_breaker_single_probe() {
+    let inner = service_fn(|rx: oneshot::Receiver<Result<i32, u32>>| {
+        rx.then(|res| res.unwrap())
+    });
+    let service = CircuitBreaker::new(&inner, 1, 1, Duration::from_millis(0));
+    let (tx, rx) = oneshot::channel();
+    tx.complete(Err(0));
+    assert_done(|| service.call(rx), Err(CircuitBreakerError::Service(0)));
+
+    // Only one probe is let through while half-open, and abandoning it lets
+    // another request probe instead.
+    let (_tx1, rx1) = oneshot::channel();
+    let (_tx2, rx2) = oneshot::channel();
+    let probe = service.call(rx1);
+    assert_done(|| service.call(rx2), Err(CircuitBreakerError::Open));
+    drop(probe);
+    let (tx3, rx3) = oneshot::channel();
+    tx3.complete(Ok(3));
+    assert_done(|| service.call(rx3), Ok(3));
+    assert_eq!(service.state(), CircuitState::Closed);
+}
+
+#[test]
+fn instrument_uses_task_clock() {
+    let mock = MockClock::new();
+    let (tx, rx) = oneshot::channel::<i32>();
+    let elapsed = RefCell::new(None);
+    let inner = service_fn(|rx: oneshot::Receiver<i32>| rx);
+    let service = Instrument::new(&inner, |_: &Result<i32, _>, dur| {
+        *elapsed.borrow_mut() = Some(dur);
+    });
+
+    let call = future::lazy(|| service.call(rx));
+    let mut task = executor::spawn(Clocked(Arc::new(mock.clone()), call));
+    assert!(task.poll_future(unpark_noop()).unwrap().is_not_ready());
+    mock.advance(Duration::from_secs(5));
+    tx.complete(1);
+    assert_eq!(task.poll_future(unpark_noop()), Ok(Async::Ready(1)));
+    assert_eq!(*elapsed.borrow(), Some(Duration::from_secs(5)));
+}
+
+#[test]
+fn circuit_breaker_uses_task_clock() {
+    let mock = MockClock::new();
+    let clocked = |f: &Fn() -> CircuitState| {
+        Clocked(Arc::new(mock.clone()), future::lazy(|| Ok::<_, ()>(f()))).wait().unwrap()
+    };
+    let inner = service_fn(|a: i32| r_err(a as u32));
+    let service = CircuitBreaker::new(&inner, 1, 1, Duration::from_secs(30));
+
+    assert_eq!(clocked(&|| {
+        // polled on this task, so the breaker opens at the mock time
+        assert!(service.call(1).poll().is_err());
+        service.state()
+    }), CircuitState::Open);
+    mock.advance(Duration::from_secs(29));
+    assert_eq!(clocked(&|| service.state()), CircuitState::Open);
+    mock.advance(Duration::from_secs(1));
+    assert_eq!(clocked(&|| service.state()), CircuitState::HalfOpen);
+}
+
+#[test]
+fn timeout_passes_responses_through() {
+    let inner = service_fn(|a: i32| if a > 0 { r_ok(a) } else { r_err(0) });
+    let service = Timeout::new(&inner, Duration::from_secs(5));
+    assert_done(|| service.call(1), Ok(1));
+    assert_done(|| service.call(0), Err(TimeoutError::Service(0)));
+}
+
+#[test]
+fn timeout_uses_task_clock() {
+    let mock = MockClock::new();
+    let (_tx, rx) = oneshot::channel::<i32>();
+    let inner = service_fn(|rx: oneshot::Receiver<i32>| rx);
+    let service = Timeout::new(&inner, Duration::from_secs(5));
+
+    let call = future::lazy(|| service.call(rx));
+    let mut task = executor::spawn(Clocked(Arc::new(mock.clone()), call));
+    assert!(task.poll_future(unpark_noop()).unwrap().is_not_ready());
+    mock.advance(Duration::from_secs(4));
+    assert!(task.poll_future(unpark_noop()).unwrap().is_not_ready());
+    mock.advance(Duration::from_secs(1));
+    assert_eq!(task.poll_future(unpark_noop()), Err(TimeoutError::Elapsed));
+}
@@ -66,3 +66,46 @@ fn drop_on_one_task_ok() {
     assert_eq!(result, 42);
     t2.join().unwrap();
 }
+
+#[test]
+fn peek() {
+    let (tx, rx) = oneshot::channel::<u32>();
+    let f1 = rx.shared();
+    let f2 = f1.clone();
+    assert!(f1.peek().is_none());
+
+    tx.complete(42);
+    // Nothing has polled the original future yet
+    assert!(f2.peek().is_none());
+    assert_eq!(*f1.wait().unwrap(), 42);
+    assert_eq!(*f2.peek().unwrap().unwrap(), 42);
+
+    let (tx, rx) = oneshot::channel::<u32>();
+    let f = rx.shared();
+    drop(tx);
+    assert!(f.clone().wait().is_err());
+    assert!(f.peek().unwrap().is_err());
+}
+
+#[test]
+fn downgrade() {
+    let (tx, rx) = oneshot::channel::<u32>();
+    let f1 = rx.shared();
+    let weak = f1.downgrade();
+    let f2 = weak.upgrade().unwrap();
+    drop(f1);
+    assert!(!tx.is_canceled());
+
+    // Once the last strong handle is gone the original future is dropped
+    drop(f2);
+    assert!(tx.is_canceled());
+    assert!(weak.upgrade().is_none());
+    assert!(weak.clone().upgrade().is_none());
+
+    let (tx, rx) = oneshot::channel::<u32>();
+    let f = rx.shared();
+    let weak = f.downgrade();
+    tx.complete(6);
+    assert_eq!(*weak.upgrade().unwrap().wait().unwrap(), 6);
+    assert_eq!(*f.peek().unwrap().unwrap(), 6);
+}
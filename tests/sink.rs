@@ -56,6 +56,40 @@ fn send_all() {
         Ok(vec![0, 1, 2, 3, 4, 5]));
 }
 
+#[test]
+fn send_iter() {
+    let v = Vec::new();
+
+    let v = v.send_iter(vec![0, 1]).wait().unwrap();
+    assert_eq!(v, vec![0, 1]);
+
+    let v = v.send_iter(2..4).wait().unwrap();
+    assert_eq!(v, vec![0, 1, 2, 3]);
+
+    assert_done(move || v.send_iter(vec![4, 5]), Ok(vec![0, 1, 2, 3, 4, 5]));
+}
+
+#[test]
+// Test that `send_iter` waits for a full `mpsc` channel to accept each item
+fn send_iter_blocking() {
+    let (tx, mut rx) = mpsc::channel::<i32>(0);
+
+    futures::future::lazy(|| {
+        let flag = Flag::new();
+        let mut task = executor::spawn(tx.send_iter(vec![0, 1]));
+
+        assert!(task.poll_future(flag.clone()).unwrap().is_not_ready());
+        assert!(!flag.get());
+        sassert_next(&mut rx, 0);
+        assert!(flag.get());
+        flag.set(false);
+        assert!(task.poll_future(flag.clone()).unwrap().is_ready());
+        sassert_next(&mut rx, 1);
+
+        Ok::<(), ()>(())
+    }).wait().unwrap();
+}
+
 // An Unpark struct that records unpark events for inspection
 struct Flag(pub AtomicBool);
 
@@ -0,0 +1,77 @@
+extern crate futures;
+
+use std::thread;
+
+use futures::{Future, Stream, Sink, Async, AsyncSink};
+use futures::future::lazy;
+use futures::stream;
+use futures::sync::spsc;
+
+mod support;
+use support::*;
+
+#[test]
+fn send_recv() {
+    let (tx, rx) = spsc::channel::<i32>(4);
+    let tx = tx.send(1).wait().unwrap();
+    let tx = tx.send(2).wait().unwrap();
+    drop(tx);
+    assert_eq!(rx.collect().wait(), Ok(vec![1, 2]));
+}
+
+#[test]
+fn full_channel_blocks() {
+    let (mut tx, mut rx) = spsc::channel::<i32>(2);
+    lazy(move || {
+        assert_eq!(tx.start_send(1).unwrap(), AsyncSink::Ready);
+        assert_eq!(tx.start_send(2).unwrap(), AsyncSink::Ready);
+        assert!(tx.is_full());
+        assert_eq!(tx.start_send(3).unwrap(), AsyncSink::NotReady(3));
+        assert_eq!(rx.poll(), Ok(Async::Ready(Some(1))));
+        assert_eq!(tx.start_send(3).unwrap(), AsyncSink::Ready);
+        assert_eq!(rx.poll(), Ok(Async::Ready(Some(2))));
+        assert_eq!(rx.poll(), Ok(Async::Ready(Some(3))));
+        assert_eq!(rx.poll(), Ok(Async::NotReady));
+        drop(tx);
+        assert_eq!(rx.poll(), Ok(Async::Ready(None)));
+        Ok::<(), ()>(())
+    }).wait().unwrap();
+}
+
+#[test]
+fn drop_receiver() {
+    let (tx, rx) = spsc::channel::<i32>(1);
+    drop(rx);
+    match tx.send(1).wait() {
+        Err(e) => assert_eq!(e.into_inner(), 1),
+        Ok(_) => panic!(),
+    }
+}
+
+#[test]
+fn unparks_receiver() {
+    let (tx, rx) = spsc::channel::<i32>(1);
+    let mut rx = futures::executor::spawn(rx);
+    assert!(rx.poll_stream(unpark_noop()).unwrap().is_not_ready());
+    let t = thread::spawn(move || tx.send(1).wait().unwrap());
+    assert_eq!(rx.wait_stream(), Some(Ok(1)));
+    drop(t.join().unwrap());
+    assert_eq!(rx.wait_stream(), None);
+}
+
+#[test]
+fn threads() {
+    const AMT: u32 = 100_000;
+
+    let (tx, rx) = spsc::channel::<u32>(8);
+    let t = thread::spawn(move || {
+        tx.send_all(stream::iter((0..AMT).map(Ok))).map(|_| ()).wait().ok().unwrap();
+    });
+    let mut next = 0;
+    for i in rx.wait() {
+        assert_eq!(i, Ok(next));
+        next += 1;
+    }
+    assert_eq!(next, AMT);
+    t.join().unwrap();
+}
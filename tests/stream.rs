@@ -1,8 +1,11 @@
 #[macro_use]
 extern crate futures;
 
-use futures::{Poll, Future, Stream, Sink};
-use futures::executor;
+use std::sync::Arc;
+use std::thread;
+
+use futures::{Poll, Async, Future, Stream, Sink};
+use futures::executor::{self, Executor, Run};
 use futures::future::{ok, err};
 use futures::stream::{iter, Peekable, BoxStream};
 use futures::sync::oneshot;
@@ -82,6 +85,30 @@ fn or_else() {
     }).collect(), Ok(vec![1, 2, 3]));
 }
 
+#[test]
+fn first() {
+    assert_done(|| list().first(), Ok(Some(1)));
+    assert_done(|| err_list().skip(2).first(), Err(3));
+    assert_done(|| iter::<_, i32, u32>(vec![]).first(), Ok(None));
+}
+
+#[test]
+fn first_drops_stream() {
+    let (tx, rx) = mpsc::channel::<i32>(1);
+    let tx = tx.send(1).wait().unwrap();
+    let mut first = executor::spawn(rx.first());
+    assert_eq!(first.poll_future(unpark_noop()), Ok(Async::Ready(Some(1))));
+    // the receiver has been dropped, so sends fail
+    assert!(tx.send(2).wait().is_err());
+}
+
+#[test]
+fn last() {
+    assert_done(|| list().last(), Ok(Some(3)));
+    assert_done(|| err_list().last(), Err(3));
+    assert_done(|| iter::<_, i32, u32>(vec![]).last(), Ok(None));
+}
+
 #[test]
 fn flatten() {
     assert_done(|| list().map(|_| list()).flatten().collect(),
@@ -231,6 +258,40 @@ fn unordered() {
     assert_eq!(rx.next(), None);
 }
 
+#[test]
+fn flatten_unordered() {
+    let (a_tx, a_rx) = mpsc::channel::<u32>(1);
+    let (b_tx, b_rx) = mpsc::channel::<u32>(1);
+    let (c_tx, c_rx) = mpsc::channel::<u32>(1);
+    let rx = iter(vec![Ok(a_rx), Ok(b_rx), Ok(c_rx)]);
+
+    let mut rx = executor::spawn(rx.flatten_unordered(2));
+    let mut next = || rx.poll_stream(unpark_noop()).unwrap();
+    assert!(next().is_not_ready());
+
+    // Items from the first two streams are yielded as they arrive
+    let b_tx = b_tx.send(1).wait().unwrap();
+    assert_eq!(next(), Async::Ready(Some(1)));
+    let a_tx = a_tx.send(2).wait().unwrap();
+    assert_eq!(next(), Async::Ready(Some(2)));
+
+    // The third stream isn't polled until one of the others finishes
+    let c_tx = c_tx.send(3).wait().unwrap();
+    assert!(next().is_not_ready());
+    drop(a_tx);
+    assert_eq!(next(), Async::Ready(Some(3)));
+
+    drop(b_tx);
+    drop(c_tx);
+    assert_eq!(next(), Async::Ready(None));
+}
+
+#[test]
+#[should_panic]
+fn flatten_unordered_panic_on_limit_zero() {
+    let _ = iter(vec![Ok::<_, u32>(list())]).flatten_unordered(0);
+}
+
 #[test]
 fn zip() {
     assert_done(|| list().zip(list()).collect(),
@@ -294,6 +355,19 @@ fn chunks_panic_on_cap_zero() {
     let _ = list().chunks(0);
 }
 
+#[test]
+fn chunked_by() {
+    assert_done(|| list().chunked_by(|_, _| false).collect(), Ok(vec![vec![1, 2, 3]]));
+    assert_done(|| list().chunked_by(|_, _| true).collect(), Ok(vec![vec![1], vec![2], vec![3]]));
+    let too_big = |chunk: &[i32], a: &i32| chunk.iter().fold(0, |sum, n| sum + n) + a > 3;
+    assert_done(|| list().chunked_by(too_big).collect(), Ok(vec![vec![1, 2], vec![3]]));
+    let mut list = executor::spawn(err_list().chunked_by(|_, a| *a == 2));
+    assert_eq!(list.wait_stream().unwrap().unwrap(), vec![1]);
+    assert_eq!(list.wait_stream().unwrap().unwrap(), vec![2]);
+    assert_eq!(list.wait_stream().unwrap().unwrap_err(), 3);
+    assert!(list.wait_stream().is_none());
+}
+
 #[test]
 fn select() {
     let a = iter(vec![Ok::<_, u32>(1), Ok(2), Ok(3)]);
@@ -309,6 +383,27 @@ fn select() {
     assert_done(|| a.select(b).collect(), Ok(vec![1, 1, 2, 2, 3]));
 }
 
+#[test]
+fn select_biased() {
+    let a = iter(vec![Ok::<_, u32>(1), Ok(2), Ok(3)]);
+    let b = iter(vec![Ok(4), Ok(5), Ok(6)]);
+    assert_done(|| a.select_biased(b).collect(), Ok(vec![1, 2, 3, 4, 5, 6]));
+
+    // The second stream is only polled while the first isn't ready
+    let (mut tx, rx) = mpsc::unbounded::<u32>();
+    let b = iter(vec![Ok(4), Ok(5)]);
+    let mut merged = executor::spawn(rx.select_biased(b));
+    mpsc::UnboundedSender::send(&mut tx, 1).unwrap();
+    assert_eq!(merged.poll_stream(unpark_noop()), Ok(Async::Ready(Some(1))));
+    assert_eq!(merged.poll_stream(unpark_noop()), Ok(Async::Ready(Some(4))));
+    mpsc::UnboundedSender::send(&mut tx, 2).unwrap();
+    assert_eq!(merged.poll_stream(unpark_noop()), Ok(Async::Ready(Some(2))));
+    assert_eq!(merged.poll_stream(unpark_noop()), Ok(Async::Ready(Some(5))));
+    assert_eq!(merged.poll_stream(unpark_noop()), Ok(Async::NotReady));
+    drop(tx);
+    assert_eq!(merged.poll_stream(unpark_noop()), Ok(Async::Ready(None)));
+}
+
 #[test]
 fn forward() {
     let v = Vec::new();
@@ -321,3 +416,271 @@ fn forward() {
     assert_done(move || iter(vec![Ok(4), Ok(5)]).forward(v).map(|(_, s)| s),
                 Ok::<_, ()>(vec![0, 1, 2, 3, 4, 5]));
 }
+
+#[test]
+fn collect_into_sink_groups() {
+    let created = std::cell::Cell::new(0);
+    let items = iter(vec![Ok::<i32, ()>(1), Ok(2), Ok(3)]);
+    let groups = items.collect_into_sink_groups(|a| a % 2, |_| {
+        created.set(created.get() + 1);
+        Ok::<Vec<i32>, ()>(Vec::new())
+    });
+    let mut groups = groups.wait().unwrap();
+    assert_eq!(created.get(), 2);
+    assert_eq!(groups.remove(&1), Some(vec![1, 3]));
+    assert_eq!(groups.remove(&0), Some(vec![2]));
+
+    assert_done(|| {
+        iter(vec![Ok(1), Err(())])
+            .collect_into_sink_groups(|a| *a, |_| ok::<Vec<i32>, ()>(Vec::new()))
+            .map(|groups| groups.len())
+    }, Err(()));
+}
+
+#[test]
+fn collect_into_sink_groups_async_factory() {
+    let (tx, rx) = oneshot::channel::<Vec<i32>>();
+    let mut rx = Some(rx);
+    let items = iter(vec![Ok::<i32, ()>(1), Ok(2), Ok(3)]);
+    let mut groups = executor::spawn(items.collect_into_sink_groups(|_| (), move |_| {
+        rx.take().unwrap().map_err(|_| ())
+    }));
+    assert!(groups.poll_future(unpark_noop()).unwrap().is_not_ready());
+    tx.complete(vec![0]);
+    let groups = groups.wait_future().unwrap();
+    assert_eq!(groups[&()], [0, 1, 2, 3]);
+}
+
+#[test]
+fn collect_into_sink_groups_closes_idle_sinks() {
+    use std::cell::Cell;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::Duration;
+    use futures::executor::Unpark;
+
+    struct Woken(AtomicBool);
+
+    impl Unpark for Woken {
+        fn unpark(&self) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    let mock = MockClock::new();
+    let woken = Arc::new(Woken(AtomicBool::new(false)));
+    let created = Cell::new(0);
+    let (mut tx, rx) = mpsc::unbounded::<i32>();
+    let groups = rx.collect_into_sink_groups(|a| a % 2, |_| {
+        created.set(created.get() + 1);
+        Ok::<Vec<i32>, ()>(Vec::new())
+    }).close_idle_after(Duration::from_secs(10));
+    let mut groups = executor::spawn(Clocked(Arc::new(mock.clone()), groups));
+
+    mpsc::UnboundedSender::send(&mut tx, 1).unwrap();
+    assert!(groups.poll_future(woken.clone()).unwrap().is_not_ready());
+    mock.advance(Duration::from_secs(5));
+    mpsc::UnboundedSender::send(&mut tx, 2).unwrap();
+    assert!(groups.poll_future(woken.clone()).unwrap().is_not_ready());
+    assert_eq!(created.get(), 2);
+
+    // The sink for odd numbers goes idle first, and the timer wakes the task
+    // up to close it, so a new one is created for the next odd number.
+    woken.0.store(false, Ordering::SeqCst);
+    mock.advance(Duration::from_secs(5));
+    assert!(woken.0.load(Ordering::SeqCst));
+    assert!(groups.poll_future(woken.clone()).unwrap().is_not_ready());
+    mpsc::UnboundedSender::send(&mut tx, 3).unwrap();
+    assert!(groups.poll_future(woken.clone()).unwrap().is_not_ready());
+    assert_eq!(created.get(), 3);
+
+    drop(tx);
+    match groups.poll_future(woken.clone()) {
+        Ok(Async::Ready(groups)) => {
+            assert_eq!(groups[&1], [3]);
+            assert_eq!(groups[&0], [2]);
+        }
+        _ => panic!("groups weren't ready"),
+    }
+}
+
+#[test]
+fn spawned() {
+    struct ThreadPerRun;
+
+    impl Executor for ThreadPerRun {
+        fn execute(&self, r: Run) {
+            thread::Builder::new()
+                .name("executor".to_string())
+                .spawn(move || r.run())
+                .unwrap();
+        }
+    }
+
+    let s = iter(vec![Ok(1), Err(2), Ok(3)])
+        .map(|n| (n, thread::current().name() == Some("executor")))
+        .spawned(Arc::new(ThreadPerRun), 1);
+    let mut s = s.wait();
+    assert_eq!(s.next(), Some(Ok((1, true))));
+    assert_eq!(s.next(), Some(Err(2)));
+    assert_eq!(s.next(), Some(Ok((3, true))));
+    assert_eq!(s.next(), None);
+}
+
+#[test]
+fn timeout_map() {
+    use std::time::Duration;
+
+    // Items which are already available never produce a fallback
+    let quick = iter(vec![Ok::<i32, u32>(1), Ok(2), Err(3)])
+        .timeout_map(Duration::from_millis(10), || 0)
+        .then(Ok::<_, ()>)
+        .collect();
+    assert_eq!(quick.wait(), Ok(vec![Ok(1), Ok(2), Err(3)]));
+
+    // A slow item gets a single fallback, after which the real item follows
+    let (mut tx, rx) = mpsc::unbounded::<i32>();
+    mpsc::UnboundedSender::send(&mut tx, 1).unwrap();
+    let t = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(200));
+        mpsc::UnboundedSender::send(&mut tx, 2).unwrap();
+    });
+    let slow = rx.timeout_map(Duration::from_millis(20), || 0).take(3).collect();
+    assert_eq!(slow.wait(), Ok(vec![1, 0, 2]));
+    t.join().unwrap();
+}
+
+#[test]
+fn terminate_after_idle() {
+    use std::time::Duration;
+
+    // Items arriving within the idle period are passed through, after which
+    // the stream ends even though the sender is still alive
+    let (mut tx, rx) = mpsc::unbounded::<i32>();
+    mpsc::UnboundedSender::send(&mut tx, 1).unwrap();
+    let t = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(20));
+        mpsc::UnboundedSender::send(&mut tx, 2).unwrap();
+        thread::sleep(Duration::from_millis(500));
+        drop(tx);
+    });
+    let mut items = rx.terminate_after_idle(Duration::from_millis(200)).wait();
+    assert_eq!(items.next(), Some(Ok(1)));
+    assert_eq!(items.next(), Some(Ok(2)));
+    assert_eq!(items.next(), None);
+    assert_eq!(items.next(), None);
+    t.join().unwrap();
+
+    // A stream which ends on its own isn't affected
+    let quick = iter(vec![Ok::<i32, u32>(1), Err(2)])
+        .terminate_after_idle(Duration::from_millis(10))
+        .then(Ok::<_, ()>)
+        .collect();
+    assert_eq!(quick.wait(), Ok(vec![Ok(1), Err(2)]));
+}
+
+#[test]
+fn pipe() {
+    let (tx, rx) = futures::stream::pipe::<i32, u32>(1);
+    let tx2 = tx.clone();
+    let t = thread::spawn(move || {
+        let items = iter(vec![Ok::<i32, u32>(1), Err(2), Ok(3)])
+            .then(Ok::<_, mpsc::SendError<Result<i32, u32>>>);
+        assert!(items.forward(tx).wait().is_ok());
+        drop(tx2);
+    });
+    let received = rx.then(Ok::<_, ()>).collect();
+    assert_eq!(received.wait(), Ok(vec![Ok(1), Err(2), Ok(3)]));
+    t.join().unwrap();
+
+    // Nothing can be sent once the receiver is gone
+    let (tx, rx) = futures::stream::pipe::<i32, u32>(1);
+    drop(rx);
+    assert!(tx.send(Ok(1)).wait().is_err());
+}
+
+#[test]
+fn map_concurrent_keyed() {
+    let (a1_tx, a1_rx) = oneshot::channel::<i32>();
+    let (a2_tx, a2_rx) = oneshot::channel::<i32>();
+    let (b_tx, b_rx) = oneshot::channel::<i32>();
+    let mut rxs = [Some(a1_rx), Some(b_rx), Some(a2_rx)];
+    let started = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let started2 = started.clone();
+    let events = iter(vec![Ok::<_, oneshot::Canceled>(("a", 0)), Ok(("b", 1)), Ok(("a", 2))]);
+    let stream = events.map_concurrent_keyed(|e| e.0, 3, move |e| {
+        started2.lock().unwrap().push(e);
+        rxs[e.1].take().unwrap()
+    });
+    let mut task = executor::spawn(stream);
+
+    // The second element for "a" waits for the first one
+    assert!(task.poll_stream(unpark_noop()).unwrap().is_not_ready());
+    assert_eq!(*started.lock().unwrap(), [("a", 0), ("b", 1)]);
+
+    // Different keys run concurrently
+    b_tx.complete(2);
+    assert_eq!(task.poll_stream(unpark_noop()), Ok(Async::Ready(Some(2))));
+
+    // Completing the second future for "a" early doesn't let it overtake
+    a2_tx.complete(3);
+    assert!(task.poll_stream(unpark_noop()).unwrap().is_not_ready());
+    a1_tx.complete(1);
+    assert_eq!(task.poll_stream(unpark_noop()), Ok(Async::Ready(Some(1))));
+    assert_eq!(*started.lock().unwrap(), [("a", 0), ("b", 1), ("a", 2)]);
+    assert_eq!(task.poll_stream(unpark_noop()), Ok(Async::Ready(Some(3))));
+    assert_eq!(task.poll_stream(unpark_noop()), Ok(Async::Ready(None)));
+}
+
+#[test]
+fn map_concurrent_keyed_limit() {
+    // With a limit of 1 the second element isn't started until the first one
+    // finishes, even though it has a different key
+    let (tx, rx) = oneshot::channel::<i32>();
+    let mut rx = Some(rx);
+    let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let calls2 = calls.clone();
+    let stream = iter(vec![Ok::<_, oneshot::Canceled>(0), Ok(1)])
+        .map_concurrent_keyed(|e| *e, 1, move |e| {
+            calls2.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            match e {
+                0 => rx.take().unwrap().boxed(),
+                _ => ok(e).boxed(),
+            }
+        });
+    let mut task = executor::spawn(stream);
+    assert!(task.poll_stream(unpark_noop()).unwrap().is_not_ready());
+    assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 1);
+    tx.complete(0);
+    assert_eq!(task.wait_stream(), Some(Ok(0)));
+    assert_eq!(task.wait_stream(), Some(Ok(1)));
+    assert_eq!(task.wait_stream(), None);
+    assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 2);
+}
+
+#[test]
+fn prefetch() {
+    let (tx, rx) = mpsc::channel::<i32>(8);
+    let tx = tx.send(1).wait().unwrap().send(2).wait().unwrap();
+    let mut s = executor::spawn(rx.prefetch(4));
+
+    // Everything available is read ahead, up to the limit
+    assert_eq!(s.poll_stream(unpark_noop()), Ok(Async::Ready(Some(1))));
+    assert_eq!(s.get_ref().buffered(), 1);
+    let tx = tx.send(3).wait().unwrap();
+    assert_eq!(s.poll_stream(unpark_noop()), Ok(Async::Ready(Some(2))));
+    assert_eq!(s.get_ref().buffered(), 1);
+    assert_eq!(s.poll_stream(unpark_noop()), Ok(Async::Ready(Some(3))));
+    assert!(s.poll_stream(unpark_noop()).unwrap().is_not_ready());
+
+    let tx = (4..10).fold(tx, |tx, i| tx.send(i).wait().unwrap());
+    assert_eq!(s.poll_stream(unpark_noop()), Ok(Async::Ready(Some(4))));
+    assert_eq!(s.get_ref().buffered(), 4);
+    drop(tx);
+    assert_eq!(s.wait_stream(), Some(Ok(5)));
+    let rest = s.into_inner().collect().wait();
+    assert_eq!(rest, Ok(vec![6, 7, 8, 9]));
+
+    // Errors are kept in order
+    let s = iter(vec![Ok(1), Err(2), Ok(3)]).prefetch(2).then(Ok::<_, ()>);
+    assert_eq!(s.collect().wait(), Ok(vec![Ok::<i32, i32>(1), Err(2), Ok(3)]));
+}
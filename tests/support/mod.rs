@@ -1,14 +1,16 @@
 #![allow(dead_code)]
 
 use std::fmt;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::{Duration, Instant};
 
 use futures::{Future, IntoFuture, Async, Poll};
-use futures::future::FutureResult;
+use futures::clock::{self, Clock};
+use futures::future::{BoxFuture, FutureResult};
 use futures::stream::Stream;
 use futures::executor::{self, Unpark};
-use futures::task;
+use futures::task::{self, Task};
 
 pub fn f_ok(a: i32) -> FutureResult<i32, u32> { Ok(a).into_future() }
 pub fn f_err(a: u32) -> FutureResult<i32, u32> { Err(a).into_future() }
@@ -128,3 +130,85 @@ pub fn delay_future<F>(f: F) -> DelayFuture<F::Future>
     DelayFuture(f.into_future(), false)
 }
 
+
+/// A clock which only moves forward when it's advanced
+#[derive(Clone)]
+pub struct MockClock(Arc<Mutex<MockState>>);
+
+struct MockState {
+    now: Instant,
+    sleepers: Vec<(Instant, Task)>,
+}
+
+impl MockClock {
+    pub fn new() -> MockClock {
+        MockClock(Arc::new(Mutex::new(MockState {
+            now: Instant::now(),
+            sleepers: Vec::new(),
+        })))
+    }
+
+    pub fn advance(&self, dur: Duration) {
+        let expired = {
+            let mut state = self.0.lock().unwrap();
+            state.now += dur;
+            let now = state.now;
+            let (expired, sleepers) = state.sleepers.drain(..).partition(|s| s.0 <= now);
+            state.sleepers = sleepers;
+            expired
+        };
+        for (_, task) in expired {
+            task.unpark();
+        }
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        self.0.lock().unwrap().now
+    }
+
+    fn delay(&self, deadline: Instant) -> BoxFuture<(), ()> {
+        Box::new(MockDelay(self.clone(), deadline))
+    }
+}
+
+struct MockDelay(MockClock, Instant);
+
+impl Future for MockDelay {
+    type Item = ();
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<(), ()> {
+        let mut state = (self.0).0.lock().unwrap();
+        if state.now >= self.1 {
+            Ok(Async::Ready(()))
+        } else {
+            state.sleepers.push((self.1, task::park()));
+            Ok(Async::NotReady)
+        }
+    }
+}
+
+/// Polls a future or stream with a clock set for its task
+pub struct Clocked<T>(pub Arc<Clock>, pub T);
+
+impl<F: Future> Future for Clocked<F> {
+    type Item = F::Item;
+    type Error = F::Error;
+
+    fn poll(&mut self) -> Poll<F::Item, F::Error> {
+        let future = &mut self.1;
+        clock::scope(self.0.clone(), || future.poll())
+    }
+}
+
+impl<S: Stream> Stream for Clocked<S> {
+    type Item = S::Item;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Poll<Option<S::Item>, S::Error> {
+        let stream = &mut self.1;
+        clock::scope(self.0.clone(), || stream.poll())
+    }
+}
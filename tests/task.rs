@@ -0,0 +1,462 @@
+extern crate futures;
+
+use std::cell::Cell;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use futures::{Async, Future, Poll, Stream};
+use futures::executor::{self, Executor, LocalUnpark, PollHooks, Run, Unpark};
+use futures::future;
+use futures::sync::oneshot;
+use futures::task::{self, Task, EventSet, UnparkEvent};
+
+struct Counter(AtomicUsize);
+
+impl Unpark for Counter {
+    fn unpark(&self) {
+        self.0.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+fn park_counted() -> (Task, Arc<Counter>) {
+    let counter = Arc::new(Counter(AtomicUsize::new(0)));
+    let mut slot = None;
+    {
+        let mut spawn = executor::spawn(future::poll_fn(|| -> Poll<(), ()> {
+            slot = Some(task::park());
+            Ok(Async::Ready(()))
+        }));
+        spawn.poll_future(counter.clone()).unwrap();
+    }
+    (slot.unwrap(), counter)
+}
+
+#[test]
+fn raw_unpark_from_foreign_thread() {
+    let (task, counter) = park_counted();
+    let (ptr, unpark) = task.into_raw();
+    let ptr = ptr as usize;
+
+    thread::spawn(move || unsafe {
+        unpark(ptr as *mut ());
+    }).join().unwrap();
+
+    assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn raw_reclaim_without_unpark() {
+    let (task, counter) = park_counted();
+    let (ptr, _unpark) = task.into_raw();
+    let task = unsafe { Task::from_raw(ptr) };
+    assert_eq!(counter.0.load(Ordering::SeqCst), 0);
+    task.unpark();
+    assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+    drop(task);
+    assert!(Arc::try_unwrap(counter).is_ok());
+}
+
+struct Recorded(Mutex<Vec<usize>>);
+
+impl EventSet for Recorded {
+    fn insert(&self, id: usize) {
+        self.0.lock().unwrap().push(id);
+    }
+}
+
+#[test]
+fn with_unpark_events_registers_all() {
+    let set = Arc::new(Recorded(Mutex::new(Vec::new())));
+    let slot = Arc::new(Mutex::new(None));
+    {
+        let (set, slot) = (set.clone(), slot.clone());
+        let mut spawn = executor::spawn(future::poll_fn(move || -> Poll<(), ()> {
+            let events = (0..3).map(|i| UnparkEvent::new(set.clone(), i));
+            let outer = UnparkEvent::new(set.clone(), 10);
+            let task = task::with_unpark_event(outer, || {
+                task::with_unpark_events(events, task::park)
+            });
+            *slot.lock().unwrap() = Some(task);
+            Ok(Async::Ready(()))
+        }));
+        spawn.poll_future(Arc::new(Counter(AtomicUsize::new(0)))).unwrap();
+    }
+    slot.lock().unwrap().take().unwrap().unpark();
+    let mut seen = set.0.lock().unwrap().clone();
+    seen.sort();
+    assert_eq!(seen, [0, 1, 2, 10]);
+}
+
+#[test]
+fn many_unpark_events() {
+    let set = Arc::new(Recorded(Mutex::new(Vec::new())));
+    let slot = Arc::new(Mutex::new(None));
+    {
+        let (set, slot) = (set.clone(), slot.clone());
+        let mut spawn = executor::spawn(future::poll_fn(move || -> Poll<(), ()> {
+            let events = (0..100_000).map(|i| UnparkEvent::new(set.clone(), i));
+            let task = task::with_unpark_events(events, task::park);
+            *slot.lock().unwrap() = Some(task);
+            Ok(Async::Ready(()))
+        }));
+        spawn.poll_future(Arc::new(Counter(AtomicUsize::new(0)))).unwrap();
+    }
+    let task = slot.lock().unwrap().take().unwrap();
+    task.clone().unpark();
+    drop(task);
+    assert_eq!(set.0.lock().unwrap().len(), 100_000);
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Route {
+    Read(u64),
+    Write(u64),
+}
+
+struct Routes(Mutex<Vec<Route>>);
+
+impl EventSet<Route> for Routes {
+    fn insert(&self, route: Route) {
+        self.0.lock().unwrap().push(route);
+    }
+}
+
+#[test]
+fn unpark_events_with_custom_ids() {
+    let routes = Arc::new(Routes(Mutex::new(Vec::new())));
+    let ids = Arc::new(Recorded(Mutex::new(Vec::new())));
+    let slot = Arc::new(Mutex::new(None));
+    {
+        let (routes, ids, slot) = (routes.clone(), ids.clone(), slot.clone());
+        let mut spawn = executor::spawn(future::poll_fn(move || -> Poll<(), ()> {
+            let read = UnparkEvent::new(routes.clone(), Route::Read(1 << 40));
+            let write = UnparkEvent::new(routes.clone(), Route::Write(7));
+            let task = task::with_unpark_event(UnparkEvent::new(ids.clone(), 3), || {
+                task::with_unpark_events(vec![read, write], task::park)
+            });
+            *slot.lock().unwrap() = Some(task);
+            Ok(Async::Ready(()))
+        }));
+        spawn.poll_future(Arc::new(Counter(AtomicUsize::new(0)))).unwrap();
+    }
+    slot.lock().unwrap().take().unwrap().unpark();
+    let mut seen = routes.0.lock().unwrap().clone();
+    seen.sort_by_key(|route| match *route { Route::Read(n) | Route::Write(n) => n });
+    assert_eq!(seen, [Route::Write(7), Route::Read(1 << 40)]);
+    assert_eq!(*ids.0.lock().unwrap(), [3]);
+}
+
+#[test]
+fn redundant_unparks_are_coalesced() {
+    let first = Arc::new(Counter(AtomicUsize::new(0)));
+    let second = Arc::new(Counter(AtomicUsize::new(0)));
+    let slot = Arc::new(Mutex::new(Vec::new()));
+    let mut spawn = {
+        let slot = slot.clone();
+        executor::spawn(future::poll_fn(move || -> Poll<(), ()> {
+            slot.lock().unwrap().push(task::park());
+            Ok(Async::NotReady)
+        }))
+    };
+
+    spawn.poll_future(first.clone()).unwrap();
+    let old = slot.lock().unwrap().pop().unwrap();
+    old.unpark();
+    old.clone().unpark();
+    assert_eq!(first.0.load(Ordering::SeqCst), 1);
+
+    // A new poll needs a new notification, and a stale handle being unparked
+    // doesn't swallow unparks of the current one.
+    spawn.poll_future(second.clone()).unwrap();
+    let new = slot.lock().unwrap().pop().unwrap();
+    old.unpark();
+    new.unpark();
+    new.unpark();
+    assert_eq!(first.0.load(Ordering::SeqCst), 2);
+    assert_eq!(second.0.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn coalesced_unparks_still_trigger_events() {
+    let set = Arc::new(Recorded(Mutex::new(Vec::new())));
+    let counter = Arc::new(Counter(AtomicUsize::new(0)));
+    let slot = Arc::new(Mutex::new(Vec::new()));
+    let mut spawn = {
+        let (set, slot) = (set.clone(), slot.clone());
+        executor::spawn(future::poll_fn(move || -> Poll<(), ()> {
+            for i in 0..2 {
+                let event = UnparkEvent::new(set.clone(), i);
+                slot.lock().unwrap().push(task::with_unpark_event(event, task::park));
+            }
+            Ok(Async::NotReady)
+        }))
+    };
+    spawn.poll_future(counter.clone()).unwrap();
+    for task in slot.lock().unwrap().drain(..) {
+        task.unpark();
+    }
+    assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+    assert_eq!(*set.0.lock().unwrap(), [0, 1]);
+}
+
+struct Inline;
+
+impl Executor for Inline {
+    fn execute(&self, r: Run) {
+        r.run()
+    }
+}
+
+struct Profile(Mutex<Vec<(&'static str, usize, Option<bool>)>>);
+
+impl PollHooks for Profile {
+    fn on_poll_start(&self, task_id: usize) {
+        self.0.lock().unwrap().push(("start", task_id, None));
+    }
+
+    fn on_poll_end(&self, task_id: usize, _elapsed: Duration, result: &Poll<(), ()>) {
+        let ready = result.as_ref().map(|r| r.is_ready()).unwrap_or(true);
+        self.0.lock().unwrap().push(("end", task_id, Some(ready)));
+    }
+}
+
+#[test]
+fn poll_hooks() {
+    let (tx, rx) = oneshot::channel::<()>();
+    let profile = Arc::new(Profile(Mutex::new(Vec::new())));
+    let mut id = None;
+    let spawn = executor::spawn(rx.map_err(|_| ()));
+    spawn.execute_with_hooks(Arc::new(Inline), profile.clone());
+    tx.complete(());
+
+    let events = profile.0.lock().unwrap();
+    assert_eq!(events.len(), 4);
+    for (i, &(kind, task_id, ready)) in events.iter().enumerate() {
+        assert_eq!(*id.get_or_insert(task_id), task_id);
+        assert_eq!(kind, if i % 2 == 0 { "start" } else { "end" });
+        assert_eq!(ready, match i { 1 => Some(false), 3 => Some(true), _ => None });
+    }
+}
+
+struct Collect(Mutex<Vec<Run>>);
+
+impl Executor for Collect {
+    fn execute(&self, r: Run) {
+        self.0.lock().unwrap().push(r);
+    }
+}
+
+#[test]
+fn execute_batch_defaults_to_execute() {
+    let collect = Arc::new(Collect(Mutex::new(Vec::new())));
+    let order = Arc::new(Mutex::new(Vec::new()));
+    for i in 0..3 {
+        let order = order.clone();
+        let f = future::lazy(move || {
+            order.lock().unwrap().push(i);
+            Ok::<(), ()>(())
+        });
+        executor::spawn(f).execute(collect.clone());
+    }
+    let runs = collect.0.lock().unwrap().drain(..).collect();
+    Inline.execute_batch(runs);
+    assert_eq!(*order.lock().unwrap(), [0, 1, 2]);
+}
+
+#[test]
+fn current_id_matches_task() {
+    let ids = Arc::new(Mutex::new(Vec::new()));
+    let profile = Arc::new(Profile(Mutex::new(Vec::new())));
+    let spawn = {
+        let ids = ids.clone();
+        executor::spawn(future::poll_fn(move || -> Poll<(), ()> {
+            let set = Arc::new(Recorded(Mutex::new(Vec::new())));
+            let task = task::with_unpark_event(UnparkEvent::new(set, 0), task::park);
+            ids.lock().unwrap().push(task::current_id());
+            ids.lock().unwrap().push(task::park().id());
+            ids.lock().unwrap().push(task.id());
+            Ok(Async::Ready(()))
+        }))
+    };
+    spawn.execute_with_hooks(Arc::new(Inline), profile.clone());
+
+    let ids = ids.lock().unwrap();
+    let hook_id = profile.0.lock().unwrap()[0].1;
+    assert_eq!(*ids, [hook_id, hook_id, hook_id]);
+
+    let other = executor::spawn(future::poll_fn(|| -> Poll<usize, ()> {
+        Ok(Async::Ready(task::current_id()))
+    })).wait_future().unwrap();
+    assert!(other != hook_id);
+}
+
+#[test]
+fn yield_now_resubmits_task() {
+    let collect = Arc::new(Collect(Mutex::new(Vec::new())));
+    let polls = Arc::new(AtomicUsize::new(0));
+    let f = {
+        let polls = polls.clone();
+        let mut yields = task::yield_now::<()>();
+        future::poll_fn(move || {
+            polls.fetch_add(1, Ordering::SeqCst);
+            yields.poll()
+        })
+    };
+    executor::spawn(f).execute(collect.clone());
+
+    let run = collect.0.lock().unwrap().pop().unwrap();
+    run.run();
+    assert_eq!(polls.load(Ordering::SeqCst), 1);
+
+    let run = collect.0.lock().unwrap().pop().unwrap();
+    run.run();
+    assert_eq!(polls.load(Ordering::SeqCst), 2);
+    assert!(collect.0.lock().unwrap().is_empty());
+}
+
+#[test]
+fn busy_task_is_resubmitted() {
+    let collect = Arc::new(Collect(Mutex::new(Vec::new())));
+    let polls = Arc::new(AtomicUsize::new(0));
+    let f = {
+        let polls = polls.clone();
+        future::poll_fn(move || -> Poll<(), ()> {
+            polls.fetch_add(1, Ordering::SeqCst);
+            task::park().unpark();
+            Ok(Async::NotReady)
+        })
+    };
+    executor::spawn(f).execute(collect.clone());
+
+    let run = collect.0.lock().unwrap().pop().unwrap();
+    run.run();
+    let polled = polls.load(Ordering::SeqCst);
+    assert!(polled > 1);
+    assert_eq!(collect.0.lock().unwrap().len(), 1);
+
+    let run = collect.0.lock().unwrap().pop().unwrap();
+    run.run();
+    assert_eq!(polls.load(Ordering::SeqCst), polled * 2);
+}
+
+struct LocalCounter(Cell<usize>);
+
+impl LocalUnpark for LocalCounter {
+    fn unpark(&self) {
+        self.0.set(self.0.get() + 1);
+    }
+}
+
+#[test]
+fn park_local() {
+    let local = Rc::new(LocalCounter(Cell::new(0)));
+    let counter = Arc::new(Counter(AtomicUsize::new(0)));
+    let mut spawn = executor::spawn(future::poll_fn(|| -> Poll<(), ()> {
+        task::park_local().unpark();
+        task::park().unpark();
+
+        // Tasks polled from within this one aren't local.
+        let mut inner = executor::spawn(future::poll_fn(|| -> Poll<(), ()> {
+            task::park_local().unpark();
+            Ok(Async::NotReady)
+        }));
+        inner.poll_future(Arc::new(Counter(AtomicUsize::new(0)))).unwrap();
+        task::park_local().unpark();
+        Ok(Async::NotReady)
+    }));
+    spawn.poll_future_local(counter.clone(), local.clone()).unwrap();
+    assert_eq!(local.0.get(), 2);
+    assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+
+    // Outside of a local poll, handles fall back to the task's `Unpark`.
+    spawn.poll_future(counter.clone()).unwrap();
+    assert_eq!(local.0.get(), 2);
+    assert_eq!(counter.0.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn yield_every_resubmits_task() {
+    let collect = Arc::new(Collect(Mutex::new(Vec::new())));
+    let items = Arc::new(Mutex::new(Vec::new()));
+    let f = {
+        let items = items.clone();
+        let numbers = futures::stream::iter((0..5).map(Ok::<u32, ()>));
+        future::yield_every(2, numbers).for_each(move |n| {
+            items.lock().unwrap().push(n);
+            Ok(())
+        })
+    };
+    executor::spawn(f).execute(collect.clone());
+
+    let mut runs = 0;
+    loop {
+        let run = match collect.0.lock().unwrap().pop() {
+            Some(run) => run,
+            None => break,
+        };
+        runs += 1;
+        let seen = items.lock().unwrap().len();
+        run.run();
+        assert!(items.lock().unwrap().len() - seen <= 2);
+    }
+    assert_eq!(runs, 3);
+    assert_eq!(*items.lock().unwrap(), [0, 1, 2, 3, 4]);
+}
+
+// Completes once it has been unparked, using only its explicit context.
+struct Explicit {
+    parked: Option<Task>,
+}
+
+impl task::ContextFuture for Explicit {
+    type Item = usize;
+    type Error = ();
+
+    fn poll_context(&mut self, cx: &mut task::TaskContext) -> Poll<usize, ()> {
+        match self.parked.take() {
+            Some(_) => Ok(Async::Ready(cx.id())),
+            None => {
+                let task = cx.park();
+                task.unpark();
+                self.parked = Some(task);
+                Ok(Async::NotReady)
+            }
+        }
+    }
+}
+
+#[test]
+fn poll_context() {
+    let counter = Arc::new(Counter(AtomicUsize::new(0)));
+    let mut spawn = executor::spawn(Explicit { parked: None });
+    assert_eq!(spawn.poll_context(counter.clone()), Ok(Async::NotReady));
+    assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+    let id = match spawn.poll_context(counter.clone()) {
+        Ok(Async::Ready(id)) => id,
+        res => panic!("unexpected result: {:?}", res),
+    };
+
+    // Implicit futures are polled by entering the context.
+    let mut spawn = executor::spawn(future::lazy(|| Ok::<_, ()>(task::current_id())));
+    match spawn.poll_context(counter) {
+        Ok(Async::Ready(other)) => assert!(other != id),
+        res => panic!("unexpected result: {:?}", res),
+    }
+}
+
+#[test]
+fn implicit_context() {
+    let f = task::implicit(Explicit { parked: None }).map(|id| {
+        assert_eq!(id, task::current_id());
+        id
+    });
+    assert!(future::lazy(|| f).wait().is_ok());
+
+    future::lazy(|| {
+        assert_eq!(task::with_context(|cx| cx.id()), task::current_id());
+        Ok::<_, ()>(())
+    }).wait().unwrap();
+}
@@ -0,0 +1,54 @@
+#![cfg(not(feature = "use_std"))]
+
+extern crate futures;
+
+use std::cell::Cell;
+use std::ptr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+
+use futures::Async;
+use futures::executor::{self, Unpark};
+use futures::future;
+use futures::task;
+
+thread_local!(static CURRENT: Cell<*mut u8> = Cell::new(ptr::null_mut()));
+
+static SETS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+fn get() -> *mut u8 {
+    CURRENT.with(|c| c.get())
+}
+
+fn set(ptr: *mut u8) {
+    SETS.fetch_add(1, Ordering::SeqCst);
+    CURRENT.with(|c| c.set(ptr))
+}
+
+struct Noop;
+
+impl Unpark for Noop {
+    fn unpark(&self) {}
+}
+
+fn current_id() -> usize {
+    let f = future::lazy(|| Ok::<_, ()>(task::current_id()));
+    match executor::spawn(f).poll_future(Arc::new(Noop)) {
+        Ok(Async::Ready(id)) => id,
+        _ => panic!("lazy future didn't complete"),
+    }
+}
+
+// This is the only test in this file, as registering storage for the current
+// task affects every task polled by the process from then on.
+#[test]
+fn init() {
+    assert!(task::init(get, set));
+    assert!(!task::init(get, set));
+
+    let sets = SETS.load(Ordering::SeqCst);
+    let id = current_id();
+    assert!(SETS.load(Ordering::SeqCst) > sets);
+    assert!(get().is_null());
+    assert!(current_id() != id);
+}
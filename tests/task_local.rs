@@ -0,0 +1,368 @@
+#[macro_use]
+extern crate futures;
+
+use std::cell::Cell;
+use std::panic;
+use std::sync::{Arc, Mutex};
+
+use futures::executor;
+use futures::future::{self, Future};
+use futures::task::{self, LocalMap};
+
+task_local!(static COUNT: Cell<u32> = Cell::new(0));
+
+#[test]
+fn with_initializes_per_task() {
+    future::lazy(|| {
+        COUNT.with(|c| c.set(c.get() + 1));
+        COUNT.with(|c| assert_eq!(c.get(), 1));
+        Ok::<(), ()>(())
+    }).wait().unwrap();
+
+    future::lazy(|| {
+        COUNT.with(|c| assert_eq!(c.get(), 0));
+        Ok::<(), ()>(())
+    }).wait().unwrap();
+}
+
+#[test]
+fn remove_and_replace() {
+    future::lazy(|| {
+        assert!(COUNT.remove().is_none());
+
+        COUNT.with(|c| c.set(3));
+        assert_eq!(COUNT.replace(Cell::new(4)).map(|c| c.get()), Some(3));
+        COUNT.with(|c| assert_eq!(c.get(), 4));
+
+        assert_eq!(COUNT.remove().map(|c| c.get()), Some(4));
+        assert!(COUNT.remove().is_none());
+
+        // accessing the key again re-runs the initializer
+        COUNT.with(|c| assert_eq!(c.get(), 0));
+        Ok::<(), ()>(())
+    }).wait().unwrap();
+}
+
+#[test]
+fn remove_while_borrowed_panics() {
+    let res = panic::catch_unwind(|| {
+        future::lazy(|| {
+            COUNT.with(|_| {
+                COUNT.remove();
+            });
+            Ok::<(), ()>(())
+        }).wait()
+    });
+    assert!(res.is_err());
+}
+
+#[test]
+fn replace_while_borrowed_panics() {
+    future::lazy(|| {
+        COUNT.with(|c| {
+            c.set(1);
+            let res = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+                COUNT.replace(Cell::new(2));
+            }));
+            assert!(res.is_err());
+            // the borrowed value is still alive and in place
+            assert_eq!(c.get(), 1);
+        });
+        COUNT.with(|c| assert_eq!(c.get(), 1));
+        Ok::<(), ()>(())
+    }).wait().unwrap();
+}
+
+task_local!(static NAMES: Vec<&'static str> = vec!["a"]);
+
+#[test]
+fn with_mut_get_and_set() {
+    future::lazy(|| {
+        NAMES.with_mut(|names| names.push("b"));
+        assert_eq!(NAMES.get(), ["a", "b"]);
+
+        NAMES.set(vec!["c"]);
+        NAMES.with(|names| assert_eq!(*names, ["c"]));
+        Ok::<(), ()>(())
+    }).wait().unwrap();
+
+    // `set` doesn't run the initializer for a fresh task
+    future::lazy(|| {
+        NAMES.set(Vec::new());
+        assert!(NAMES.get().is_empty());
+        Ok::<(), ()>(())
+    }).wait().unwrap();
+}
+
+#[test]
+fn with_mut_while_borrowed_panics() {
+    let res = panic::catch_unwind(|| {
+        future::lazy(|| {
+            NAMES.with(|_| NAMES.with_mut(|_| ()));
+            Ok::<(), ()>(())
+        }).wait()
+    });
+    assert!(res.is_err());
+
+    let res = panic::catch_unwind(|| {
+        future::lazy(|| {
+            NAMES.with_mut(|_| NAMES.get());
+            Ok::<(), ()>(())
+        }).wait()
+    });
+    assert!(res.is_err());
+
+    // borrows are released once the closures return
+    future::lazy(|| {
+        NAMES.with(|_| ());
+        NAMES.with_mut(|_| ());
+        NAMES.with(|_| NAMES.with(|_| ()));
+        Ok::<(), ()>(())
+    }).wait().unwrap();
+}
+
+struct Noisy(&'static str, Arc<Mutex<Vec<&'static str>>>);
+
+impl Drop for Noisy {
+    fn drop(&mut self) {
+        self.1.lock().unwrap().push(self.0);
+    }
+}
+
+task_local!(static FIRST: Mutex<Option<Noisy>> = Mutex::new(None));
+task_local!(static SECOND: Mutex<Option<Noisy>> = Mutex::new(None));
+task_local!(static THIRD: Mutex<Option<Noisy>> = Mutex::new(None));
+
+#[test]
+fn destroyed_in_reverse_insertion_order() {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let log2 = log.clone();
+    future::lazy(move || {
+        SECOND.with(|s| *s.lock().unwrap() = Some(Noisy("second", log2.clone())));
+        FIRST.with(|s| *s.lock().unwrap() = Some(Noisy("first", log2.clone())));
+        THIRD.with(|s| *s.lock().unwrap() = Some(Noisy("third", log2.clone())));
+
+        // replacing a value moves it to the back of the line
+        SECOND.replace(Mutex::new(Some(Noisy("second again", log2.clone()))));
+        Ok::<(), ()>(())
+    }).wait().unwrap();
+
+    assert_eq!(*log.lock().unwrap(),
+               ["second", "second again", "third", "first"]);
+}
+
+task_local!(static REQUEST_ID: Cell<Option<u32>> = Cell::new(None));
+
+#[test]
+fn scope_overrides_and_restores() {
+    future::lazy(|| {
+        REQUEST_ID.with(|id| id.set(Some(1)));
+
+        let seen = REQUEST_ID.scope(Cell::new(Some(2)), || {
+            let inner = REQUEST_ID.scope(Cell::new(None), || {
+                REQUEST_ID.with(|id| id.get())
+            });
+            assert_eq!(inner, None);
+            REQUEST_ID.with(|id| id.get())
+        });
+        assert_eq!(seen, Some(2));
+        REQUEST_ID.with(|id| assert_eq!(id.get(), Some(1)));
+        Ok::<(), ()>(())
+    }).wait().unwrap();
+}
+
+#[test]
+fn scope_without_previous_value() {
+    future::lazy(|| {
+        REQUEST_ID.scope(Cell::new(Some(3)), || {
+            REQUEST_ID.with(|id| assert_eq!(id.get(), Some(3)));
+        });
+        assert!(REQUEST_ID.remove().is_none());
+        Ok::<(), ()>(())
+    }).wait().unwrap();
+}
+
+#[test]
+fn scope_restores_on_panic() {
+    future::lazy(|| {
+        REQUEST_ID.with(|id| id.set(Some(1)));
+        let res = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            REQUEST_ID.scope(Cell::new(Some(2)), || panic!())
+        }));
+        assert!(res.is_err());
+        REQUEST_ID.with(|id| assert_eq!(id.get(), Some(1)));
+        Ok::<(), ()>(())
+    }).wait().unwrap();
+}
+
+#[test]
+fn spawn_with_local_data() {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let mut locals = LocalMap::new();
+    locals.insert(&REQUEST_ID, Cell::new(Some(7)))
+          .insert(&FIRST, Mutex::new(Some(Noisy("first", log.clone()))))
+          .insert(&SECOND, Mutex::new(Some(Noisy("second", log.clone()))));
+
+    let mut spawn = executor::spawn(future::lazy(|| {
+        REQUEST_ID.with(|id| assert_eq!(id.get(), Some(7)));
+        COUNT.with(|c| assert_eq!(c.get(), 0));
+        Ok::<(), ()>(())
+    })).with_local_data(locals);
+    spawn.wait_future().unwrap();
+    assert!(log.lock().unwrap().is_empty());
+
+    // Installing more data replaces existing values for the same key.
+    let mut locals = LocalMap::new();
+    locals.insert(&FIRST, Mutex::new(Some(Noisy("first again", log.clone()))));
+    let spawn = spawn.with_local_data(locals);
+    assert_eq!(*log.lock().unwrap(), ["first"]);
+
+    drop(spawn);
+    assert_eq!(*log.lock().unwrap(), ["first", "first again", "second"]);
+}
+
+#[test]
+fn move_local_data_between_tasks() {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let log2 = log.clone();
+    let mut first = executor::spawn(future::lazy(move || {
+        SECOND.with(|s| *s.lock().unwrap() = Some(Noisy("second", log2.clone())));
+        FIRST.with(|s| *s.lock().unwrap() = Some(Noisy("first", log2.clone())));
+        REQUEST_ID.with(|id| id.set(Some(9)));
+        Ok::<(), ()>(())
+    }));
+    first.wait_future().unwrap();
+    let locals = first.take_local_data();
+    drop(first);
+    assert!(log.lock().unwrap().is_empty());
+
+    let mut second = executor::spawn(future::lazy(|| {
+        REQUEST_ID.with(|id| assert_eq!(id.get(), Some(9)));
+        Ok::<(), ()>(())
+    })).with_local_data(locals);
+    second.wait_future().unwrap();
+    drop(second);
+    assert_eq!(*log.lock().unwrap(), ["first", "second"]);
+}
+
+#[test]
+fn with_explicit_context() {
+    future::lazy(|| {
+        COUNT.with(|c| c.set(3));
+        task::with_context(|cx| {
+            COUNT.with_context(cx, |c| assert_eq!(c.get(), 3));
+        });
+        Ok::<(), ()>(())
+    }).wait().unwrap();
+}
+
+task_local!(static TRACE_ID: u64 = 0);
+task_local!(static DEADLINE: Cell<Option<u32>> = Cell::new(None));
+
+#[test]
+fn spawn_inheriting_clones_data() {
+    let (parent, mut child) = future::lazy(|| {
+        TRACE_ID.replace(7);
+        DEADLINE.with(|d| d.set(Some(100)));
+        let child = executor::spawn_inheriting(future::lazy(|| {
+            Ok::<_, ()>((TRACE_ID.with(|id| *id),
+                         DEADLINE.with(|d| d.get()),
+                         COUNT.with(|c| c.get())))
+        }), &[&TRACE_ID, &DEADLINE, &COUNT]);
+
+        // The child's data is a snapshot, so later changes aren't seen.
+        DEADLINE.with(|d| d.set(Some(50)));
+        Ok::<_, ()>((DEADLINE.with(|d| d.get()), child))
+    }).wait().unwrap();
+
+    assert_eq!(parent, Some(50));
+    // `COUNT` was never initialized in the parent, so the child starts with
+    // its initial value.
+    assert_eq!(child.wait_future(), Ok((7, Some(100), 0)));
+}
+
+static FLUSHED: Mutex<Vec<u32>> = Mutex::new(Vec::new());
+
+fn flush(buffer: Vec<u32>) {
+    FLUSHED.lock().unwrap().extend(buffer);
+}
+
+task_local!(static BUFFER: Vec<u32> = Vec::new(); evict = flush);
+
+#[test]
+fn evict_on_task_drop() {
+    future::lazy(|| {
+        BUFFER.with_mut(|b| b.push(1));
+        assert_eq!(BUFFER.replace(vec![2, 3]), Some(vec![1]));
+        BUFFER.with_mut(|b| b.push(4));
+        Ok::<(), ()>(())
+    }).wait().unwrap();
+    // Values handed back by `replace` aren't evicted.
+    assert_eq!(*FLUSHED.lock().unwrap(), [2, 3, 4]);
+
+    let mut locals = LocalMap::new();
+    locals.insert(&BUFFER, vec![5]);
+    drop(locals);
+    assert_eq!(*FLUSHED.lock().unwrap(), [2, 3, 4, 5]);
+
+    let mut spawn = executor::spawn(future::lazy(|| {
+        BUFFER.with_mut(|b| b.push(6));
+        Ok::<(), ()>(())
+    }));
+    spawn.wait_future().unwrap();
+    let locals = spawn.take_local_data();
+    drop(spawn);
+    assert_eq!(*FLUSHED.lock().unwrap(), [2, 3, 4, 5]);
+
+    let mut spawn = executor::spawn(future::lazy(|| {
+        BUFFER.with(|b| assert_eq!(*b, [6]));
+        Ok::<(), ()>(())
+    })).with_local_data(locals);
+    spawn.wait_future().unwrap();
+    drop(spawn);
+    assert_eq!(*FLUSHED.lock().unwrap(), [2, 3, 4, 5, 6]);
+}
+
+task_local!(static SPAN: Cell<u32> = Cell::new(0));
+
+#[test]
+fn with_locals_installs_around_polls() {
+    let (tx, rx) = futures::sync::oneshot::channel::<()>();
+    let mut locals = LocalMap::new();
+    locals.insert(&SPAN, Cell::new(1));
+    let inner = future::lazy(|| {
+        SPAN.with(|s| {
+            assert_eq!(s.get(), 1);
+            s.set(2);
+        });
+        rx
+    }).map(|()| SPAN.with(|s| s.get())).map_err(|_| ());
+
+    let mut spawn = executor::spawn(future::lazy(move || {
+        SPAN.with(|s| s.set(10));
+        let mut inner = inner.with_locals(locals);
+        assert!(inner.poll().unwrap().is_not_ready());
+        // The task's own value is restored after each poll
+        SPAN.with(|s| assert_eq!(s.get(), 10));
+        Ok::<_, ()>(inner)
+    }));
+    let inner = spawn.wait_future().unwrap();
+
+    // Changes made during one poll are seen by the next, from another task
+    tx.complete(());
+    assert_eq!(inner.wait(), Ok(2));
+}
+
+#[test]
+fn with_locals_restores_on_panic() {
+    let mut locals = LocalMap::new();
+    locals.insert(&SPAN, Cell::new(1));
+    let f = future::lazy(|| -> Result<(), ()> { panic!("boom") }).with_locals(locals);
+    future::lazy(move || {
+        SPAN.with(|s| s.set(5));
+        let mut f = panic::AssertUnwindSafe(f);
+        assert!(panic::catch_unwind(move || f.poll()).is_err());
+        SPAN.with(|s| assert_eq!(s.get(), 5));
+        Ok::<(), ()>(())
+    }).wait().unwrap();
+}
@@ -0,0 +1,58 @@
+extern crate futures;
+
+use futures::{Async, Future};
+use futures::future;
+use futures::task::{TaskLocalCell, TaskLocalCellError};
+
+#[test]
+fn borrows_are_checked() {
+    future::lazy(|| {
+        let cell = TaskLocalCell::new(1);
+        {
+            let a = cell.borrow();
+            let b = cell.try_borrow().unwrap();
+            assert_eq!(*a + *b, 2);
+            assert_eq!(cell.try_borrow_mut().err(), Some(TaskLocalCellError::Borrowed));
+        }
+        {
+            let mut a = cell.borrow_mut();
+            *a += 1;
+            assert_eq!(cell.try_borrow().err(), Some(TaskLocalCellError::Borrowed));
+            assert_eq!(cell.clone().try_borrow_mut().err(), Some(TaskLocalCellError::Borrowed));
+        }
+        assert!(!cell.is_borrowed());
+        assert_eq!(*cell.borrow(), 2);
+        Ok::<(), ()>(())
+    }).wait().unwrap();
+}
+
+#[test]
+fn wrong_task_or_no_task() {
+    let cell = future::lazy(|| Ok::<_, ()>(TaskLocalCell::new(1))).wait().unwrap();
+    assert_eq!(cell.try_borrow().err(), Some(TaskLocalCellError::NoTask));
+
+    let res = future::lazy(|| Ok::<_, ()>(cell.try_borrow_mut().err())).wait().unwrap();
+    assert_eq!(res, Some(TaskLocalCellError::WrongTask));
+}
+
+#[test]
+fn released_waits_for_borrows() {
+    future::lazy(|| {
+        let cell = TaskLocalCell::new(Vec::new());
+
+        // One future holds a borrow across polls while another waits for it.
+        let mut guard = Some(cell.borrow_mut());
+        let waiter = cell.clone();
+        let mut released = cell.released().map(move |()| {
+            waiter.borrow_mut().push(2);
+        });
+
+        assert_eq!(released.poll(), Ok(Async::NotReady));
+        guard.as_mut().unwrap().push(1);
+        drop(guard.take());
+        assert_eq!(released.poll(), Ok(Async::Ready(())));
+
+        assert_eq!(*cell.borrow(), [1, 2]);
+        Ok::<(), TaskLocalCellError>(())
+    }).wait().unwrap();
+}
@@ -0,0 +1,66 @@
+#![cfg(feature = "with-deprecated")]
+#![allow(deprecated)]
+
+extern crate futures;
+
+use std::cell::Cell;
+use std::panic::{self, AssertUnwindSafe};
+
+use futures::future::{self, Future};
+use futures::task::{TaskRc, TaskRcError};
+
+#[test]
+fn try_with_on_owning_task() {
+    future::lazy(|| {
+        let rc = TaskRc::new(Cell::new(1));
+        rc.with(|c| c.set(2));
+        assert_eq!(rc.try_with(|c| c.get()), Ok(2));
+        Ok::<(), ()>(())
+    }).wait().unwrap();
+}
+
+#[test]
+fn try_with_wrong_task_or_no_task() {
+    let rc = future::lazy(|| Ok::<_, ()>(TaskRc::new(1))).wait().unwrap();
+    assert_eq!(rc.try_with(|n| *n), Err(TaskRcError::NoTask));
+
+    let res = future::lazy(|| Ok::<_, ()>(rc.try_with(|n| *n))).wait().unwrap();
+    assert_eq!(res, Err(TaskRcError::WrongTask));
+}
+
+#[test]
+fn panic_poisons() {
+    future::lazy(|| {
+        let rc = TaskRc::new(Cell::new(1));
+        assert!(!rc.is_poisoned());
+        let res = panic::catch_unwind(AssertUnwindSafe(|| {
+            rc.with(|c| {
+                c.set(2);
+                panic!("half-way through an update");
+            })
+        }));
+        assert!(res.is_err());
+        assert!(rc.is_poisoned());
+        assert_eq!(rc.clone().try_with(|c| c.get()), Err(TaskRcError::Poisoned));
+
+        // `with` still provides access to poisoned data.
+        assert_eq!(rc.with(|c| c.get()), 2);
+        Ok::<(), ()>(())
+    }).wait().unwrap();
+}
+
+#[test]
+fn get_mut_and_try_unwrap() {
+    let mut rc = future::lazy(|| Ok::<_, ()>(TaskRc::new(vec![1, 2]))).wait().unwrap();
+    let other = rc.clone();
+    assert!(rc.get_mut().is_none());
+    let rc = match TaskRc::try_unwrap(rc) {
+        Ok(_) => panic!("unwrapped a shared TaskRc"),
+        Err(rc) => rc,
+    };
+    drop(other);
+
+    let mut rc = rc;
+    rc.get_mut().unwrap().push(3);
+    assert_eq!(TaskRc::try_unwrap(rc).ok().unwrap(), [1, 2, 3]);
+}
@@ -0,0 +1,144 @@
+extern crate futures;
+
+use std::cell::Cell;
+use std::panic;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use futures::{Async, Future, Sink, Stream};
+use futures::executor::{self, Executor, Run};
+use futures::future;
+use futures::stream;
+use futures::sync::{mpsc, oneshot};
+
+#[test]
+fn wait_ignores_spurious_wakeups() {
+    let (tx, mut rx) = oneshot::channel::<u32>();
+    let polls = Cell::new(0);
+    let waiter = thread::current();
+    let t = thread::spawn(move || {
+        for _ in 0..10 {
+            waiter.unpark();
+            thread::sleep(Duration::from_millis(1));
+        }
+        tx.complete(1);
+    });
+    let res = future::poll_fn(|| {
+        polls.set(polls.get() + 1);
+        rx.poll()
+    }).wait();
+    t.join().unwrap();
+    assert_eq!(res.unwrap(), 1);
+    assert_eq!(polls.get(), 2);
+}
+
+#[test]
+fn wait_future_timeout() {
+    let (tx, rx) = oneshot::channel::<u32>();
+    let mut spawn = executor::spawn(rx);
+
+    let start = Instant::now();
+    assert_eq!(spawn.wait_future_timeout(Duration::from_millis(20)), Ok(Async::NotReady));
+    assert!(start.elapsed() >= Duration::from_millis(20));
+
+    // An expired deadline still polls the future once.
+    tx.complete(2);
+    assert_eq!(spawn.wait_future_deadline(start), Ok(Async::Ready(2)));
+}
+
+#[test]
+fn wait_future_timeout_completes() {
+    let (tx, rx) = oneshot::channel::<u32>();
+    let t = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(10));
+        tx.complete(3);
+    });
+    let mut spawn = executor::spawn(rx);
+    assert_eq!(spawn.wait_future_timeout(Duration::from_secs(60)), Ok(Async::Ready(3)));
+    t.join().unwrap();
+}
+
+#[test]
+fn wait_stream_timeout() {
+    let (tx, rx) = mpsc::channel::<u32>(1);
+    let mut spawn = executor::spawn(rx);
+    assert_eq!(spawn.wait_stream_timeout(Duration::from_millis(10)), Ok(Async::NotReady));
+
+    let tx = tx.send(4).wait().unwrap();
+    assert_eq!(spawn.wait_stream_timeout(Duration::from_millis(10)), Ok(Async::Ready(Some(4))));
+    drop(tx);
+    assert_eq!(spawn.wait_stream_timeout(Duration::from_millis(10)), Ok(Async::Ready(None)));
+}
+
+#[test]
+fn wait_woken_through_earlier_handle() {
+    let (tx1, rx1) = oneshot::channel::<u32>();
+    let (tx2, rx2) = oneshot::channel::<u32>();
+    let mut stream = stream::futures_unordered(vec![rx1, rx2]).wait();
+    let t = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(20));
+        tx1.complete(1);
+        thread::sleep(Duration::from_millis(20));
+        tx2.complete(2);
+    });
+    // Futures in a `FuturesUnordered` are only polled again once notified,
+    // so the second receiver is completed through the task handle it got
+    // while waiting for the first element.
+    assert_eq!(stream.next(), Some(Ok(1)));
+    assert_eq!(stream.next(), Some(Ok(2)));
+    t.join().unwrap();
+}
+
+struct Inline;
+
+impl Executor for Inline {
+    fn execute(&self, r: Run) {
+        r.run()
+    }
+}
+
+#[test]
+fn wait_within_executor_panics() {
+    let (tx, rx) = oneshot::channel();
+    let f = future::lazy(move || {
+        let res = panic::catch_unwind(|| future::ok::<(), ()>(()).wait());
+        tx.complete(res.is_err());
+        Ok(())
+    });
+    executor::spawn(f).execute(Arc::new(Inline));
+    assert_eq!(rx.wait(), Ok(true));
+}
+
+#[test]
+fn enter_guards_nest() {
+    {
+        let _outer = executor::enter();
+        {
+            let _inner = executor::enter();
+        }
+        assert!(panic::catch_unwind(|| future::ok::<(), ()>(()).wait()).is_err());
+    }
+    assert_eq!(future::ok::<u32, ()>(1).wait(), Ok(1));
+}
+
+#[test]
+fn block_on() {
+    let (tx, rx) = oneshot::channel();
+    let t = thread::spawn(move || tx.complete(1));
+    assert_eq!(executor::block_on(rx.map_err(|_| ())), Ok(1));
+    t.join().unwrap();
+
+    assert_eq!(executor::block_on(future::err::<(), u32>(2)), Err(2));
+}
+
+#[test]
+fn block_on_nested_panics() {
+    let res = panic::catch_unwind(|| {
+        executor::block_on(future::lazy(|| executor::block_on(future::ok::<(), ()>(()))))
+    });
+    assert!(res.is_err());
+
+    // The guard is released while unwinding, so blocking works again.
+    assert_eq!(executor::block_on(future::ok::<u32, ()>(3)), Ok(3));
+}
@@ -0,0 +1,122 @@
+extern crate futures;
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use futures::{Future, Async};
+use futures::executor::{self, Executor, Run};
+use futures::future::{self, lazy};
+use futures::sync::oneshot;
+
+mod support;
+use support::*;
+
+struct ThreadPerRun;
+
+impl Executor for ThreadPerRun {
+    fn execute(&self, r: Run) {
+        thread::Builder::new()
+            .name("executor".to_string())
+            .spawn(move || r.run())
+            .unwrap();
+    }
+}
+
+fn on_executor() -> bool {
+    thread::current().name() == Some("executor")
+}
+
+struct Queue(Mutex<Vec<Run>>);
+
+impl Queue {
+    fn run_all(&self) {
+        let runs = ::std::mem::replace(&mut *self.0.lock().unwrap(), Vec::new());
+        for run in runs {
+            run.run();
+        }
+    }
+}
+
+impl Executor for Queue {
+    fn execute(&self, r: Run) {
+        self.0.lock().unwrap().push(r);
+    }
+}
+
+#[test]
+fn runs_on_executor() {
+    let f = lazy(|| Ok::<_, u32>(on_executor()))
+        .with_executor(Arc::new(ThreadPerRun));
+    assert!(f.wait().unwrap());
+
+    let f = future::err::<(), u32>(3).with_executor(Arc::new(ThreadPerRun));
+    assert_eq!(f.wait(), Err(3));
+}
+
+#[test]
+fn spawned_on_first_poll() {
+    let queue = Arc::new(Queue(Mutex::new(Vec::new())));
+    let (tx, rx) = oneshot::channel::<u32>();
+    let mut f = executor::spawn(rx.with_executor(queue.clone()));
+    assert_eq!(queue.0.lock().unwrap().len(), 0);
+
+    assert!(f.poll_future(unpark_noop()).unwrap().is_not_ready());
+    queue.run_all();
+    assert!(f.poll_future(unpark_noop()).unwrap().is_not_ready());
+
+    tx.complete(5);
+    queue.run_all();
+    assert_eq!(f.poll_future(unpark_noop()), Ok(Async::Ready(5)));
+}
+
+#[test]
+fn drop_cancels() {
+    let queue = Arc::new(Queue(Mutex::new(Vec::new())));
+    let (tx, rx) = oneshot::channel::<u32>();
+    let mut tx = Some(tx);
+    {
+        let mut f = executor::spawn(rx.with_executor(queue.clone()));
+        assert!(f.poll_future(unpark_noop()).unwrap().is_not_ready());
+        queue.run_all();
+    }
+    queue.run_all();
+    assert!(tx.as_mut().unwrap().poll_cancel().unwrap().is_ready());
+}
+
+#[test]
+fn propagates_panics() {
+    let f = lazy(|| -> Result<(), ()> { panic!("boom") })
+        .with_executor(Arc::new(ThreadPerRun));
+    assert!(panic::catch_unwind(AssertUnwindSafe(|| f.wait())).is_err());
+}
+
+#[test]
+fn and_then_spawn() {
+    let queue = Arc::new(Queue(Mutex::new(Vec::new())));
+    let (tx, rx) = oneshot::channel::<u32>();
+    let mut f = executor::spawn(rx.and_then_spawn(queue.clone(), |n| Ok(n * 2)));
+
+    // Nothing is shipped to the executor until the first future resolves
+    assert!(f.poll_future(unpark_noop()).unwrap().is_not_ready());
+    assert_eq!(queue.0.lock().unwrap().len(), 0);
+
+    tx.complete(4);
+    assert!(f.poll_future(unpark_noop()).unwrap().is_not_ready());
+    assert_eq!(queue.0.lock().unwrap().len(), 1);
+    queue.run_all();
+    assert_eq!(f.poll_future(unpark_noop()), Ok(Async::Ready(8)));
+
+    // The continuation runs on the executor
+    let f = future::ok::<_, u32>(()).and_then_spawn(Arc::new(ThreadPerRun), |()| {
+        Ok(on_executor())
+    });
+    assert!(f.wait().unwrap());
+
+    // Errors skip the continuation
+    let f = future::err::<u32, u32>(3)
+        .and_then_spawn(Arc::new(ThreadPerRun), |_| -> Result<u32, u32> {
+            panic!("not called")
+        });
+    assert_eq!(f.wait(), Err(3));
+}